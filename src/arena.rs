@@ -0,0 +1,847 @@
+use std::convert::TryFrom;
+
+use crate::error::RloxError;
+use crate::expr::LiteralExpr;
+use crate::parser::{Builder, Built, Parser, Target};
+use crate::resolver::{FunctionType, Resolver};
+use crate::token::{Token, TokenType};
+
+/// Index of an expression in an `Ast`. Children always have smaller ids than their parents.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ExprId(u32);
+
+/// Index of a statement in an `Ast`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct StmtId(u32);
+
+impl ExprId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl StmtId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// An expression whose children are addressed by id, mirroring the variants of `Expr`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprNode {
+    Assign {
+        name: Token,
+        value: ExprId,
+    },
+    Binary {
+        operator: Token,
+        lhs: ExprId,
+        rhs: ExprId,
+    },
+    Call {
+        callee: ExprId,
+        paren: Token,
+        arguments: Vec<ExprId>,
+    },
+    Function {
+        keyword: Token,
+        params: Vec<Token>,
+        body: Vec<StmtId>,
+    },
+    Get {
+        object: ExprId,
+        name: Token,
+    },
+    Grouping {
+        expression: ExprId,
+    },
+    Index {
+        object: ExprId,
+        bracket: Token,
+        index: ExprId,
+    },
+    IndexSet {
+        object: ExprId,
+        bracket: Token,
+        index: ExprId,
+        value: ExprId,
+    },
+    List {
+        bracket: Token,
+        elements: Vec<ExprId>,
+    },
+    Literal(LiteralExpr),
+    Logical {
+        operator: Token,
+        lhs: ExprId,
+        rhs: ExprId,
+    },
+    Map {
+        brace: Token,
+        entries: Vec<(ExprId, ExprId)>,
+    },
+    Set {
+        object: ExprId,
+        name: Token,
+        value: ExprId,
+    },
+    Super {
+        keyword: Token,
+        method: Token,
+    },
+    This {
+        keyword: Token,
+    },
+    Unary {
+        operator: Token,
+        rhs: ExprId,
+    },
+    Variable {
+        name: Token,
+    },
+}
+
+/// A statement whose children are addressed by id, mirroring the variants of `Stmt`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StmtNode {
+    Block(Vec<StmtId>),
+    Break(Token),
+    // Every method is a `Function` statement
+    Class {
+        name: Token,
+        superclass: Option<ExprId>,
+        methods: Vec<StmtId>,
+    },
+    Continue(Token),
+    Expression(ExprId),
+    // `function` is a `Function` expression holding the parameters and body
+    Function {
+        name: Token,
+        function: ExprId,
+    },
+    If {
+        condition: ExprId,
+        then_branch: StmtId,
+        else_branch: Option<StmtId>,
+    },
+    Import {
+        keyword: Token,
+        path: String,
+        name: Token,
+    },
+    Print(ExprId),
+    Return {
+        keyword: Token,
+        value: Option<ExprId>,
+    },
+    Throw {
+        keyword: Token,
+        value: ExprId,
+    },
+    Try {
+        body: Vec<StmtId>,
+        catch: Option<(Token, Vec<StmtId>)>,
+        finally: Option<Vec<StmtId>>,
+    },
+    Var {
+        name: Token,
+        initializer: Option<ExprId>,
+    },
+    While {
+        condition: ExprId,
+        body: StmtId,
+        increment: Option<ExprId>,
+    },
+}
+
+/// The first and last source lines of an expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+impl Span {
+    fn line(line: usize) -> Span {
+        Span {
+            start_line: line,
+            end_line: line,
+        }
+    }
+
+    fn union(self, other: Span) -> Span {
+        Span {
+            start_line: self.start_line.min(other.start_line),
+            end_line: self.end_line.max(other.end_line),
+        }
+    }
+}
+
+/// The type an expression is known to have before running it, whenever it doesn't raise an error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StaticType {
+    Nil,
+    Bool,
+    Number,
+    String,
+    List,
+    Map,
+    Function,
+}
+
+/**
+ * Information about expressions kept beside an `Ast` rather than in its nodes.
+ *   Any pass can build its own table, so attaching information never needs the tree to change.
+ *   Stored densely by id, as most tables have an entry for a good share of the expressions.
+ */
+#[derive(Debug, Clone)]
+pub struct SideTable<T> {
+    values: Vec<Option<T>>,
+}
+
+impl<T> Default for SideTable<T> {
+    fn default() -> Self {
+        SideTable { values: Vec::new() }
+    }
+}
+
+impl<T> SideTable<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, id: ExprId) -> Option<&T> {
+        self.values.get(id.index()).and_then(Option::as_ref)
+    }
+
+    /// Sets the entry of `id`, returning the one it replaces.
+    pub fn insert(&mut self, id: ExprId, value: T) -> Option<T> {
+        if self.values.len() <= id.index() {
+            self.values.resize_with(id.index() + 1, || None);
+        }
+        self.values[id.index()].replace(value)
+    }
+
+    /// The entries in order of id.
+    pub fn iter(&self) -> impl Iterator<Item = (ExprId, &T)> {
+        (0..)
+            .zip(&self.values)
+            .filter_map(|(id, value)| value.as_ref().map(|value| (ExprId(id), value)))
+    }
+
+    // Drops the entries of `len` and the ids after it
+    fn truncate(&mut self, len: usize) {
+        self.values.truncate(len);
+    }
+}
+
+/**
+ * A program whose nodes live in two flat vectors and refer to each other by id.
+ *   The parser adds the nodes as it goes, without making an `Rc` tree first. Three side tables
+ *   come with it: the span of each expression, the scope distance of each local variable as the
+ *   resolver finds it, and the static type of each expression where the operators and literals
+ *   involved fix it.
+ */
+#[derive(Debug, Default)]
+pub struct Ast {
+    exprs: Vec<ExprNode>,
+    stmts: Vec<StmtNode>,
+    program: Vec<StmtId>,
+    spans: SideTable<Span>,
+    scopes: SideTable<usize>,
+    types: SideTable<StaticType>,
+}
+
+impl Ast {
+    /// Parses a program. Fails on syntax errors, and like `Resolver::resolve` on misplaced
+    /// `return`s and such.
+    pub fn parse(source: &str) -> Result<Ast, RloxError> {
+        let mut parser = Parser::with_builder(crate::tokenize(source)?, AstBuilder::default());
+        let program = parser.parse()?;
+        let mut ast = parser.into_builder().ast;
+        ast.program = program;
+        ast.resolve()?;
+        ast.infer_types();
+        Ok(ast)
+    }
+
+    /// The top-level statements of the program.
+    pub fn program(&self) -> &[StmtId] {
+        &self.program
+    }
+
+    pub fn expr(&self, id: ExprId) -> &ExprNode {
+        &self.exprs[id.index()]
+    }
+
+    pub fn stmt(&self, id: StmtId) -> &StmtNode {
+        &self.stmts[id.index()]
+    }
+
+    /// All expression ids, children before their parents.
+    pub fn expr_ids(&self) -> impl Iterator<Item = ExprId> {
+        (0..).take(self.exprs.len()).map(ExprId)
+    }
+
+    /// The lines covered by the tokens and literals of each expression and its children.
+    pub fn spans(&self) -> &SideTable<Span> {
+        &self.spans
+    }
+
+    /// The number of scopes between each local variable, `this` or `super` and its declaration.
+    /// Globals have no entry.
+    pub fn scopes(&self) -> &SideTable<usize> {
+        &self.scopes
+    }
+
+    pub fn types(&self) -> &SideTable<StaticType> {
+        &self.types
+    }
+
+    // Runs the rules of `Resolver` over the nodes, in the order it walks an `Rc` tree
+    fn resolve(&mut self) -> Result<(), RloxError> {
+        let resolver = Resolver::new();
+        self.resolve_stmts(&resolver, &self.program);
+        let locals = resolver.finish()?;
+        let mut scopes = SideTable::new();
+        for id in self.expr_ids() {
+            if let Some(&distance) = locals.get(&id.index()) {
+                scopes.insert(id, distance);
+            }
+        }
+        self.scopes = scopes;
+        Ok(())
+    }
+
+    fn resolve_stmts(&self, resolver: &Resolver, stmts: &[StmtId]) {
+        for stmt in stmts {
+            self.resolve_stmt(resolver, *stmt);
+        }
+    }
+
+    fn resolve_scope(&self, resolver: &Resolver, stmts: &[StmtId]) {
+        resolver.begin_scope();
+        self.resolve_stmts(resolver, stmts);
+        resolver.end_scope();
+    }
+
+    fn resolve_function(&self, resolver: &Resolver, function: ExprId, kind: FunctionType) {
+        if let ExprNode::Function { params, body, .. } = self.expr(function) {
+            let enclosing_function = resolver.begin_function(params, kind);
+            self.resolve_stmts(resolver, body);
+            resolver.end_function(enclosing_function);
+        }
+    }
+
+    fn resolve_stmt(&self, resolver: &Resolver, id: StmtId) {
+        match self.stmt(id) {
+            StmtNode::Block(statements) => self.resolve_scope(resolver, statements),
+            StmtNode::Break(_) | StmtNode::Continue(_) => (),
+            StmtNode::Class {
+                name,
+                superclass,
+                methods,
+            } => {
+                let enclosing_class = resolver.begin_class(name);
+                if let Some(superclass) = *superclass {
+                    let superclass_name = match self.expr(superclass) {
+                        ExprNode::Variable { name } => Some(name),
+                        _ => None,
+                    };
+                    resolver.superclass(name, superclass_name);
+                    self.resolve_expr(resolver, superclass);
+                }
+                resolver.begin_methods(superclass.is_some());
+                for method in methods {
+                    if let StmtNode::Function { name, function } = self.stmt(*method) {
+                        self.resolve_function(resolver, *function, FunctionType::method(name));
+                    }
+                }
+                resolver.end_class(superclass.is_some(), enclosing_class);
+            }
+            StmtNode::Expression(expr) | StmtNode::Print(expr) => {
+                self.resolve_expr(resolver, *expr)
+            }
+            StmtNode::Function { name, function } => {
+                resolver.declare(name);
+                resolver.define(name);
+                self.resolve_function(resolver, *function, FunctionType::Function);
+            }
+            StmtNode::If {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.resolve_expr(resolver, *condition);
+                self.resolve_stmt(resolver, *then_branch);
+                if let Some(else_branch) = *else_branch {
+                    self.resolve_stmt(resolver, else_branch);
+                }
+            }
+            StmtNode::Import { keyword, name, .. } => resolver.import(keyword, name),
+            StmtNode::Return { keyword, value } => {
+                resolver.return_value(keyword, value.is_some());
+                if let Some(value) = *value {
+                    self.resolve_expr(resolver, value);
+                }
+            }
+            StmtNode::Throw { value, .. } => self.resolve_expr(resolver, *value),
+            StmtNode::Try {
+                body,
+                catch,
+                finally,
+            } => {
+                self.resolve_scope(resolver, body);
+                if let Some((name, body)) = catch {
+                    resolver.begin_scope();
+                    resolver.declare(name);
+                    resolver.define(name);
+                    self.resolve_stmts(resolver, body);
+                    resolver.end_scope();
+                }
+                if let Some(finally) = finally {
+                    self.resolve_scope(resolver, finally);
+                }
+            }
+            StmtNode::Var { name, initializer } => {
+                resolver.declare(name);
+                if let Some(initializer) = *initializer {
+                    self.resolve_expr(resolver, initializer);
+                }
+                resolver.define(name);
+            }
+            StmtNode::While {
+                condition,
+                body,
+                increment,
+            } => {
+                self.resolve_expr(resolver, *condition);
+                self.resolve_stmt(resolver, *body);
+                if let Some(increment) = *increment {
+                    self.resolve_expr(resolver, increment);
+                }
+            }
+        }
+    }
+
+    fn resolve_expr(&self, resolver: &Resolver, id: ExprId) {
+        let resolve = |id: &ExprId| self.resolve_expr(resolver, *id);
+        match self.expr(id) {
+            ExprNode::Assign { name, value } => {
+                resolve(value);
+                resolver.resolve_local(id.index(), name);
+            }
+            ExprNode::Binary { lhs, rhs, .. } | ExprNode::Logical { lhs, rhs, .. } => {
+                resolve(lhs);
+                resolve(rhs);
+            }
+            ExprNode::Call {
+                callee, arguments, ..
+            } => {
+                resolve(callee);
+                arguments.iter().for_each(resolve);
+            }
+            ExprNode::Function { .. } => {
+                self.resolve_function(resolver, id, FunctionType::Function)
+            }
+            ExprNode::Get { object, .. } => resolve(object),
+            ExprNode::Grouping { expression } => resolve(expression),
+            ExprNode::Index { object, index, .. } => {
+                resolve(object);
+                resolve(index);
+            }
+            ExprNode::IndexSet {
+                object,
+                index,
+                value,
+                ..
+            } => {
+                resolve(object);
+                resolve(index);
+                resolve(value);
+            }
+            ExprNode::List { elements, .. } => elements.iter().for_each(resolve),
+            ExprNode::Literal(_) => (),
+            ExprNode::Map { entries, .. } => {
+                for (key, value) in entries {
+                    resolve(key);
+                    resolve(value);
+                }
+            }
+            ExprNode::Set { object, value, .. } => {
+                resolve(value);
+                resolve(object);
+            }
+            ExprNode::Super { keyword, .. } => resolver.super_keyword(id.index(), keyword),
+            ExprNode::This { keyword } => resolver.this_keyword(id.index(), keyword),
+            ExprNode::Unary { rhs, .. } => resolve(rhs),
+            ExprNode::Variable { name } => resolver.variable(id.index(), name),
+        }
+    }
+
+    // Children come first, so one pass in order of id sees every operand before its operator
+    fn infer_types(&mut self) {
+        for id in self.expr_ids() {
+            let operand = |id: &ExprId| self.types.get(*id).copied();
+            let static_type = match self.expr(id) {
+                ExprNode::Literal(LiteralExpr::Nil) => Some(StaticType::Nil),
+                ExprNode::Literal(LiteralExpr::Bool(_)) => Some(StaticType::Bool),
+                ExprNode::Literal(LiteralExpr::Float(_)) => Some(StaticType::Number),
+                ExprNode::Literal(LiteralExpr::String(_)) => Some(StaticType::String),
+                ExprNode::List { .. } => Some(StaticType::List),
+                ExprNode::Map { .. } => Some(StaticType::Map),
+                ExprNode::Function { .. } => Some(StaticType::Function),
+                ExprNode::Grouping { expression: value } | ExprNode::Assign { value, .. } => {
+                    operand(value)
+                }
+                ExprNode::Unary { operator, .. } => match operator.token_type() {
+                    TokenType::Minus => Some(StaticType::Number),
+                    _ => Some(StaticType::Bool),
+                },
+                ExprNode::Binary { operator, lhs, rhs } => match operator.token_type() {
+                    TokenType::Minus | TokenType::Star | TokenType::Slash => {
+                        Some(StaticType::Number)
+                    }
+                    // Adds numbers or concatenates strings, depending on its operands
+                    TokenType::Plus => match (operand(lhs), operand(rhs)) {
+                        (Some(a), Some(b)) if a == b => Some(a),
+                        _ => None,
+                    },
+                    _ => Some(StaticType::Bool),
+                },
+                // Evaluates to one of its operands
+                ExprNode::Logical { lhs, rhs, .. } => match (operand(lhs), operand(rhs)) {
+                    (Some(a), Some(b)) if a == b => Some(a),
+                    _ => None,
+                },
+                _ => None,
+            };
+            if let Some(static_type) = static_type {
+                self.types.insert(id, static_type);
+            }
+        }
+    }
+}
+
+/**
+ * Adds nodes to an `Ast` for the parser, working out the span of each expression as it goes.
+ *   Statements are given spans too, which are only kept to span the functions they make up.
+ */
+#[derive(Default)]
+pub(crate) struct AstBuilder {
+    ast: Ast,
+    stmt_spans: Vec<Option<Span>>, // by StmtId
+}
+
+impl AstBuilder {
+    fn push_expr(&mut self, node: ExprNode, span: Option<Span>) -> Built<ExprId> {
+        let id = u32::try_from(self.ast.exprs.len())
+            .map_err(|_| "Too many expressions in one program.")?;
+        self.ast.exprs.push(node);
+        if let Some(span) = span {
+            self.ast.spans.insert(ExprId(id), span);
+        }
+        Ok(ExprId(id))
+    }
+
+    fn push_stmt(&mut self, node: StmtNode, span: Option<Span>) -> Built<StmtId> {
+        let id = u32::try_from(self.ast.stmts.len())
+            .map_err(|_| "Too many statements in one program.")?;
+        self.ast.stmts.push(node);
+        self.stmt_spans.push(span);
+        Ok(StmtId(id))
+    }
+
+    // The union of the lines of `tokens` and the spans of the expressions and statements given
+    fn span(&self, tokens: &[&Token], exprs: &[ExprId], stmts: &[StmtId]) -> Option<Span> {
+        let lines = tokens.iter().map(|token| Span::line(*token.line_number()));
+        let exprs = exprs.iter().filter_map(|id| self.ast.spans.get(*id));
+        let stmts = stmts
+            .iter()
+            .filter_map(|id| self.stmt_spans[id.index()].as_ref());
+        lines.chain(exprs.chain(stmts).copied()).reduce(Span::union)
+    }
+}
+
+impl Builder for AstBuilder {
+    type Expr = ExprId;
+    type Stmt = StmtId;
+    type Function = StmtId;
+    // Whatever can be assigned to is the last expression made, so it is taken back out
+    fn assignment_target(&mut self, expr: ExprId) -> Result<Target<ExprId>, ExprId> {
+        debug_assert_eq!(expr.index() + 1, self.ast.exprs.len());
+        let target = match self.ast.exprs.pop() {
+            Some(ExprNode::Variable { name }) => Target::Variable(name),
+            Some(ExprNode::Get { object, name }) => Target::Get(object, name),
+            Some(ExprNode::Index {
+                object,
+                bracket,
+                index,
+            }) => Target::Index(object, bracket, index),
+            node => {
+                self.ast.exprs.extend(node);
+                return Err(expr);
+            }
+        };
+        self.ast.spans.truncate(expr.index());
+        Ok(target)
+    }
+
+    fn assign_expr(&mut self, name: Token, value: ExprId) -> Built<ExprId> {
+        let span = self.span(&[&name], &[value], &[]);
+        self.push_expr(ExprNode::Assign { name, value }, span)
+    }
+
+    fn binary_expr(&mut self, operator: Token, lhs: ExprId, rhs: ExprId) -> Built<ExprId> {
+        let span = self.span(&[&operator], &[lhs, rhs], &[]);
+        self.push_expr(ExprNode::Binary { operator, lhs, rhs }, span)
+    }
+
+    fn call_expr(&mut self, callee: ExprId, paren: Token, arguments: Vec<ExprId>) -> Built<ExprId> {
+        let span = self.span(&[&paren], &[&[callee], &arguments[..]].concat(), &[]);
+        let node = ExprNode::Call {
+            callee,
+            paren,
+            arguments,
+        };
+        self.push_expr(node, span)
+    }
+
+    fn function_expr(
+        &mut self,
+        keyword: Token,
+        params: Vec<Token>,
+        body: Vec<StmtId>,
+    ) -> Built<ExprId> {
+        let span = self.span(&[&keyword], &[], &body);
+        let node = ExprNode::Function {
+            keyword,
+            params,
+            body,
+        };
+        self.push_expr(node, span)
+    }
+
+    fn get_expr(&mut self, object: ExprId, name: Token) -> Built<ExprId> {
+        let span = self.span(&[&name], &[object], &[]);
+        self.push_expr(ExprNode::Get { object, name }, span)
+    }
+
+    fn grouping_expr(&mut self, expression: ExprId) -> Built<ExprId> {
+        let span = self.span(&[], &[expression], &[]);
+        self.push_expr(ExprNode::Grouping { expression }, span)
+    }
+
+    fn index_expr(&mut self, object: ExprId, bracket: Token, index: ExprId) -> Built<ExprId> {
+        let span = self.span(&[&bracket], &[object, index], &[]);
+        let node = ExprNode::Index {
+            object,
+            bracket,
+            index,
+        };
+        self.push_expr(node, span)
+    }
+
+    fn index_set_expr(
+        &mut self,
+        object: ExprId,
+        bracket: Token,
+        index: ExprId,
+        value: ExprId,
+    ) -> Built<ExprId> {
+        let span = self.span(&[&bracket], &[object, index, value], &[]);
+        let node = ExprNode::IndexSet {
+            object,
+            bracket,
+            index,
+            value,
+        };
+        self.push_expr(node, span)
+    }
+
+    fn list_expr(&mut self, bracket: Token, elements: Vec<ExprId>) -> Built<ExprId> {
+        let span = self.span(&[&bracket], &elements, &[]);
+        self.push_expr(ExprNode::List { bracket, elements }, span)
+    }
+
+    fn literal_expr(&mut self, value: LiteralExpr, line: usize) -> Built<ExprId> {
+        self.push_expr(ExprNode::Literal(value), Some(Span::line(line)))
+    }
+
+    fn logical_expr(&mut self, operator: Token, lhs: ExprId, rhs: ExprId) -> Built<ExprId> {
+        let span = self.span(&[&operator], &[lhs, rhs], &[]);
+        self.push_expr(ExprNode::Logical { operator, lhs, rhs }, span)
+    }
+
+    fn map_expr(&mut self, brace: Token, entries: Vec<(ExprId, ExprId)>) -> Built<ExprId> {
+        let children = entries.iter().flat_map(|(k, v)| vec![*k, *v]);
+        let span = self.span(&[&brace], &children.collect::<Vec<_>>(), &[]);
+        self.push_expr(ExprNode::Map { brace, entries }, span)
+    }
+
+    fn set_expr(&mut self, object: ExprId, name: Token, value: ExprId) -> Built<ExprId> {
+        let span = self.span(&[&name], &[object, value], &[]);
+        let node = ExprNode::Set {
+            object,
+            name,
+            value,
+        };
+        self.push_expr(node, span)
+    }
+
+    fn super_expr(&mut self, keyword: Token, method: Token) -> Built<ExprId> {
+        let span = self.span(&[&keyword, &method], &[], &[]);
+        self.push_expr(ExprNode::Super { keyword, method }, span)
+    }
+
+    fn this_expr(&mut self, keyword: Token) -> Built<ExprId> {
+        let span = self.span(&[&keyword], &[], &[]);
+        self.push_expr(ExprNode::This { keyword }, span)
+    }
+
+    fn unary_expr(&mut self, operator: Token, rhs: ExprId) -> Built<ExprId> {
+        let span = self.span(&[&operator], &[rhs], &[]);
+        self.push_expr(ExprNode::Unary { operator, rhs }, span)
+    }
+
+    fn variable_expr(&mut self, name: Token) -> Built<ExprId> {
+        let span = self.span(&[&name], &[], &[]);
+        self.push_expr(ExprNode::Variable { name }, span)
+    }
+
+    fn block_stmt(&mut self, statements: Vec<StmtId>) -> Built<StmtId> {
+        let span = self.span(&[], &[], &statements);
+        self.push_stmt(StmtNode::Block(statements), span)
+    }
+
+    fn break_stmt(&mut self, keyword: Token) -> Built<StmtId> {
+        let span = self.span(&[&keyword], &[], &[]);
+        self.push_stmt(StmtNode::Break(keyword), span)
+    }
+
+    fn class_stmt(
+        &mut self,
+        name: Token,
+        superclass: Option<ExprId>,
+        methods: Vec<StmtId>,
+    ) -> Built<StmtId> {
+        let span = self.span(&[&name], superclass.as_slice(), &methods);
+        let node = StmtNode::Class {
+            name,
+            superclass,
+            methods,
+        };
+        self.push_stmt(node, span)
+    }
+
+    fn continue_stmt(&mut self, keyword: Token) -> Built<StmtId> {
+        let span = self.span(&[&keyword], &[], &[]);
+        self.push_stmt(StmtNode::Continue(keyword), span)
+    }
+
+    fn expression_stmt(&mut self, expression: ExprId) -> Built<StmtId> {
+        let span = self.span(&[], &[expression], &[]);
+        self.push_stmt(StmtNode::Expression(expression), span)
+    }
+
+    // The parameters and body go in a `Function` expression, named by the statement
+    fn function_declaration(
+        &mut self,
+        name: Token,
+        params: Vec<Token>,
+        body: Vec<StmtId>,
+    ) -> Built<StmtId> {
+        let function = self.function_expr(name.clone(), params, body)?;
+        let span = self.span(&[], &[function], &[]);
+        self.push_stmt(StmtNode::Function { name, function }, span)
+    }
+
+    fn function_stmt(&mut self, function: StmtId) -> Built<StmtId> {
+        Ok(function)
+    }
+
+    fn if_stmt(
+        &mut self,
+        condition: ExprId,
+        then_branch: StmtId,
+        else_branch: Option<StmtId>,
+    ) -> Built<StmtId> {
+        let branches = [&[then_branch], else_branch.as_slice()].concat();
+        let span = self.span(&[], &[condition], &branches);
+        let node = StmtNode::If {
+            condition,
+            then_branch,
+            else_branch,
+        };
+        self.push_stmt(node, span)
+    }
+
+    fn import_stmt(&mut self, keyword: Token, path: String, name: Token) -> Built<StmtId> {
+        let span = self.span(&[&keyword, &name], &[], &[]);
+        self.push_stmt(
+            StmtNode::Import {
+                keyword,
+                path,
+                name,
+            },
+            span,
+        )
+    }
+
+    fn print_stmt(&mut self, keyword: Token, expression: ExprId) -> Built<StmtId> {
+        let span = self.span(&[&keyword], &[expression], &[]);
+        self.push_stmt(StmtNode::Print(expression), span)
+    }
+
+    fn return_stmt(&mut self, keyword: Token, value: Option<ExprId>) -> Built<StmtId> {
+        let span = self.span(&[&keyword], value.as_slice(), &[]);
+        self.push_stmt(StmtNode::Return { keyword, value }, span)
+    }
+
+    fn throw_stmt(&mut self, keyword: Token, value: ExprId) -> Built<StmtId> {
+        let span = self.span(&[&keyword], &[value], &[]);
+        self.push_stmt(StmtNode::Throw { keyword, value }, span)
+    }
+
+    fn try_stmt(
+        &mut self,
+        body: Vec<StmtId>,
+        catch: Option<(Token, Vec<StmtId>)>,
+        finally: Option<Vec<StmtId>>,
+    ) -> Built<StmtId> {
+        let catch_name = catch.iter().map(|(name, _)| name).collect::<Vec<_>>();
+        let catch_body = catch.iter().flat_map(|(_, body)| body);
+        let stmts = body
+            .iter()
+            .chain(catch_body)
+            .chain(finally.iter().flatten());
+        let span = self.span(&catch_name, &[], &stmts.copied().collect::<Vec<_>>());
+        let node = StmtNode::Try {
+            body,
+            catch,
+            finally,
+        };
+        self.push_stmt(node, span)
+    }
+
+    fn var_stmt(&mut self, name: Token, initializer: Option<ExprId>) -> Built<StmtId> {
+        let span = self.span(&[&name], initializer.as_slice(), &[]);
+        self.push_stmt(StmtNode::Var { name, initializer }, span)
+    }
+
+    fn while_stmt(
+        &mut self,
+        condition: ExprId,
+        body: StmtId,
+        increment: Option<ExprId>,
+    ) -> Built<StmtId> {
+        let exprs = [&[condition], increment.as_slice()].concat();
+        let span = self.span(&[], &exprs, &[body]);
+        let node = StmtNode::While {
+            condition,
+            body,
+            increment,
+        };
+        self.push_stmt(node, span)
+    }
+}
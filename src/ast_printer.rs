@@ -1,56 +1,261 @@
-use crate::expr::{BinaryExpr, Expr, Visitor, GroupingExpr, LiteralExpr, UnaryExpr};
+use crate::expr::{
+    AssignExpr, BinaryExpr, CallExpr, Expr, FunctionExpr, GetExpr, GroupingExpr, IndexExpr,
+    IndexSetExpr, ListExpr, LiteralExpr, LogicalExpr, MapExpr, SetExpr, SuperExpr, UnaryExpr,
+    Visitor,
+};
+use crate::stmt::{self, ClassStmt, Stmt};
+use crate::token::Token;
 
 pub struct AstPrinter;
 
 impl Visitor<String> for AstPrinter {
     fn visit_expr(&self, expr: &Expr) -> String {
-        match expr {
-            Expr::Binary(expr) => self.visit_binary_expr(&expr),
-            Expr::Grouping(expr) => self.visit_grouping_expr(&expr),
-            Expr::Literal(expr) => self.visit_literal_expr(&expr),
-            Expr::Unary(expr) => self.visit_unary_expr(&expr),
-        }
+        PLAIN.visit_expr(expr)
+    }
+}
+
+impl stmt::Visitor<String> for AstPrinter {
+    fn visit_stmt(&self, stmt: &Stmt) -> String {
+        PLAIN.visit_stmt(stmt)
     }
 }
 
 impl AstPrinter {
     pub fn print(&self, expr: Expr) -> String {
-        expr.accept::<String>(self).to_string()
+        expr.accept::<String>(self)
+    }
+
+    pub fn print_stmt(&self, stmt: &Stmt) -> String {
+        stmt.accept::<String>(self)
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Self {
         AstPrinter {}
     }
+}
+
+const PLAIN: Printer = Printer { readable: false };
+
+/**
+ * Does the printing for `AstPrinter`, and for `sexpr::print_sexpr` when `readable` is set.
+ *   Readable text quotes strings, so they can't be taken for names, and spells the numbers that
+ *   have no digits as `+inf.0`, `-inf.0`, `+nan.0` and `-nan.0`, so they can't either.
+ */
+pub(crate) struct Printer {
+    pub(crate) readable: bool,
+}
+
+impl Visitor<String> for Printer {
+    fn visit_expr(&self, expr: &Expr) -> String {
+        match expr {
+            Expr::Assign(expr) => self.visit_assign_expr(expr),
+            Expr::Binary(expr) => self.visit_binary_expr(expr),
+            Expr::Call(expr) => self.visit_call_expr(expr),
+            Expr::Function(expr) => self.visit_function_expr(None, expr),
+            Expr::Get(expr) => self.visit_get_expr(expr),
+            Expr::Grouping(expr) => self.visit_grouping_expr(expr),
+            Expr::Index(expr) => self.visit_index_expr(expr),
+            Expr::IndexSet(expr) => self.visit_index_set_expr(expr),
+            Expr::List(expr) => self.visit_list_expr(expr),
+            Expr::Literal(expr) => self.visit_literal_expr(expr),
+            Expr::Logical(expr) => self.visit_logical_expr(expr),
+            Expr::Map(expr) => self.visit_map_expr(expr),
+            Expr::Set(expr) => self.visit_set_expr(expr),
+            Expr::Super(expr) => self.visit_super_expr(expr),
+            Expr::This(_) => "this".to_string(),
+            Expr::Unary(expr) => self.visit_unary_expr(expr),
+            Expr::Variable(expr) => expr.name().lexeme().to_string(),
+        }
+    }
+}
+
+impl Printer {
+    pub(crate) fn print_stmt(&self, stmt: &Stmt) -> String {
+        stmt.accept::<String>(self)
+    }
 
     fn parenthesize(&self, name: &str, expressions: &[&Expr]) -> String {
         let mut s = format!("({}", name);
         for expr in expressions {
-            s.push_str(" ");
+            s.push(' ');
             s.push_str(&expr.accept::<String>(self));
         }
-        s.push_str(")");
+        s.push(')');
+        s
+    }
 
-        return s.to_string();
+    fn visit_assign_expr(&self, expr: &AssignExpr) -> String {
+        format!("(= {} {})", expr.name().lexeme(), expr.value().accept(self))
     }
 
     fn visit_binary_expr(&self, expr: &BinaryExpr) -> String {
-        self.parenthesize(&expr.operator().lexeme(), &[expr.lhs(), expr.rhs()])
+        self.parenthesize(expr.operator().lexeme(), &[expr.lhs(), expr.rhs()])
+    }
+
+    fn visit_call_expr(&self, expr: &CallExpr) -> String {
+        let mut expressions = vec![expr.callee()];
+        expressions.extend(expr.arguments());
+        self.parenthesize("call", &expressions)
+    }
+
+    // (fun name (params) body...), the name is left out for lambdas
+    fn visit_function_expr(&self, name: Option<&Token>, expr: &FunctionExpr) -> String {
+        let mut s = String::from("(fun");
+        if let Some(name) = name {
+            s.push(' ');
+            s.push_str(name.lexeme());
+        }
+        let params = expr.params().iter().map(|p| p.lexeme()).collect::<Vec<_>>();
+        s.push_str(&format!(" ({})", params.join(" ")));
+        for stmt in expr.body() {
+            s.push(' ');
+            s.push_str(&self.print_stmt(stmt));
+        }
+        s.push(')');
+        s
+    }
+
+    fn visit_get_expr(&self, expr: &GetExpr) -> String {
+        format!("(. {} {})", expr.object().accept(self), expr.name().lexeme())
     }
 
     fn visit_grouping_expr(&self, expr: &GroupingExpr) -> String {
-        self.parenthesize("group", &[&expr.expression()])
+        self.parenthesize("group", &[expr.expression()])
+    }
+
+    fn visit_index_expr(&self, expr: &IndexExpr) -> String {
+        self.parenthesize("index", &[expr.object(), expr.index()])
+    }
+
+    fn visit_index_set_expr(&self, expr: &IndexSetExpr) -> String {
+        self.parenthesize("index=", &[expr.object(), expr.index(), expr.value()])
+    }
+
+    fn visit_list_expr(&self, expr: &ListExpr) -> String {
+        self.parenthesize("list", &expr.elements().iter().collect::<Vec<_>>())
     }
 
     fn visit_literal_expr(&self, expr: &LiteralExpr) -> String {
         match expr {
             LiteralExpr::Nil => "nil".to_string(),
+            // Lox strings never contain quotes, so quoting them is enough to tell them from names
+            LiteralExpr::String(s) if self.readable => format!("\"{}\"", s),
             LiteralExpr::String(s) => s.to_string(),
+            LiteralExpr::Float(f) if self.readable && !f.is_finite() => {
+                let sign = if f.is_sign_negative() { '-' } else { '+' };
+                let name = if f.is_nan() { "nan" } else { "inf" };
+                format!("{}{}.0", sign, name)
+            }
             LiteralExpr::Float(f) => f.to_string(),
             LiteralExpr::Bool(b) => b.to_string(),
         }
     }
 
+    fn visit_logical_expr(&self, expr: &LogicalExpr) -> String {
+        self.parenthesize(expr.operator().lexeme(), &[expr.lhs(), expr.rhs()])
+    }
+
+    fn visit_map_expr(&self, expr: &MapExpr) -> String {
+        let entries = expr
+            .entries()
+            .iter()
+            .flat_map(|(key, value)| vec![key, value])
+            .collect::<Vec<_>>();
+        self.parenthesize("map", &entries)
+    }
+
+    fn visit_set_expr(&self, expr: &SetExpr) -> String {
+        format!(
+            "(= (. {} {}) {})",
+            expr.object().accept(self),
+            expr.name().lexeme(),
+            expr.value().accept(self)
+        )
+    }
+
+    fn visit_super_expr(&self, expr: &SuperExpr) -> String {
+        format!("(super {})", expr.method().lexeme())
+    }
+
     fn visit_unary_expr(&self, expr: &UnaryExpr) -> String {
-        self.parenthesize(expr.operator().lexeme(), &[&expr.rhs()])
+        self.parenthesize(expr.operator().lexeme(), &[expr.rhs()])
+    }
+}
+
+impl stmt::Visitor<String> for Printer {
+    fn visit_stmt(&self, stmt: &Stmt) -> String {
+        match stmt {
+            Stmt::Block(s) => self.parenthesize_stmts("block", &[], s.statements()),
+            Stmt::Break(_) => "(break)".to_string(),
+            Stmt::Class(s) => self.visit_class_stmt(s),
+            Stmt::Continue(_) => "(continue)".to_string(),
+            Stmt::Expression(s) => s.expression().accept(self),
+            Stmt::Function(s) => self.visit_function_expr(Some(s.name()), s.function()),
+            Stmt::If(s) => {
+                let mut branches = vec![s.then_branch().clone()];
+                branches.extend(s.else_branch().cloned());
+                self.parenthesize_stmts("if", &[s.condition()], &branches)
+            }
+            Stmt::Import(s) if self.readable => {
+                format!("(import \"{}\" {})", s.path(), s.name().lexeme())
+            }
+            Stmt::Import(s) => format!("(import {:?} {})", s.path(), s.name().lexeme()),
+            Stmt::Print(s) => self.parenthesize("print", &[s.expression()]),
+            Stmt::Return(s) => {
+                let value = s.value().into_iter().collect::<Vec<_>>();
+                self.parenthesize("return", &value)
+            }
+            Stmt::Throw(s) => self.parenthesize("throw", &[s.value()]),
+            Stmt::Try(s) => {
+                let mut printed = self.parenthesize_stmts("try", &[], s.body());
+                printed.pop();
+                if let (Some(name), Some(body)) = (s.catch_name(), s.catch_body()) {
+                    let name = format!("catch {}", name.lexeme());
+                    printed.push_str(&format!(" {}", self.parenthesize_stmts(&name, &[], body)));
+                }
+                if let Some(finally) = s.finally_body() {
+                    let finally = self.parenthesize_stmts("finally", &[], finally);
+                    printed.push_str(&format!(" {}", finally));
+                }
+                printed.push(')');
+                printed
+            }
+            Stmt::Var(s) => {
+                let initializer = s.initializer().into_iter().collect::<Vec<_>>();
+                self.parenthesize(&format!("var {}", s.name().lexeme()), &initializer)
+            }
+            Stmt::While(s) => {
+                let mut expressions = vec![s.condition()];
+                expressions.extend(s.increment());
+                self.parenthesize_stmts("while", &expressions, &[s.body().clone()])
+            }
+        }
+    }
+}
+
+impl Printer {
+    fn parenthesize_stmts(&self, name: &str, expressions: &[&Expr], statements: &[Stmt]) -> String {
+        let mut s = self.parenthesize(name, expressions);
+        s.pop();
+        for stmt in statements {
+            s.push(' ');
+            s.push_str(&self.print_stmt(stmt));
+        }
+        s.push(')');
+        s
+    }
+
+    fn visit_class_stmt(&self, stmt: &ClassStmt) -> String {
+        let mut s = format!("(class {}", stmt.name().lexeme());
+        if let Some(superclass) = stmt.superclass() {
+            s.push_str(&format!(" < {}", superclass.accept(self)));
+        }
+        for method in stmt.methods() {
+            s.push(' ');
+            s.push_str(&self.visit_function_expr(Some(method.name()), method.function()));
+        }
+        s.push(')');
+        s
     }
 }
@@ -0,0 +1,328 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+use crate::error::RloxError;
+use crate::expr::{Expr, FunctionExpr, LiteralExpr, Visitor};
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::stmt::{self, Stmt};
+use crate::token::Token;
+
+/// Parses `source` and prints it as a DOT digraph, with lines on literals and prints too.
+pub fn print_source(source: &str) -> Result<String, RloxError> {
+    let mut parser = Parser::new(Scanner::try_new(source.to_string())?.into_tokens());
+    let statements = parser.parse()?;
+    let printer = AstPrinterDot {
+        spans: parser.take_spans(),
+        literal_lines: parser.take_literal_lines(),
+        ..AstPrinterDot::default()
+    };
+    Ok(printer.print(&statements))
+}
+
+/**
+ * Prints the syntax tree as a Graphviz DOT digraph, for rendering with `dot -Tsvg`.
+ *   Each node is labelled with its `Expr` or `Stmt` variant, its operator or name, and the line
+ *   of its token when it has one. Literals and prints have no token, so they only get a line
+ *   when printed by `print_source`, from the lines the parser recorded. Edges to children are
+ *   labelled with the role of the child, named after its accessor, except for the elements of
+ *   lists which are drawn in order.
+ */
+pub struct AstPrinterDot {
+    out: RefCell<String>,
+    next_id: Cell<usize>,
+    spans: HashMap<usize, (usize, usize)>, // Stmt::id -> first and last line
+    literal_lines: HashMap<usize, usize>,  // Expr::id -> line of the literal
+}
+
+impl AstPrinterDot {
+    /// Prints a whole program, as a tree under a "Program" node.
+    pub fn print(&self, statements: &[Stmt]) -> String {
+        self.begin();
+        let program = self.node("Program", None);
+        self.statements(program, statements, "");
+        self.end()
+    }
+
+    pub fn print_expr(&self, expr: &Expr) -> String {
+        self.begin();
+        expr.accept(self);
+        self.end()
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn default() -> Self {
+        AstPrinterDot {
+            out: RefCell::new(String::new()),
+            next_id: Cell::new(0),
+            spans: HashMap::new(),
+            literal_lines: HashMap::new(),
+        }
+    }
+
+    fn begin(&self) {
+        self.next_id.set(0);
+        self.out.replace("digraph ast {\n  node [shape=box];\n".to_string());
+    }
+
+    fn end(&self) -> String {
+        self.out.borrow_mut().push_str("}\n");
+        self.out.take()
+    }
+
+    // Declares a node and returns its id
+    fn node(&self, label: &str, token: Option<&Token>) -> usize {
+        self.node_at_line(label, token.map(|t| *t.line_number()))
+    }
+
+    fn node_at_line(&self, label: &str, line: Option<usize>) -> usize {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        let mut label = escape(label);
+        if let Some(line) = line {
+            label.push_str(&format!("\\nline {}", line));
+        }
+        self.out.borrow_mut().push_str(&format!("  n{} [label=\"{}\"];\n", id, label));
+        id
+    }
+
+    fn edge(&self, from: usize, to: usize, label: &str) {
+        let edge = match label {
+            "" => format!("  n{} -> n{};\n", from, to),
+            _ => format!("  n{} -> n{} [label=\"{}\"];\n", from, to, escape(label)),
+        };
+        self.out.borrow_mut().push_str(&edge);
+    }
+
+    fn child(&self, parent: usize, expr: &Expr, label: &str) {
+        let child = expr.accept(self);
+        self.edge(parent, child, label);
+    }
+
+    fn children(&self, parent: usize, exprs: &[Expr]) {
+        for expr in exprs {
+            self.child(parent, expr, "");
+        }
+    }
+
+    fn statement(&self, parent: usize, stmt: &Stmt, label: &str) {
+        let child = stmt.accept(self);
+        self.edge(parent, child, label);
+    }
+
+    fn statements(&self, parent: usize, statements: &[Stmt], label: &str) {
+        for stmt in statements {
+            self.statement(parent, stmt, label);
+        }
+    }
+
+    fn function(&self, name: Option<&Token>, function: &FunctionExpr) -> usize {
+        let params = function.params().iter().map(|p| p.lexeme()).collect::<Vec<_>>();
+        let label = format!(
+            "Function {}({})",
+            name.map_or("", |n| n.lexeme()),
+            params.join(", ")
+        );
+        let id = self.node(&label, Some(name.unwrap_or_else(|| function.keyword())));
+        self.statements(id, function.body(), "");
+        id
+    }
+}
+
+fn escape(label: &str) -> String {
+    label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+impl Visitor<usize> for AstPrinterDot {
+    fn visit_expr(&self, expr: &Expr) -> usize {
+        match expr {
+            Expr::Assign(e) => {
+                let id = self.node(&format!("Assign {}", e.name().lexeme()), Some(e.name()));
+                self.child(id, e.value(), "value");
+                id
+            }
+            Expr::Binary(e) => {
+                let operator = e.operator();
+                let id = self.node(&format!("Binary {}", operator.lexeme()), Some(operator));
+                self.child(id, e.lhs(), "lhs");
+                self.child(id, e.rhs(), "rhs");
+                id
+            }
+            Expr::Call(e) => {
+                let id = self.node("Call", Some(e.paren()));
+                self.child(id, e.callee(), "callee");
+                self.children(id, e.arguments());
+                id
+            }
+            Expr::Function(e) => self.function(None, e),
+            Expr::Get(e) => {
+                let id = self.node(&format!("Get .{}", e.name().lexeme()), Some(e.name()));
+                self.child(id, e.object(), "object");
+                id
+            }
+            Expr::Grouping(e) => {
+                let id = self.node("Grouping", None);
+                self.child(id, e.expression(), "");
+                id
+            }
+            Expr::Index(e) => {
+                let id = self.node("Index", Some(e.bracket()));
+                self.child(id, e.object(), "object");
+                self.child(id, e.index(), "index");
+                id
+            }
+            Expr::IndexSet(e) => {
+                let id = self.node("IndexSet", Some(e.bracket()));
+                self.child(id, e.object(), "object");
+                self.child(id, e.index(), "index");
+                self.child(id, e.value(), "value");
+                id
+            }
+            Expr::List(e) => {
+                let id = self.node("List", Some(e.bracket()));
+                self.children(id, e.elements());
+                id
+            }
+            Expr::Literal(e) => {
+                let label = match &**e {
+                    LiteralExpr::Nil => "nil".to_string(),
+                    LiteralExpr::String(s) => format!("\"{}\"", s),
+                    LiteralExpr::Float(n) => n.to_string(),
+                    LiteralExpr::Bool(b) => b.to_string(),
+                };
+                let line = self.literal_lines.get(&expr.id()).copied();
+                self.node_at_line(&format!("Literal {}", label), line)
+            }
+            Expr::Logical(e) => {
+                let operator = e.operator();
+                let id = self.node(&format!("Logical {}", operator.lexeme()), Some(operator));
+                self.child(id, e.lhs(), "lhs");
+                self.child(id, e.rhs(), "rhs");
+                id
+            }
+            Expr::Map(e) => {
+                let id = self.node("Map", Some(e.brace()));
+                for (key, value) in e.entries() {
+                    self.child(id, key, "key");
+                    self.child(id, value, "value");
+                }
+                id
+            }
+            Expr::Set(e) => {
+                let id = self.node(&format!("Set .{}", e.name().lexeme()), Some(e.name()));
+                self.child(id, e.object(), "object");
+                self.child(id, e.value(), "value");
+                id
+            }
+            Expr::Super(e) => {
+                self.node(&format!("Super .{}", e.method().lexeme()), Some(e.method()))
+            }
+            Expr::This(e) => self.node("This", Some(e.keyword())),
+            Expr::Unary(e) => {
+                let operator = e.operator();
+                let id = self.node(&format!("Unary {}", operator.lexeme()), Some(operator));
+                self.child(id, e.rhs(), "rhs");
+                id
+            }
+            Expr::Variable(e) => {
+                self.node(&format!("Variable {}", e.name().lexeme()), Some(e.name()))
+            }
+        }
+    }
+}
+
+impl stmt::Visitor<usize> for AstPrinterDot {
+    fn visit_stmt(&self, stmt: &Stmt) -> usize {
+        match stmt {
+            Stmt::Block(s) => {
+                let id = self.node("Block", None);
+                self.statements(id, s.statements(), "");
+                id
+            }
+            Stmt::Break(s) => self.node("Break", Some(s.keyword())),
+            Stmt::Class(s) => {
+                let id = self.node(&format!("Class {}", s.name().lexeme()), Some(s.name()));
+                if let Some(superclass) = s.superclass() {
+                    self.child(id, superclass, "superclass");
+                }
+                for method in s.methods() {
+                    let child = self.function(Some(method.name()), method.function());
+                    self.edge(id, child, "method");
+                }
+                id
+            }
+            Stmt::Continue(s) => self.node("Continue", Some(s.keyword())),
+            Stmt::Expression(s) => {
+                let id = self.node("Expression", None);
+                self.child(id, s.expression(), "");
+                id
+            }
+            Stmt::Function(s) => self.function(Some(s.name()), s.function()),
+            Stmt::If(s) => {
+                let id = self.node("If", None);
+                self.child(id, s.condition(), "condition");
+                self.statement(id, s.then_branch(), "then_branch");
+                if let Some(else_branch) = s.else_branch() {
+                    self.statement(id, else_branch, "else_branch");
+                }
+                id
+            }
+            Stmt::Import(s) => {
+                let label = format!("Import \"{}\" as {}", s.path(), s.name().lexeme());
+                self.node(&label, Some(s.keyword()))
+            }
+            Stmt::Print(s) => {
+                let line = self.spans.get(&stmt.id()).map(|(first, _)| *first);
+                let id = self.node_at_line("Print", line);
+                self.child(id, s.expression(), "");
+                id
+            }
+            Stmt::Return(s) => {
+                let id = self.node("Return", Some(s.keyword()));
+                if let Some(value) = s.value() {
+                    self.child(id, value, "value");
+                }
+                id
+            }
+            Stmt::Throw(s) => {
+                let id = self.node("Throw", Some(s.keyword()));
+                self.child(id, s.value(), "value");
+                id
+            }
+            Stmt::Try(s) => {
+                let id = self.node("Try", None);
+                self.statements(id, s.body(), "body");
+                if let (Some(name), Some(body)) = (s.catch_name(), s.catch_body()) {
+                    let catch = self.node(&format!("Catch {}", name.lexeme()), Some(name));
+                    self.edge(id, catch, "catch");
+                    self.statements(catch, body, "");
+                }
+                if let Some(body) = s.finally_body() {
+                    let finally = self.node("Finally", None);
+                    self.edge(id, finally, "finally");
+                    self.statements(finally, body, "");
+                }
+                id
+            }
+            Stmt::Var(s) => {
+                let id = self.node(&format!("Var {}", s.name().lexeme()), Some(s.name()));
+                if let Some(initializer) = s.initializer() {
+                    self.child(id, initializer, "initializer");
+                }
+                id
+            }
+            Stmt::While(s) => {
+                let id = self.node("While", None);
+                self.child(id, s.condition(), "condition");
+                self.statement(id, s.body(), "body");
+                if let Some(increment) = s.increment() {
+                    self.child(id, increment, "increment");
+                }
+                id
+            }
+        }
+    }
+}
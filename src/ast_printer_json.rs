@@ -0,0 +1,372 @@
+use crate::expr::{Expr, FunctionExpr, LiteralExpr, Visitor};
+use crate::stmt::{self, Stmt};
+use crate::token::Token;
+
+/**
+ * Prints the syntax tree as JSON, one object per node.
+ *   Every node has a "type" naming its `Expr` or `Stmt` variant, followed by the fields of that
+ *   variant under the names of their accessors, always all of them and always in the same order.
+ *   Missing optional children are null, and "line" is present on nodes that carry a token.
+ */
+pub struct AstPrinterJson;
+
+// The JSON values the printer builds, before they are rendered as text
+enum Json {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    fn node(node_type: &str, fields: Vec<(&'static str, Json)>) -> Json {
+        let mut object = vec![("type", Json::String(node_type.to_string()))];
+        object.extend(fields);
+        Json::Object(object)
+    }
+
+    fn render(&self, out: &mut String, depth: usize) {
+        match self {
+            Json::Null => out.push_str("null"),
+            Json::Bool(b) => out.push_str(&b.to_string()),
+            // JSON has no infinities or NaN
+            Json::Number(n) if !n.is_finite() => out.push_str("null"),
+            Json::Number(n) => out.push_str(&n.to_string()),
+            Json::String(s) => render_string(out, s),
+            Json::Array(elements) if elements.is_empty() => out.push_str("[]"),
+            Json::Array(elements) => {
+                out.push('[');
+                for (i, element) in elements.iter().enumerate() {
+                    out.push_str(if i == 0 { "\n" } else { ",\n" });
+                    indent(out, depth + 1);
+                    element.render(out, depth + 1);
+                }
+                out.push('\n');
+                indent(out, depth);
+                out.push(']');
+            }
+            Json::Object(fields) => {
+                out.push('{');
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    out.push_str(if i == 0 { "\n" } else { ",\n" });
+                    indent(out, depth + 1);
+                    render_string(out, key);
+                    out.push_str(": ");
+                    value.render(out, depth + 1);
+                }
+                out.push('\n');
+                indent(out, depth);
+                out.push('}');
+            }
+        }
+    }
+}
+
+fn indent(out: &mut String, depth: usize) {
+    out.push_str(&"  ".repeat(depth));
+}
+
+fn render_string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn string(s: &str) -> Json {
+    Json::String(s.to_string())
+}
+
+fn line(token: &Token) -> Json {
+    Json::Number(*token.line_number() as f64)
+}
+
+impl AstPrinterJson {
+    /// Prints a whole program as a "Program" node holding its statements.
+    pub fn print(&self, statements: &[Stmt]) -> String {
+        let program = Json::node("Program", vec![("statements", self.statements(statements))]);
+        let mut out = String::new();
+        program.render(&mut out, 0);
+        out.push('\n');
+        out
+    }
+
+    pub fn print_expr(&self, expr: &Expr) -> String {
+        let mut out = String::new();
+        expr.accept(self).render(&mut out, 0);
+        out.push('\n');
+        out
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn default() -> Self {
+        AstPrinterJson {}
+    }
+
+    fn statements(&self, statements: &[Stmt]) -> Json {
+        Json::Array(statements.iter().map(|s| s.accept(self)).collect())
+    }
+
+    fn optional(&self, expr: Option<&Expr>) -> Json {
+        expr.map_or(Json::Null, |e| e.accept(self))
+    }
+
+    fn params(&self, function: &FunctionExpr) -> Json {
+        Json::Array(function.params().iter().map(|p| string(p.lexeme())).collect())
+    }
+
+    fn function(&self, name: &Token, function: &FunctionExpr) -> Json {
+        Json::node(
+            "Function",
+            vec![
+                ("name", string(name.lexeme())),
+                ("line", line(name)),
+                ("params", self.params(function)),
+                ("body", self.statements(function.body())),
+            ],
+        )
+    }
+}
+
+impl Visitor<Json> for AstPrinterJson {
+    fn visit_expr(&self, expr: &Expr) -> Json {
+        match expr {
+            Expr::Assign(e) => Json::node(
+                "Assign",
+                vec![
+                    ("name", string(e.name().lexeme())),
+                    ("line", line(e.name())),
+                    ("value", e.value().accept(self)),
+                ],
+            ),
+            Expr::Binary(e) => Json::node(
+                "Binary",
+                vec![
+                    ("operator", string(e.operator().lexeme())),
+                    ("line", line(e.operator())),
+                    ("lhs", e.lhs().accept(self)),
+                    ("rhs", e.rhs().accept(self)),
+                ],
+            ),
+            Expr::Call(e) => Json::node(
+                "Call",
+                vec![
+                    ("line", line(e.paren())),
+                    ("callee", e.callee().accept(self)),
+                    (
+                        "arguments",
+                        Json::Array(e.arguments().iter().map(|a| a.accept(self)).collect()),
+                    ),
+                ],
+            ),
+            Expr::Function(e) => Json::node(
+                "Function",
+                vec![
+                    ("line", line(e.keyword())),
+                    ("params", self.params(e)),
+                    ("body", self.statements(e.body())),
+                ],
+            ),
+            Expr::Get(e) => Json::node(
+                "Get",
+                vec![
+                    ("name", string(e.name().lexeme())),
+                    ("line", line(e.name())),
+                    ("object", e.object().accept(self)),
+                ],
+            ),
+            Expr::Grouping(e) => {
+                Json::node("Grouping", vec![("expression", e.expression().accept(self))])
+            }
+            Expr::Index(e) => Json::node(
+                "Index",
+                vec![
+                    ("line", line(e.bracket())),
+                    ("object", e.object().accept(self)),
+                    ("index", e.index().accept(self)),
+                ],
+            ),
+            Expr::IndexSet(e) => Json::node(
+                "IndexSet",
+                vec![
+                    ("line", line(e.bracket())),
+                    ("object", e.object().accept(self)),
+                    ("index", e.index().accept(self)),
+                    ("value", e.value().accept(self)),
+                ],
+            ),
+            Expr::List(e) => Json::node(
+                "List",
+                vec![
+                    ("line", line(e.bracket())),
+                    (
+                        "elements",
+                        Json::Array(e.elements().iter().map(|e| e.accept(self)).collect()),
+                    ),
+                ],
+            ),
+            Expr::Literal(e) => {
+                let value = match &**e {
+                    LiteralExpr::Nil => Json::Null,
+                    LiteralExpr::String(s) => string(s),
+                    LiteralExpr::Float(n) => Json::Number(*n),
+                    LiteralExpr::Bool(b) => Json::Bool(*b),
+                };
+                Json::node("Literal", vec![("value", value)])
+            }
+            Expr::Logical(e) => Json::node(
+                "Logical",
+                vec![
+                    ("operator", string(e.operator().lexeme())),
+                    ("line", line(e.operator())),
+                    ("lhs", e.lhs().accept(self)),
+                    ("rhs", e.rhs().accept(self)),
+                ],
+            ),
+            Expr::Map(e) => {
+                let entries = e.entries().iter().map(|(key, value)| {
+                    Json::Object(vec![("key", key.accept(self)), ("value", value.accept(self))])
+                });
+                Json::node(
+                    "Map",
+                    vec![
+                        ("line", line(e.brace())),
+                        ("entries", Json::Array(entries.collect())),
+                    ],
+                )
+            }
+            Expr::Set(e) => Json::node(
+                "Set",
+                vec![
+                    ("name", string(e.name().lexeme())),
+                    ("line", line(e.name())),
+                    ("object", e.object().accept(self)),
+                    ("value", e.value().accept(self)),
+                ],
+            ),
+            Expr::Super(e) => Json::node(
+                "Super",
+                vec![("method", string(e.method().lexeme())), ("line", line(e.method()))],
+            ),
+            Expr::This(e) => Json::node("This", vec![("line", line(e.keyword()))]),
+            Expr::Unary(e) => Json::node(
+                "Unary",
+                vec![
+                    ("operator", string(e.operator().lexeme())),
+                    ("line", line(e.operator())),
+                    ("rhs", e.rhs().accept(self)),
+                ],
+            ),
+            Expr::Variable(e) => Json::node(
+                "Variable",
+                vec![("name", string(e.name().lexeme())), ("line", line(e.name()))],
+            ),
+        }
+    }
+}
+
+impl stmt::Visitor<Json> for AstPrinterJson {
+    fn visit_stmt(&self, stmt: &Stmt) -> Json {
+        match stmt {
+            Stmt::Block(s) => {
+                Json::node("Block", vec![("statements", self.statements(s.statements()))])
+            }
+            Stmt::Break(s) => Json::node("Break", vec![("line", line(s.keyword()))]),
+            Stmt::Class(s) => Json::node(
+                "Class",
+                vec![
+                    ("name", string(s.name().lexeme())),
+                    ("line", line(s.name())),
+                    ("superclass", self.optional(s.superclass())),
+                    (
+                        "methods",
+                        Json::Array(
+                            s.methods()
+                                .iter()
+                                .map(|m| self.function(m.name(), m.function()))
+                                .collect(),
+                        ),
+                    ),
+                ],
+            ),
+            Stmt::Continue(s) => Json::node("Continue", vec![("line", line(s.keyword()))]),
+            Stmt::Expression(s) => {
+                Json::node("Expression", vec![("expression", s.expression().accept(self))])
+            }
+            Stmt::Function(s) => self.function(s.name(), s.function()),
+            Stmt::If(s) => Json::node(
+                "If",
+                vec![
+                    ("condition", s.condition().accept(self)),
+                    ("then_branch", s.then_branch().accept(self)),
+                    ("else_branch", s.else_branch().map_or(Json::Null, |e| e.accept(self))),
+                ],
+            ),
+            Stmt::Import(s) => Json::node(
+                "Import",
+                vec![
+                    ("path", string(s.path())),
+                    ("name", string(s.name().lexeme())),
+                    ("line", line(s.keyword())),
+                ],
+            ),
+            Stmt::Print(s) => {
+                Json::node("Print", vec![("expression", s.expression().accept(self))])
+            }
+            Stmt::Return(s) => Json::node(
+                "Return",
+                vec![("line", line(s.keyword())), ("value", self.optional(s.value()))],
+            ),
+            Stmt::Throw(s) => Json::node(
+                "Throw",
+                vec![("line", line(s.keyword())), ("value", s.value().accept(self))],
+            ),
+            Stmt::Try(s) => {
+                let catch = match (s.catch_name(), s.catch_body()) {
+                    (Some(name), Some(body)) => Json::Object(vec![
+                        ("name", string(name.lexeme())),
+                        ("line", line(name)),
+                        ("body", self.statements(body)),
+                    ]),
+                    _ => Json::Null,
+                };
+                let finally = s.finally_body().map_or(Json::Null, |body| self.statements(body));
+                Json::node(
+                    "Try",
+                    vec![
+                        ("body", self.statements(s.body())),
+                        ("catch", catch),
+                        ("finally", finally),
+                    ],
+                )
+            }
+            Stmt::Var(s) => Json::node(
+                "Var",
+                vec![
+                    ("name", string(s.name().lexeme())),
+                    ("line", line(s.name())),
+                    ("initializer", self.optional(s.initializer())),
+                ],
+            ),
+            Stmt::While(s) => Json::node(
+                "While",
+                vec![
+                    ("condition", s.condition().accept(self)),
+                    ("body", s.body().accept(self)),
+                    ("increment", self.optional(s.increment())),
+                ],
+            ),
+        }
+    }
+}
@@ -1,23 +1,41 @@
-use crate::expr::{BinaryExpr, Expr, GroupingExpr, LiteralExpr, UnaryExpr, Visitor};
+use crate::expr::{
+    AssignExpr, BinaryExpr, CallExpr, Expr, GetExpr, GroupingExpr, IndexExpr, IndexSetExpr,
+    ListExpr, LiteralExpr, LogicalExpr, MapExpr, SetExpr, SuperExpr, UnaryExpr, Visitor,
+};
 
 pub struct AstPrinterRpn;
 
 impl Visitor<String> for AstPrinterRpn {
     fn visit_expr(&self, expr: &Expr) -> String {
         match expr {
-            Expr::Binary(expr) => self.visit_binary_expr(&expr),
-            Expr::Grouping(expr) => self.visit_grouping_expr(&expr),
-            Expr::Literal(expr) => self.visit_literal_expr(&expr),
-            Expr::Unary(expr) => self.visit_unary_expr(&expr),
+            Expr::Assign(expr) => self.visit_assign_expr(expr),
+            Expr::Binary(expr) => self.visit_binary_expr(expr),
+            Expr::Call(expr) => self.visit_call_expr(expr),
+            // a function body is made of statements, which have no postfix form
+            Expr::Function(expr) => format!("fun/{}", expr.params().len()),
+            Expr::Get(expr) => self.visit_get_expr(expr),
+            Expr::Grouping(expr) => self.visit_grouping_expr(expr),
+            Expr::Index(expr) => self.visit_index_expr(expr),
+            Expr::IndexSet(expr) => self.visit_index_set_expr(expr),
+            Expr::List(expr) => self.visit_list_expr(expr),
+            Expr::Literal(expr) => self.visit_literal_expr(expr),
+            Expr::Logical(expr) => self.visit_logical_expr(expr),
+            Expr::Map(expr) => self.visit_map_expr(expr),
+            Expr::Set(expr) => self.visit_set_expr(expr),
+            Expr::Super(expr) => self.visit_super_expr(expr),
+            Expr::This(_) => "this".to_string(),
+            Expr::Unary(expr) => self.visit_unary_expr(expr),
+            Expr::Variable(expr) => expr.name().lexeme().to_string(),
         }
     }
 }
 
 impl AstPrinterRpn {
     pub fn print(&self, expr: Expr) -> String {
-        expr.accept::<String>(self).to_string()
+        expr.accept::<String>(self)
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Self {
         AstPrinterRpn {}
     }
@@ -27,20 +45,49 @@ impl AstPrinterRpn {
         let mut s = String::from("");
         for expr in expressions {
             s.push_str(&expr.accept::<String>(self));
-            s.push_str(" ");
+            s.push(' ');
         }
         s.push_str(name);
-        return s.to_string();
+        s
+    }
+
+    fn visit_assign_expr(&self, expr: &AssignExpr) -> String {
+        format!("{} {} =", expr.value().accept(self), expr.name().lexeme())
     }
 
     fn visit_binary_expr(&self, expr: &BinaryExpr) -> String {
-        self.format_in_rpn(&expr.operator().lexeme(), &[expr.lhs(), expr.rhs()])
+        self.format_in_rpn(expr.operator().lexeme(), &[expr.lhs(), expr.rhs()])
+    }
+
+    // arguments first, then the callee, then the call operator tagged with its argument count
+    fn visit_call_expr(&self, expr: &CallExpr) -> String {
+        let mut expressions = expr.arguments().iter().collect::<Vec<_>>();
+        expressions.push(expr.callee());
+        self.format_in_rpn(&format!("call/{}", expr.arguments().len()), &expressions)
+    }
+
+    fn visit_get_expr(&self, expr: &GetExpr) -> String {
+        format!("{} {} .", expr.object().accept(self), expr.name().lexeme())
     }
 
     fn visit_grouping_expr(&self, expr: &GroupingExpr) -> String {
         expr.expression().accept::<String>(self) // Don't format GroupingExpr, just visit the contained expr
     }
 
+    fn visit_index_expr(&self, expr: &IndexExpr) -> String {
+        self.format_in_rpn("index", &[expr.object(), expr.index()])
+    }
+
+    fn visit_index_set_expr(&self, expr: &IndexSetExpr) -> String {
+        self.format_in_rpn("index=", &[expr.object(), expr.index(), expr.value()])
+    }
+
+    // elements first, then the list constructor tagged with its element count
+    fn visit_list_expr(&self, expr: &ListExpr) -> String {
+        let elements = expr.elements().iter().collect::<Vec<_>>();
+        self.format_in_rpn(&format!("list/{}", elements.len()), &elements)
+    }
+
     fn visit_literal_expr(&self, expr: &LiteralExpr) -> String {
         match expr {
             LiteralExpr::Nil => "nil".to_string(),
@@ -50,7 +97,34 @@ impl AstPrinterRpn {
         }
     }
 
+    fn visit_logical_expr(&self, expr: &LogicalExpr) -> String {
+        self.format_in_rpn(expr.operator().lexeme(), &[expr.lhs(), expr.rhs()])
+    }
+
+    // keys and values alternating, then the map constructor tagged with its entry count
+    fn visit_map_expr(&self, expr: &MapExpr) -> String {
+        let entries = expr
+            .entries()
+            .iter()
+            .flat_map(|(key, value)| vec![key, value])
+            .collect::<Vec<_>>();
+        self.format_in_rpn(&format!("map/{}", expr.entries().len()), &entries)
+    }
+
+    fn visit_set_expr(&self, expr: &SetExpr) -> String {
+        format!(
+            "{} {} {} .=",
+            expr.object().accept(self),
+            expr.value().accept(self),
+            expr.name().lexeme()
+        )
+    }
+
+    fn visit_super_expr(&self, expr: &SuperExpr) -> String {
+        format!("super {} .", expr.method().lexeme())
+    }
+
     fn visit_unary_expr(&self, expr: &UnaryExpr) -> String {
-        self.format_in_rpn(expr.operator().lexeme(), &[&expr.rhs()])
+        self.format_in_rpn(expr.operator().lexeme(), &[expr.rhs()])
     }
 }
@@ -0,0 +1,444 @@
+use std::convert::TryFrom;
+use std::rc::Rc;
+
+use crate::chunk::{Chunk, Constant, OpCode, Prototype};
+use crate::error::RloxError;
+use crate::interner::intern;
+
+/**
+ * The binary format of compiled scripts, as written by `rlox --compile`.
+ *   A header of magic number, format version and checksums is followed by the script's
+ *   prototype, which holds the prototypes of the functions it defines among its constants.
+ *   Integers are little-endian; strings and lists are prefixed with a u32 length.
+ */
+const MAGIC: &[u8; 4] = b"LOXC";
+pub const VERSION: u16 = 1;
+
+// Function prototypes nest no deeper than this, so a crafted file can't exhaust the stack
+const MAX_NESTING: usize = 256;
+
+const TAG_NUMBER: u8 = 0;
+const TAG_STRING: u8 = 1;
+const TAG_FUNCTION: u8 = 2;
+
+/// A script loaded from a compiled file, with the checksum of the source it was compiled from.
+pub struct CompiledScript {
+    pub source_checksum: u64,
+    pub script: Rc<Prototype>,
+}
+
+/// 64-bit FNV-1a hash, used for the checksums of the source and of the file contents.
+pub fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Encodes `script`, compiled from `source`, in the compiled script format.
+pub fn encode(script: &Prototype, source: &str) -> Vec<u8> {
+    let mut body = Vec::new();
+    write_prototype(&mut body, script);
+
+    let mut bytes = MAGIC.to_vec();
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&checksum(source.as_bytes()).to_le_bytes());
+    bytes.extend_from_slice(&checksum(&body).to_le_bytes());
+    bytes.extend(body);
+    bytes
+}
+
+/// Decodes a compiled script, rejecting damaged files rather than handing them to the VM.
+pub fn decode(bytes: &[u8]) -> Result<CompiledScript, RloxError> {
+    let mut reader = Reader { bytes, position: 0 };
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err(invalid("not a compiled Lox script."));
+    }
+    let version = u16::from_le_bytes([reader.byte()?, reader.byte()?]);
+    if version != VERSION {
+        return Err(invalid(format!(
+            "format version {} is not supported, expected {}.",
+            version, VERSION
+        )));
+    }
+    let source_checksum = reader.u64()?;
+    let body_checksum = reader.u64()?;
+    if checksum(&bytes[reader.position..]) != body_checksum {
+        return Err(invalid("checksum mismatch, the file is corrupted."));
+    }
+    let script = reader.prototype(0)?;
+    if reader.position != bytes.len() {
+        return Err(invalid("unexpected data after the script."));
+    }
+    Ok(CompiledScript {
+        source_checksum,
+        script: Rc::new(script),
+    })
+}
+
+impl CompiledScript {
+    /// Rejects the script if it was compiled from something other than `source`, such as an
+    /// older version of it.
+    pub fn check_source(&self, source: &str) -> Result<(), RloxError> {
+        match checksum(source.as_bytes()) == self.source_checksum {
+            true => Ok(()),
+            false => Err(invalid("the source has changed since it was compiled.")),
+        }
+    }
+}
+
+fn invalid(description: impl Into<String>) -> RloxError {
+    RloxError::BytecodeError(description.into())
+}
+
+fn write_u32(out: &mut Vec<u8>, n: usize) {
+    out.extend_from_slice(&(n as u32).to_le_bytes());
+}
+
+fn write_str(out: &mut Vec<u8>, s: &str) {
+    write_u32(out, s.len());
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn write_prototype(out: &mut Vec<u8>, prototype: &Prototype) {
+    match prototype.name() {
+        Some(name) => {
+            out.push(1);
+            write_str(out, name);
+        }
+        None => out.push(0),
+    }
+    write_u32(out, prototype.arity());
+    write_u32(out, prototype.upvalue_count());
+
+    let chunk = prototype.chunk();
+    write_u32(out, chunk.code().len());
+    out.extend_from_slice(chunk.code());
+    // The line table is run-length encoded, as most instructions share a line with their neighbours
+    let mut runs: Vec<(usize, usize)> = Vec::new();
+    for line in chunk.lines() {
+        match runs.last_mut() {
+            Some((run_line, count)) if run_line == line => *count += 1,
+            _ => runs.push((*line, 1)),
+        }
+    }
+    write_u32(out, runs.len());
+    for (line, count) in runs {
+        write_u32(out, line);
+        write_u32(out, count);
+    }
+
+    write_u32(out, chunk.constants().len());
+    for constant in chunk.constants() {
+        match constant {
+            Constant::Number(n) => {
+                out.push(TAG_NUMBER);
+                out.extend_from_slice(&n.to_bits().to_le_bytes());
+            }
+            Constant::String(s) => {
+                out.push(TAG_STRING);
+                write_str(out, s);
+            }
+            Constant::Function(function) => {
+                out.push(TAG_FUNCTION);
+                write_prototype(out, function);
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], RloxError> {
+        let end = self.position.checked_add(len).filter(|end| *end <= self.bytes.len());
+        let end = end.ok_or_else(|| invalid("unexpected end of file."))?;
+        let taken = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(taken)
+    }
+
+    fn byte(&mut self) -> Result<u8, RloxError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<usize, RloxError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize)
+    }
+
+    fn u64(&mut self) -> Result<u64, RloxError> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn string(&mut self) -> Result<String, RloxError> {
+        let len = self.u32()?;
+        let bytes = self.take(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| invalid("string is not valid UTF-8."))
+    }
+
+    fn prototype(&mut self, depth: usize) -> Result<Prototype, RloxError> {
+        if depth > MAX_NESTING {
+            return Err(invalid("functions are nested too deeply."));
+        }
+        let name = match self.byte()? {
+            0 => None,
+            1 => Some(self.string()?),
+            _ => return Err(invalid("malformed function name.")),
+        };
+        let arity = self.u32()?;
+        let upvalue_count = self.u32()?;
+
+        let code_len = self.u32()?;
+        let code = self.take(code_len)?.to_vec();
+        let mut lines = Vec::with_capacity(code.len());
+        for _ in 0..self.u32()? {
+            let line = self.u32()?;
+            let count = self.u32()?;
+            if lines.len() + count > code.len() {
+                return Err(invalid("line table is longer than the code."));
+            }
+            lines.resize(lines.len() + count, line);
+        }
+        if lines.len() != code.len() {
+            return Err(invalid("line table is shorter than the code."));
+        }
+
+        let mut constants = Vec::new();
+        for _ in 0..self.u32()? {
+            let constant = match self.byte()? {
+                TAG_NUMBER => Constant::Number(f64::from_bits(self.u64()?)),
+                TAG_STRING => Constant::String(intern(&self.string()?)),
+                TAG_FUNCTION => Constant::Function(Rc::new(self.prototype(depth + 1)?)),
+                tag => return Err(invalid(format!("unknown constant tag {}.", tag))),
+            };
+            constants.push(constant);
+        }
+
+        let chunk = Chunk::from_parts(code, lines, constants);
+        verify(&chunk, arity, upvalue_count)?;
+        Ok(Prototype::new(name, arity, upvalue_count, chunk))
+    }
+}
+
+/**
+ * Checks that every instruction is known, has all its operands, refers to constants of the
+ *   right kind and jumps to the start of an instruction within the chunk, then that the stack
+ *   stays balanced along every path through it.
+ */
+fn verify(chunk: &Chunk, arity: usize, upvalue_count: usize) -> Result<(), RloxError> {
+    let code = chunk.code();
+    let constants = chunk.constants();
+    let mut instructions = vec![None; code.len()];
+    let mut jumps = Vec::new();
+    let mut offset = 0;
+    while offset < code.len() {
+        let op = OpCode::try_from(code[offset])
+            .map_err(|byte| invalid(format!("unknown opcode {} at {}.", byte, offset)))?;
+        let operands = code.get(offset + 1..).unwrap_or_default();
+        let operand = |index: usize| {
+            operands.get(index).map(|byte| *byte as usize).ok_or_else(|| {
+                invalid(format!("instruction at {} is missing its operands.", offset))
+            })
+        };
+        let len = match op {
+            OpCode::Constant => {
+                match constants.get(operand(0)?) {
+                    Some(Constant::Function(_)) | None => return Err(bad_constant(offset)),
+                    _ => (),
+                }
+                2
+            }
+            OpCode::GetGlobal
+            | OpCode::DefineGlobal
+            | OpCode::SetGlobal
+            | OpCode::GetProperty
+            | OpCode::SetProperty
+            | OpCode::GetSuper
+            | OpCode::Class
+            | OpCode::Method
+            | OpCode::Import => match constants.get(operand(0)?) {
+                Some(Constant::String(_)) => 2,
+                _ => return Err(bad_constant(offset)),
+            },
+            OpCode::GetUpvalue | OpCode::SetUpvalue => match operand(0)? < upvalue_count {
+                true => 2,
+                false => return Err(invalid(format!("bad upvalue at {}.", offset))),
+            },
+            OpCode::GetLocal | OpCode::SetLocal | OpCode::Call => {
+                operand(0)?;
+                2
+            }
+            OpCode::List | OpCode::Map => {
+                operand(1)?;
+                3
+            }
+            OpCode::Jump | OpCode::JumpIfFalse | OpCode::PushCatch | OpCode::PushFinally => {
+                jumps.push((offset, offset + 3 + (operand(0)? << 8 | operand(1)?)));
+                3
+            }
+            OpCode::Loop => {
+                let jump = operand(0)? << 8 | operand(1)?;
+                let target = (offset + 3).checked_sub(jump);
+                jumps.push((offset, target.ok_or_else(|| bad_jump(offset))?));
+                3
+            }
+            OpCode::Closure => match constants.get(operand(0)?) {
+                Some(Constant::Function(function)) => {
+                    let captures = function.upvalue_count();
+                    for i in 0..captures {
+                        let is_local = operand(1 + 2 * i)?;
+                        let index = operand(2 + 2 * i)?;
+                        if is_local > 1 || (is_local == 0 && index >= upvalue_count) {
+                            return Err(invalid(format!("bad capture at {}.", offset)));
+                        }
+                    }
+                    2 + 2 * captures
+                }
+                _ => return Err(bad_constant(offset)),
+            },
+            _ => 1,
+        };
+        instructions[offset] = Some(Instruction { op, len });
+        offset += len;
+    }
+    let is_start = |target: usize| instructions.get(target).is_some_and(Option::is_some);
+    if let Some((offset, _)) = jumps.into_iter().find(|(_, target)| !is_start(*target)) {
+        return Err(bad_jump(offset));
+    }
+    verify_stack(code, &instructions, arity)
+}
+
+#[derive(Clone, Copy)]
+struct Instruction {
+    op: OpCode,
+    len: usize,
+}
+
+/**
+ * Follows every path through the code, counting the values on the stack above the frame's base,
+ *   which start as the callee and its arguments. No instruction may pop more than is there or
+ *   use a local slot past the top, paths meeting at an instruction must agree on the count, as
+ *   they do in compiled code, and none may run past the end of the code.
+ */
+fn verify_stack(
+    code: &[u8],
+    instructions: &[Option<Instruction>],
+    arity: usize,
+) -> Result<(), RloxError> {
+    let mut depths = vec![None; code.len()];
+    let mut pending = vec![(0, arity + 1)];
+    while let Some((offset, depth)) = pending.pop() {
+        let Instruction { op, len } = match instructions.get(offset).copied().flatten() {
+            Some(instruction) => instruction,
+            None => return Err(invalid("execution runs past the end of the code.")),
+        };
+        match depths[offset] {
+            Some(seen) if seen == depth => continue,
+            Some(_) => {
+                let description = format!("stack depth differs between paths to {}.", offset);
+                return Err(invalid(description));
+            }
+            None => depths[offset] = Some(depth),
+        }
+        let operand = |index: usize| code[offset + 1 + index] as usize;
+        let slot = |slot: usize| match slot < depth {
+            true => Ok(()),
+            false => Err(invalid(format!("local slot out of bounds at {}.", offset))),
+        };
+        // How many values the instruction needs on the stack, pops from it and pushes onto it
+        let (needs, pops, pushes) = match op {
+            OpCode::GetLocal => {
+                slot(operand(0))?;
+                (0, 0, 1)
+            }
+            OpCode::SetLocal => {
+                slot(operand(0))?;
+                (1, 0, 0)
+            }
+            OpCode::Closure => {
+                for capture in code[offset + 2..offset + len].chunks(2) {
+                    if capture[0] == 1 {
+                        slot(capture[1] as usize)?;
+                    }
+                }
+                (0, 0, 1)
+            }
+            OpCode::Constant
+            | OpCode::Nil
+            | OpCode::True
+            | OpCode::False
+            | OpCode::GetGlobal
+            | OpCode::GetUpvalue
+            | OpCode::Class
+            | OpCode::Import => (0, 0, 1),
+            OpCode::Pop
+            | OpCode::DefineGlobal
+            | OpCode::Print
+            | OpCode::CloseUpvalue
+            | OpCode::Return
+            | OpCode::Throw
+            | OpCode::Rethrow => (1, 1, 0),
+            OpCode::SetGlobal | OpCode::SetUpvalue | OpCode::JumpIfFalse => (1, 0, 0),
+            OpCode::GetProperty | OpCode::Not | OpCode::Negate => (1, 1, 1),
+            OpCode::SetProperty
+            | OpCode::GetSuper
+            | OpCode::GetIndex
+            | OpCode::Equal
+            | OpCode::Greater
+            | OpCode::GreaterEqual
+            | OpCode::Less
+            | OpCode::LessEqual
+            | OpCode::Add
+            | OpCode::Subtract
+            | OpCode::Multiply
+            | OpCode::Divide => (2, 2, 1),
+            OpCode::SetIndex => (3, 3, 1),
+            OpCode::Inherit | OpCode::Method => (2, 1, 0),
+            OpCode::Call => (operand(0) + 1, operand(0) + 1, 1),
+            OpCode::List => {
+                let count = operand(0) << 8 | operand(1);
+                (count, count, 1)
+            }
+            OpCode::Map => {
+                let count = 2 * (operand(0) << 8 | operand(1));
+                (count, count, 1)
+            }
+            OpCode::Jump
+            | OpCode::Loop
+            | OpCode::PushCatch
+            | OpCode::PushFinally
+            | OpCode::PopHandler => (0, 0, 0),
+        };
+        if depth < needs {
+            return Err(invalid(format!("stack underflow at {}.", offset)));
+        }
+        let depth = depth - pops + pushes;
+        let next = offset + len;
+        let jump = || operand(0) << 8 | operand(1);
+        match op {
+            OpCode::Return | OpCode::Throw | OpCode::Rethrow => (),
+            OpCode::Jump => pending.push((next + jump(), depth)),
+            OpCode::Loop => pending.push((next - jump(), depth)),
+            OpCode::JumpIfFalse => pending.extend(vec![(next + jump(), depth), (next, depth)]),
+            // A handler runs with the caught value pushed where the stack stood
+            OpCode::PushCatch | OpCode::PushFinally => {
+                pending.extend(vec![(next + jump(), depth + 1), (next, depth)])
+            }
+            _ => pending.push((next, depth)),
+        }
+    }
+    Ok(())
+}
+
+fn bad_constant(offset: usize) -> RloxError {
+    invalid(format!("bad constant operand at {}.", offset))
+}
+
+fn bad_jump(offset: usize) -> RloxError {
+    invalid(format!("jump out of bounds at {}.", offset))
+}
@@ -0,0 +1,239 @@
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
+use std::rc::Rc;
+
+use crate::interner::Symbol;
+use crate::value::Value;
+
+/**
+ * Instructions of the bytecode virtual machine.
+ *   Operands follow the opcode byte: constant, slot and argument operands are one byte, jump
+ *   offsets and element counts are two bytes, big-endian.
+ */
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OpCode {
+    Constant,
+    Nil,
+    True,
+    False,
+    Pop,
+    GetLocal,
+    SetLocal,
+    GetGlobal,
+    DefineGlobal,
+    SetGlobal,
+    GetUpvalue,
+    SetUpvalue,
+    GetProperty,
+    SetProperty,
+    GetSuper,
+    GetIndex,
+    SetIndex,
+    Equal,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Not,
+    Negate,
+    Print,
+    Jump,
+    JumpIfFalse,
+    Loop,
+    Call,
+    Closure,
+    CloseUpvalue,
+    Return,
+    Class,
+    Inherit,
+    Method,
+    List,
+    Map,
+    Import,
+    Throw,
+    PushCatch,
+    PushFinally,
+    PopHandler,
+    Rethrow,
+}
+
+impl OpCode {
+    const ALL: [OpCode; 47] = [
+        OpCode::Constant,
+        OpCode::Nil,
+        OpCode::True,
+        OpCode::False,
+        OpCode::Pop,
+        OpCode::GetLocal,
+        OpCode::SetLocal,
+        OpCode::GetGlobal,
+        OpCode::DefineGlobal,
+        OpCode::SetGlobal,
+        OpCode::GetUpvalue,
+        OpCode::SetUpvalue,
+        OpCode::GetProperty,
+        OpCode::SetProperty,
+        OpCode::GetSuper,
+        OpCode::GetIndex,
+        OpCode::SetIndex,
+        OpCode::Equal,
+        OpCode::Greater,
+        OpCode::GreaterEqual,
+        OpCode::Less,
+        OpCode::LessEqual,
+        OpCode::Add,
+        OpCode::Subtract,
+        OpCode::Multiply,
+        OpCode::Divide,
+        OpCode::Not,
+        OpCode::Negate,
+        OpCode::Print,
+        OpCode::Jump,
+        OpCode::JumpIfFalse,
+        OpCode::Loop,
+        OpCode::Call,
+        OpCode::Closure,
+        OpCode::CloseUpvalue,
+        OpCode::Return,
+        OpCode::Class,
+        OpCode::Inherit,
+        OpCode::Method,
+        OpCode::List,
+        OpCode::Map,
+        OpCode::Import,
+        OpCode::Throw,
+        OpCode::PushCatch,
+        OpCode::PushFinally,
+        OpCode::PopHandler,
+        OpCode::Rethrow,
+    ];
+}
+
+impl TryFrom<u8> for OpCode {
+    type Error = u8;
+
+    fn try_from(byte: u8) -> Result<Self, Self::Error> {
+        OpCode::ALL.get(byte as usize).copied().ok_or(byte)
+    }
+}
+
+/// Values known at compile time, referred to by index from the instructions of a chunk.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constant {
+    Number(f64),
+    String(Symbol),
+    Function(Rc<Prototype>),
+}
+
+impl Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Constant::Number(n) => write!(f, "{}", Value::Number(*n)),
+            Constant::String(s) => write!(f, "{}", s),
+            Constant::Function(function) => match function.name() {
+                Some(name) => write!(f, "<fn {}>", name),
+                None => write!(f, "<fn>"),
+            },
+        }
+    }
+}
+
+/**
+ * A compiled sequence of instructions with its constants table.
+ *   `lines` holds the source line of every byte in `code`, for error messages.
+ */
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Chunk {
+    code: Vec<u8>,
+    lines: Vec<usize>,
+    constants: Vec<Constant>,
+}
+
+impl Chunk {
+    pub fn new() -> Self {
+        Chunk::default()
+    }
+
+    pub(crate) fn from_parts(code: Vec<u8>, lines: Vec<usize>, constants: Vec<Constant>) -> Self {
+        Chunk {
+            code,
+            lines,
+            constants,
+        }
+    }
+
+    pub fn code(&self) -> &[u8] {
+        &self.code
+    }
+
+    pub fn lines(&self) -> &[usize] {
+        &self.lines
+    }
+
+    pub fn constants(&self) -> &[Constant] {
+        &self.constants
+    }
+
+    pub fn write(&mut self, byte: u8, line: usize) {
+        self.code.push(byte);
+        self.lines.push(line);
+    }
+
+    pub(crate) fn patch(&mut self, offset: usize, byte: u8) {
+        self.code[offset] = byte;
+    }
+
+    // Reuses the slot of an identical constant, so repeated names don't fill up the table
+    pub fn add_constant(&mut self, constant: Constant) -> usize {
+        let existing = self.constants.iter().position(|c| match (c, &constant) {
+            (Constant::Number(a), Constant::Number(b)) => a.to_bits() == b.to_bits(),
+            (Constant::String(a), Constant::String(b)) => a == b,
+            _ => false,
+        });
+        existing.unwrap_or_else(|| {
+            self.constants.push(constant);
+            self.constants.len() - 1
+        })
+    }
+}
+
+/// A compiled function: the code the VM runs whenever a closure over it is called.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Prototype {
+    name: Option<String>, // None for the top level of a script and for lambdas
+    arity: usize,
+    upvalue_count: usize,
+    chunk: Chunk,
+}
+
+impl Prototype {
+    pub fn new(name: Option<String>, arity: usize, upvalue_count: usize, chunk: Chunk) -> Self {
+        Prototype {
+            name,
+            arity,
+            upvalue_count,
+            chunk,
+        }
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+
+    pub fn upvalue_count(&self) -> usize {
+        self.upvalue_count
+    }
+
+    pub fn chunk(&self) -> &Chunk {
+        &self.chunk
+    }
+}
@@ -0,0 +1,120 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::error::RloxError;
+use crate::function::{Callable, LoxFunction};
+use crate::interner::Symbol;
+use crate::interpreter::Interpreter;
+use crate::token::Token;
+use crate::value::Value;
+
+pub struct LoxClass {
+    name: String,
+    superclass: Option<Rc<LoxClass>>,
+    methods: HashMap<Symbol, Rc<LoxFunction>>,
+}
+
+impl LoxClass {
+    pub(crate) fn new(
+        name: &str,
+        superclass: Option<Rc<LoxClass>>,
+        methods: HashMap<Symbol, Rc<LoxFunction>>,
+    ) -> Self {
+        LoxClass {
+            name: name.to_string(),
+            superclass,
+            methods,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn find_method(&self, name: impl Into<Symbol>) -> Option<Rc<LoxFunction>> {
+        self.lookup_method(&name.into())
+    }
+
+    fn lookup_method(&self, name: &Symbol) -> Option<Rc<LoxFunction>> {
+        match self.methods.get(name) {
+            Some(method) => Some(Rc::clone(method)),
+            None => self
+                .superclass
+                .as_ref()
+                .and_then(|superclass| superclass.lookup_method(name)),
+        }
+    }
+}
+
+/**
+ * Calling a class constructs a new instance and runs its "init" method, if any, on it.
+ *   The class is passed in as an `Rc` so the instance can point back to it.
+ */
+pub(crate) fn instantiate(
+    class: &Rc<LoxClass>,
+    interpreter: &Interpreter,
+    arguments: Vec<Value>,
+) -> Result<Value, RloxError> {
+    let instance = Rc::new(RefCell::new(LoxInstance::new(Rc::clone(class))));
+    if let Some(initializer) = class.find_method("init") {
+        initializer
+            .bind(Rc::clone(&instance))
+            .call(interpreter, arguments)?;
+    }
+    Ok(Value::Instance(instance))
+}
+
+pub(crate) fn class_arity(class: &LoxClass) -> usize {
+    class
+        .find_method("init")
+        .map_or(0, |initializer| initializer.arity())
+}
+
+pub struct LoxInstance {
+    class: Rc<LoxClass>,
+    fields: HashMap<Symbol, Value>,
+}
+
+impl LoxInstance {
+    pub(crate) fn new(class: Rc<LoxClass>) -> Self {
+        LoxInstance {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    pub fn class(&self) -> &Rc<LoxClass> {
+        &self.class
+    }
+
+    // Fields shadow methods; methods are bound to the instance on the way out
+    pub(crate) fn get(
+        instance: &Rc<RefCell<LoxInstance>>,
+        name: &Token,
+    ) -> Result<Value, RloxError> {
+        if let Some(value) = instance.borrow().fields.get(name.symbol()) {
+            return Ok(value.clone());
+        }
+        let method = instance.borrow().class.find_method(name.symbol());
+        match method {
+            Some(method) => Ok(Value::Function(Rc::new(method.bind(Rc::clone(instance))))),
+            None => Err(RloxError::runtime_at(
+                *name.line_number(),
+                format!("Undefined property '{}'.", name.lexeme()),
+            )),
+        }
+    }
+
+    pub(crate) fn set(&mut self, name: &Token, value: Value) {
+        self.set_field(name.symbol(), value);
+    }
+
+    pub fn field(&self, name: impl Into<Symbol>) -> Option<&Value> {
+        self.fields.get(&name.into())
+    }
+
+    pub(crate) fn set_field(&mut self, name: impl Into<Symbol>, value: Value) {
+        self.fields.insert(name.into(), value);
+    }
+}
@@ -0,0 +1,795 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use crate::chunk::{Chunk, Constant, OpCode, Prototype};
+use crate::error::{RloxError, RloxSyntaxError};
+use crate::expr::{self, Expr, FunctionExpr, LiteralExpr};
+use crate::interner::intern;
+use crate::stmt::{self, ClassStmt, Stmt, TryStmt, WhileStmt};
+use crate::token::{Token, TokenType};
+
+const MAX_SLOTS: usize = u8::MAX as usize + 1;
+
+#[derive(Clone, Copy, PartialEq)]
+enum FunctionKind {
+    Script,
+    Function,
+    Method,
+    Initializer,
+}
+
+struct Local {
+    name: String,
+    depth: usize,
+    is_captured: bool,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Upvalue {
+    index: u8,
+    is_local: bool,
+}
+
+struct Loop {
+    scope_depth: usize,
+    try_depth: usize, // try statements already open when the loop started
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+}
+
+struct Try {
+    handlers: usize, // handlers this statement has pushed and not popped yet
+    finally: Option<Vec<Stmt>>,
+}
+
+// Everything the compiler tracks for the function whose body it is in
+struct FunctionState {
+    kind: FunctionKind,
+    chunk: Chunk,
+    locals: Vec<Local>,
+    upvalues: Vec<Upvalue>,
+    scope_depth: usize,
+    loops: Vec<Loop>,
+    tries: Vec<Try>,
+}
+
+impl FunctionState {
+    fn new(kind: FunctionKind) -> Self {
+        // Slot zero holds the function being called, or the receiver inside methods
+        let receiver = match kind {
+            FunctionKind::Method | FunctionKind::Initializer => "this",
+            _ => "",
+        };
+        FunctionState {
+            kind,
+            chunk: Chunk::new(),
+            locals: vec![Local {
+                name: receiver.to_string(),
+                depth: 0,
+                is_captured: false,
+            }],
+            upvalues: Vec::new(),
+            scope_depth: 0,
+            loops: Vec::new(),
+            tries: Vec::new(),
+        }
+    }
+}
+
+/**
+ * Compiles a resolved program into bytecode for the VM, in a single pass over the tree.
+ *   Like the Resolver, it keeps its state in cells so it can implement the shared visitors.
+ *   Variables are resolved again here, into stack slots and upvalues, in the manner of clox.
+ */
+pub struct Compiler {
+    functions: RefCell<Vec<FunctionState>>, // the innermost function being compiled is last
+    line: Cell<usize>,                      // line of the last token seen, for the code emitted next
+    errors: RefCell<Vec<RloxSyntaxError>>,
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Compiler::new()
+    }
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Compiler {
+            functions: RefCell::new(vec![FunctionState::new(FunctionKind::Script)]),
+            line: Cell::new(1),
+            errors: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Compiles a script into the prototype of its top level.
+    pub fn compile(self, statements: &[Stmt]) -> Result<Rc<Prototype>, RloxError> {
+        for statement in statements {
+            statement.accept(self.as_stmt_visitor());
+        }
+        self.emit_op(OpCode::Nil);
+        self.emit_op(OpCode::Return);
+        let errors = self.errors.into_inner();
+        if !errors.is_empty() {
+            return Err(RloxError::SyntaxErrors(errors));
+        }
+        let script = self.functions.into_inner().pop().expect("the script is never popped");
+        Ok(Rc::new(Prototype::new(None, 0, 0, script.chunk)))
+    }
+
+    fn as_stmt_visitor(&self) -> &dyn stmt::Visitor<()> {
+        self
+    }
+
+    fn compile_expr(&self, expr: &Expr) {
+        expr.accept(self)
+    }
+
+    fn compile_stmt(&self, stmt: &Stmt) {
+        stmt.accept(self)
+    }
+
+    fn at_line(&self, token: &Token) {
+        self.line.set(*token.line_number());
+    }
+
+    fn error(&self, msg: &str) {
+        self.errors.borrow_mut().push(RloxSyntaxError {
+            line_number: self.line.get(),
+            description: msg.to_string(),
+        });
+    }
+
+    fn with_function<T>(&self, f: impl FnOnce(&mut FunctionState) -> T) -> T {
+        f(self.functions.borrow_mut().last_mut().unwrap())
+    }
+
+    fn emit_byte(&self, byte: u8) {
+        let line = self.line.get();
+        self.with_function(|function| function.chunk.write(byte, line));
+    }
+
+    fn emit_op(&self, op: OpCode) {
+        self.emit_byte(op as u8);
+    }
+
+    fn emit_op_with(&self, op: OpCode, operand: u8) {
+        self.emit_op(op);
+        self.emit_byte(operand);
+    }
+
+    fn emit_op_with_u16(&self, op: OpCode, operand: usize) {
+        if operand > u16::MAX as usize {
+            self.error("Too many elements in a literal.");
+        }
+        self.emit_op(op);
+        self.emit_byte((operand >> 8) as u8);
+        self.emit_byte(operand as u8);
+    }
+
+    fn code_len(&self) -> usize {
+        self.with_function(|function| function.chunk.code().len())
+    }
+
+    fn make_constant(&self, constant: Constant) -> u8 {
+        let index = self.with_function(|function| function.chunk.add_constant(constant));
+        if index >= MAX_SLOTS {
+            self.error("Too many constants in one chunk.");
+            return 0;
+        }
+        index as u8
+    }
+
+    fn identifier_constant(&self, name: &str) -> u8 {
+        self.make_constant(Constant::String(intern(name)))
+    }
+
+    fn emit_constant(&self, constant: Constant) {
+        let index = self.make_constant(constant);
+        self.emit_op_with(OpCode::Constant, index);
+    }
+
+    // Emits a forward jump with a placeholder offset, returning where to patch it
+    fn emit_jump(&self, op: OpCode) -> usize {
+        self.emit_op(op);
+        self.emit_byte(0xff);
+        self.emit_byte(0xff);
+        self.code_len() - 2
+    }
+
+    fn patch_jump(&self, offset: usize) {
+        let jump = self.code_len() - offset - 2;
+        if jump > u16::MAX as usize {
+            self.error("Too much code to jump over.");
+        }
+        self.with_function(|function| {
+            function.chunk.patch(offset, (jump >> 8) as u8);
+            function.chunk.patch(offset + 1, jump as u8);
+        });
+    }
+
+    fn emit_loop(&self, start: usize) {
+        self.emit_op(OpCode::Loop);
+        let offset = self.code_len() - start + 2;
+        if offset > u16::MAX as usize {
+            self.error("Loop body too large.");
+        }
+        self.emit_byte((offset >> 8) as u8);
+        self.emit_byte(offset as u8);
+    }
+
+    fn begin_scope(&self) {
+        self.with_function(|function| function.scope_depth += 1);
+    }
+
+    fn end_scope(&self) {
+        let depth = self.with_function(|function| {
+            function.scope_depth -= 1;
+            function.scope_depth
+        });
+        self.discard_locals(depth, true);
+    }
+
+    /**
+     * Emits the code that drops the locals deeper than `depth` from the stack.
+     *   They are only forgotten by the compiler when their scope really ends, a break or
+     *   continue leaves them declared for the code that follows it.
+     */
+    fn discard_locals(&self, depth: usize, forget: bool) {
+        let captured = self.with_function(|function| {
+            let count = function.locals.iter().rev().take_while(|l| l.depth > depth).count();
+            let start = function.locals.len() - count;
+            let captured = function.locals[start..]
+                .iter()
+                .rev()
+                .map(|l| l.is_captured)
+                .collect::<Vec<_>>();
+            if forget {
+                function.locals.truncate(start);
+            }
+            captured
+        });
+        for is_captured in captured {
+            match is_captured {
+                true => self.emit_op(OpCode::CloseUpvalue),
+                false => self.emit_op(OpCode::Pop),
+            }
+        }
+    }
+
+    fn add_local(&self, name: &str) {
+        let too_many = self.with_function(|function| {
+            function.locals.push(Local {
+                name: name.to_string(),
+                depth: function.scope_depth,
+                is_captured: false,
+            });
+            function.locals.len() > MAX_SLOTS
+        });
+        if too_many {
+            self.error("Too many local variables in function.");
+        }
+    }
+
+    fn is_global_scope(&self) -> bool {
+        self.with_function(|function| function.scope_depth == 0)
+            && self.functions.borrow().len() == 1
+    }
+
+    /**
+     * Stores the value on top of the stack in a new variable.
+     *   At the top level of a script that is a global, anywhere else the value simply stays on
+     *   the stack as a local.
+     */
+    fn define_variable(&self, name: &Token) {
+        match self.is_global_scope() {
+            true => {
+                let constant = self.identifier_constant(name.lexeme());
+                self.emit_op_with(OpCode::DefineGlobal, constant);
+            }
+            false => self.add_local(name.lexeme()),
+        }
+    }
+
+    fn resolve_local(&self, level: usize, name: &str) -> Option<u8> {
+        let functions = self.functions.borrow();
+        functions[level]
+            .locals
+            .iter()
+            .rposition(|local| local.name == name)
+            .map(|slot| slot as u8)
+    }
+
+    // Finds `name` in an enclosing function, threading an upvalue through every function between
+    fn resolve_upvalue(&self, level: usize, name: &str) -> Option<u8> {
+        if level == 0 {
+            return None;
+        }
+        if let Some(slot) = self.resolve_local(level - 1, name) {
+            self.functions.borrow_mut()[level - 1].locals[slot as usize].is_captured = true;
+            return Some(self.add_upvalue(level, slot, true));
+        }
+        let index = self.resolve_upvalue(level - 1, name)?;
+        Some(self.add_upvalue(level, index, false))
+    }
+
+    fn add_upvalue(&self, level: usize, index: u8, is_local: bool) -> u8 {
+        let upvalue = Upvalue { index, is_local };
+        let mut functions = self.functions.borrow_mut();
+        let upvalues = &mut functions[level].upvalues;
+        if let Some(existing) = upvalues.iter().position(|u| *u == upvalue) {
+            return existing as u8;
+        }
+        if upvalues.len() == MAX_SLOTS {
+            drop(functions);
+            self.error("Too many closure variables in function.");
+            return 0;
+        }
+        upvalues.push(upvalue);
+        (upvalues.len() - 1) as u8
+    }
+
+    fn variable_ops(&self, name: &str) -> (OpCode, OpCode, u8) {
+        let level = self.functions.borrow().len() - 1;
+        if let Some(slot) = self.resolve_local(level, name) {
+            (OpCode::GetLocal, OpCode::SetLocal, slot)
+        } else if let Some(index) = self.resolve_upvalue(level, name) {
+            (OpCode::GetUpvalue, OpCode::SetUpvalue, index)
+        } else {
+            let constant = self.identifier_constant(name);
+            (OpCode::GetGlobal, OpCode::SetGlobal, constant)
+        }
+    }
+
+    fn get_variable(&self, name: &str) {
+        let (get, _, operand) = self.variable_ops(name);
+        self.emit_op_with(get, operand);
+    }
+
+    fn set_variable(&self, name: &str) {
+        let (_, set, operand) = self.variable_ops(name);
+        self.emit_op_with(set, operand);
+    }
+
+    // Compiles a function body as a prototype, and emits the code creating a closure over it
+    fn function(&self, kind: FunctionKind, name: Option<&Token>, declaration: &FunctionExpr) {
+        self.at_line(declaration.keyword());
+        self.functions.borrow_mut().push(FunctionState::new(kind));
+        self.begin_scope();
+        for param in declaration.params() {
+            self.add_local(param.lexeme());
+        }
+        for statement in declaration.body() {
+            self.compile_stmt(statement);
+        }
+        self.emit_implicit_return();
+        let function = self.functions.borrow_mut().pop().unwrap();
+
+        let prototype = Prototype::new(
+            name.map(|name| name.lexeme().to_string()),
+            declaration.params().len(),
+            function.upvalues.len(),
+            function.chunk,
+        );
+        self.at_line(declaration.keyword());
+        let constant = self.make_constant(Constant::Function(Rc::new(prototype)));
+        self.emit_op_with(OpCode::Closure, constant);
+        for upvalue in function.upvalues {
+            self.emit_byte(upvalue.is_local as u8);
+            self.emit_byte(upvalue.index);
+        }
+    }
+
+    fn emit_implicit_return(&self) {
+        match self.with_function(|function| function.kind) {
+            FunctionKind::Initializer => self.emit_op_with(OpCode::GetLocal, 0),
+            _ => self.emit_op(OpCode::Nil),
+        }
+        self.emit_op(OpCode::Return);
+    }
+
+    /**
+     * Emits what has to happen before a jump out of the try statements from `try_depth` on:
+     *   their handlers are popped and their finally blocks run, innermost first. While a
+     *   finally block is inlined, its own try statement counts as closed.
+     */
+    fn exit_tries(&self, try_depth: usize) {
+        let depth = self.with_function(|function| function.tries.len());
+        for i in (try_depth..depth).rev() {
+            let (handlers, finally) = self.with_function(|function| {
+                let t = &function.tries[i];
+                (t.handlers, t.finally.clone())
+            });
+            for _ in 0..handlers {
+                self.emit_op(OpCode::PopHandler);
+            }
+            if let Some(finally) = finally {
+                let closed = self.with_function(|function| function.tries.split_off(i));
+                self.block(&finally);
+                self.with_function(|function| function.tries.extend(closed));
+            }
+        }
+    }
+
+    fn block(&self, statements: &[Stmt]) {
+        self.begin_scope();
+        for statement in statements {
+            self.compile_stmt(statement);
+        }
+        self.end_scope();
+    }
+
+    fn loop_jump(&self, keyword: &Token) {
+        self.at_line(keyword);
+        let (scope_depth, try_depth) = self.with_function(|function| {
+            let innermost = function.loops.last().expect("the parser rejects jumps outside loops");
+            (innermost.scope_depth, innermost.try_depth)
+        });
+        self.exit_tries(try_depth);
+        self.discard_locals(scope_depth, false);
+        let jump = self.emit_jump(OpCode::Jump);
+        self.with_function(|function| {
+            let innermost = function.loops.last_mut().unwrap();
+            match keyword.token_type() {
+                TokenType::Break => innermost.breaks.push(jump),
+                _ => innermost.continues.push(jump),
+            }
+        });
+    }
+
+    fn visit_while_stmt(&self, stmt: &WhileStmt) {
+        let start = self.code_len();
+        self.compile_expr(stmt.condition());
+        let exit = self.emit_jump(OpCode::JumpIfFalse);
+        self.emit_op(OpCode::Pop);
+
+        self.with_function(|function| {
+            function.loops.push(Loop {
+                scope_depth: function.scope_depth,
+                try_depth: function.tries.len(),
+                breaks: Vec::new(),
+                continues: Vec::new(),
+            })
+        });
+        self.compile_stmt(stmt.body());
+        let innermost = self.with_function(|function| function.loops.pop().unwrap());
+
+        for jump in innermost.continues {
+            self.patch_jump(jump);
+        }
+        if let Some(increment) = stmt.increment() {
+            self.compile_expr(increment);
+            self.emit_op(OpCode::Pop);
+        }
+        self.emit_loop(start);
+        self.patch_jump(exit);
+        self.emit_op(OpCode::Pop);
+        for jump in innermost.breaks {
+            self.patch_jump(jump);
+        }
+    }
+
+    fn visit_class_stmt(&self, stmt: &ClassStmt) {
+        let name = stmt.name();
+        self.at_line(name);
+        let constant = self.identifier_constant(name.lexeme());
+        self.emit_op_with(OpCode::Class, constant);
+        self.define_variable(name);
+
+        if let Some(superclass) = stmt.superclass() {
+            self.compile_expr(superclass);
+            self.begin_scope();
+            self.add_local("super");
+            self.get_variable(name.lexeme());
+            self.at_line(name);
+            self.emit_op(OpCode::Inherit);
+        }
+
+        self.get_variable(name.lexeme());
+        for method in stmt.methods() {
+            let kind = match method.name().lexeme() {
+                "init" => FunctionKind::Initializer,
+                _ => FunctionKind::Method,
+            };
+            self.function(kind, Some(method.name()), method.function());
+            let constant = self.identifier_constant(method.name().lexeme());
+            self.emit_op_with(OpCode::Method, constant);
+        }
+        self.emit_op(OpCode::Pop);
+
+        if stmt.superclass().is_some() {
+            self.end_scope();
+        }
+    }
+
+    /**
+     * The try body runs under a catch handler, and both it and the catch clause under a finally
+     *   handler. The VM unwinds to the handler's stack height and pushes the caught value, which
+     *   becomes the catch variable, or the pending error that the finally block rethrows.
+     */
+    fn visit_try_stmt(&self, stmt: &TryStmt) {
+        let finally_handler = stmt.finally_body().map(|_| self.emit_jump(OpCode::PushFinally));
+        let catch_handler = stmt.catch_body().map(|_| self.emit_jump(OpCode::PushCatch));
+        self.with_function(|function| {
+            function.tries.push(Try {
+                handlers: finally_handler.iter().count() + catch_handler.iter().count(),
+                finally: stmt.finally_body().map(<[Stmt]>::to_vec),
+            })
+        });
+        self.block(stmt.body());
+
+        if let (Some(handler), Some(name), Some(body)) =
+            (catch_handler, stmt.catch_name(), stmt.catch_body())
+        {
+            self.emit_op(OpCode::PopHandler);
+            self.set_try_handlers(finally_handler.iter().count());
+            let end = self.emit_jump(OpCode::Jump);
+            self.patch_jump(handler);
+            self.at_line(name);
+            self.begin_scope();
+            self.add_local(name.lexeme());
+            for statement in body {
+                self.compile_stmt(statement);
+            }
+            self.end_scope();
+            self.patch_jump(end);
+        }
+
+        self.with_function(|function| function.tries.pop());
+        if let (Some(handler), Some(finally)) = (finally_handler, stmt.finally_body()) {
+            self.emit_op(OpCode::PopHandler);
+            self.block(finally);
+            let end = self.emit_jump(OpCode::Jump);
+
+            self.patch_jump(handler);
+            self.begin_scope();
+            self.add_local(""); // the pending error
+            for statement in finally {
+                self.compile_stmt(statement);
+            }
+            self.emit_op(OpCode::Rethrow);
+            // Nothing runs after the rethrow, so the pending error needs no pop
+            self.with_function(|function| {
+                function.scope_depth -= 1;
+                function.locals.pop();
+            });
+            self.patch_jump(end);
+        }
+    }
+
+    fn set_try_handlers(&self, handlers: usize) {
+        self.with_function(|function| function.tries.last_mut().unwrap().handlers = handlers);
+    }
+
+    fn visit_return_stmt(&self, keyword: &Token, value: Option<&Expr>) {
+        self.at_line(keyword);
+        match value {
+            Some(value) => self.compile_expr(value),
+            None => self.emit_implicit_return_value(),
+        }
+        self.at_line(keyword);
+        let has_finally = self.with_function(|function| {
+            function.tries.iter().any(|t| t.finally.is_some())
+        });
+        if has_finally {
+            // The return value waits in a slot of its own while the finally blocks run
+            self.add_local("");
+            self.exit_tries(0);
+            self.with_function(|function| function.locals.pop());
+        }
+        self.emit_op(OpCode::Return);
+    }
+
+    fn emit_implicit_return_value(&self) {
+        match self.with_function(|function| function.kind) {
+            FunctionKind::Initializer => self.emit_op_with(OpCode::GetLocal, 0),
+            _ => self.emit_op(OpCode::Nil),
+        }
+    }
+}
+
+impl expr::Visitor<()> for Compiler {
+    fn visit_expr(&self, expr: &Expr) {
+        match expr {
+            Expr::Assign(e) => {
+                self.compile_expr(e.value());
+                self.at_line(e.name());
+                self.set_variable(e.name().lexeme());
+            }
+            Expr::Binary(e) => {
+                self.compile_expr(e.lhs());
+                self.compile_expr(e.rhs());
+                self.at_line(e.operator());
+                match e.operator().token_type() {
+                    TokenType::Plus => self.emit_op(OpCode::Add),
+                    TokenType::Minus => self.emit_op(OpCode::Subtract),
+                    TokenType::Star => self.emit_op(OpCode::Multiply),
+                    TokenType::Slash => self.emit_op(OpCode::Divide),
+                    TokenType::Greater => self.emit_op(OpCode::Greater),
+                    TokenType::GreaterEqual => self.emit_op(OpCode::GreaterEqual),
+                    TokenType::Less => self.emit_op(OpCode::Less),
+                    TokenType::LessEqual => self.emit_op(OpCode::LessEqual),
+                    TokenType::EqualEqual => self.emit_op(OpCode::Equal),
+                    TokenType::BangEqual => {
+                        self.emit_op(OpCode::Equal);
+                        self.emit_op(OpCode::Not);
+                    }
+                    _ => self.error("Unknown binary operator."),
+                }
+            }
+            Expr::Call(e) => {
+                self.compile_expr(e.callee());
+                for argument in e.arguments() {
+                    self.compile_expr(argument);
+                }
+                self.at_line(e.paren());
+                self.emit_op_with(OpCode::Call, e.arguments().len() as u8);
+            }
+            Expr::Function(e) => self.function(FunctionKind::Function, None, e),
+            Expr::Get(e) => {
+                self.compile_expr(e.object());
+                self.at_line(e.name());
+                let constant = self.identifier_constant(e.name().lexeme());
+                self.emit_op_with(OpCode::GetProperty, constant);
+            }
+            Expr::Grouping(e) => self.compile_expr(e.expression()),
+            Expr::Index(e) => {
+                self.compile_expr(e.object());
+                self.compile_expr(e.index());
+                self.at_line(e.bracket());
+                self.emit_op(OpCode::GetIndex);
+            }
+            Expr::IndexSet(e) => {
+                self.compile_expr(e.object());
+                self.compile_expr(e.index());
+                self.compile_expr(e.value());
+                self.at_line(e.bracket());
+                self.emit_op(OpCode::SetIndex);
+            }
+            Expr::List(e) => {
+                for element in e.elements() {
+                    self.compile_expr(element);
+                }
+                self.at_line(e.bracket());
+                self.emit_op_with_u16(OpCode::List, e.elements().len());
+            }
+            Expr::Literal(e) => match &**e {
+                LiteralExpr::Nil => self.emit_op(OpCode::Nil),
+                LiteralExpr::Bool(true) => self.emit_op(OpCode::True),
+                LiteralExpr::Bool(false) => self.emit_op(OpCode::False),
+                LiteralExpr::Float(n) => self.emit_constant(Constant::Number(*n)),
+                LiteralExpr::String(s) => {
+                    self.emit_constant(Constant::String(intern(s)))
+                }
+            },
+            Expr::Logical(e) => {
+                self.compile_expr(e.lhs());
+                self.at_line(e.operator());
+                match e.operator().token_type() {
+                    TokenType::Or => {
+                        let else_jump = self.emit_jump(OpCode::JumpIfFalse);
+                        let end_jump = self.emit_jump(OpCode::Jump);
+                        self.patch_jump(else_jump);
+                        self.emit_op(OpCode::Pop);
+                        self.compile_expr(e.rhs());
+                        self.patch_jump(end_jump);
+                    }
+                    _ => {
+                        let end_jump = self.emit_jump(OpCode::JumpIfFalse);
+                        self.emit_op(OpCode::Pop);
+                        self.compile_expr(e.rhs());
+                        self.patch_jump(end_jump);
+                    }
+                }
+            }
+            Expr::Map(e) => {
+                for (key, value) in e.entries() {
+                    self.compile_expr(key);
+                    self.compile_expr(value);
+                }
+                self.at_line(e.brace());
+                self.emit_op_with_u16(OpCode::Map, e.entries().len());
+            }
+            Expr::Set(e) => {
+                self.compile_expr(e.object());
+                self.compile_expr(e.value());
+                self.at_line(e.name());
+                let constant = self.identifier_constant(e.name().lexeme());
+                self.emit_op_with(OpCode::SetProperty, constant);
+            }
+            Expr::Super(e) => {
+                self.at_line(e.keyword());
+                self.get_variable("this");
+                self.get_variable("super");
+                self.at_line(e.method());
+                let constant = self.identifier_constant(e.method().lexeme());
+                self.emit_op_with(OpCode::GetSuper, constant);
+            }
+            Expr::This(e) => {
+                self.at_line(e.keyword());
+                self.get_variable("this");
+            }
+            Expr::Unary(e) => {
+                self.compile_expr(e.rhs());
+                self.at_line(e.operator());
+                match e.operator().token_type() {
+                    TokenType::Minus => self.emit_op(OpCode::Negate),
+                    _ => self.emit_op(OpCode::Not),
+                }
+            }
+            Expr::Variable(e) => {
+                self.at_line(e.name());
+                self.get_variable(e.name().lexeme());
+            }
+        }
+    }
+}
+
+impl stmt::Visitor<()> for Compiler {
+    fn visit_stmt(&self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Block(s) => self.block(s.statements()),
+            Stmt::Break(s) => self.loop_jump(s.keyword()),
+            Stmt::Class(s) => self.visit_class_stmt(s),
+            Stmt::Continue(s) => self.loop_jump(s.keyword()),
+            Stmt::Expression(s) => {
+                self.compile_expr(s.expression());
+                self.emit_op(OpCode::Pop);
+            }
+            Stmt::Function(s) => {
+                // Declared before the body is compiled, so the function can refer to itself
+                match self.is_global_scope() {
+                    true => {
+                        self.function(FunctionKind::Function, Some(s.name()), s.function());
+                        self.define_variable(s.name());
+                    }
+                    false => {
+                        self.add_local(s.name().lexeme());
+                        self.function(FunctionKind::Function, Some(s.name()), s.function());
+                    }
+                }
+            }
+            Stmt::If(s) => {
+                self.compile_expr(s.condition());
+                let then_jump = self.emit_jump(OpCode::JumpIfFalse);
+                self.emit_op(OpCode::Pop);
+                self.compile_stmt(s.then_branch());
+                let else_jump = self.emit_jump(OpCode::Jump);
+                self.patch_jump(then_jump);
+                self.emit_op(OpCode::Pop);
+                if let Some(else_branch) = s.else_branch() {
+                    self.compile_stmt(else_branch);
+                }
+                self.patch_jump(else_jump);
+            }
+            Stmt::Import(s) => {
+                self.at_line(s.keyword());
+                let constant = self.make_constant(Constant::String(intern(s.path())));
+                self.emit_op_with(OpCode::Import, constant);
+                self.define_variable(s.name());
+            }
+            Stmt::Print(s) => {
+                self.compile_expr(s.expression());
+                self.emit_op(OpCode::Print);
+            }
+            Stmt::Return(s) => self.visit_return_stmt(s.keyword(), s.value()),
+            Stmt::Throw(s) => {
+                self.at_line(s.keyword());
+                self.compile_expr(s.value());
+                self.at_line(s.keyword());
+                self.emit_op(OpCode::Throw);
+            }
+            Stmt::Try(s) => self.visit_try_stmt(s),
+            Stmt::Var(s) => {
+                self.at_line(s.name());
+                match s.initializer() {
+                    Some(initializer) => self.compile_expr(initializer),
+                    None => self.emit_op(OpCode::Nil),
+                }
+                self.at_line(s.name());
+                self.define_variable(s.name());
+            }
+            Stmt::While(s) => self.visit_while_stmt(s),
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::convert::TryFrom;
+use std::fmt::Write;
+
+use crate::chunk::{Chunk, Constant, OpCode, Prototype};
+
+/**
+ * Renders a compiled script and every function nested in it, one chunk after another.
+ *   The script comes first, followed by the functions in the order they appear in its constants.
+ */
+pub fn disassemble(script: &Prototype) -> String {
+    let mut out = String::new();
+    disassemble_prototype(&mut out, script, "<script>");
+    out
+}
+
+fn disassemble_prototype(out: &mut String, prototype: &Prototype, name: &str) {
+    out.push_str(&disassemble_chunk(prototype.chunk(), name));
+    for constant in prototype.chunk().constants() {
+        if let Constant::Function(function) = constant {
+            disassemble_prototype(out, function, function.name().unwrap_or("<fn>"));
+        }
+    }
+}
+
+/**
+ * Renders one chunk in the format of clox's `disassembleChunk`:
+ *   the byte offset, the source line (or `|` when unchanged), the opcode and its operands.
+ */
+pub fn disassemble_chunk(chunk: &Chunk, name: &str) -> String {
+    let mut out = format!("== {} ==\n", name);
+    let mut offset = 0;
+    while offset < chunk.code().len() {
+        offset = disassemble_instruction(&mut out, chunk, offset);
+    }
+    out
+}
+
+/// Renders the instruction at `offset`, returning the offset of the next one.
+pub fn disassemble_instruction(out: &mut String, chunk: &Chunk, offset: usize) -> usize {
+    let _ = write!(out, "{:04} ", offset);
+    let line = chunk.lines()[offset];
+    if offset > 0 && line == chunk.lines()[offset - 1] {
+        out.push_str("   | ");
+    } else {
+        let _ = write!(out, "{:4} ", line);
+    }
+
+    let op = match OpCode::try_from(chunk.code()[offset]) {
+        Ok(op) => op,
+        Err(byte) => {
+            let _ = writeln!(out, "Unknown opcode {}", byte);
+            return offset + 1;
+        }
+    };
+    let name = op_name(op);
+    match op {
+        OpCode::Constant
+        | OpCode::GetGlobal
+        | OpCode::DefineGlobal
+        | OpCode::SetGlobal
+        | OpCode::GetProperty
+        | OpCode::SetProperty
+        | OpCode::GetSuper
+        | OpCode::Class
+        | OpCode::Method
+        | OpCode::Import => constant_instruction(out, name, chunk, offset),
+        OpCode::GetLocal
+        | OpCode::SetLocal
+        | OpCode::GetUpvalue
+        | OpCode::SetUpvalue
+        | OpCode::Call => byte_instruction(out, name, chunk, offset),
+        OpCode::List | OpCode::Map => {
+            let _ = writeln!(out, "{:<16} {:4}", name, read_u16(chunk, offset + 1));
+            offset + 3
+        }
+        OpCode::Jump | OpCode::JumpIfFalse | OpCode::PushCatch | OpCode::PushFinally => {
+            jump_instruction(out, name, true, chunk, offset)
+        }
+        OpCode::Loop => jump_instruction(out, name, false, chunk, offset),
+        OpCode::Closure => closure_instruction(out, chunk, offset),
+        _ => {
+            let _ = writeln!(out, "{}", name);
+            offset + 1
+        }
+    }
+}
+
+fn constant_instruction(out: &mut String, name: &str, chunk: &Chunk, offset: usize) -> usize {
+    let constant = chunk.code()[offset + 1];
+    let _ = writeln!(
+        out,
+        "{:<16} {:4} '{}'",
+        name, constant, chunk.constants()[constant as usize]
+    );
+    offset + 2
+}
+
+fn byte_instruction(out: &mut String, name: &str, chunk: &Chunk, offset: usize) -> usize {
+    let _ = writeln!(out, "{:<16} {:4}", name, chunk.code()[offset + 1]);
+    offset + 2
+}
+
+fn jump_instruction(
+    out: &mut String,
+    name: &str,
+    forward: bool,
+    chunk: &Chunk,
+    offset: usize,
+) -> usize {
+    let jump = read_u16(chunk, offset + 1);
+    let target = match forward {
+        true => offset + 3 + jump,
+        false => (offset + 3).saturating_sub(jump),
+    };
+    let _ = writeln!(out, "{:<16} {:4} -> {}", name, offset, target);
+    offset + 3
+}
+
+// A closure is followed by an (is_local, index) pair for each variable it captures
+fn closure_instruction(out: &mut String, chunk: &Chunk, offset: usize) -> usize {
+    let constant = chunk.code()[offset + 1];
+    let function = &chunk.constants()[constant as usize];
+    let _ = writeln!(out, "{:<16} {:4} {}", "OP_CLOSURE", constant, function);
+    let mut offset = offset + 2;
+    if let Constant::Function(function) = function {
+        for _ in 0..function.upvalue_count() {
+            let is_local = chunk.code()[offset];
+            let index = chunk.code()[offset + 1];
+            let kind = if is_local == 1 { "local" } else { "upvalue" };
+            let _ = writeln!(out, "{:04}      |                     {} {}", offset, kind, index);
+            offset += 2;
+        }
+    }
+    offset
+}
+
+fn read_u16(chunk: &Chunk, offset: usize) -> usize {
+    (chunk.code()[offset] as usize) << 8 | chunk.code()[offset + 1] as usize
+}
+
+fn op_name(op: OpCode) -> &'static str {
+    match op {
+        OpCode::Constant => "OP_CONSTANT",
+        OpCode::Nil => "OP_NIL",
+        OpCode::True => "OP_TRUE",
+        OpCode::False => "OP_FALSE",
+        OpCode::Pop => "OP_POP",
+        OpCode::GetLocal => "OP_GET_LOCAL",
+        OpCode::SetLocal => "OP_SET_LOCAL",
+        OpCode::GetGlobal => "OP_GET_GLOBAL",
+        OpCode::DefineGlobal => "OP_DEFINE_GLOBAL",
+        OpCode::SetGlobal => "OP_SET_GLOBAL",
+        OpCode::GetUpvalue => "OP_GET_UPVALUE",
+        OpCode::SetUpvalue => "OP_SET_UPVALUE",
+        OpCode::GetProperty => "OP_GET_PROPERTY",
+        OpCode::SetProperty => "OP_SET_PROPERTY",
+        OpCode::GetSuper => "OP_GET_SUPER",
+        OpCode::GetIndex => "OP_GET_INDEX",
+        OpCode::SetIndex => "OP_SET_INDEX",
+        OpCode::Equal => "OP_EQUAL",
+        OpCode::Greater => "OP_GREATER",
+        OpCode::GreaterEqual => "OP_GREATER_EQUAL",
+        OpCode::Less => "OP_LESS",
+        OpCode::LessEqual => "OP_LESS_EQUAL",
+        OpCode::Add => "OP_ADD",
+        OpCode::Subtract => "OP_SUBTRACT",
+        OpCode::Multiply => "OP_MULTIPLY",
+        OpCode::Divide => "OP_DIVIDE",
+        OpCode::Not => "OP_NOT",
+        OpCode::Negate => "OP_NEGATE",
+        OpCode::Print => "OP_PRINT",
+        OpCode::Jump => "OP_JUMP",
+        OpCode::JumpIfFalse => "OP_JUMP_IF_FALSE",
+        OpCode::Loop => "OP_LOOP",
+        OpCode::Call => "OP_CALL",
+        OpCode::Closure => "OP_CLOSURE",
+        OpCode::CloseUpvalue => "OP_CLOSE_UPVALUE",
+        OpCode::Return => "OP_RETURN",
+        OpCode::Class => "OP_CLASS",
+        OpCode::Inherit => "OP_INHERIT",
+        OpCode::Method => "OP_METHOD",
+        OpCode::List => "OP_LIST",
+        OpCode::Map => "OP_MAP",
+        OpCode::Import => "OP_IMPORT",
+        OpCode::Throw => "OP_THROW",
+        OpCode::PushCatch => "OP_PUSH_CATCH",
+        OpCode::PushFinally => "OP_PUSH_FINALLY",
+        OpCode::PopHandler => "OP_POP_HANDLER",
+        OpCode::Rethrow => "OP_RETHROW",
+    }
+}
@@ -0,0 +1,117 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::error::RloxError;
+use crate::interner::{intern, Symbol};
+use crate::token::Token;
+use crate::value::Value;
+
+/**
+ * Variable bindings for one scope, chained to the scope that encloses it.
+ *   Lookups by name walk the chain outwards; lookups with a resolved distance jump straight to the right scope.
+ */
+#[derive(Clone, Default)]
+pub struct Environment {
+    values: HashMap<Symbol, Value>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+impl Environment {
+    pub fn new() -> Rc<RefCell<Environment>> {
+        Rc::new(RefCell::new(Environment::default()))
+    }
+
+    pub fn new_enclosed(enclosing: &Rc<RefCell<Environment>>) -> Rc<RefCell<Environment>> {
+        Rc::new(RefCell::new(Environment {
+            values: HashMap::new(),
+            enclosing: Some(Rc::clone(enclosing)),
+        }))
+    }
+
+    pub fn define(&mut self, name: impl Into<Symbol>, value: Value) {
+        self.values.insert(name.into(), value);
+    }
+
+    pub fn get(&self, name: &Token) -> Result<Value, RloxError> {
+        match self.values.get(name.symbol()) {
+            Some(value) => Ok(value.clone()),
+            None => match &self.enclosing {
+                Some(enclosing) => enclosing.borrow().get(name),
+                None => Err(undefined_variable(name)),
+            },
+        }
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<Value> {
+        self.values.get(&intern(name)).cloned()
+    }
+
+    // The variables of this scope alone, in no particular order
+    pub(crate) fn bindings(&self) -> impl Iterator<Item = (&Symbol, &Value)> {
+        self.values.iter()
+    }
+
+    pub fn assign(&mut self, name: &Token, value: Value) -> Result<(), RloxError> {
+        match self.values.get_mut(name.symbol()) {
+            Some(slot) => {
+                *slot = value;
+                Ok(())
+            }
+            None => match &self.enclosing {
+                Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+                None => Err(undefined_variable(name)),
+            },
+        }
+    }
+
+    // The outermost scope of the chain, which holds the globals of the file the code came from
+    pub fn global(env: &Rc<RefCell<Environment>>) -> Rc<RefCell<Environment>> {
+        let mut environment = Rc::clone(env);
+        loop {
+            let enclosing = environment.borrow().enclosing.clone();
+            match enclosing {
+                Some(enclosing) => environment = enclosing,
+                None => return environment,
+            }
+        }
+    }
+
+    pub fn get_at(
+        env: &Rc<RefCell<Environment>>,
+        distance: usize,
+        name: impl Into<Symbol>,
+    ) -> Option<Value> {
+        Environment::ancestor(env, distance)
+            .borrow()
+            .values
+            .get(&name.into())
+            .cloned()
+    }
+
+    pub fn assign_at(env: &Rc<RefCell<Environment>>, distance: usize, name: &Token, value: Value) {
+        Environment::ancestor(env, distance)
+            .borrow_mut()
+            .define(name.symbol(), value);
+    }
+
+    fn ancestor(env: &Rc<RefCell<Environment>>, distance: usize) -> Rc<RefCell<Environment>> {
+        let mut environment = Rc::clone(env);
+        for _ in 0..distance {
+            let enclosing = environment
+                .borrow()
+                .enclosing
+                .clone()
+                .expect("resolver computed a distance deeper than the environment chain");
+            environment = enclosing;
+        }
+        environment
+    }
+}
+
+fn undefined_variable(name: &Token) -> RloxError {
+    RloxError::runtime_at(
+        *name.line_number(),
+        format!("Undefined variable '{}'.", name.lexeme()),
+    )
+}
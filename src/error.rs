@@ -1,9 +1,16 @@
 use std::fmt::{self, Display, Formatter};
 
+use crate::value::Value;
+
 #[derive(Debug)]
 pub enum RloxError {
     IoError(std::io::Error),
     SyntaxError(RloxSyntaxError),
+    SyntaxErrors(Vec<RloxSyntaxError>),
+    RuntimeError(Box<RloxRuntimeError>), // boxed so results stay small on deep call stacks
+    BytecodeError(String), // a compiled script file that is corrupted or from another version
+    UnsupportedError(String), // a program the requested output has no way to express
+    Exit(i32), // raised by the `exit` native, left to the host to act on
 }
 
 #[derive(Debug)]
@@ -22,6 +29,101 @@ impl Display for RloxSyntaxError {
     }
 }
 
+/**
+ * An error raised by the interpreter or a value thrown by a Lox `throw` statement.
+ *   Both can be caught by a Lox `try` statement. The stack trace is recorded as the error leaves
+ *   the innermost Lox function, so it stays empty for errors raised outside of any function.
+ */
+#[derive(Debug)]
+pub struct RloxRuntimeError {
+    pub(crate) line_number: Option<usize>, // None until the error is attributed to a call site
+    pub(crate) description: String,
+    pub(crate) thrown: Option<Value>,
+    pub(crate) stack_trace: Vec<String>, // innermost frame first, e.g. "[line 3] in f()"
+}
+
+impl RloxRuntimeError {
+    pub fn line_number(&self) -> Option<usize> {
+        self.line_number
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// The value given to `throw`, or None for errors raised by the interpreter itself.
+    pub fn thrown(&self) -> Option<&Value> {
+        self.thrown.as_ref()
+    }
+
+    pub fn stack_trace(&self) -> &[String] {
+        &self.stack_trace
+    }
+}
+
+impl Display for RloxRuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.description)?;
+        match self.line_number {
+            // Runs of the same frame, as deep recursion leaves, are shown once with their count
+            _ if !self.stack_trace.is_empty() => {
+                let mut frames = self.stack_trace.iter().peekable();
+                while let Some(frame) = frames.next() {
+                    write!(f, "\n{}", frame)?;
+                    let mut repeats = 0;
+                    while frames.next_if_eq(&frame).is_some() {
+                        repeats += 1;
+                    }
+                    if repeats > 0 {
+                        write!(f, "\n[previous frame repeated {} more times]", repeats)?;
+                    }
+                }
+                Ok(())
+            }
+            Some(line_number) => write!(f, "\n[line {}]", line_number),
+            None => Ok(()),
+        }
+    }
+}
+
+impl RloxError {
+    /// A runtime error not tied to a source line yet, as raised by native functions and value conversions.
+    pub fn runtime(description: impl Into<String>) -> Self {
+        Self::RuntimeError(Box::new(RloxRuntimeError {
+            line_number: None,
+            description: description.into(),
+            thrown: None,
+            stack_trace: Vec::new(),
+        }))
+    }
+
+    pub(crate) fn runtime_at(line_number: usize, description: impl Into<String>) -> Self {
+        Self::RuntimeError(Box::new(RloxRuntimeError {
+            line_number: Some(line_number),
+            description: description.into(),
+            thrown: None,
+            stack_trace: Vec::new(),
+        }))
+    }
+
+    pub(crate) fn thrown(line_number: usize, description: impl Into<String>, value: Value) -> Self {
+        Self::RuntimeError(Box::new(RloxRuntimeError {
+            line_number: Some(line_number),
+            description: description.into(),
+            thrown: Some(value),
+            stack_trace: Vec::new(),
+        }))
+    }
+
+    // Attributes a runtime error raised without a location to the given line
+    pub(crate) fn or_at_line(mut self, line_number: usize) -> Self {
+        if let Self::RuntimeError(e) = &mut self {
+            e.line_number.get_or_insert(line_number);
+        }
+        self
+    }
+}
+
 impl From<std::io::Error> for RloxError {
     fn from(e: std::io::Error) -> Self {
         Self::IoError(e)
@@ -40,6 +142,17 @@ impl Display for RloxError {
         match self {
             IoError(e) => write!(f, "error reading script: {}", e),
             SyntaxError(e) => write!(f, "Syntax error: {}", e),
+            SyntaxErrors(errors) => {
+                let errors = errors
+                    .iter()
+                    .map(|e| format!("Syntax error: {}", e).trim_end().to_string())
+                    .collect::<Vec<_>>();
+                write!(f, "{}", errors.join("\n"))
+            }
+            RuntimeError(e) => write!(f, "Runtime error: {}", e),
+            BytecodeError(description) => write!(f, "Invalid bytecode file: {}", description),
+            UnsupportedError(description) => write!(f, "Unsupported: {}", description),
+            Exit(code) => write!(f, "exit({})", code),
         }
     }
 }
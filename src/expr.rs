@@ -1,19 +1,126 @@
+// Constructors return the wrapping `Expr` rather than `Self` so nodes can be nested directly.
+#![allow(clippy::new_ret_no_self)]
+
+use std::hash::{Hash, Hasher};
 use std::rc::Rc;
 
-use crate::token::{Literal, Token};
+use crate::stmt::Stmt;
+use crate::token::Token;
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Expr {
+    Assign(Rc<AssignExpr>),
     Binary(Rc<BinaryExpr>),
+    Call(Rc<CallExpr>),
+    Function(Rc<FunctionExpr>),
+    Get(Rc<GetExpr>),
     Grouping(Rc<GroupingExpr>),
+    Index(Rc<IndexExpr>),
+    IndexSet(Rc<IndexSetExpr>),
+    List(Rc<ListExpr>),
     Literal(Rc<LiteralExpr>),
+    Logical(Rc<LogicalExpr>),
+    Map(Rc<MapExpr>),
+    Set(Rc<SetExpr>),
+    Super(Rc<SuperExpr>),
+    This(Rc<ThisExpr>),
     Unary(Rc<UnaryExpr>),
+    Variable(Rc<VariableExpr>),
 }
 
 impl Expr {
-    pub(crate) fn accept<T>(&self, visitor: &dyn Visitor<T>) -> T {
+    pub fn accept<T>(&self, visitor: &dyn Visitor<T>) -> T {
         visitor.visit_expr(self)
     }
+
+    // Identity of the node, stable for as long as the tree is alive. Used to key side tables.
+    pub(crate) fn id(&self) -> usize {
+        match self {
+            Expr::Assign(e) => Rc::as_ptr(e) as *const () as usize,
+            Expr::Binary(e) => Rc::as_ptr(e) as *const () as usize,
+            Expr::Call(e) => Rc::as_ptr(e) as *const () as usize,
+            Expr::Function(e) => Rc::as_ptr(e) as *const () as usize,
+            Expr::Get(e) => Rc::as_ptr(e) as *const () as usize,
+            Expr::Grouping(e) => Rc::as_ptr(e) as *const () as usize,
+            Expr::Index(e) => Rc::as_ptr(e) as *const () as usize,
+            Expr::IndexSet(e) => Rc::as_ptr(e) as *const () as usize,
+            Expr::List(e) => Rc::as_ptr(e) as *const () as usize,
+            Expr::Literal(e) => Rc::as_ptr(e) as *const () as usize,
+            Expr::Logical(e) => Rc::as_ptr(e) as *const () as usize,
+            Expr::Map(e) => Rc::as_ptr(e) as *const () as usize,
+            Expr::Set(e) => Rc::as_ptr(e) as *const () as usize,
+            Expr::Super(e) => Rc::as_ptr(e) as *const () as usize,
+            Expr::This(e) => Rc::as_ptr(e) as *const () as usize,
+            Expr::Unary(e) => Rc::as_ptr(e) as *const () as usize,
+            Expr::Variable(e) => Rc::as_ptr(e) as *const () as usize,
+        }
+    }
+
+    /**
+     * Compares two trees like `==`, but ignores the lines their tokens come from.
+     *   Useful to check a tree against one parsed from differently laid out source, or read back
+     *   from another representation that doesn't keep locations.
+     */
+    pub fn eq_ignoring_locations(&self, other: &Expr) -> bool {
+        match (self, other) {
+            (Expr::Assign(a), Expr::Assign(b)) => {
+                a.name.eq_ignoring_location(&b.name) && a.value.eq_ignoring_locations(&b.value)
+            }
+            (Expr::Binary(a), Expr::Binary(b)) => {
+                a.operator.eq_ignoring_location(&b.operator)
+                    && a.lhs.eq_ignoring_locations(&b.lhs)
+                    && a.rhs.eq_ignoring_locations(&b.rhs)
+            }
+            (Expr::Call(a), Expr::Call(b)) => {
+                a.callee.eq_ignoring_locations(&b.callee)
+                    && all_eq(&a.arguments, &b.arguments, Expr::eq_ignoring_locations)
+            }
+            (Expr::Function(a), Expr::Function(b)) => a.eq_ignoring_locations(b),
+            (Expr::Get(a), Expr::Get(b)) => {
+                a.object.eq_ignoring_locations(&b.object) && a.name.eq_ignoring_location(&b.name)
+            }
+            (Expr::Grouping(a), Expr::Grouping(b)) => {
+                a.expression.eq_ignoring_locations(&b.expression)
+            }
+            (Expr::Index(a), Expr::Index(b)) => {
+                a.object.eq_ignoring_locations(&b.object) && a.index.eq_ignoring_locations(&b.index)
+            }
+            (Expr::IndexSet(a), Expr::IndexSet(b)) => {
+                a.object.eq_ignoring_locations(&b.object)
+                    && a.index.eq_ignoring_locations(&b.index)
+                    && a.value.eq_ignoring_locations(&b.value)
+            }
+            (Expr::List(a), Expr::List(b)) => {
+                all_eq(&a.elements, &b.elements, Expr::eq_ignoring_locations)
+            }
+            (Expr::Literal(a), Expr::Literal(b)) => a == b,
+            (Expr::Logical(a), Expr::Logical(b)) => {
+                a.operator.eq_ignoring_location(&b.operator)
+                    && a.lhs.eq_ignoring_locations(&b.lhs)
+                    && a.rhs.eq_ignoring_locations(&b.rhs)
+            }
+            (Expr::Map(a), Expr::Map(b)) => all_eq(&a.entries, &b.entries, |(k1, v1), (k2, v2)| {
+                k1.eq_ignoring_locations(k2) && v1.eq_ignoring_locations(v2)
+            }),
+            (Expr::Set(a), Expr::Set(b)) => {
+                a.object.eq_ignoring_locations(&b.object)
+                    && a.name.eq_ignoring_location(&b.name)
+                    && a.value.eq_ignoring_locations(&b.value)
+            }
+            (Expr::Super(a), Expr::Super(b)) => a.method.eq_ignoring_location(&b.method),
+            (Expr::This(_), Expr::This(_)) => true,
+            (Expr::Unary(a), Expr::Unary(b)) => {
+                a.operator.eq_ignoring_location(&b.operator) && a.rhs.eq_ignoring_locations(&b.rhs)
+            }
+            (Expr::Variable(a), Expr::Variable(b)) => a.name.eq_ignoring_location(&b.name),
+            _ => false,
+        }
+    }
+}
+
+// Compares two slices element by element with `eq`
+pub(crate) fn all_eq<T>(a: &[T], b: &[T], eq: impl Fn(&T, &T) -> bool) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(a, b)| eq(a, b))
 }
 
 /**
@@ -21,10 +128,31 @@ impl Expr {
  *   Matching on Expr will force implementer to implement a match arm for every Expr variant that exists.
  *   Adding new Expr variances will conveniently raise syntax errors in existing implementations that do not provide match arms for those Expr variants.
  */
-pub(crate) trait Visitor<T> {
+pub trait Visitor<T> {
     fn visit_expr(&self, expr: &Expr) -> T;
 }
 
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct AssignExpr {
+    name: Token,
+    value: Expr,
+}
+
+impl AssignExpr {
+    pub fn new(name: Token, value: Expr) -> Expr {
+        Expr::Assign(Rc::new(AssignExpr { name, value }))
+    }
+
+    pub fn name(&self) -> &Token {
+        &self.name
+    }
+
+    pub fn value(&self) -> &Expr {
+        &self.value
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
 pub struct BinaryExpr {
     operator: Token,
     lhs: Expr,
@@ -36,19 +164,116 @@ impl BinaryExpr {
         Expr::Binary(Rc::new(BinaryExpr { operator, lhs, rhs }))
     }
 
-    pub(crate) fn operator(&self) -> &Token {
+    pub fn operator(&self) -> &Token {
         &self.operator
     }
 
-    pub(crate) fn lhs(&self) -> &Expr {
+    pub fn lhs(&self) -> &Expr {
         &self.lhs
     }
 
-    pub(crate) fn rhs(&self) -> &Expr {
+    pub fn rhs(&self) -> &Expr {
         &self.rhs
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct CallExpr {
+    callee: Expr,
+    paren: Token, // closing paren, kept for the line number of runtime errors
+    arguments: Vec<Expr>,
+}
+
+impl CallExpr {
+    pub fn new(callee: Expr, paren: Token, arguments: Vec<Expr>) -> Expr {
+        Expr::Call(Rc::new(CallExpr {
+            callee,
+            paren,
+            arguments,
+        }))
+    }
+
+    pub fn callee(&self) -> &Expr {
+        &self.callee
+    }
+
+    pub fn paren(&self) -> &Token {
+        &self.paren
+    }
+
+    pub fn arguments(&self) -> &[Expr] {
+        &self.arguments
+    }
+}
+
+/**
+ * Parameters and body of a function.
+ *   On its own it is an anonymous function (lambda) expression, `FunctionStmt` adds a name to it.
+ */
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct FunctionExpr {
+    keyword: Token, // `fun`, or the name of a declared function
+    params: Vec<Token>,
+    body: Vec<Stmt>,
+}
+
+impl FunctionExpr {
+    pub fn new(keyword: Token, params: Vec<Token>, body: Vec<Stmt>) -> Expr {
+        Expr::Function(FunctionExpr::new_declaration(keyword, params, body))
+    }
+
+    pub(crate) fn new_declaration(
+        keyword: Token,
+        params: Vec<Token>,
+        body: Vec<Stmt>,
+    ) -> Rc<FunctionExpr> {
+        Rc::new(FunctionExpr {
+            keyword,
+            params,
+            body,
+        })
+    }
+
+    pub fn keyword(&self) -> &Token {
+        &self.keyword
+    }
+
+    pub fn params(&self) -> &[Token] {
+        &self.params
+    }
+
+    pub fn body(&self) -> &[Stmt] {
+        &self.body
+    }
+
+    pub fn eq_ignoring_locations(&self, other: &FunctionExpr) -> bool {
+        self.keyword.eq_ignoring_location(&other.keyword)
+            && all_eq(&self.params, &other.params, Token::eq_ignoring_location)
+            && all_eq(&self.body, &other.body, Stmt::eq_ignoring_locations)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct GetExpr {
+    object: Expr,
+    name: Token,
+}
+
+impl GetExpr {
+    pub fn new(object: Expr, name: Token) -> Expr {
+        Expr::Get(Rc::new(GetExpr { object, name }))
+    }
+
+    pub fn object(&self) -> &Expr {
+        &self.object
+    }
+
+    pub fn name(&self) -> &Token {
+        &self.name
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
 pub struct GroupingExpr {
     expression: Expr,
 }
@@ -58,16 +283,101 @@ impl GroupingExpr {
         Expr::Grouping(Rc::new(GroupingExpr { expression }))
     }
 
-    pub(crate) fn expression(&self) -> &Expr {
+    pub fn expression(&self) -> &Expr {
         &self.expression
     }
 }
 
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct IndexExpr {
+    object: Expr,
+    bracket: Token, // closing bracket, kept for the line number of runtime errors
+    index: Expr,
+}
+
+impl IndexExpr {
+    pub fn new(object: Expr, bracket: Token, index: Expr) -> Expr {
+        Expr::Index(Rc::new(IndexExpr {
+            object,
+            bracket,
+            index,
+        }))
+    }
+
+    pub fn object(&self) -> &Expr {
+        &self.object
+    }
+
+    pub fn bracket(&self) -> &Token {
+        &self.bracket
+    }
+
+    pub fn index(&self) -> &Expr {
+        &self.index
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct IndexSetExpr {
+    object: Expr,
+    bracket: Token,
+    index: Expr,
+    value: Expr,
+}
+
+impl IndexSetExpr {
+    pub fn new(object: Expr, bracket: Token, index: Expr, value: Expr) -> Expr {
+        Expr::IndexSet(Rc::new(IndexSetExpr {
+            object,
+            bracket,
+            index,
+            value,
+        }))
+    }
+
+    pub fn object(&self) -> &Expr {
+        &self.object
+    }
+
+    pub fn bracket(&self) -> &Token {
+        &self.bracket
+    }
+
+    pub fn index(&self) -> &Expr {
+        &self.index
+    }
+
+    pub fn value(&self) -> &Expr {
+        &self.value
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct ListExpr {
+    bracket: Token, // opening bracket
+    elements: Vec<Expr>,
+}
+
+impl ListExpr {
+    pub fn new(bracket: Token, elements: Vec<Expr>) -> Expr {
+        Expr::List(Rc::new(ListExpr { bracket, elements }))
+    }
+
+    pub fn bracket(&self) -> &Token {
+        &self.bracket
+    }
+
+    pub fn elements(&self) -> &[Expr] {
+        &self.elements
+    }
+}
+
+#[derive(Clone, Debug)]
 pub enum LiteralExpr {
     Nil,
     String(String),
-    Float(f32),
-    Bool(bool)
+    Float(f64),
+    Bool(bool),
 }
 
 impl LiteralExpr {
@@ -76,6 +386,144 @@ impl LiteralExpr {
     }
 }
 
+// Numbers are compared by their bits like in `token::Literal`, so `-0` and `0` are different
+// literals while `NaN`, which constant folding can produce, is equal to itself
+impl PartialEq for LiteralExpr {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (LiteralExpr::Nil, LiteralExpr::Nil) => true,
+            (LiteralExpr::String(a), LiteralExpr::String(b)) => a == b,
+            (LiteralExpr::Float(a), LiteralExpr::Float(b)) => a.to_bits() == b.to_bits(),
+            (LiteralExpr::Bool(a), LiteralExpr::Bool(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for LiteralExpr {}
+
+impl Hash for LiteralExpr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            LiteralExpr::Nil => (),
+            LiteralExpr::String(s) => s.hash(state),
+            LiteralExpr::Float(n) => n.to_bits().hash(state),
+            LiteralExpr::Bool(b) => b.hash(state),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct LogicalExpr {
+    operator: Token,
+    lhs: Expr,
+    rhs: Expr,
+}
+
+impl LogicalExpr {
+    pub fn new(operator: Token, lhs: Expr, rhs: Expr) -> Expr {
+        Expr::Logical(Rc::new(LogicalExpr { operator, lhs, rhs }))
+    }
+
+    pub fn operator(&self) -> &Token {
+        &self.operator
+    }
+
+    pub fn lhs(&self) -> &Expr {
+        &self.lhs
+    }
+
+    pub fn rhs(&self) -> &Expr {
+        &self.rhs
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct MapExpr {
+    brace: Token, // opening brace
+    entries: Vec<(Expr, Expr)>,
+}
+
+impl MapExpr {
+    pub fn new(brace: Token, entries: Vec<(Expr, Expr)>) -> Expr {
+        Expr::Map(Rc::new(MapExpr { brace, entries }))
+    }
+
+    pub fn brace(&self) -> &Token {
+        &self.brace
+    }
+
+    pub fn entries(&self) -> &[(Expr, Expr)] {
+        &self.entries
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct SetExpr {
+    object: Expr,
+    name: Token,
+    value: Expr,
+}
+
+impl SetExpr {
+    pub fn new(object: Expr, name: Token, value: Expr) -> Expr {
+        Expr::Set(Rc::new(SetExpr {
+            object,
+            name,
+            value,
+        }))
+    }
+
+    pub fn object(&self) -> &Expr {
+        &self.object
+    }
+
+    pub fn name(&self) -> &Token {
+        &self.name
+    }
+
+    pub fn value(&self) -> &Expr {
+        &self.value
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct SuperExpr {
+    keyword: Token,
+    method: Token,
+}
+
+impl SuperExpr {
+    pub fn new(keyword: Token, method: Token) -> Expr {
+        Expr::Super(Rc::new(SuperExpr { keyword, method }))
+    }
+
+    pub fn keyword(&self) -> &Token {
+        &self.keyword
+    }
+
+    pub fn method(&self) -> &Token {
+        &self.method
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct ThisExpr {
+    keyword: Token,
+}
+
+impl ThisExpr {
+    pub fn new(keyword: Token) -> Expr {
+        Expr::This(Rc::new(ThisExpr { keyword }))
+    }
+
+    pub fn keyword(&self) -> &Token {
+        &self.keyword
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
 pub struct UnaryExpr {
     operator: Token,
     rhs: Expr,
@@ -86,10 +534,26 @@ impl UnaryExpr {
         Expr::Unary(Rc::new(UnaryExpr { operator, rhs }))
     }
 
-    pub(crate) fn operator(&self) -> &Token {
+    pub fn operator(&self) -> &Token {
         &self.operator
     }
-    pub(crate) fn rhs(&self) -> &Expr {
+
+    pub fn rhs(&self) -> &Expr {
         &self.rhs
     }
 }
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct VariableExpr {
+    name: Token,
+}
+
+impl VariableExpr {
+    pub fn new(name: Token) -> Expr {
+        Expr::Variable(Rc::new(VariableExpr { name }))
+    }
+
+    pub fn name(&self) -> &Token {
+        &self.name
+    }
+}
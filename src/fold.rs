@@ -0,0 +1,299 @@
+use std::rc::Rc;
+
+use crate::expr::{
+    AssignExpr, BinaryExpr, CallExpr, Expr, FunctionExpr, GetExpr, GroupingExpr, IndexExpr,
+    IndexSetExpr, ListExpr, LogicalExpr, MapExpr, SetExpr, UnaryExpr,
+};
+use crate::stmt::{
+    BlockStmt, ClassStmt, ExpressionStmt, FunctionStmt, IfStmt, PrintStmt, ReturnStmt, Stmt,
+    ThrowStmt, TryStmt, VarStmt, WhileStmt,
+};
+
+/**
+ * A rewrite of expressions and statements into new trees, for optimizers and desugarers.
+ *   Every method defaults to the matching `fold_*` function, which rebuilds the node from its
+ *   folded children, so implementers override only the nodes they rewrite. Leaves are shared with
+ *   the original tree rather than copied. The result must be resolved again before it is run.
+ */
+pub trait Fold {
+    fn fold_expr(&mut self, expr: &Expr) -> Expr {
+        fold_expr(self, expr)
+    }
+
+    fn fold_stmt(&mut self, stmt: &Stmt) -> Stmt {
+        fold_stmt(self, stmt)
+    }
+
+    fn fold_assign_expr(&mut self, expr: &AssignExpr) -> Expr {
+        fold_assign_expr(self, expr)
+    }
+
+    fn fold_binary_expr(&mut self, expr: &BinaryExpr) -> Expr {
+        fold_binary_expr(self, expr)
+    }
+
+    fn fold_call_expr(&mut self, expr: &CallExpr) -> Expr {
+        fold_call_expr(self, expr)
+    }
+
+    fn fold_function_expr(&mut self, expr: &FunctionExpr) -> Rc<FunctionExpr> {
+        fold_function_expr(self, expr)
+    }
+
+    fn fold_get_expr(&mut self, expr: &GetExpr) -> Expr {
+        fold_get_expr(self, expr)
+    }
+
+    fn fold_grouping_expr(&mut self, expr: &GroupingExpr) -> Expr {
+        fold_grouping_expr(self, expr)
+    }
+
+    fn fold_index_expr(&mut self, expr: &IndexExpr) -> Expr {
+        fold_index_expr(self, expr)
+    }
+
+    fn fold_index_set_expr(&mut self, expr: &IndexSetExpr) -> Expr {
+        fold_index_set_expr(self, expr)
+    }
+
+    fn fold_list_expr(&mut self, expr: &ListExpr) -> Expr {
+        fold_list_expr(self, expr)
+    }
+
+    fn fold_logical_expr(&mut self, expr: &LogicalExpr) -> Expr {
+        fold_logical_expr(self, expr)
+    }
+
+    fn fold_map_expr(&mut self, expr: &MapExpr) -> Expr {
+        fold_map_expr(self, expr)
+    }
+
+    fn fold_set_expr(&mut self, expr: &SetExpr) -> Expr {
+        fold_set_expr(self, expr)
+    }
+
+    fn fold_unary_expr(&mut self, expr: &UnaryExpr) -> Expr {
+        fold_unary_expr(self, expr)
+    }
+
+    fn fold_block_stmt(&mut self, stmt: &BlockStmt) -> Stmt {
+        BlockStmt::new(fold_stmts(self, stmt.statements()))
+    }
+
+    fn fold_class_stmt(&mut self, stmt: &ClassStmt) -> Stmt {
+        fold_class_stmt(self, stmt)
+    }
+
+    fn fold_expression_stmt(&mut self, stmt: &ExpressionStmt) -> Stmt {
+        ExpressionStmt::new(self.fold_expr(stmt.expression()))
+    }
+
+    // Also called for the methods of classes
+    fn fold_function_stmt(&mut self, stmt: &FunctionStmt) -> Rc<FunctionStmt> {
+        fold_function_stmt(self, stmt)
+    }
+
+    fn fold_if_stmt(&mut self, stmt: &IfStmt) -> Stmt {
+        fold_if_stmt(self, stmt)
+    }
+
+    fn fold_print_stmt(&mut self, stmt: &PrintStmt) -> Stmt {
+        PrintStmt::new(self.fold_expr(stmt.expression()))
+    }
+
+    fn fold_return_stmt(&mut self, stmt: &ReturnStmt) -> Stmt {
+        let value = stmt.value().map(|value| self.fold_expr(value));
+        ReturnStmt::new(stmt.keyword().clone(), value)
+    }
+
+    fn fold_throw_stmt(&mut self, stmt: &ThrowStmt) -> Stmt {
+        ThrowStmt::new(stmt.keyword().clone(), self.fold_expr(stmt.value()))
+    }
+
+    fn fold_try_stmt(&mut self, stmt: &TryStmt) -> Stmt {
+        fold_try_stmt(self, stmt)
+    }
+
+    fn fold_var_stmt(&mut self, stmt: &VarStmt) -> Stmt {
+        let initializer = stmt
+            .initializer()
+            .map(|initializer| self.fold_expr(initializer));
+        VarStmt::new(stmt.name().clone(), initializer)
+    }
+
+    fn fold_while_stmt(&mut self, stmt: &WhileStmt) -> Stmt {
+        fold_while_stmt(self, stmt)
+    }
+}
+
+/// Dispatches to the `fold_*_expr` method for the variant of `expr`. Leaves are kept as they are.
+pub fn fold_expr<F: Fold + ?Sized>(folder: &mut F, expr: &Expr) -> Expr {
+    match expr {
+        Expr::Assign(e) => folder.fold_assign_expr(e),
+        Expr::Binary(e) => folder.fold_binary_expr(e),
+        Expr::Call(e) => folder.fold_call_expr(e),
+        Expr::Function(e) => Expr::Function(folder.fold_function_expr(e)),
+        Expr::Get(e) => folder.fold_get_expr(e),
+        Expr::Grouping(e) => folder.fold_grouping_expr(e),
+        Expr::Index(e) => folder.fold_index_expr(e),
+        Expr::IndexSet(e) => folder.fold_index_set_expr(e),
+        Expr::List(e) => folder.fold_list_expr(e),
+        Expr::Logical(e) => folder.fold_logical_expr(e),
+        Expr::Map(e) => folder.fold_map_expr(e),
+        Expr::Set(e) => folder.fold_set_expr(e),
+        Expr::Unary(e) => folder.fold_unary_expr(e),
+        Expr::Literal(_) | Expr::Super(_) | Expr::This(_) | Expr::Variable(_) => expr.clone(),
+    }
+}
+
+/// Dispatches to the `fold_*_stmt` method for the variant of `stmt`. Leaves are kept as they are.
+pub fn fold_stmt<F: Fold + ?Sized>(folder: &mut F, stmt: &Stmt) -> Stmt {
+    match stmt {
+        Stmt::Block(s) => folder.fold_block_stmt(s),
+        Stmt::Class(s) => folder.fold_class_stmt(s),
+        Stmt::Expression(s) => folder.fold_expression_stmt(s),
+        Stmt::Function(s) => Stmt::Function(folder.fold_function_stmt(s)),
+        Stmt::If(s) => folder.fold_if_stmt(s),
+        Stmt::Print(s) => folder.fold_print_stmt(s),
+        Stmt::Return(s) => folder.fold_return_stmt(s),
+        Stmt::Throw(s) => folder.fold_throw_stmt(s),
+        Stmt::Try(s) => folder.fold_try_stmt(s),
+        Stmt::Var(s) => folder.fold_var_stmt(s),
+        Stmt::While(s) => folder.fold_while_stmt(s),
+        Stmt::Break(_) | Stmt::Continue(_) | Stmt::Import(_) => stmt.clone(),
+    }
+}
+
+pub fn fold_exprs<F: Fold + ?Sized>(folder: &mut F, exprs: &[Expr]) -> Vec<Expr> {
+    exprs.iter().map(|expr| folder.fold_expr(expr)).collect()
+}
+
+pub fn fold_stmts<F: Fold + ?Sized>(folder: &mut F, stmts: &[Stmt]) -> Vec<Stmt> {
+    stmts.iter().map(|stmt| folder.fold_stmt(stmt)).collect()
+}
+
+pub fn fold_assign_expr<F: Fold + ?Sized>(folder: &mut F, expr: &AssignExpr) -> Expr {
+    AssignExpr::new(expr.name().clone(), folder.fold_expr(expr.value()))
+}
+
+pub fn fold_binary_expr<F: Fold + ?Sized>(folder: &mut F, expr: &BinaryExpr) -> Expr {
+    let lhs = folder.fold_expr(expr.lhs());
+    let rhs = folder.fold_expr(expr.rhs());
+    BinaryExpr::new(expr.operator().clone(), lhs, rhs)
+}
+
+pub fn fold_call_expr<F: Fold + ?Sized>(folder: &mut F, expr: &CallExpr) -> Expr {
+    let callee = folder.fold_expr(expr.callee());
+    let arguments = fold_exprs(folder, expr.arguments());
+    CallExpr::new(callee, expr.paren().clone(), arguments)
+}
+
+pub fn fold_function_expr<F: Fold + ?Sized>(
+    folder: &mut F,
+    expr: &FunctionExpr,
+) -> Rc<FunctionExpr> {
+    FunctionExpr::new_declaration(
+        expr.keyword().clone(),
+        expr.params().to_vec(),
+        fold_stmts(folder, expr.body()),
+    )
+}
+
+pub fn fold_get_expr<F: Fold + ?Sized>(folder: &mut F, expr: &GetExpr) -> Expr {
+    GetExpr::new(folder.fold_expr(expr.object()), expr.name().clone())
+}
+
+pub fn fold_grouping_expr<F: Fold + ?Sized>(folder: &mut F, expr: &GroupingExpr) -> Expr {
+    GroupingExpr::new(folder.fold_expr(expr.expression()))
+}
+
+pub fn fold_index_expr<F: Fold + ?Sized>(folder: &mut F, expr: &IndexExpr) -> Expr {
+    let object = folder.fold_expr(expr.object());
+    let index = folder.fold_expr(expr.index());
+    IndexExpr::new(object, expr.bracket().clone(), index)
+}
+
+pub fn fold_index_set_expr<F: Fold + ?Sized>(folder: &mut F, expr: &IndexSetExpr) -> Expr {
+    let object = folder.fold_expr(expr.object());
+    let index = folder.fold_expr(expr.index());
+    let value = folder.fold_expr(expr.value());
+    IndexSetExpr::new(object, expr.bracket().clone(), index, value)
+}
+
+pub fn fold_list_expr<F: Fold + ?Sized>(folder: &mut F, expr: &ListExpr) -> Expr {
+    ListExpr::new(expr.bracket().clone(), fold_exprs(folder, expr.elements()))
+}
+
+pub fn fold_logical_expr<F: Fold + ?Sized>(folder: &mut F, expr: &LogicalExpr) -> Expr {
+    let lhs = folder.fold_expr(expr.lhs());
+    let rhs = folder.fold_expr(expr.rhs());
+    LogicalExpr::new(expr.operator().clone(), lhs, rhs)
+}
+
+pub fn fold_map_expr<F: Fold + ?Sized>(folder: &mut F, expr: &MapExpr) -> Expr {
+    let entries = expr
+        .entries()
+        .iter()
+        .map(|(key, value)| (folder.fold_expr(key), folder.fold_expr(value)))
+        .collect();
+    MapExpr::new(expr.brace().clone(), entries)
+}
+
+pub fn fold_set_expr<F: Fold + ?Sized>(folder: &mut F, expr: &SetExpr) -> Expr {
+    let object = folder.fold_expr(expr.object());
+    let value = folder.fold_expr(expr.value());
+    SetExpr::new(object, expr.name().clone(), value)
+}
+
+pub fn fold_unary_expr<F: Fold + ?Sized>(folder: &mut F, expr: &UnaryExpr) -> Expr {
+    UnaryExpr::new(expr.operator().clone(), folder.fold_expr(expr.rhs()))
+}
+
+pub fn fold_class_stmt<F: Fold + ?Sized>(folder: &mut F, stmt: &ClassStmt) -> Stmt {
+    let superclass = stmt
+        .superclass()
+        .map(|superclass| folder.fold_expr(superclass));
+    let methods = stmt
+        .methods()
+        .iter()
+        .map(|method| folder.fold_function_stmt(method))
+        .collect();
+    ClassStmt::new(stmt.name().clone(), superclass, methods)
+}
+
+pub fn fold_function_stmt<F: Fold + ?Sized>(
+    folder: &mut F,
+    stmt: &FunctionStmt,
+) -> Rc<FunctionStmt> {
+    FunctionStmt::new(
+        stmt.name().clone(),
+        stmt.params().to_vec(),
+        fold_stmts(folder, stmt.body()),
+    )
+}
+
+pub fn fold_if_stmt<F: Fold + ?Sized>(folder: &mut F, stmt: &IfStmt) -> Stmt {
+    let condition = folder.fold_expr(stmt.condition());
+    let then_branch = folder.fold_stmt(stmt.then_branch());
+    let else_branch = stmt.else_branch().map(|branch| folder.fold_stmt(branch));
+    IfStmt::new(condition, then_branch, else_branch)
+}
+
+pub fn fold_try_stmt<F: Fold + ?Sized>(folder: &mut F, stmt: &TryStmt) -> Stmt {
+    let body = fold_stmts(folder, stmt.body());
+    let catch = match (stmt.catch_name(), stmt.catch_body()) {
+        (Some(name), Some(body)) => Some((name.clone(), fold_stmts(folder, body))),
+        _ => None,
+    };
+    let finally = stmt.finally_body().map(|body| fold_stmts(folder, body));
+    TryStmt::new(body, catch, finally)
+}
+
+pub fn fold_while_stmt<F: Fold + ?Sized>(folder: &mut F, stmt: &WhileStmt) -> Stmt {
+    let condition = folder.fold_expr(stmt.condition());
+    let body = folder.fold_stmt(stmt.body());
+    let increment = stmt
+        .increment()
+        .map(|increment| folder.fold_expr(increment));
+    WhileStmt::with_increment(condition, body, increment)
+}
@@ -0,0 +1,411 @@
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+
+use crate::error::RloxError;
+use crate::expr::{self, Expr, FunctionExpr, LiteralExpr};
+use crate::parser::{ForLoop, Parser};
+use crate::scanner::{Comment, Scanner};
+use crate::stmt::{self, Stmt, WhileStmt};
+use crate::token::{Token, TokenType};
+
+const INDENT: &str = "  ";
+
+/// Binding strength of an expression, from assignment (loosest) to primary expressions.
+#[derive(Clone, Copy, PartialEq, PartialOrd)]
+enum Precedence {
+    Assignment,
+    Or,
+    And,
+    Equality,
+    Comparison,
+    Term,
+    Factor,
+    Unary,
+    Call,
+    Primary,
+}
+
+impl Precedence {
+    // The operands to the right of a left-associative operator bind one level tighter
+    fn next(self) -> Self {
+        use Precedence::*;
+        match self {
+            Assignment => Or,
+            Or => And,
+            And => Equality,
+            Equality => Comparison,
+            Comparison => Term,
+            Term => Factor,
+            Factor => Unary,
+            Unary => Call,
+            Call | Primary => Primary,
+        }
+    }
+
+    fn of_operator(operator: &Token) -> Self {
+        match operator.token_type() {
+            TokenType::Or => Precedence::Or,
+            TokenType::And => Precedence::And,
+            TokenType::EqualEqual | TokenType::BangEqual => Precedence::Equality,
+            TokenType::Greater
+            | TokenType::GreaterEqual
+            | TokenType::Less
+            | TokenType::LessEqual => Precedence::Comparison,
+            TokenType::Plus | TokenType::Minus => Precedence::Term,
+            _ => Precedence::Factor,
+        }
+    }
+}
+
+/// Formats a whole Lox source file, keeping its comments.
+pub fn format_source(source: &str) -> Result<String, RloxError> {
+    let scanner = Scanner::try_new(source.to_string())?;
+    let comments = scanner.comments().to_vec();
+    let mut parser = Parser::new(scanner.into_tokens());
+    let statements = parser.parse()?;
+    let formatter = Formatter::new(parser.take_spans(), parser.take_for_loops(), comments);
+    Ok(formatter.format(&statements))
+}
+
+/**
+ * Prints a parsed program back as canonically laid out Lox source.
+ *   Blocks are indented by two spaces, expressions get only the parentheses their precedence
+ *   requires, and at most one blank line is kept between statements. `for` loops are printed as
+ *   they were written, from the clauses the parser recorded for them. Comments are placed by the
+ *   line spans the parser recorded: before the statement that follows them, or at the end of the
+ *   line they trailed.
+ */
+struct Formatter {
+    out: RefCell<String>,
+    depth: Cell<usize>,
+    comments: RefCell<VecDeque<Comment>>,
+    spans: HashMap<usize, (usize, usize)>,
+    for_loops: HashMap<usize, ForLoop>,
+}
+
+impl Formatter {
+    fn new(
+        spans: HashMap<usize, (usize, usize)>,
+        for_loops: HashMap<usize, ForLoop>,
+        comments: Vec<Comment>,
+    ) -> Self {
+        Formatter {
+            out: RefCell::new(String::new()),
+            depth: Cell::new(0),
+            comments: RefCell::new(comments.into()),
+            spans,
+            for_loops,
+        }
+    }
+
+    fn format(&self, statements: &[Stmt]) -> String {
+        self.write_statements(statements, usize::MAX, |s| s.accept(self));
+        self.out.take()
+    }
+
+    fn write(&self, s: &str) {
+        self.out.borrow_mut().push_str(s);
+    }
+
+    fn write_indent(&self) {
+        self.write(&INDENT.repeat(self.depth.get()));
+    }
+
+    fn span(&self, id: usize) -> Option<(usize, usize)> {
+        self.spans.get(&id).copied()
+    }
+
+    // The line of the closing brace of a node, or 0 when unknown so no comment is placed before it
+    fn end_line(&self, id: usize) -> usize {
+        self.span(id).map_or(0, |(_, end)| end)
+    }
+
+    /**
+     * Writes a list of statements one per line, with the comments before each of them.
+     *   Comments left before the line `until` are written after the last statement, and a blank
+     *   line separates items that had blank lines between them in the source.
+     */
+    fn write_statements(&self, statements: &[Stmt], until: usize, write: impl Fn(&Stmt)) {
+        let mut previous = None;
+        for stmt in statements {
+            let span = self.span(stmt.id());
+            if let Some((start, _)) = span {
+                self.write_comments(start, &mut previous);
+                if previous.is_some_and(|line| start > line + 1) {
+                    self.write("\n");
+                }
+            }
+            self.write_indent();
+            write(stmt);
+            self.write("\n");
+            // Comments sharing the closing line of the enclosing block are left to follow it
+            if let Some((_, end)) = span {
+                previous = Some(end);
+                self.write_comments((end + 1).min(until), &mut previous);
+            }
+        }
+        self.write_comments(until, &mut previous);
+    }
+
+    // Writes the comments before the line `until`, updating the line of the last item written
+    fn write_comments(&self, until: usize, previous: &mut Option<usize>) {
+        loop {
+            let comment = match self.comments.borrow_mut().front() {
+                Some(c) if c.line_number() < until => c.clone(),
+                _ => break,
+            };
+            self.comments.borrow_mut().pop_front();
+            let mut out = self.out.borrow_mut();
+            if comment.is_trailing() && out.ends_with('\n') {
+                out.pop();
+                out.push(' ');
+                out.push_str(comment.text());
+                out.push('\n');
+                continue;
+            }
+            if previous.is_some_and(|line| comment.line_number() > line + 1) {
+                out.push('\n');
+            }
+            out.push_str(&INDENT.repeat(self.depth.get()));
+            out.push_str(comment.text());
+            out.push('\n');
+            *previous = Some(comment.line_number());
+        }
+    }
+
+    fn write_block(&self, statements: &[Stmt], until: usize) {
+        self.write_nested(|| self.write_statements(statements, until, |s| s.accept(self)));
+    }
+
+    // Writes braces around lines written one level deeper, or `{}` when there are none
+    fn write_nested(&self, write_lines: impl FnOnce()) {
+        self.write("{\n");
+        self.depth.set(self.depth.get() + 1);
+        write_lines();
+        self.depth.set(self.depth.get() - 1);
+        if self.out.borrow().ends_with("{\n") {
+            self.out.borrow_mut().pop();
+        } else {
+            self.write_indent();
+        }
+        self.write("}");
+    }
+
+    // Writes `(params) { body }`, the part functions, methods and lambdas have in common
+    fn write_function(&self, function: &Rc<FunctionExpr>) {
+        let params = function.params().iter().map(|p| p.lexeme()).collect::<Vec<_>>();
+        self.write(&format!("({}) ", params.join(", ")));
+        let id = Rc::as_ptr(function) as *const () as usize;
+        self.write_block(function.body(), self.end_line(id));
+    }
+
+    // A `for` loop, which the parser turned into a while loop carrying its increment
+    fn write_for(&self, initializer: Option<&Stmt>, stmt: &WhileStmt, form: ForLoop) {
+        self.write("for (");
+        match initializer {
+            Some(initializer) => initializer.accept(self),
+            None => self.write(";"),
+        }
+        match form.condition {
+            true => self.write(&format!(" {};", self.expression(stmt.condition()))),
+            false => self.write(";"),
+        }
+        if let Some(increment) = stmt.increment() {
+            self.write(&format!(" {}", self.expression(increment)));
+        }
+        self.write(") ");
+        stmt.body().accept(self);
+    }
+
+    fn expression(&self, expr: &Expr) -> String {
+        self.operand(expr, Precedence::Assignment)
+    }
+
+    // Formats `expr`, in parentheses if it binds more loosely than its position requires
+    fn operand(&self, expr: &Expr, min: Precedence) -> String {
+        let (text, precedence) = expr.accept(self);
+        match precedence < min {
+            true => format!("({})", text),
+            false => text,
+        }
+    }
+
+    fn list(&self, exprs: &[Expr]) -> String {
+        exprs.iter().map(|e| self.expression(e)).collect::<Vec<_>>().join(", ")
+    }
+}
+
+impl expr::Visitor<(String, Precedence)> for Formatter {
+    fn visit_expr(&self, expr: &Expr) -> (String, Precedence) {
+        use Precedence::*;
+        match expr {
+            Expr::Assign(e) => {
+                let value = self.operand(e.value(), Assignment);
+                (format!("{} = {}", e.name().lexeme(), value), Assignment)
+            }
+            Expr::Binary(e) => {
+                let precedence = Precedence::of_operator(e.operator());
+                let lhs = self.operand(e.lhs(), precedence);
+                let rhs = self.operand(e.rhs(), precedence.next());
+                (format!("{} {} {}", lhs, e.operator().lexeme(), rhs), precedence)
+            }
+            Expr::Call(e) => {
+                let callee = self.operand(e.callee(), Call);
+                (format!("{}({})", callee, self.list(e.arguments())), Call)
+            }
+            Expr::Function(e) => {
+                // The body goes to a buffer of its own, as the enclosing line isn't written yet
+                let enclosing = self.out.take();
+                self.write("fun ");
+                self.write_function(e);
+                (self.out.replace(enclosing), Primary)
+            }
+            Expr::Get(e) => {
+                let object = self.operand(e.object(), Call);
+                (format!("{}.{}", object, e.name().lexeme()), Call)
+            }
+            Expr::Grouping(e) => e.expression().accept(self),
+            Expr::Index(e) => {
+                let object = self.operand(e.object(), Call);
+                (format!("{}[{}]", object, self.expression(e.index())), Call)
+            }
+            Expr::IndexSet(e) => {
+                let object = self.operand(e.object(), Call);
+                let index = self.expression(e.index());
+                let value = self.operand(e.value(), Assignment);
+                (format!("{}[{}] = {}", object, index, value), Assignment)
+            }
+            Expr::List(e) => (format!("[{}]", self.list(e.elements())), Primary),
+            Expr::Literal(e) => {
+                let text = match &**e {
+                    LiteralExpr::Nil => "nil".to_string(),
+                    LiteralExpr::String(s) => format!("\"{}\"", s),
+                    LiteralExpr::Float(n) => n.to_string(),
+                    LiteralExpr::Bool(b) => b.to_string(),
+                };
+                (text, Primary)
+            }
+            Expr::Logical(e) => {
+                let precedence = Precedence::of_operator(e.operator());
+                let lhs = self.operand(e.lhs(), precedence);
+                let rhs = self.operand(e.rhs(), precedence.next());
+                (format!("{} {} {}", lhs, e.operator().lexeme(), rhs), precedence)
+            }
+            Expr::Map(e) => {
+                let entries = e
+                    .entries()
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}", self.expression(k), self.expression(v)))
+                    .collect::<Vec<_>>();
+                (format!("{{{}}}", entries.join(", ")), Primary)
+            }
+            Expr::Set(e) => {
+                let object = self.operand(e.object(), Call);
+                let value = self.operand(e.value(), Assignment);
+                (format!("{}.{} = {}", object, e.name().lexeme(), value), Assignment)
+            }
+            Expr::Super(e) => (format!("super.{}", e.method().lexeme()), Primary),
+            Expr::This(_) => ("this".to_string(), Primary),
+            Expr::Unary(e) => {
+                let operator = e.operator().lexeme();
+                let rhs = self.operand(e.rhs(), Unary);
+                // `- -x` rather than `--x`, which reads like a decrement
+                let space = if rhs.starts_with(operator) && operator == "-" { " " } else { "" };
+                (format!("{}{}{}", operator, space, rhs), Unary)
+            }
+            Expr::Variable(e) => (e.name().lexeme().to_string(), Primary),
+        }
+    }
+}
+
+impl stmt::Visitor<()> for Formatter {
+    fn visit_stmt(&self, stmt: &Stmt) {
+        let for_loop = self.for_loops.get(&stmt.id()).copied();
+        match stmt {
+            Stmt::Block(s) => match (s.statements(), for_loop) {
+                ([initializer, Stmt::While(w)], Some(form)) => {
+                    self.write_for(Some(initializer), w, form)
+                }
+                (statements, _) => self.write_block(statements, self.end_line(stmt.id())),
+            },
+            Stmt::Break(_) => self.write("break;"),
+            Stmt::Class(s) => {
+                self.write(&format!("class {} ", s.name().lexeme()));
+                if let Some(superclass) = s.superclass() {
+                    self.write(&format!("< {} ", self.expression(superclass)));
+                }
+                // Methods are listed like statements, keyed by the spans of their bodies
+                let methods = s.methods().iter().map(|m| Stmt::Function(Rc::clone(m)));
+                let methods = methods.collect::<Vec<_>>();
+                self.write_nested(|| {
+                    self.write_statements(&methods, self.end_line(stmt.id()), |m| {
+                        if let Stmt::Function(m) = m {
+                            self.write(m.name().lexeme());
+                            self.write_function(m.function());
+                        }
+                    })
+                });
+            }
+            Stmt::Continue(_) => self.write("continue;"),
+            Stmt::Expression(s) => self.write(&format!("{};", self.expression(s.expression()))),
+            Stmt::Function(s) => {
+                self.write(&format!("fun {}", s.name().lexeme()));
+                self.write_function(s.function());
+            }
+            Stmt::If(s) => {
+                self.write(&format!("if ({}) ", self.expression(s.condition())));
+                s.then_branch().accept(self);
+                if let Some(else_branch) = s.else_branch() {
+                    match s.then_branch() {
+                        Stmt::Block(_) => self.write(" "),
+                        _ => {
+                            self.write("\n");
+                            self.write_indent();
+                        }
+                    }
+                    self.write("else ");
+                    else_branch.accept(self);
+                }
+            }
+            Stmt::Import(s) => {
+                self.write(&format!("import \"{}\" as {};", s.path(), s.name().lexeme()))
+            }
+            Stmt::Print(s) => self.write(&format!("print {};", self.expression(s.expression()))),
+            Stmt::Return(s) => match s.value() {
+                Some(value) => self.write(&format!("return {};", self.expression(value))),
+                None => self.write("return;"),
+            },
+            Stmt::Throw(s) => self.write(&format!("throw {};", self.expression(s.value()))),
+            Stmt::Try(s) => {
+                // Only the end of the whole statement is known, which closes its last block
+                let end = self.end_line(stmt.id());
+                self.write("try ");
+                self.write_block(s.body(), 0);
+                if let (Some(name), Some(body)) = (s.catch_name(), s.catch_body()) {
+                    self.write(&format!(" catch ({}) ", name.lexeme()));
+                    let has_finally = s.finally_body().is_some();
+                    self.write_block(body, if has_finally { 0 } else { end });
+                }
+                if let Some(body) = s.finally_body() {
+                    self.write(" finally ");
+                    self.write_block(body, end);
+                }
+            }
+            Stmt::Var(s) => match s.initializer() {
+                Some(value) => {
+                    let value = self.expression(value);
+                    self.write(&format!("var {} = {};", s.name().lexeme(), value))
+                }
+                None => self.write(&format!("var {};", s.name().lexeme())),
+            },
+            Stmt::While(s) => match for_loop {
+                Some(form) => self.write_for(None, s, form),
+                None => {
+                    self.write(&format!("while ({}) ", self.expression(s.condition())));
+                    s.body().accept(self);
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::cell::RefCell;
+use std::fmt::{self, Display};
+use std::rc::Rc;
+
+use crate::class::LoxInstance;
+use crate::environment::Environment;
+use crate::error::RloxError;
+use crate::interpreter::{Interpreter, Unwind};
+use crate::expr::FunctionExpr;
+use crate::value::Value;
+
+/**
+ * Anything that can be invoked with `callee(args)` in Lox.
+ *   The interpreter checks the argument count against `arity` before calling.
+ */
+pub trait Callable {
+    fn arity(&self) -> usize;
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Value>) -> Result<Value, RloxError>;
+}
+
+pub struct LoxFunction {
+    name: Option<String>, // None for anonymous functions
+    declaration: Rc<FunctionExpr>,
+    closure: Rc<RefCell<Environment>>,
+    is_initializer: bool,
+}
+
+impl LoxFunction {
+    pub(crate) fn new(
+        name: Option<&str>,
+        declaration: Rc<FunctionExpr>,
+        closure: Rc<RefCell<Environment>>,
+        is_initializer: bool,
+    ) -> Self {
+        LoxFunction {
+            name: name.map(str::to_string),
+            declaration,
+            closure,
+            is_initializer,
+        }
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    // Produces a method whose closure has "this" bound to the given instance
+    pub(crate) fn bind(&self, instance: Rc<RefCell<LoxInstance>>) -> LoxFunction {
+        let environment = Environment::new_enclosed(&self.closure);
+        environment
+            .borrow_mut()
+            .define("this", Value::Instance(instance));
+        LoxFunction {
+            name: self.name.clone(),
+            declaration: Rc::clone(&self.declaration),
+            closure: environment,
+            is_initializer: self.is_initializer,
+        }
+    }
+
+    fn this(&self) -> Value {
+        Environment::get_at(&self.closure, 0, "this").unwrap_or(Value::Nil)
+    }
+}
+
+impl Callable for LoxFunction {
+    fn arity(&self) -> usize {
+        self.declaration.params().len()
+    }
+
+    fn call(&self, interpreter: &Interpreter, arguments: Vec<Value>) -> Result<Value, RloxError> {
+        let environment = Environment::new_enclosed(&self.closure);
+        for (param, argument) in self.declaration.params().iter().zip(arguments) {
+            environment.borrow_mut().define(param.lexeme(), argument);
+        }
+        let value = match interpreter.execute_block(self.declaration.body(), environment) {
+            Ok(()) => Value::Nil,
+            Err(Unwind::Return(value)) => value,
+            Err(Unwind::Error(e)) => return Err(e),
+            Err(Unwind::Break) | Err(Unwind::Continue) => {
+                unreachable!("the parser rejects break and continue outside of loops")
+            }
+        };
+        // An initializer always hands back the instance, even on an early bare `return;`
+        match self.is_initializer {
+            true => Ok(self.this()),
+            false => Ok(value),
+        }
+    }
+}
+
+impl Display for LoxFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "<fn {}>", name),
+            None => write!(f, "<fn>"),
+        }
+    }
+}
+
+type NativeFn = dyn Fn(&[Value]) -> Result<Value, RloxError>;
+
+/// A function implemented in Rust and exposed to Lox code.
+pub struct NativeFunction {
+    name: String,
+    arity: usize,
+    function: Box<NativeFn>,
+}
+
+impl NativeFunction {
+    pub fn new<F>(name: &str, arity: usize, function: F) -> Self
+    where
+        F: Fn(&[Value]) -> Result<Value, RloxError> + 'static,
+    {
+        NativeFunction {
+            name: name.to_string(),
+            arity,
+            function: Box::new(function),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Natives don't need the interpreter, so the VM can call them too
+    pub(crate) fn invoke(&self, arguments: &[Value]) -> Result<Value, RloxError> {
+        (self.function)(arguments)
+    }
+}
+
+impl Callable for NativeFunction {
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(&self, _interpreter: &Interpreter, arguments: Vec<Value>) -> Result<Value, RloxError> {
+        self.invoke(&arguments)
+    }
+}
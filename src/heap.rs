@@ -0,0 +1,271 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use crate::chunk::Prototype;
+use crate::error::RloxError;
+use crate::function::NativeFunction;
+use crate::interner::Symbol;
+use crate::map::LoxMap;
+
+/// A value on the VM stack. Anything bigger than a number lives on the heap.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VmValue {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    Object(ObjRef),
+}
+
+impl VmValue {
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, VmValue::Nil | VmValue::Bool(false))
+    }
+}
+
+/// Handle to an object on the VM heap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObjRef(usize);
+
+pub enum Object {
+    String(Symbol),
+    Closure(Closure),
+    Upvalue(Upvalue),
+    Native(Rc<NativeFunction>),
+    BuiltinMethod(BuiltinMethod),
+    Class(Class),
+    Instance(Instance),
+    BoundMethod(BoundMethod),
+    List(Vec<VmValue>),
+    Map(LoxMap<VmValue>),
+    Module(Module),
+    Exception(Option<(RloxError, Option<VmValue>)>), // an error on its way through a finally block
+}
+
+impl Object {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::String(_) => "string",
+            Object::Closure(_)
+            | Object::Native(_)
+            | Object::BuiltinMethod(_)
+            | Object::BoundMethod(_) => "function",
+            Object::Class(_) => "class",
+            Object::Instance(_) => "instance",
+            Object::List(_) => "list",
+            Object::Map(_) => "map",
+            Object::Module(_) => "module",
+            Object::Upvalue(_) | Object::Exception(_) => "internal",
+        }
+    }
+}
+
+pub struct Closure {
+    pub(crate) prototype: Rc<Prototype>,
+    pub(crate) upvalues: Vec<ObjRef>,
+    pub(crate) module: ObjRef, // whose globals the function reads and writes
+}
+
+// A variable captured by a closure: a stack slot while the variable is in scope, then its own copy
+pub enum Upvalue {
+    Open(usize),
+    Closed(VmValue),
+}
+
+// A method of a list or map, looked up with `xs.push` and not called yet
+pub struct BuiltinMethod {
+    pub(crate) receiver: ObjRef,
+    pub(crate) name: &'static str,
+    pub(crate) arity: usize,
+}
+
+pub struct Class {
+    pub(crate) name: String,
+    pub(crate) methods: HashMap<Symbol, ObjRef>, // inherited methods are copied in
+}
+
+pub struct Instance {
+    pub(crate) class: ObjRef,
+    pub(crate) fields: HashMap<Symbol, VmValue>,
+}
+
+pub struct BoundMethod {
+    pub(crate) receiver: VmValue,
+    pub(crate) method: ObjRef,
+}
+
+/// A loaded file, either the main program or an imported module.
+pub struct Module {
+    pub(crate) path: Option<PathBuf>, // None for the main program
+    pub(crate) globals: HashMap<Symbol, VmValue>,
+}
+
+impl Object {
+    // References this object holds, which a collection must keep alive along with it
+    pub(crate) fn trace(&self, mut mark: impl FnMut(VmValue)) {
+        match self {
+            Object::String(_) | Object::Native(_) => (),
+            Object::Closure(closure) => {
+                closure.upvalues.iter().for_each(|r| mark(VmValue::Object(*r)));
+                mark(VmValue::Object(closure.module));
+            }
+            Object::Upvalue(Upvalue::Open(_)) => (),
+            Object::Upvalue(Upvalue::Closed(value)) => mark(*value),
+            Object::BuiltinMethod(method) => mark(VmValue::Object(method.receiver)),
+            Object::Class(class) => class.methods.values().for_each(|r| mark(VmValue::Object(*r))),
+            Object::Instance(instance) => {
+                mark(VmValue::Object(instance.class));
+                instance.fields.values().for_each(|value| mark(*value));
+            }
+            Object::BoundMethod(bound) => {
+                mark(bound.receiver);
+                mark(VmValue::Object(bound.method));
+            }
+            Object::List(elements) => elements.iter().for_each(|value| mark(*value)),
+            Object::Map(map) => map.iter().for_each(|(_, value)| mark(*value)),
+            Object::Module(module) => module.globals.values().for_each(|value| mark(*value)),
+            Object::Exception(pending) => {
+                if let Some((_, Some(thrown))) = pending {
+                    mark(*thrown);
+                }
+            }
+        }
+    }
+
+    // A rough count of the bytes the object owns, to decide when to collect
+    fn size(&self) -> usize {
+        let value = std::mem::size_of::<VmValue>();
+        let entry = std::mem::size_of::<String>() + value;
+        std::mem::size_of::<Object>()
+            + match self {
+                Object::String(s) => s.len(),
+                Object::Closure(closure) => closure.upvalues.len() * value,
+                Object::Class(class) => class.methods.len() * entry,
+                Object::Instance(instance) => instance.fields.len() * entry,
+                Object::List(elements) => elements.len() * value,
+                Object::Map(map) => map.len() * entry,
+                Object::Module(module) => module.globals.len() * entry,
+                _ => 0,
+            }
+    }
+}
+
+/// When the garbage collector runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GcConfig {
+    pub initial_threshold: usize, // bytes allocated before the first collection
+    pub growth_factor: usize,     // the next collection waits until the live heap grows this much
+    pub stress: bool,             // collect before every allocation, to shake out missing roots
+}
+
+impl Default for GcConfig {
+    fn default() -> Self {
+        GcConfig {
+            initial_threshold: 1024 * 1024,
+            growth_factor: 2,
+            stress: false,
+        }
+    }
+}
+
+/**
+ * Owner of every object the VM allocates, reclaimed by mark and sweep.
+ *   The heap doesn't know the roots: the VM marks them, then asks the heap to trace and sweep.
+ *   Slots of collected objects are reused, so an `ObjRef` is only valid while reachable.
+ */
+#[derive(Default)]
+pub struct Heap {
+    objects: Vec<Option<Object>>,
+    marks: Vec<bool>,
+    free: Vec<usize>,
+    gray: Vec<ObjRef>,
+    bytes_allocated: usize,
+    next_gc: usize,
+    config: GcConfig,
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Heap::with_config(GcConfig::default())
+    }
+
+    pub fn with_config(config: GcConfig) -> Self {
+        Heap {
+            next_gc: config.initial_threshold,
+            config,
+            ..Heap::default()
+        }
+    }
+
+    pub fn alloc(&mut self, object: Object) -> ObjRef {
+        self.bytes_allocated += object.size();
+        match self.free.pop() {
+            Some(index) => {
+                self.objects[index] = Some(object);
+                ObjRef(index)
+            }
+            None => {
+                self.objects.push(Some(object));
+                self.marks.push(false);
+                ObjRef(self.objects.len() - 1)
+            }
+        }
+    }
+
+    pub fn get(&self, reference: ObjRef) -> &Object {
+        self.objects[reference.0].as_ref().expect("use of a collected object")
+    }
+
+    pub fn get_mut(&mut self, reference: ObjRef) -> &mut Object {
+        self.objects[reference.0].as_mut().expect("use of a collected object")
+    }
+
+    pub fn should_collect(&self) -> bool {
+        self.config.stress || self.bytes_allocated > self.next_gc
+    }
+
+    /// Number of objects currently allocated, reachable or not.
+    pub fn live_objects(&self) -> usize {
+        self.objects.len() - self.free.len()
+    }
+
+    pub fn bytes_allocated(&self) -> usize {
+        self.bytes_allocated
+    }
+
+    pub fn mark(&mut self, value: VmValue) {
+        if let VmValue::Object(reference) = value {
+            if !self.marks[reference.0] {
+                self.marks[reference.0] = true;
+                self.gray.push(reference);
+            }
+        }
+    }
+
+    /// Frees every object not reachable from the marked roots, returning how many were freed.
+    pub fn trace_and_sweep(&mut self) -> usize {
+        while let Some(reference) = self.gray.pop() {
+            let mut children = Vec::new();
+            self.get(reference).trace(|value| children.push(value));
+            children.into_iter().for_each(|child| self.mark(child));
+        }
+
+        let mut freed = 0;
+        self.bytes_allocated = 0;
+        for (index, slot) in self.objects.iter_mut().enumerate() {
+            match (slot.as_ref(), self.marks[index]) {
+                (Some(object), true) => self.bytes_allocated += object.size(),
+                (Some(_), false) => {
+                    *slot = None;
+                    self.free.push(index);
+                    freed += 1;
+                }
+                (None, _) => (),
+            }
+            self.marks[index] = false;
+        }
+        self.next_gc = (self.bytes_allocated * self.config.growth_factor)
+            .max(self.config.initial_threshold);
+        freed
+    }
+}
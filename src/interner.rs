@@ -0,0 +1,145 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt::{self, Debug, Display};
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::rc::Rc;
+
+/**
+ * A string interned in the interner of the current thread.
+ *   Equal symbols share one allocation, so comparing and hashing them only looks at the
+ *   pointer. That makes variable lookups and string equality independent of string length.
+ */
+#[derive(Clone)]
+pub struct Symbol(Rc<str>);
+
+impl Symbol {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (self.0.as_ptr() as usize).hash(state)
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", &*self.0)
+    }
+}
+
+impl Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}", &*self.0)
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(s: &str) -> Self {
+        intern(s)
+    }
+}
+
+impl From<&Symbol> for Symbol {
+    fn from(s: &Symbol) -> Self {
+        s.clone()
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(s: String) -> Self {
+        intern(&s)
+    }
+}
+
+/// What `--intern-stats` reports about the interner of the current thread.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InternerStats {
+    pub strings: usize, // distinct strings currently interned
+    pub bytes: usize,   // their total length
+    pub lookups: usize, // calls to `intern`
+    pub hits: usize,    // lookups that found the string already interned
+}
+
+impl Display for InternerStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "interned strings: {} ({} bytes), lookups: {}, hits: {}",
+            self.strings, self.bytes, self.lookups, self.hits
+        )
+    }
+}
+
+// Unused strings are dropped from the table whenever it has doubled since the last pruning
+const MIN_PRUNE_THRESHOLD: usize = 1024;
+
+struct Interner {
+    strings: HashSet<Rc<str>>,
+    prune_threshold: usize,
+    lookups: usize,
+    hits: usize,
+}
+
+impl Interner {
+    fn intern(&mut self, s: &str) -> Symbol {
+        self.lookups += 1;
+        if let Some(existing) = self.strings.get(s) {
+            self.hits += 1;
+            return Symbol(Rc::clone(existing));
+        }
+        if self.strings.len() >= self.prune_threshold {
+            // Only the table refers to these, so no symbol can be compared with them any more
+            self.strings.retain(|s| Rc::strong_count(s) > 1);
+            self.prune_threshold = (self.strings.len() * 2).max(MIN_PRUNE_THRESHOLD);
+        }
+        let s: Rc<str> = Rc::from(s);
+        self.strings.insert(Rc::clone(&s));
+        Symbol(s)
+    }
+}
+
+thread_local! {
+    static INTERNER: RefCell<Interner> = RefCell::new(Interner {
+        strings: HashSet::new(),
+        prune_threshold: MIN_PRUNE_THRESHOLD,
+        lookups: 0,
+        hits: 0,
+    });
+}
+
+/// Returns the symbol for `s`, shared with every other use of the same string on this thread.
+pub fn intern(s: &str) -> Symbol {
+    INTERNER.with(|interner| interner.borrow_mut().intern(s))
+}
+
+pub fn stats() -> InternerStats {
+    INTERNER.with(|interner| {
+        let interner = interner.borrow();
+        InternerStats {
+            strings: interner.strings.len(),
+            bytes: interner.strings.iter().map(|s| s.len()).sum(),
+            lookups: interner.lookups,
+            hits: interner.hits,
+        }
+    })
+}
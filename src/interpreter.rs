@@ -0,0 +1,885 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
+
+use crate::class::{self, LoxClass, LoxInstance};
+use crate::environment::Environment;
+use crate::error::{RloxError, RloxRuntimeError};
+use crate::expr::{
+    self, BinaryExpr, CallExpr, Expr, FunctionExpr, IndexExpr, IndexSetExpr, LiteralExpr,
+    LogicalExpr, MapExpr, UnaryExpr,
+};
+use crate::function::{Callable, LoxFunction, NativeFunction};
+use crate::list;
+use crate::map::{self, LoxMap, MapKey};
+use crate::module::{self, LoxModule};
+use crate::natives;
+use crate::optimizer::Optimizer;
+use crate::parser::Parser;
+use crate::resolver::Resolver;
+use crate::stmt::{self, ClassStmt, ImportStmt, Stmt, TryStmt};
+use crate::token::{Token, TokenType};
+use crate::value::Value;
+use crate::visit::{self, VisitorMut};
+
+// Catches runaway recursion. The native stack grows on the heap as needed, so this isn't what
+// keeps deep programs from overflowing it
+pub(crate) const MAX_CALL_DEPTH: usize = 10_000;
+
+// Evaluating a node switches to a new stack segment once less than `RED_ZONE` bytes are left
+pub(crate) const RED_ZONE: usize = 128 * 1024;
+pub(crate) const STACK_SEGMENT: usize = 2 * 1024 * 1024;
+
+/**
+ * Non-local exits that unwind through statement execution.
+ *   Errors abort the program, a return is caught by the function call that is being executed,
+ *   break and continue by the innermost loop.
+ */
+pub(crate) enum Unwind {
+    Error(RloxError),
+    Return(Value),
+    Break,
+    Continue,
+}
+
+impl From<RloxError> for Unwind {
+    fn from(e: RloxError) -> Self {
+        Unwind::Error(e)
+    }
+}
+
+impl From<std::io::Error> for Unwind {
+    fn from(e: std::io::Error) -> Self {
+        Unwind::Error(RloxError::IoError(e))
+    }
+}
+
+/**
+ * Tree-walking interpreter, and the handle for embedding Lox in a Rust program.
+ *   Globals persist across calls to `eval` and `interpret`, so a host can define values and
+ *   native functions up front and run several scripts against them.
+ */
+pub struct Interpreter {
+    globals: Rc<RefCell<Environment>>,
+    builtins: RefCell<Environment>, // what the globals of every imported module start out with
+    environment: RefCell<Rc<RefCell<Environment>>>,
+    locals: RefCell<HashMap<usize, usize>>, // Expr::id -> scope distance, filled in by the Resolver
+    functions: RefCell<Vec<(Weak<FunctionExpr>, Vec<usize>)>>, // the locals of each function body
+    output: RefCell<Box<dyn Write>>,
+    call_depth: Cell<usize>,
+    frames: RefCell<Vec<(String, usize)>>, // Lox functions being executed, with their call lines
+    modules: RefCell<HashMap<PathBuf, Rc<LoxModule>>>,
+    loading: RefCell<Vec<PathBuf>>, // files being executed, the importing file last
+    optimize: Cell<bool>,           // whether programs are constant folded before they run
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Interpreter::with_output(std::io::stdout())
+    }
+
+    /// Creates an interpreter whose `print` statements write to `output` instead of stdout.
+    pub fn with_output(output: impl Write + 'static) -> Self {
+        let globals = Environment::new();
+        let interpreter = Interpreter {
+            environment: RefCell::new(Rc::clone(&globals)),
+            globals,
+            builtins: RefCell::new(Environment::default()),
+            locals: RefCell::new(HashMap::new()),
+            functions: RefCell::new(Vec::new()),
+            output: RefCell::new(Box::new(output)),
+            call_depth: Cell::new(0),
+            frames: RefCell::new(Vec::new()),
+            modules: RefCell::new(HashMap::new()),
+            loading: RefCell::new(Vec::new()),
+            optimize: Cell::new(true),
+        };
+        natives::define_globals(&interpreter);
+        interpreter
+            .builtins
+            .replace(interpreter.globals.borrow().clone());
+        interpreter
+    }
+
+    /**
+     * Runs the script at `path`.
+     *   Unlike `interpret`, imports in the script are resolved relative to the script's directory.
+     */
+    pub fn run_file(&self, path: impl AsRef<Path>) -> Result<(), RloxError> {
+        let source = fs::read_to_string(&path)?;
+        let statements = crate::parse_program(&source)?;
+        self.loading.borrow_mut().push(path.as_ref().canonicalize()?);
+        let result = self.interpret(statements);
+        self.loading.borrow_mut().pop();
+        result
+    }
+
+    /**
+     * Runs `source` and returns the value of its trailing expression statement, or nil.
+     *   A lone expression without the trailing semicolon is accepted too, e.g. `eval("1 + 2")`.
+     */
+    pub fn eval(&self, source: &str) -> Result<Value, RloxError> {
+        let tokens = crate::tokenize(source)?;
+        let mut statements = match Parser::new(tokens.clone()).parse_expression() {
+            Ok(expr) => vec![stmt::ExpressionStmt::new(expr)],
+            Err(_) => Parser::new(tokens).parse()?,
+        };
+        let last = match statements.last() {
+            Some(Stmt::Expression(_)) => statements.pop(),
+            _ => None,
+        };
+        self.interpret(statements)?;
+        match last {
+            Some(Stmt::Expression(last)) => {
+                let expression = last.expression().clone();
+                self.interpret_expression(expression)
+            }
+            _ => Ok(Value::Nil),
+        }
+    }
+
+    /// Resolves and executes a parsed program.
+    pub fn interpret(&self, statements: Vec<Stmt>) -> Result<(), RloxError> {
+        let statements = self.optimize(statements);
+        let top_level = self.resolve(&statements)?;
+        let result = statements.iter().try_for_each(|s| self.execute(s));
+        self.forget(top_level);
+        match result {
+            Ok(()) => Ok(()),
+            Err(Unwind::Error(e)) => Err(e),
+            Err(Unwind::Return(_)) => unreachable!("the resolver rejects top-level returns"),
+            Err(Unwind::Break) | Err(Unwind::Continue) => {
+                unreachable!("the parser rejects break and continue outside of loops")
+            }
+        }
+    }
+
+    fn interpret_expression(&self, expression: Expr) -> Result<Value, RloxError> {
+        let statement = stmt::ExpressionStmt::new(expression.clone());
+        let top_level = self.resolve(std::slice::from_ref(&statement))?;
+        let value = self.evaluate(&expression);
+        self.forget(top_level);
+        value
+    }
+
+    /// Turns constant folding of the programs run from now on off or back on, as `--no-opt` does.
+    pub fn set_optimize(&self, optimize: bool) {
+        self.optimize.set(optimize);
+    }
+
+    fn optimize(&self, statements: Vec<Stmt>) -> Vec<Stmt> {
+        match self.optimize.get() {
+            true => Optimizer::new().optimize(&statements),
+            false => statements,
+        }
+    }
+
+    /**
+     * Resolves a program about to run, returning the ids of its top-level locals to `forget` once
+     *   it has run. Those in function bodies are kept while the function is alive, as closures can
+     *   still call it, and forgotten here once it is dropped, before its ids can be reused.
+     */
+    fn resolve(&self, statements: &[Stmt]) -> Result<Vec<usize>, RloxError> {
+        self.functions.borrow_mut().retain(|(function, ids)| {
+            if function.strong_count() > 0 {
+                return true;
+            }
+            self.forget(ids.iter().copied());
+            false
+        });
+        let locals = Resolver::new().resolve(statements)?;
+        let mut owners = LocalOwners {
+            locals: &locals,
+            top_level: Vec::new(),
+            functions: Vec::new(),
+            enclosing: Vec::new(),
+        };
+        visit::walk_stmts(&mut owners, statements);
+        let top_level = owners.top_level;
+        self.functions.borrow_mut().append(&mut owners.functions);
+        self.locals.borrow_mut().extend(locals);
+        Ok(top_level)
+    }
+
+    fn forget(&self, ids: impl IntoIterator<Item = usize>) {
+        let mut locals = self.locals.borrow_mut();
+        for id in ids {
+            locals.remove(&id);
+        }
+    }
+
+    /// Defines a global for the main program, and for every module imported after this call.
+    pub fn define_global(&self, name: &str, value: impl Into<Value>) {
+        let value = value.into();
+        self.builtins.borrow_mut().define(name, value.clone());
+        self.globals.borrow_mut().define(name, value);
+    }
+
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        self.globals.borrow().get_by_name(name)
+    }
+
+    /// The globals defined by the programs run so far, sorted by name, leaving out the natives
+    /// and prelude functions they didn't redefine.
+    pub fn user_globals(&self) -> Vec<(String, Value)> {
+        let builtins = self.builtins.borrow();
+        let mut globals = self
+            .globals
+            .borrow()
+            .bindings()
+            .filter(|(name, value)| builtins.get_by_name(name).as_ref() != Some(*value))
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect::<Vec<_>>();
+        globals.sort_by(|a, b| a.0.cmp(&b.0));
+        globals
+    }
+
+    /**
+     * Registers a Rust closure as a global Lox function taking exactly `arity` arguments.
+     *   Errors returned from the closure surface as runtime errors on the line of the Lox call.
+     */
+    pub fn define_native<F>(&self, name: &str, arity: usize, function: F)
+    where
+        F: Fn(&[Value]) -> Result<Value, RloxError> + 'static,
+    {
+        let native = NativeFunction::new(name, arity, function);
+        self.define_global(name, Value::NativeFunction(Rc::new(native)));
+    }
+
+    /// Calls a Lox function, native function or class from Rust.
+    pub fn call(&self, callee: &Value, arguments: Vec<Value>) -> Result<Value, RloxError> {
+        let callable: &dyn Callable = match callee {
+            Value::Function(f) => f.as_ref(),
+            Value::NativeFunction(f) => f.as_ref(),
+            Value::Class(c) => {
+                check_arity(class::class_arity(c), arguments.len())?;
+                return self.with_call_depth(|| class::instantiate(c, self, arguments));
+            }
+            _ => return Err(RloxError::runtime("Can only call functions and classes.")),
+        };
+        check_arity(callable.arity(), arguments.len())?;
+        self.with_call_depth(|| callable.call(self, arguments))
+    }
+
+    fn with_call_depth<T>(&self, f: impl FnOnce() -> Result<T, RloxError>) -> Result<T, RloxError> {
+        if self.call_depth.get() >= MAX_CALL_DEPTH {
+            return Err(RloxError::runtime("Stack overflow."));
+        }
+        self.call_depth.set(self.call_depth.get() + 1);
+        let result = f();
+        self.call_depth.set(self.call_depth.get() - 1);
+        result
+    }
+
+    fn evaluate(&self, expr: &Expr) -> Result<Value, RloxError> {
+        stacker::maybe_grow(RED_ZONE, STACK_SEGMENT, || expr.accept(self))
+    }
+
+    fn execute(&self, stmt: &Stmt) -> Result<(), Unwind> {
+        stacker::maybe_grow(RED_ZONE, STACK_SEGMENT, || stmt.accept(self))
+    }
+
+    pub(crate) fn execute_block(
+        &self,
+        statements: &[Stmt],
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<(), Unwind> {
+        let previous = self.environment.replace(environment);
+        let result = statements.iter().try_for_each(|s| self.execute(s));
+        self.environment.replace(previous);
+        result
+    }
+
+    fn current_environment(&self) -> Rc<RefCell<Environment>> {
+        Rc::clone(&self.environment.borrow())
+    }
+
+    fn look_up_variable(&self, name: &Token, expr: &Expr) -> Result<Value, RloxError> {
+        let distance = self.locals.borrow().get(&expr.id()).copied();
+        match distance {
+            Some(distance) => {
+                Environment::get_at(&self.current_environment(), distance, name.lexeme())
+                    .ok_or_else(|| {
+                        RloxError::runtime_at(
+                            *name.line_number(),
+                            format!("Undefined variable '{}'.", name.lexeme()),
+                        )
+                    })
+            }
+            None => Environment::global(&self.current_environment()).borrow().get(name),
+        }
+    }
+
+    fn visit_binary_expr(&self, expr: &BinaryExpr) -> Result<Value, RloxError> {
+        let lhs = self.evaluate(expr.lhs())?;
+        let rhs = self.evaluate(expr.rhs())?;
+        let operator = expr.operator();
+        match operator.token_type() {
+            TokenType::Plus => match (lhs, rhs) {
+                (Value::Number(a), Value::Number(b)) => Ok(Value::Number(a + b)),
+                (Value::String(a), Value::String(b)) => Ok(Value::from(format!("{}{}", a, b))),
+                _ => Err(operand_error(
+                    operator,
+                    "Operands must be two numbers or two strings.",
+                )),
+            },
+            TokenType::Minus => number_operands(operator, lhs, rhs, |a, b| Value::Number(a - b)),
+            TokenType::Slash => number_operands(operator, lhs, rhs, |a, b| Value::Number(a / b)),
+            TokenType::Star => number_operands(operator, lhs, rhs, |a, b| Value::Number(a * b)),
+            TokenType::Greater => number_operands(operator, lhs, rhs, |a, b| Value::Bool(a > b)),
+            TokenType::GreaterEqual => {
+                number_operands(operator, lhs, rhs, |a, b| Value::Bool(a >= b))
+            }
+            TokenType::Less => number_operands(operator, lhs, rhs, |a, b| Value::Bool(a < b)),
+            TokenType::LessEqual => number_operands(operator, lhs, rhs, |a, b| Value::Bool(a <= b)),
+            TokenType::BangEqual => Ok(Value::Bool(lhs != rhs)),
+            TokenType::EqualEqual => Ok(Value::Bool(lhs == rhs)),
+            _ => Err(operand_error(operator, "Unknown binary operator.")),
+        }
+    }
+
+    fn visit_call_expr(&self, expr: &CallExpr) -> Result<Value, RloxError> {
+        let callee = self.evaluate(expr.callee())?;
+        let arguments = expr
+            .arguments()
+            .iter()
+            .map(|argument| self.evaluate(argument))
+            .collect::<Result<Vec<_>, _>>()?;
+        let line = *expr.paren().line_number();
+        let (frame, arity) = match &callee {
+            Value::Function(f) => (format!("{}()", f.name().unwrap_or("<fn>")), f.arity()),
+            Value::Class(c) => (format!("{}()", c.name()), class::class_arity(c)),
+            _ => return self.call(&callee, arguments).map_err(|e| e.or_at_line(line)),
+        };
+        // A call with the wrong number of arguments fails in the caller, as on the VM
+        check_arity(arity, arguments.len()).map_err(|e| e.or_at_line(line))?;
+        self.frames.borrow_mut().push((frame, line));
+        let result = self
+            .call(&callee, arguments)
+            .map_err(|e| self.record_stack_trace(e.or_at_line(line)));
+        self.frames.borrow_mut().pop();
+        result
+    }
+
+    /**
+     * Fills in the stack trace of an error leaving the innermost Lox function.
+     *   Each frame reports the line it was executing, which for all but the innermost one is the
+     *   line of the call into the next frame.
+     */
+    fn record_stack_trace(&self, mut e: RloxError) -> RloxError {
+        if let RloxError::RuntimeError(e) = &mut e {
+            if e.stack_trace.is_empty() {
+                let mut line = e.line_number.unwrap_or_default();
+                for (function, call_line) in self.frames.borrow().iter().rev() {
+                    e.stack_trace.push(format!("[line {}] in {}", line, function));
+                    line = *call_line;
+                }
+                e.stack_trace.push(format!("[line {}] in script", line));
+            }
+        }
+        e
+    }
+
+    /**
+     * The value a catch clause binds: a thrown value as is, or an instance of the global
+     *   `Error` class carrying the message and line of an error raised by the interpreter.
+     */
+    fn error_object(&self, e: RloxRuntimeError) -> Value {
+        if let Some(value) = e.thrown {
+            return value;
+        }
+        match self.get_global("Error") {
+            Some(Value::Class(class)) => {
+                let mut instance = LoxInstance::new(class);
+                instance.set_field("message", Value::from(e.description));
+                instance.set_field("line", e.line_number.map(|line| line as f64).into());
+                Value::Instance(Rc::new(RefCell::new(instance)))
+            }
+            _ => Value::from(e.description),
+        }
+    }
+
+    fn visit_index_expr(&self, expr: &IndexExpr) -> Result<Value, RloxError> {
+        let object = self.evaluate(expr.object())?;
+        let index = self.evaluate(expr.index())?;
+        let line = *expr.bracket().line_number();
+        match object {
+            Value::List(list) => {
+                let list = list.borrow();
+                let i = list::index(&index, list.len()).map_err(|e| e.or_at_line(line))?;
+                Ok(list[i].clone())
+            }
+            Value::Map(map) => {
+                let key = MapKey::from_value(&index).map_err(|e| e.or_at_line(line))?;
+                match map.borrow().get(&key) {
+                    Some(value) => Ok(value.clone()),
+                    None => Err(RloxError::runtime_at(line, format!("Undefined key {:?}.", index))),
+                }
+            }
+            _ => Err(RloxError::runtime_at(line, "Only lists and maps can be indexed.")),
+        }
+    }
+
+    fn visit_index_set_expr(&self, expr: &IndexSetExpr) -> Result<Value, RloxError> {
+        let object = self.evaluate(expr.object())?;
+        let index = self.evaluate(expr.index())?;
+        let value = self.evaluate(expr.value())?;
+        let line = *expr.bracket().line_number();
+        match object {
+            Value::List(list) => {
+                let mut list = list.borrow_mut();
+                let i = list::index(&index, list.len()).map_err(|e| e.or_at_line(line))?;
+                list[i] = value.clone();
+                Ok(value)
+            }
+            Value::Map(map) => {
+                let key = MapKey::from_value(&index).map_err(|e| e.or_at_line(line))?;
+                map.borrow_mut().insert(key, value.clone());
+                Ok(value)
+            }
+            _ => Err(RloxError::runtime_at(line, "Only lists and maps can be indexed.")),
+        }
+    }
+
+    fn visit_map_expr(&self, expr: &MapExpr) -> Result<Value, RloxError> {
+        let mut map = LoxMap::new();
+        for (key, value) in expr.entries() {
+            let key = self.evaluate(key)?;
+            let key = MapKey::from_value(&key).map_err(|e| e.or_at_line(*expr.brace().line_number()))?;
+            map.insert(key, self.evaluate(value)?);
+        }
+        Ok(Value::Map(Rc::new(RefCell::new(map))))
+    }
+
+    fn visit_literal_expr(&self, expr: &LiteralExpr) -> Value {
+        match expr {
+            LiteralExpr::Nil => Value::Nil,
+            LiteralExpr::String(s) => Value::from(s.as_str()),
+            LiteralExpr::Float(f) => Value::Number(*f),
+            LiteralExpr::Bool(b) => Value::Bool(*b),
+        }
+    }
+
+    fn visit_logical_expr(&self, expr: &LogicalExpr) -> Result<Value, RloxError> {
+        let lhs = self.evaluate(expr.lhs())?;
+        let short_circuits = match expr.operator().token_type() {
+            TokenType::Or => lhs.is_truthy(),
+            _ => !lhs.is_truthy(),
+        };
+        match short_circuits {
+            true => Ok(lhs),
+            false => self.evaluate(expr.rhs()),
+        }
+    }
+
+    fn visit_unary_expr(&self, expr: &UnaryExpr) -> Result<Value, RloxError> {
+        let rhs = self.evaluate(expr.rhs())?;
+        match (expr.operator().token_type(), rhs) {
+            (TokenType::Bang, rhs) => Ok(Value::Bool(!rhs.is_truthy())),
+            (TokenType::Minus, Value::Number(n)) => Ok(Value::Number(-n)),
+            _ => Err(operand_error(expr.operator(), "Operand must be a number.")),
+        }
+    }
+
+    fn visit_import_stmt(&self, stmt: &ImportStmt) -> Result<(), Unwind> {
+        let line = *stmt.keyword().line_number();
+        let importer = self.loading.borrow().last().cloned();
+        let path = module::find(importer.as_deref(), stmt.path()).ok_or_else(|| {
+            RloxError::runtime_at(line, format!("Could not find module '{}'.", stmt.path()))
+        })?;
+        let cached = self.modules.borrow().get(&path).cloned();
+        let module = match cached {
+            Some(module) => module,
+            None => self.load_module(path, line)?,
+        };
+        self.environment
+            .borrow()
+            .borrow_mut()
+            .define(stmt.name().lexeme(), Value::Module(module));
+        Ok(())
+    }
+
+    // Runs a module file in globals of its own, the first time it is imported
+    fn load_module(&self, path: PathBuf, line: usize) -> Result<Rc<LoxModule>, Unwind> {
+        if let Some(start) = self.loading.borrow().iter().position(|p| *p == path) {
+            let chain = self.loading.borrow()[start..]
+                .iter()
+                .chain(std::iter::once(&path))
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>();
+            let description = format!("Import cycle: {}.", chain.join(" -> "));
+            return Err(RloxError::runtime_at(line, description).into());
+        }
+        let source = fs::read_to_string(&path).map_err(|e| {
+            let description = format!("Could not read module '{}': {}", path.display(), e);
+            RloxError::runtime_at(line, description)
+        })?;
+        let statements = self.optimize(crate::parse_program(&source)?);
+        let top_level = self.resolve(&statements)?;
+
+        let globals = Rc::new(RefCell::new(self.builtins.borrow().clone()));
+        self.loading.borrow_mut().push(path.clone());
+        let result = self.execute_block(&statements, Rc::clone(&globals));
+        self.loading.borrow_mut().pop();
+        self.forget(top_level);
+        result?;
+
+        let module = Rc::new(LoxModule::new(path.clone(), globals));
+        self.modules.borrow_mut().insert(path, Rc::clone(&module));
+        Ok(module)
+    }
+
+    fn visit_throw_stmt(&self, stmt: &stmt::ThrowStmt) -> Result<(), Unwind> {
+        let value = self.evaluate(stmt.value())?;
+        let line = *stmt.keyword().line_number();
+        let message = match &value {
+            Value::Instance(instance) => {
+                let mut instance = instance.borrow_mut();
+                // Error objects learn where they were thrown from, unless they are being rethrown
+                if let Some(Value::Nil) = instance.field("line") {
+                    instance.set_field("line", Value::Number(line as f64));
+                }
+                let message = instance.field("message").cloned();
+                message.map(|message| format!("{}: {}", instance.class().name(), message))
+            }
+            _ => None,
+        };
+        let description = match message {
+            Some(message) => format!("Uncaught {}", message),
+            None => format!("Uncaught exception: {}", value),
+        };
+        Err(RloxError::thrown(line, description, value).into())
+    }
+
+    // A return, break or continue leaving the finally block replaces whatever was unwinding before
+    fn visit_try_stmt(&self, stmt: &TryStmt) -> Result<(), Unwind> {
+        let environment = Environment::new_enclosed(&self.current_environment());
+        let mut result = self.execute_block(stmt.body(), environment);
+        if let (Some(name), Some(body)) = (stmt.catch_name(), stmt.catch_body()) {
+            result = match result {
+                Err(Unwind::Error(RloxError::RuntimeError(e))) => {
+                    let environment = Environment::new_enclosed(&self.current_environment());
+                    environment
+                        .borrow_mut()
+                        .define(name.lexeme(), self.error_object(*e));
+                    self.execute_block(body, environment)
+                }
+                result => result,
+            };
+        }
+        if let Some(finally) = stmt.finally_body() {
+            let environment = Environment::new_enclosed(&self.current_environment());
+            self.execute_block(finally, environment)?;
+        }
+        result
+    }
+
+    fn visit_class_stmt(&self, stmt: &ClassStmt) -> Result<(), Unwind> {
+        let superclass = match stmt.superclass() {
+            Some(superclass) => match self.evaluate(superclass)? {
+                Value::Class(class) => Some(class),
+                _ => {
+                    return Err(RloxError::runtime_at(
+                        *stmt.name().line_number(),
+                        "Superclass must be a class.",
+                    )
+                    .into())
+                }
+            },
+            None => None,
+        };
+        self.environment
+            .borrow()
+            .borrow_mut()
+            .define(stmt.name().lexeme(), Value::Nil);
+
+        let mut environment = self.current_environment();
+        if let Some(superclass) = &superclass {
+            environment = Environment::new_enclosed(&environment);
+            environment
+                .borrow_mut()
+                .define("super", Value::Class(Rc::clone(superclass)));
+        }
+        let methods = stmt
+            .methods()
+            .iter()
+            .map(|method| {
+                let is_initializer = method.name().lexeme() == "init";
+                let function = LoxFunction::new(
+                    Some(method.name().lexeme()),
+                    Rc::clone(method.function()),
+                    Rc::clone(&environment),
+                    is_initializer,
+                );
+                (method.name().symbol().clone(), Rc::new(function))
+            })
+            .collect();
+
+        let class = LoxClass::new(stmt.name().lexeme(), superclass, methods);
+        self.environment
+            .borrow()
+            .borrow_mut()
+            .assign(stmt.name(), Value::Class(Rc::new(class)))?;
+        Ok(())
+    }
+}
+
+impl expr::Visitor<Result<Value, RloxError>> for Interpreter {
+    fn visit_expr(&self, expr: &Expr) -> Result<Value, RloxError> {
+        match expr {
+            Expr::Assign(e) => {
+                let value = self.evaluate(e.value())?;
+                let distance = self.locals.borrow().get(&expr.id()).copied();
+                match distance {
+                    Some(distance) => Environment::assign_at(
+                        &self.current_environment(),
+                        distance,
+                        e.name(),
+                        value.clone(),
+                    ),
+                    None => Environment::global(&self.current_environment())
+                        .borrow_mut()
+                        .assign(e.name(), value.clone())?,
+                }
+                Ok(value)
+            }
+            Expr::Binary(e) => self.visit_binary_expr(e),
+            Expr::Call(e) => self.visit_call_expr(e),
+            Expr::Function(e) => {
+                let function = LoxFunction::new(None, Rc::clone(e), self.current_environment(), false);
+                Ok(Value::Function(Rc::new(function)))
+            }
+            Expr::Get(e) => match self.evaluate(e.object())? {
+                Value::Instance(instance) => LoxInstance::get(&instance, e.name()),
+                Value::List(list) => list::get_method(&list, e.name()),
+                Value::Map(map) => map::get_method(&map, e.name()),
+                Value::Module(module) => module.get(e.name()),
+                _ => Err(RloxError::runtime_at(
+                    *e.name().line_number(),
+                    "Only instances have properties.",
+                )),
+            },
+            Expr::Grouping(e) => self.evaluate(e.expression()),
+            Expr::Index(e) => self.visit_index_expr(e),
+            Expr::IndexSet(e) => self.visit_index_set_expr(e),
+            Expr::List(e) => {
+                let elements = e
+                    .elements()
+                    .iter()
+                    .map(|element| self.evaluate(element))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Value::from(elements))
+            }
+            Expr::Literal(e) => Ok(self.visit_literal_expr(e)),
+            Expr::Logical(e) => self.visit_logical_expr(e),
+            Expr::Map(e) => self.visit_map_expr(e),
+            Expr::Set(e) => match self.evaluate(e.object())? {
+                Value::Instance(instance) => {
+                    let value = self.evaluate(e.value())?;
+                    instance.borrow_mut().set(e.name(), value.clone());
+                    Ok(value)
+                }
+                _ => Err(RloxError::runtime_at(
+                    *e.name().line_number(),
+                    "Only instances have fields.",
+                )),
+            },
+            Expr::Super(e) => {
+                let distance = self.locals.borrow().get(&expr.id()).copied().unwrap_or(0);
+                let environment = self.current_environment();
+                let superclass = Environment::get_at(&environment, distance, "super");
+                // "this" is always bound in the scope just inside the one holding "super"
+                let object = Environment::get_at(&environment, distance - 1, "this");
+                let method = match superclass {
+                    Some(Value::Class(superclass)) => superclass.find_method(e.method().lexeme()),
+                    _ => None,
+                };
+                match (method, object) {
+                    (Some(method), Some(Value::Instance(object))) => {
+                        Ok(Value::Function(Rc::new(method.bind(object))))
+                    }
+                    _ => Err(RloxError::runtime_at(
+                        *e.method().line_number(),
+                        format!("Undefined property '{}'.", e.method().lexeme()),
+                    )),
+                }
+            }
+            Expr::This(e) => self.look_up_variable(e.keyword(), expr),
+            Expr::Unary(e) => self.visit_unary_expr(e),
+            Expr::Variable(e) => self.look_up_variable(e.name(), expr),
+        }
+    }
+}
+
+impl stmt::Visitor<Result<(), Unwind>> for Interpreter {
+    fn visit_stmt(&self, stmt: &Stmt) -> Result<(), Unwind> {
+        match stmt {
+            Stmt::Block(s) => {
+                let environment = Environment::new_enclosed(&self.current_environment());
+                self.execute_block(s.statements(), environment)
+            }
+            Stmt::Break(_) => Err(Unwind::Break),
+            Stmt::Class(s) => self.visit_class_stmt(s),
+            Stmt::Continue(_) => Err(Unwind::Continue),
+            Stmt::Expression(s) => {
+                self.evaluate(s.expression())?;
+                Ok(())
+            }
+            Stmt::Function(s) => {
+                let function = LoxFunction::new(
+                    Some(s.name().lexeme()),
+                    Rc::clone(s.function()),
+                    self.current_environment(),
+                    false,
+                );
+                self.environment
+                    .borrow()
+                    .borrow_mut()
+                    .define(s.name().lexeme(), Value::Function(Rc::new(function)));
+                Ok(())
+            }
+            Stmt::If(s) => {
+                if self.evaluate(s.condition())?.is_truthy() {
+                    self.execute(s.then_branch())
+                } else if let Some(else_branch) = s.else_branch() {
+                    self.execute(else_branch)
+                } else {
+                    Ok(())
+                }
+            }
+            Stmt::Import(s) => self.visit_import_stmt(s),
+            Stmt::Print(s) => {
+                let value = self.evaluate(s.expression())?;
+                writeln!(self.output.borrow_mut(), "{}", value)?;
+                Ok(())
+            }
+            Stmt::Return(s) => {
+                let value = match s.value() {
+                    Some(value) => self.evaluate(value)?,
+                    None => Value::Nil,
+                };
+                Err(Unwind::Return(value))
+            }
+            Stmt::Throw(s) => self.visit_throw_stmt(s),
+            Stmt::Try(s) => self.visit_try_stmt(s),
+            Stmt::Var(s) => {
+                let value = match s.initializer() {
+                    Some(initializer) => self.evaluate(initializer)?,
+                    None => Value::Nil,
+                };
+                self.environment
+                    .borrow()
+                    .borrow_mut()
+                    .define(s.name().lexeme(), value);
+                Ok(())
+            }
+            Stmt::While(s) => {
+                while self.evaluate(s.condition())?.is_truthy() {
+                    match self.execute(s.body()) {
+                        Ok(()) | Err(Unwind::Continue) => (),
+                        Err(Unwind::Break) => break,
+                        Err(unwind) => return Err(unwind),
+                    }
+                    if let Some(increment) = s.increment() {
+                        self.evaluate(increment)?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// Sorts the resolved locals of a program by the innermost function whose body holds them
+struct LocalOwners<'a> {
+    locals: &'a HashMap<usize, usize>,
+    top_level: Vec<usize>,
+    functions: Vec<(Weak<FunctionExpr>, Vec<usize>)>,
+    enclosing: Vec<(Weak<FunctionExpr>, Vec<usize>)>,
+}
+
+impl LocalOwners<'_> {
+    fn function(&mut self, function: &Rc<FunctionExpr>) {
+        self.enclosing.push((Rc::downgrade(function), Vec::new()));
+        visit::walk_function_expr(self, function);
+        let owned = self.enclosing.pop().expect("pushed above");
+        self.functions.push(owned);
+    }
+}
+
+impl VisitorMut for LocalOwners<'_> {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if self.locals.contains_key(&expr.id()) {
+            match self.enclosing.last_mut() {
+                Some((_, ids)) => ids.push(expr.id()),
+                None => self.top_level.push(expr.id()),
+            }
+        }
+        match expr {
+            Expr::Function(function) => self.function(function),
+            _ => visit::walk_expr(self, expr),
+        }
+    }
+
+    fn visit_function_stmt(&mut self, stmt: &stmt::FunctionStmt) {
+        self.function(stmt.function())
+    }
+}
+
+fn check_arity(arity: usize, argument_count: usize) -> Result<(), RloxError> {
+    match arity == argument_count {
+        true => Ok(()),
+        false => Err(RloxError::runtime(format!(
+            "Expected {} arguments but got {}.",
+            arity, argument_count
+        ))),
+    }
+}
+
+fn operand_error(operator: &Token, description: &str) -> RloxError {
+    RloxError::runtime_at(*operator.line_number(), description)
+}
+
+fn number_operands(
+    operator: &Token,
+    lhs: Value,
+    rhs: Value,
+    op: impl Fn(f64, f64) -> Value,
+) -> Result<Value, RloxError> {
+    match (lhs, rhs) {
+        (Value::Number(a), Value::Number(b)) => Ok(op(a, b)),
+        _ => Err(operand_error(operator, "Operands must be numbers.")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Interpreter;
+
+    #[test]
+    fn resolved_locals_live_only_as_long_as_their_trees() {
+        let interpreter = Interpreter::new();
+        let redefine = |i| format!("fun redefined(y) {{ var z = y; return z + {}; }}", i);
+        interpreter.eval(&redefine(0)).unwrap();
+        let functions = interpreter.functions.borrow().len();
+        let locals = interpreter.locals.borrow().len();
+        for i in 0..100 {
+            let source = format!("{{ var x = {}; (fun (y) {{ return x + y; }})(1); }}", i);
+            interpreter.eval(&source).unwrap();
+            interpreter.eval(&redefine(i)).unwrap();
+        }
+        // Only the body of the last `redefined` remains resolved, besides the prelude's
+        interpreter.eval("redefined(1)").unwrap();
+        assert_eq!(interpreter.functions.borrow().len(), functions);
+        assert_eq!(interpreter.locals.borrow().len(), locals);
+    }
+}
@@ -1,6 +1,83 @@
 pub mod error;
+pub mod interner;
 pub mod token;
 pub mod scanner;
 pub mod expr;
+pub mod stmt;
+pub mod visit;
+pub mod fold;
+pub mod parser;
+pub mod resolver;
+pub mod arena;
+pub mod optimizer;
+pub mod value;
+pub mod environment;
+pub mod function;
+pub mod class;
+pub mod list;
+pub mod map;
+pub mod module;
+pub mod interpreter;
+pub mod natives;
 pub mod ast_printer;
 pub mod ast_printer_rpn;
+pub mod ast_printer_dot;
+pub mod ast_printer_json;
+pub mod sexpr;
+pub mod rpn;
+pub mod line_editor;
+pub mod repl;
+pub mod formatter;
+pub mod chunk;
+pub mod bytecode;
+pub mod heap;
+pub mod compiler;
+pub mod disassembler;
+pub mod vm;
+
+pub use crate::interpreter::Interpreter;
+pub use crate::value::Value;
+pub use crate::vm::Vm;
+
+use std::rc::Rc;
+
+use crate::chunk::Prototype;
+use crate::compiler::Compiler;
+use crate::error::RloxError;
+use crate::expr::Expr;
+use crate::optimizer::Optimizer;
+use crate::parser::Parser;
+use crate::resolver::Resolver;
+use crate::scanner::Scanner;
+use crate::stmt::Stmt;
+use crate::token::Token;
+
+/// Scans `source` into tokens, terminated by an `Eof` token.
+pub fn tokenize(source: &str) -> Result<Vec<Token>, RloxError> {
+    Ok(Scanner::try_new(source.to_string())?.into_tokens())
+}
+
+/// Parses `source` as a single Lox expression.
+pub fn parse_expression(source: &str) -> Result<Expr, RloxError> {
+    Parser::new(tokenize(source)?).parse_expression()
+}
+
+/// Parses `source` as a whole Lox program, a list of declarations and statements.
+pub fn parse_program(source: &str) -> Result<Vec<Stmt>, RloxError> {
+    Parser::new(tokenize(source)?).parse()
+}
+
+/// Compiles `source` to bytecode for the VM, returning the prototype of its top level.
+pub fn compile(source: &str) -> Result<Rc<Prototype>, RloxError> {
+    compile_with(source, true)
+}
+
+/// Like `compile`, but leaves constant expressions unfolded unless `optimize` is set.
+pub fn compile_with(source: &str, optimize: bool) -> Result<Rc<Prototype>, RloxError> {
+    let mut statements = parse_program(source)?;
+    if optimize {
+        statements = Optimizer::new().optimize(&statements);
+    }
+    Resolver::new().resolve(&statements)?;
+    Compiler::new().compile(&statements)
+}
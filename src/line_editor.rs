@@ -0,0 +1,313 @@
+use std::fs::OpenOptions;
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::path::PathBuf;
+
+// Older entries are dropped from memory, though the file keeps them
+const HISTORY_LIMIT: usize = 1000;
+
+/**
+ * Reads lines from the terminal with cursor keys, Emacs-style control keys and a history
+ *   recalled with the up and down arrows. When stdin isn't a terminal, lines are read as they
+ *   come, so scripts can still be piped in.
+ */
+#[derive(Debug, Default)]
+pub struct LineEditor {
+    history: Vec<String>,
+    history_file: Option<PathBuf>,
+}
+
+// A key press, decoded from the bytes the terminal sends for it
+#[derive(Debug, PartialEq)]
+enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    KillToStart,
+    KillToEnd,
+    EndOfFile,
+    Interrupt,
+    Ignored,
+}
+
+impl LineEditor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An editor whose history is loaded from `path` and saved back to it line by line.
+    pub fn with_history_file(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let mut history = std::fs::read_to_string(&path)
+            .map(|text| text.lines().map(String::from).collect::<Vec<_>>())
+            .unwrap_or_default();
+        history.drain(..history.len().saturating_sub(HISTORY_LIMIT));
+        LineEditor {
+            history,
+            history_file: Some(path),
+        }
+    }
+
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Remembers a line unless it is blank or repeats the last one. Failing to save it to the
+    /// history file only loses it for later sessions, so such errors are ignored.
+    pub fn add_history(&mut self, line: &str) {
+        if line.trim().is_empty() || self.history.last().map(String::as_str) == Some(line) {
+            return;
+        }
+        self.history.push(line.to_string());
+        if self.history.len() > HISTORY_LIMIT {
+            self.history.remove(0);
+        }
+        if let Some(path) = &self.history_file {
+            let file = OpenOptions::new().create(true).append(true).open(path);
+            let _ = file.and_then(|mut file| writeln!(file, "{}", line));
+        }
+    }
+
+    /**
+     * Prints `prompt` and reads a line from stdin, without its line ending.
+     *   Returns `None` at the end of input, or on Ctrl-D at an empty line, and an error of kind
+     *   `Interrupted` on Ctrl-C.
+     */
+    pub fn read_line(&mut self, prompt: &str) -> io::Result<Option<String>> {
+        let mut stdout = io::stdout();
+        if RawMode::SUPPORTED && io::stdin().is_terminal() {
+            return self.edit_line(prompt, &mut io::stdin().lock(), &mut stdout);
+        }
+        write!(stdout, "{}", prompt)?;
+        stdout.flush()?;
+        let mut line = String::new();
+        match io::stdin().lock().read_line(&mut line)? {
+            0 => Ok(None),
+            _ => Ok(Some(line.trim_end_matches(&['\n', '\r'][..]).to_string())),
+        }
+    }
+
+    /**
+     * Edits a line from the key presses in `input`, echoing it to `output` as a terminal does.
+     *   A terminal on stdin is in raw mode only until the line is returned, so whatever runs
+     *   before the next one prints and reads as usual.
+     */
+    pub fn edit_line(
+        &mut self,
+        prompt: &str,
+        input: &mut impl Read,
+        output: &mut impl Write,
+    ) -> io::Result<Option<String>> {
+        let _raw_mode = RawMode::enable();
+        let mut line = Vec::<char>::new();
+        let mut cursor = 0;
+        // The position in the history of the line shown, and the line being typed before that
+        let mut position = self.history.len();
+        let mut draft = Vec::new();
+        write!(output, "{}", prompt)?;
+        output.flush()?;
+        loop {
+            match read_key(input)? {
+                Key::Char(c) => {
+                    line.insert(cursor, c);
+                    cursor += 1;
+                }
+                Key::Enter => {
+                    writeln!(output)?;
+                    return Ok(Some(line.into_iter().collect()));
+                }
+                Key::Backspace if cursor > 0 => {
+                    cursor -= 1;
+                    line.remove(cursor);
+                }
+                Key::Delete if cursor < line.len() => {
+                    line.remove(cursor);
+                }
+                Key::Left if cursor > 0 => cursor -= 1,
+                Key::Right if cursor < line.len() => cursor += 1,
+                Key::Home => cursor = 0,
+                Key::End => cursor = line.len(),
+                Key::Up if position > 0 => {
+                    if position == self.history.len() {
+                        draft = line;
+                    }
+                    position -= 1;
+                    line = self.history[position].chars().collect();
+                    cursor = line.len();
+                }
+                Key::Down if position < self.history.len() => {
+                    position += 1;
+                    line = match self.history.get(position) {
+                        Some(entry) => entry.chars().collect(),
+                        None => std::mem::take(&mut draft),
+                    };
+                    cursor = line.len();
+                }
+                Key::KillToStart => {
+                    line.drain(..cursor);
+                    cursor = 0;
+                }
+                Key::KillToEnd => line.truncate(cursor),
+                // Ctrl-D deletes forward, as in a shell, and ends input only on an empty line
+                Key::EndOfFile if line.is_empty() => {
+                    writeln!(output)?;
+                    return Ok(None);
+                }
+                Key::EndOfFile if cursor < line.len() => {
+                    line.remove(cursor);
+                }
+                Key::Interrupt => {
+                    writeln!(output, "^C")?;
+                    return Err(io::ErrorKind::Interrupted.into());
+                }
+                _ => continue,
+            }
+            redraw(output, prompt, &line, cursor)?;
+        }
+    }
+}
+
+fn redraw(output: &mut impl Write, prompt: &str, line: &[char], cursor: usize) -> io::Result<()> {
+    let text = line.iter().collect::<String>();
+    // Return to the start of the line, clear what follows the text, then move back to the cursor
+    write!(output, "\r{}{}\x1b[K", prompt, text)?;
+    if cursor < line.len() {
+        write!(output, "\x1b[{}D", line.len() - cursor)?;
+    }
+    output.flush()
+}
+
+fn read_byte(input: &mut impl Read) -> io::Result<Option<u8>> {
+    let mut byte = [0];
+    match input.read(&mut byte)? {
+        0 => Ok(None),
+        _ => Ok(Some(byte[0])),
+    }
+}
+
+// The end of input reads as Ctrl-D
+fn read_key(input: &mut impl Read) -> io::Result<Key> {
+    let byte = match read_byte(input)? {
+        Some(byte) => byte,
+        None => return Ok(Key::EndOfFile),
+    };
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        1 => Key::Home,
+        2 => Key::Left,
+        3 => Key::Interrupt,
+        4 => Key::EndOfFile,
+        5 => Key::End,
+        6 => Key::Right,
+        8 | 127 => Key::Backspace,
+        11 => Key::KillToEnd,
+        14 => Key::Down,
+        16 => Key::Up,
+        21 => Key::KillToStart,
+        0x1b => read_escape_sequence(input)?,
+        _ if byte < 0x20 => Key::Ignored,
+        _ => read_char(input, byte)?,
+    };
+    Ok(key)
+}
+
+// Decodes the rest of a UTF-8 character from its leading byte
+fn read_char(input: &mut impl Read, first: u8) -> io::Result<Key> {
+    let length = match first {
+        0xf0..=0xff => 4,
+        0xe0..=0xef => 3,
+        0xc0..=0xdf => 2,
+        _ => 1,
+    };
+    let mut bytes = vec![first; length];
+    input.read_exact(&mut bytes[1..])?;
+    Ok(match std::str::from_utf8(&bytes) {
+        Ok(text) => text.chars().next().map_or(Key::Ignored, Key::Char),
+        Err(_) => Key::Ignored,
+    })
+}
+
+// Arrows send `ESC [ A` or `ESC O A`, and keys such as Delete send `ESC [ 3 ~`
+fn read_escape_sequence(input: &mut impl Read) -> io::Result<Key> {
+    match read_byte(input)? {
+        Some(b'[') | Some(b'O') => (),
+        _ => return Ok(Key::Ignored),
+    }
+    let mut parameter = String::new();
+    while let Some(byte) = read_byte(input)? {
+        let key = match byte {
+            b'0'..=b'9' | b';' => {
+                parameter.push(byte as char);
+                continue;
+            }
+            b'A' => Key::Up,
+            b'B' => Key::Down,
+            b'C' => Key::Right,
+            b'D' => Key::Left,
+            b'H' => Key::Home,
+            b'F' => Key::End,
+            b'~' => match parameter.as_str() {
+                "1" | "7" => Key::Home,
+                "3" => Key::Delete,
+                "4" | "8" => Key::End,
+                _ => Key::Ignored,
+            },
+            _ => Key::Ignored,
+        };
+        return Ok(key);
+    }
+    Ok(Key::Ignored)
+}
+
+// Turns off line buffering, echo and signals of the terminal on stdin, until dropped
+struct RawMode {
+    #[cfg(unix)]
+    saved: libc::termios,
+}
+
+impl RawMode {
+    // Other platforms fall back to reading lines as they come
+    const SUPPORTED: bool = cfg!(unix);
+
+    #[cfg(unix)]
+    fn enable() -> Option<RawMode> {
+        let mut saved = std::mem::MaybeUninit::uninit();
+        // SAFETY: tcgetattr fills in the settings when it succeeds
+        let saved = unsafe {
+            if libc::tcgetattr(libc::STDIN_FILENO, saved.as_mut_ptr()) != 0 {
+                return None;
+            }
+            saved.assume_init()
+        };
+        let mut raw = saved;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+        // SAFETY: `raw` is a complete copy of settings read from the terminal
+        match unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } {
+            0 => Some(RawMode { saved }),
+            _ => None,
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn enable() -> Option<RawMode> {
+        None
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        // SAFETY: the settings were read from the same terminal
+        #[cfg(unix)]
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.saved);
+        }
+    }
+}
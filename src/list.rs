@@ -0,0 +1,65 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use crate::error::RloxError;
+use crate::function::NativeFunction;
+use crate::token::Token;
+use crate::value::Value;
+
+/**
+ * Methods available on list values, e.g. `xs.push(4)`.
+ *   Each one is a native function bound to the list it was looked up on.
+ */
+pub(crate) fn get_method(list: &Rc<RefCell<Vec<Value>>>, name: &Token) -> Result<Value, RloxError> {
+    let list = Rc::clone(list);
+    let method = match name.lexeme() {
+        "push" => NativeFunction::new("push", 1, move |args| {
+            list.borrow_mut().push(args[0].clone());
+            Ok(Value::Nil)
+        }),
+        "pop" => NativeFunction::new("pop", 0, move |_| {
+            list.borrow_mut()
+                .pop()
+                .ok_or_else(|| RloxError::runtime("Can't pop from an empty list."))
+        }),
+        "len" => NativeFunction::new("len", 0, move |_| {
+            Ok(Value::Number(list.borrow().len() as f64))
+        }),
+        "insert" => NativeFunction::new("insert", 2, move |args| {
+            let len = list.borrow().len();
+            // inserting just past the last element appends
+            let i = match &args[0] {
+                Value::Number(n) if *n == len as f64 => len,
+                i => index(i, len)?,
+            };
+            list.borrow_mut().insert(i, args[1].clone());
+            Ok(Value::Nil)
+        }),
+        "remove" => NativeFunction::new("remove", 1, move |args| {
+            let len = list.borrow().len();
+            let i = index(&args[0], len)?;
+            Ok(list.borrow_mut().remove(i))
+        }),
+        _ => {
+            return Err(RloxError::runtime_at(
+                *name.line_number(),
+                format!("Undefined property '{}'.", name.lexeme()),
+            ))
+        }
+    };
+    Ok(Value::NativeFunction(Rc::new(method)))
+}
+
+/// Checks that `index` is an integer in `0..len` and converts it for use as a Rust index.
+pub(crate) fn index(index: &Value, len: usize) -> Result<usize, RloxError> {
+    match index {
+        Value::Number(n) if n.fract() == 0.0 => match *n >= 0.0 && (*n as usize) < len {
+            true => Ok(*n as usize),
+            false => Err(RloxError::runtime(format!(
+                "Index {} out of bounds for list of length {}.",
+                n, len
+            ))),
+        },
+        _ => Err(RloxError::runtime("List index must be an integer.")),
+    }
+}
@@ -1,55 +1,540 @@
 use std::env;
-use std::fs;
+use std::ffi::OsStr;
+use std::io::Read;
+use std::path::{Path, PathBuf};
 
-mod error;
-mod scanner;
-mod token;
-mod expr;
-mod parser;
-mod ast_printer;     // example visitor impl
-mod ast_printer_rpn; // another example visitor impl
+use rlox::ast_printer::AstPrinter;
+use rlox::ast_printer_dot;
+use rlox::ast_printer_json::AstPrinterJson;
+use rlox::ast_printer_rpn::AstPrinterRpn;
+use rlox::bytecode;
+use rlox::error::RloxError;
+use rlox::formatter;
+use rlox::heap::GcConfig;
+use rlox::interner;
+use rlox::line_editor::LineEditor;
+use rlox::repl::{self, Command};
+use rlox::resolver::Resolver;
+use rlox::rpn;
+use rlox::stmt::Stmt;
+use rlox::{Interpreter, Vm};
 
-use crate::scanner::Scanner;
-use crate::error::RloxError;
+const COMPILED_EXTENSION: &str = "loxc";
+const HISTORY_FILE: &str = ".rlox_history";
 
-fn main() -> Result<(), RloxError> {
+// Exit codes from sysexits.h
+const EX_USAGE: i32 = 64;
+const EX_DATAERR: i32 = 65;
+const EX_NOINPUT: i32 = 66;
+const EX_SOFTWARE: i32 = 70;
+const EX_IOERR: i32 = 74;
+
+const USAGE: &str = "\
+Usage: rlox [options] [script | -e code | -]
+       rlox run [options] <script | -e code | ->
+       rlox repl [--backend=tree|vm] [--gc-stress] [--no-opt]
+       rlox tokens <script | -e code | ->
+       rlox ast [--format=lisp|rpn|json|dot] <script | -e code | ->
+       rlox check <script | -e code | ->
+       rlox fmt [--check] files...
+
+A script of `-` is read from stdin. Without a script, the REPL starts.
+
+Options:
+  --backend=tree|vm      run on the tree-walking interpreter (default) or the bytecode VM
+  --gc-stress            collect garbage before every allocation, on the VM
+  --no-opt               don't fold constant expressions
+  --intern-stats         print string interner statistics on exit
+  --dump-bytecode        print the bytecode of the script instead of running it
+  --compile [-o file]    write the bytecode of the script to a .loxc file
+  --emit=ast-dot|ast-json
+                         print the syntax tree, like `rlox ast`
+  --rpn                  evaluate one reverse Polish notation expression per line
+  -e <code>              run code given on the command line
+  -h, --help             print this message
+  -V, --version          print the version";
+
+// Which engine runs the program: the tree-walking interpreter or the bytecode VM
+#[derive(Clone, Copy, PartialEq)]
+enum Backend {
+    Tree,
+    Vm,
+}
+
+// How `rlox ast` prints the syntax tree
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Lisp,
+    Rpn,
+    Json,
+    Dot,
+}
+
+// Where the script comes from
+enum Input {
+    File(String),
+    Stdin,
+    Inline(String),
+}
+
+impl Input {
+    fn read(&self) -> Result<String, RloxError> {
+        match self {
+            Input::File(path) => Ok(std::fs::read_to_string(path)?),
+            Input::Stdin => {
+                let mut source = String::new();
+                std::io::stdin().read_to_string(&mut source)?;
+                Ok(source)
+            }
+            Input::Inline(source) => Ok(source.clone()),
+        }
+    }
+}
+
+// Everything given on the command line besides the subcommand
+struct Options {
+    backend: Option<Backend>,
+    gc: GcConfig,
+    optimize: bool,
+    intern_stats: bool,
+    dump_bytecode: bool,
+    compile: bool,
+    output: Option<String>,
+    emit: Option<Format>,
+    format: Option<Format>,
+    rpn: bool,
+    help: bool,
+    version: bool,
+    flags: Vec<String>, // the flags given, without their values
+    inputs: Vec<Input>,
+}
+
+impl Options {
+    fn parse(args: Vec<String>) -> Options {
+        let mut options = Options {
+            backend: None,
+            gc: GcConfig::default(),
+            optimize: true,
+            intern_stats: false,
+            dump_bytecode: false,
+            compile: false,
+            output: None,
+            emit: None,
+            format: None,
+            rpn: false,
+            help: false,
+            version: false,
+            flags: Vec::new(),
+            inputs: Vec::new(),
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if arg.starts_with('-') && arg != "-" {
+                options.flags.push(arg.split('=').next().unwrap_or(&arg).to_string());
+            }
+            match arg.as_str() {
+                "--backend=tree" => options.backend = Some(Backend::Tree),
+                "--backend=vm" => options.backend = Some(Backend::Vm),
+                "--dump-bytecode" => options.dump_bytecode = true,
+                "--intern-stats" => options.intern_stats = true,
+                "--no-opt" => options.optimize = false,
+                "--emit=ast-dot" => options.emit = Some(Format::Dot),
+                "--emit=ast-json" => options.emit = Some(Format::Json),
+                "--format=lisp" => options.format = Some(Format::Lisp),
+                "--format=rpn" => options.format = Some(Format::Rpn),
+                "--format=json" => options.format = Some(Format::Json),
+                "--format=dot" => options.format = Some(Format::Dot),
+                "--rpn" => options.rpn = true,
+                "--gc-stress" => options.gc.stress = true,
+                "--compile" => options.compile = true,
+                "-o" => options.output = Some(args.next().unwrap_or_else(|| usage())),
+                "-e" => {
+                    let code = args.next().unwrap_or_else(|| usage());
+                    options.inputs.push(Input::Inline(code));
+                }
+                "-h" | "--help" => options.help = true,
+                "-V" | "--version" => options.version = true,
+                "-" => options.inputs.push(Input::Stdin),
+                _ if arg.starts_with('-') => usage(),
+                _ => options.inputs.push(Input::File(arg)),
+            }
+        }
+        options
+    }
+
+    // Only the VM has a garbage collector to stress, so asking to stress it picks the VM
+    fn backend(&self) -> Backend {
+        match self.backend {
+            Some(backend) => backend,
+            None if self.gc.stress => Backend::Vm,
+            None => Backend::Tree,
+        }
+    }
+
+    // Whether the flags ask for things that exclude each other, whichever order they came in
+    fn conflict(&self) -> bool {
+        let modes = [self.rpn, self.compile, self.dump_bytecode, self.emit.is_some()];
+        let needs_vm = self.gc.stress || self.compile || self.dump_bytecode;
+        modes.iter().filter(|mode| **mode).count() > 1
+            || needs_vm && self.backend == Some(Backend::Tree)
+    }
+}
+
+fn main() {
     let args = env::args().skip(1).collect::<Vec<_>>();
-    execute(args)
+    match execute(args) {
+        Ok(()) => (),
+        Err(RloxError::Exit(code)) => std::process::exit(code),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(exit_code(&e));
+        }
+    }
+}
+
+fn exit_code(error: &RloxError) -> i32 {
+    match error {
+        RloxError::IoError(e) if e.kind() == std::io::ErrorKind::NotFound => EX_NOINPUT,
+        RloxError::IoError(_) => EX_IOERR,
+        RloxError::SyntaxError(_) | RloxError::SyntaxErrors(_) => EX_DATAERR,
+        RloxError::BytecodeError(_) | RloxError::UnsupportedError(_) => EX_DATAERR,
+        RloxError::RuntimeError(_) => EX_SOFTWARE,
+        RloxError::Exit(code) => *code,
+    }
+}
+
+pub(crate) fn execute(mut args: Vec<String>) -> Result<(), RloxError> {
+    let subcommand = match args.first().map(String::as_str) {
+        Some("run") | Some("repl") | Some("tokens") | Some("ast") | Some("check") | Some("fmt") => {
+            Some(args.remove(0))
+        }
+        _ => None,
+    };
+    if subcommand.as_deref() == Some("fmt") {
+        return format_files(args);
+    }
+    let options = Options::parse(args);
+    if options.help {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    if options.version {
+        println!("rlox {}", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+    let input = match options.inputs.as_slice() {
+        [] => None,
+        [input] => Some(input),
+        _ => usage(),
+    };
+    if !options.flags.iter().all(|flag| takes_flag(subcommand.as_deref(), flag)) {
+        usage();
+    }
+    if options.conflict() {
+        usage();
+    }
+    let result = match (subcommand.as_deref(), input) {
+        (Some("repl"), None) => run_repl(options.backend(), options.gc, options.optimize),
+        (Some("tokens"), Some(input)) => print_tokens(input),
+        (Some("ast"), Some(input)) => print_ast(input, options.format.unwrap_or(Format::Lisp)),
+        (Some("check"), Some(input)) => check(input),
+        (Some("run"), Some(_)) | (None, _) => run(&options, input),
+        _ => usage(),
+    };
+    if options.intern_stats {
+        eprintln!("{}", interner::stats());
+    }
+    result
+}
+
+// Runs the script, or does what the flags ask with it instead
+fn run(options: &Options, input: Option<&Input>) -> Result<(), RloxError> {
+    let optimize = options.optimize;
+    match input {
+        _ if options.rpn => run_rpn(input),
+        Some(input) if options.compile => compile_file(input, options.output.as_ref(), optimize),
+        _ if options.compile || options.output.is_some() => usage(),
+        Some(input) if options.dump_bytecode => dump_file(input, optimize),
+        Some(input) if options.emit.is_some() => print_ast(input, options.emit.unwrap()),
+        Some(input) => run_file(input, options.backend(), options.gc, optimize),
+        None => run_repl(options.backend(), options.gc, optimize),
+    }
+}
+
+// Whether a subcommand takes a flag; most flags only make sense when running a script
+fn takes_flag(subcommand: Option<&str>, flag: &str) -> bool {
+    match flag {
+        "-e" | "-h" | "--help" | "-V" | "--version" | "--intern-stats" => true,
+        "--backend" | "--gc-stress" | "--no-opt" if subcommand == Some("repl") => true,
+        "--format" => subcommand == Some("ast"),
+        _ => matches!(subcommand, None | Some("run")),
+    }
+}
+
+// Prints the usage to stderr and exits, for command lines that don't make sense
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    std::process::exit(EX_USAGE);
+}
+
+fn new_vm(gc: GcConfig, optimize: bool) -> Vm {
+    let mut vm = Vm::with_gc_config(std::io::stdout(), gc);
+    vm.set_optimize(optimize);
+    vm
 }
 
-pub(crate) fn execute(args: Vec<String>) -> Result<(), RloxError> {
-    match args.len() {
-        l if l > 1 => {
-            println!("Usage: rlox [script]");
-            std::process::exit(64);
+fn new_interpreter(optimize: bool) -> Interpreter {
+    let interpreter = Interpreter::new();
+    interpreter.set_optimize(optimize);
+    interpreter
+}
+
+fn run_file(
+    input: &Input,
+    backend: Backend,
+    gc: GcConfig,
+    optimize: bool,
+) -> Result<(), RloxError> {
+    let file_path = match input {
+        Input::File(file_path) => file_path,
+        // Imports in a script without a file are resolved from the working directory
+        _ => {
+            let statements = rlox::parse_program(&input.read()?)?;
+            return match backend {
+                Backend::Tree => new_interpreter(optimize).interpret(statements),
+                Backend::Vm => new_vm(gc, optimize).interpret(statements),
+            };
         }
-        1 => run_file(&args[0]),
-        _ => run_repl(),
+    };
+    // Compiled scripts can only run on the VM
+    if Path::new(file_path).extension() == Some(OsStr::new(COMPILED_EXTENSION)) {
+        return new_vm(gc, optimize).run_compiled_file(file_path);
+    }
+    match backend {
+        Backend::Tree => new_interpreter(optimize).run_file(file_path),
+        Backend::Vm => new_vm(gc, optimize).run_file(file_path),
     }
 }
 
-fn run_file(file_path: &str) -> Result<(), RloxError> {
-    let data = fs::read_to_string(&file_path)?;
-    run(data)
+// Writes the bytecode of a script next to it, or to `output` if given
+fn compile_file(input: &Input, output: Option<&String>, optimize: bool) -> Result<(), RloxError> {
+    let source = input.read()?;
+    let script = rlox::compile_with(&source, optimize)?;
+    let output = match (output, input) {
+        (Some(output), _) => PathBuf::from(output),
+        (None, Input::File(file_path)) => Path::new(file_path).with_extension(COMPILED_EXTENSION),
+        (None, _) => usage(),
+    };
+    std::fs::write(output, bytecode::encode(&script, &source))?;
+    Ok(())
+}
+
+// Prints the bytecode the VM would run for a script, without running it
+fn dump_file(input: &Input, optimize: bool) -> Result<(), RloxError> {
+    let script = rlox::compile_with(&input.read()?, optimize)?;
+    print!("{}", rlox::disassembler::disassemble(&script));
+    Ok(())
 }
 
-fn run(source: String) -> Result<(), RloxError> {
-    let scanner = Scanner::new(source);
-    for token in scanner.tokens() {
-        println!("{}", token);
+fn print_tokens(input: &Input) -> Result<(), RloxError> {
+    print!("{}", repl::tokens(&input.read()?)?);
+    Ok(())
+}
+
+// Prints the syntax tree of a script, without running it
+fn print_ast(input: &Input, format: Format) -> Result<(), RloxError> {
+    let source = input.read()?;
+    match format {
+        Format::Lisp => {
+            for statement in &rlox::parse_program(&source)? {
+                println!("{}", AstPrinter::default().print_stmt(statement));
+            }
+        }
+        Format::Rpn => {
+            for statement in &rlox::parse_program(&source)? {
+                println!("{}", print_rpn(statement)?);
+            }
+        }
+        // Printed from the source, so literals and prints get the lines the parser recorded
+        Format::Dot => print!("{}", ast_printer_dot::print_source(&source)?),
+        Format::Json => {
+            let statements = rlox::parse_program(&source)?;
+            print!("{}", AstPrinterJson::default().print(&statements))
+        }
     }
     Ok(())
 }
 
-fn run_repl() -> Result<(), RloxError> {
-    let stdin = std::io::stdin();
+// Reverse Polish notation has no place for statements, other than those holding an expression
+fn print_rpn(statement: &Stmt) -> Result<String, RloxError> {
+    match statement {
+        Stmt::Expression(s) => Ok(AstPrinterRpn::default().print(s.expression().clone())),
+        Stmt::Print(s) => Ok(AstPrinterRpn::default().print(s.expression().clone())),
+        _ => Err(RloxError::UnsupportedError(
+            "only expression and print statements can be printed in RPN".to_string(),
+        )),
+    }
+}
+
+// Reports the errors the parser and resolver find in a script, without running it
+fn check(input: &Input) -> Result<(), RloxError> {
+    let statements = rlox::parse_program(&input.read()?)?;
+    Resolver::new().resolve(&statements)?;
+    Ok(())
+}
+
+// Evaluates one expression in reverse Polish notation per line of a script, or of stdin
+fn run_rpn(input: Option<&Input>) -> Result<(), RloxError> {
+    let (mut stdout, mut stderr) = (std::io::stdout(), std::io::stderr());
+    let failed = match input {
+        Some(Input::File(file_path)) => {
+            let file = std::fs::File::open(file_path)?;
+            rpn::run(std::io::BufReader::new(file), &mut stdout, &mut stderr)?
+        }
+        Some(Input::Inline(source)) => rpn::run(source.as_bytes(), &mut stdout, &mut stderr)?,
+        Some(Input::Stdin) | None => rpn::run(std::io::stdin().lock(), &mut stdout, &mut stderr)?,
+    };
+    // Each failed line was reported already, so only the status is left
+    match failed {
+        0 => Ok(()),
+        _ => Err(RloxError::Exit(EX_SOFTWARE)),
+    }
+}
+
+// Rewrites files in canonical layout, or with `--check` only reports the ones that aren't
+fn format_files(args: Vec<String>) -> Result<(), RloxError> {
+    let check = args.iter().any(|arg| arg == "--check");
+    let files = args
+        .iter()
+        .filter(|arg| *arg != "--check")
+        .collect::<Vec<_>>();
+    if files.is_empty() || files.iter().any(|file| file.starts_with('-')) {
+        usage();
+    }
+    let mut unformatted = 0;
+    for file in files {
+        let source = std::fs::read_to_string(file)?;
+        let formatted = formatter::format_source(&source)?;
+        if formatted == source {
+            continue;
+        }
+        match check {
+            true => {
+                eprintln!("{}: not formatted", file);
+                unformatted += 1;
+            }
+            false => std::fs::write(file, formatted)?,
+        }
+    }
+    match unformatted {
+        0 => Ok(()),
+        _ => Err(RloxError::Exit(1)),
+    }
+}
+
+// The engine the REPL runs entries on, which `:reset` replaces with a fresh one
+struct Session {
+    backend: Backend,
+    gc: GcConfig,
+    optimize: bool,
+    interpreter: Interpreter,
+    vm: Vm,
+}
+
+impl Session {
+    fn new(backend: Backend, gc: GcConfig, optimize: bool) -> Self {
+        Session {
+            backend,
+            gc,
+            optimize,
+            interpreter: new_interpreter(optimize),
+            vm: new_vm(gc, optimize),
+        }
+    }
+
+    fn run(&mut self, source: &str) -> Result<(), RloxError> {
+        let statements = repl::parse_entry(source)?;
+        match self.backend {
+            Backend::Tree => self.interpreter.interpret(statements),
+            Backend::Vm => self.vm.interpret(statements),
+        }
+    }
+
+    fn command(&mut self, command: Command) -> Result<(), RloxError> {
+        match command {
+            Command::Tokens(source) => print!("{}", repl::tokens(source)?),
+            Command::Ast(source) => print!("{}", repl::ast(source)?),
+            Command::Rpn(source) => print!("{}", repl::rpn(source)?),
+            Command::Env => {
+                let globals = match self.backend {
+                    Backend::Tree => self.interpreter.user_globals(),
+                    Backend::Vm => self.vm.user_globals(),
+                };
+                for (name, value) in globals {
+                    println!("{} = {}", name, value);
+                }
+            }
+            Command::Load("") => eprintln!("Usage: :load <file>"),
+            Command::Load(file) => match self.backend {
+                Backend::Tree => self.interpreter.run_file(file)?,
+                Backend::Vm => self.vm.run_file(file)?,
+            },
+            Command::Reset => *self = Session::new(self.backend, self.gc, self.optimize),
+            Command::Help => println!("{}", repl::HELP),
+            Command::Unknown(name) => eprintln!("Unknown command ':{}', see :help.", name),
+        }
+        Ok(())
+    }
+}
+
+// Lines are gathered until their brackets balance, then run against the same globals
+fn run_repl(backend: Backend, gc: GcConfig, optimize: bool) -> Result<(), RloxError> {
+    let mut session = Session::new(backend, gc, optimize);
+    let mut editor = match env::var_os("HOME") {
+        Some(home) => LineEditor::with_history_file(Path::new(&home).join(HISTORY_FILE)),
+        None => LineEditor::new(),
+    };
+    let mut source = String::new();
     loop {
-        print!("> ");
-        let mut buffer = String::new();
-        stdin.read_line(&mut buffer)?;
-        if buffer == "exit" {
+        let prompt = if source.is_empty() { "> " } else { "... " };
+        let line = match editor.read_line(prompt) {
+            Ok(Some(line)) => Some(line),
+            Ok(None) if source.is_empty() => break Ok(()),
+            // Whatever was typed before the end of input still runs
+            Ok(None) => None,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {
+                source.clear();
+                continue;
+            }
+            Err(e) => break Err(e.into()),
+        };
+        let result = match &line {
+            Some(line) => {
+                editor.add_history(line);
+                if source.is_empty() && line.trim() == "exit" {
+                    break Ok(());
+                }
+                match Command::parse(line) {
+                    Some(command) if source.is_empty() => session.command(command),
+                    _ => {
+                        source.push_str(line);
+                        source.push('\n');
+                        if repl::is_incomplete(&source) {
+                            continue;
+                        }
+                        session.run(&std::mem::take(&mut source))
+                    }
+                }
+            }
+            None => session.run(&std::mem::take(&mut source)),
+        };
+        match result {
+            Err(RloxError::Exit(code)) => std::process::exit(code),
+            Err(e) => eprintln!("{}", e),
+            Ok(()) => (),
+        }
+        if line.is_none() {
             break Ok(());
         }
-        let _result = run(buffer);
     }
 }
@@ -0,0 +1,142 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::error::RloxError;
+use crate::function::NativeFunction;
+use crate::interner::Symbol;
+use crate::token::Token;
+use crate::value::Value;
+
+/// The subset of runtime values that can be used as map keys.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Nil,
+    Bool(bool),
+    Number(u64), // bit pattern, with -0 folded into 0 so that equal numbers hash alike
+    String(Symbol),
+}
+
+impl MapKey {
+    pub fn from_value(value: &Value) -> Result<MapKey, RloxError> {
+        match value {
+            Value::Nil => Ok(MapKey::Nil),
+            Value::Bool(b) => Ok(MapKey::Bool(*b)),
+            Value::Number(n) if n.is_nan() => Err(RloxError::runtime("NaN can't be used as a map key.")),
+            Value::Number(n) => Ok(MapKey::Number((n + 0.0).to_bits())),
+            Value::String(s) => Ok(MapKey::String(s.clone())),
+            _ => Err(RloxError::runtime(format!(
+                "Map keys must be strings, numbers, booleans or nil, not {}.",
+                value.type_name()
+            ))),
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        match self {
+            MapKey::Nil => Value::Nil,
+            MapKey::Bool(b) => Value::Bool(*b),
+            MapKey::Number(bits) => Value::Number(f64::from_bits(*bits)),
+            MapKey::String(s) => Value::String(s.clone()),
+        }
+    }
+}
+
+/**
+ * Hash map that iterates in insertion order.
+ *   Entries live in a Vec, and a HashMap from key to position gives constant time lookups.
+ *   Values are generic so the bytecode VM can store its own value representation.
+ */
+pub struct LoxMap<V = Value> {
+    entries: Vec<(MapKey, V)>,
+    positions: HashMap<MapKey, usize>,
+}
+
+impl<V> Default for LoxMap<V> {
+    fn default() -> Self {
+        LoxMap {
+            entries: Vec::new(),
+            positions: HashMap::new(),
+        }
+    }
+}
+
+impl<V> LoxMap<V> {
+    pub fn new() -> Self {
+        LoxMap::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &MapKey) -> Option<&V> {
+        self.positions.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    pub fn contains_key(&self, key: &MapKey) -> bool {
+        self.positions.contains_key(key)
+    }
+
+    // Overwriting an existing key keeps its original position
+    pub fn insert(&mut self, key: MapKey, value: V) {
+        match self.positions.get(&key) {
+            Some(&i) => self.entries[i].1 = value,
+            None => {
+                self.positions.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &MapKey) -> Option<V> {
+        let i = self.positions.remove(key)?;
+        let (_, value) = self.entries.remove(i);
+        for (key, _) in &self.entries[i..] {
+            *self.positions.get_mut(key).unwrap() -= 1;
+        }
+        Some(value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&MapKey, &V)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+}
+
+/**
+ * Methods available on map values, e.g. `m.keys()`.
+ *   Each one is a native function bound to the map it was looked up on.
+ */
+pub(crate) fn get_method(map: &Rc<RefCell<LoxMap>>, name: &Token) -> Result<Value, RloxError> {
+    let map = Rc::clone(map);
+    let method = match name.lexeme() {
+        "keys" => NativeFunction::new("keys", 0, move |_| {
+            let keys = map.borrow().iter().map(|(key, _)| key.to_value()).collect::<Vec<_>>();
+            Ok(Value::from(keys))
+        }),
+        "values" => NativeFunction::new("values", 0, move |_| {
+            let values = map.borrow().iter().map(|(_, value)| value.clone()).collect::<Vec<_>>();
+            Ok(Value::from(values))
+        }),
+        "has" => NativeFunction::new("has", 1, move |args| {
+            let key = MapKey::from_value(&args[0])?;
+            Ok(Value::Bool(map.borrow().contains_key(&key)))
+        }),
+        "remove" => NativeFunction::new("remove", 1, move |args| {
+            let key = MapKey::from_value(&args[0])?;
+            Ok(map.borrow_mut().remove(&key).into())
+        }),
+        "len" => NativeFunction::new("len", 0, move |_| Ok(Value::Number(map.borrow().len() as f64))),
+        _ => {
+            return Err(RloxError::runtime_at(
+                *name.line_number(),
+                format!("Undefined property '{}'.", name.lexeme()),
+            ))
+        }
+    };
+    Ok(Value::NativeFunction(Rc::new(method)))
+}
@@ -0,0 +1,64 @@
+use std::cell::RefCell;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::environment::Environment;
+use crate::error::RloxError;
+use crate::token::Token;
+use crate::value::Value;
+
+/**
+ * A loaded Lox file, bound to a name by `import "path" as name;`.
+ *   Its members are the globals the file defined, read with `name.member`.
+ */
+pub struct LoxModule {
+    path: PathBuf,
+    globals: Rc<RefCell<Environment>>,
+}
+
+impl LoxModule {
+    pub(crate) fn new(path: PathBuf, globals: Rc<RefCell<Environment>>) -> Self {
+        LoxModule { path, globals }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub(crate) fn get(&self, name: &Token) -> Result<Value, RloxError> {
+        self.globals
+            .borrow()
+            .get_by_name(name.lexeme())
+            .ok_or_else(|| {
+                RloxError::runtime_at(
+                    *name.line_number(),
+                    format!(
+                        "Module '{}' has no member '{}'.",
+                        self.path.display(),
+                        name.lexeme()
+                    ),
+                )
+            })
+    }
+}
+
+/**
+ * Finds the file an import refers to, as a canonical path usable as a cache key.
+ *   The path is looked up relative to the directory of the importing file, or the working
+ *   directory outside of a file, then in each directory listed in RLOX_PATH.
+ */
+pub(crate) fn find(importer: Option<&Path>, path: &str) -> Option<PathBuf> {
+    let base = match importer.and_then(Path::parent) {
+        Some(directory) => Some(directory.to_path_buf()),
+        None => env::current_dir().ok(),
+    };
+    let search_path = env::var_os("RLOX_PATH")
+        .map(|paths| env::split_paths(&paths).collect::<Vec<_>>())
+        .unwrap_or_default();
+    base.into_iter()
+        .chain(search_path)
+        .map(|directory| directory.join(path))
+        .find(|candidate| candidate.is_file())
+        .and_then(|found| found.canonicalize().ok())
+}
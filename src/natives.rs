@@ -0,0 +1,190 @@
+use std::cell::Cell;
+use std::convert::TryFrom;
+use std::fs;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::RloxError;
+use crate::function::NativeFunction;
+use crate::interpreter::Interpreter;
+use crate::value::Value;
+
+// Parts of the runtime library that are simpler to write in Lox itself
+pub(crate) const PRELUDE: &str = "
+    class Error {
+        init(message) {
+            this.message = message;
+            this.line = nil;
+        }
+    }
+";
+
+/**
+ * The runtime library every interpreter starts with.
+ *   Natives report bad arguments as runtime errors without a line, the interpreter attributes
+ *   them to the line of the Lox call.
+ *   Runtime errors caught by a `try` statement are handed to Lox code as instances of `Error`.
+ */
+pub fn define_globals(interpreter: &Interpreter) {
+    for native in library() {
+        let native = Rc::new(native);
+        interpreter.define_global(native.name(), Value::NativeFunction(Rc::clone(&native)));
+    }
+    interpreter
+        .eval(PRELUDE)
+        .expect("the prelude is valid Lox");
+}
+
+/// The native functions of the runtime library, shared by both backends.
+pub(crate) fn library() -> Vec<NativeFunction> {
+    let mut natives = vec![
+        NativeFunction::new("clock", 0, |_| Ok(Value::Number(seconds_since_epoch()))),
+        NativeFunction::new("input", 0, |_| input()),
+        NativeFunction::new("readFile", 1, |args| {
+            let path = string_arg("readFile", args, 0)?;
+            fs::read_to_string(&path).map(Value::from).map_err(|e| {
+                RloxError::runtime(format!("readFile: could not read '{}': {}", path, e))
+            })
+        }),
+        NativeFunction::new("writeFile", 2, |args| {
+            let path = string_arg("writeFile", args, 0)?;
+            let contents = string_arg("writeFile", args, 1)?;
+            fs::write(&path, contents).map(|_| Value::Nil).map_err(|e| {
+                RloxError::runtime(format!("writeFile: could not write '{}': {}", path, e))
+            })
+        }),
+        NativeFunction::new("len", 1, |args| match &args[0] {
+            Value::List(list) => Ok(Value::Number(list.borrow().len() as f64)),
+            Value::Map(map) => Ok(Value::Number(map.borrow().len() as f64)),
+            _ => {
+                let s = string_arg("len", args, 0)?;
+                Ok(Value::Number(s.chars().count() as f64))
+            }
+        }),
+        NativeFunction::new("substr", 3, substr),
+        NativeFunction::new("toUpper", 1, |args| {
+            Ok(Value::from(string_arg("toUpper", args, 0)?.to_uppercase()))
+        }),
+        NativeFunction::new("parseNumber", 1, |args| {
+            let s = string_arg("parseNumber", args, 0)?;
+            Ok(s.trim().parse::<f64>().ok().into())
+        }),
+        NativeFunction::new("str", 1, |args| Ok(Value::from(args[0].to_string()))),
+        NativeFunction::new("sqrt", 1, |args| {
+            Ok(Value::Number(number_arg("sqrt", args, 0)?.sqrt()))
+        }),
+        NativeFunction::new("floor", 1, |args| {
+            Ok(Value::Number(number_arg("floor", args, 0)?.floor()))
+        }),
+        NativeFunction::new("exit", 1, exit),
+    ];
+    natives.extend(random());
+    natives
+}
+
+fn seconds_since_epoch() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64())
+}
+
+// Reads one line from stdin without its line ending, or nil at end of input
+fn input() -> Result<Value, RloxError> {
+    let mut line = String::new();
+    match std::io::stdin().read_line(&mut line)? {
+        0 => Ok(Value::Nil),
+        _ => Ok(Value::from(line.trim_end_matches(&['\r', '\n'][..]))),
+    }
+}
+
+// substr(string, start, length), counted in characters
+fn substr(args: &[Value]) -> Result<Value, RloxError> {
+    let s = string_arg("substr", args, 0)?;
+    let start = index_arg("substr", args, 1)?;
+    let length = index_arg("substr", args, 2)?;
+    let char_count = s.chars().count();
+    // Huge numbers saturate to usize::MAX, so the end may not fit either
+    match start.checked_add(length) {
+        Some(end) if end <= char_count => {
+            Ok(Value::from(s.chars().skip(start).take(length).collect::<String>()))
+        }
+        _ => Err(RloxError::runtime(format!(
+            "substr: {} characters from {} are out of bounds for a string of length {}.",
+            args[2], args[1], char_count
+        ))),
+    }
+}
+
+// exit(code) leaves the script with a status the host can pass on to the operating system
+fn exit(args: &[Value]) -> Result<Value, RloxError> {
+    match number_arg("exit", args, 0)? {
+        code if code.fract() == 0.0 && (0.0..=255.0).contains(&code) => {
+            Err(RloxError::Exit(code as i32))
+        }
+        code => Err(RloxError::runtime(format!(
+            "exit: expected an integer from 0 to 255 but got {}.",
+            code
+        ))),
+    }
+}
+
+/**
+ * random() returns a number in [0, 1), seedRandom(n) makes the sequence reproducible.
+ *   The generator is xorshift64*, good enough for scripts and free of dependencies.
+ */
+fn random() -> Vec<NativeFunction> {
+    let state = Rc::new(Cell::new(seed_from(seconds_since_epoch().to_bits())));
+
+    let random_state = Rc::clone(&state);
+    let random = NativeFunction::new("random", 0, move |_| {
+        let mut x = random_state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        random_state.set(x);
+        let bits = x.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 11; // keep 53 bits, the f64 mantissa
+        Ok(Value::Number(bits as f64 / (1u64 << 53) as f64))
+    });
+
+    let seed_random = NativeFunction::new("seedRandom", 1, move |args| {
+        let seed = number_arg("seedRandom", args, 0)?;
+        state.set(seed_from(seed.to_bits()));
+        Ok(Value::Nil)
+    });
+    vec![random, seed_random]
+}
+
+// xorshift gets stuck on a zero state, so scramble the seed and never hand zero back
+fn seed_from(seed: u64) -> u64 {
+    match seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) {
+        0 => 0x9E37_79B9_7F4A_7C15,
+        state => state,
+    }
+}
+
+fn string_arg(function: &str, args: &[Value], i: usize) -> Result<String, RloxError> {
+    String::try_from(args[i].clone()).map_err(|_| argument_error(function, "a string", &args[i]))
+}
+
+fn number_arg(function: &str, args: &[Value], i: usize) -> Result<f64, RloxError> {
+    f64::try_from(args[i].clone()).map_err(|_| argument_error(function, "a number", &args[i]))
+}
+
+fn index_arg(function: &str, args: &[Value], i: usize) -> Result<usize, RloxError> {
+    match number_arg(function, args, i)? {
+        n if n >= 0.0 && n.fract() == 0.0 => Ok(n as usize),
+        n => Err(RloxError::runtime(format!(
+            "{}: expected a non-negative integer but got {}.",
+            function, n
+        ))),
+    }
+}
+
+fn argument_error(function: &str, expected: &str, value: &Value) -> RloxError {
+    RloxError::runtime(format!(
+        "{}: expected {} but got {}.",
+        function,
+        expected,
+        value.type_name()
+    ))
+}
@@ -0,0 +1,182 @@
+use crate::expr::{BinaryExpr, Expr, GroupingExpr, LiteralExpr, LogicalExpr, UnaryExpr};
+use crate::fold::{self, Fold};
+use crate::stmt::Stmt;
+use crate::token::TokenType;
+
+/**
+ * Constant folding and algebraic simplification, run between parsing and resolving.
+ *   Operators whose operands are all literals are evaluated ahead of time, and identities such as
+ *   `x * 1` or `!!b` are dropped when the type of the other operand guarantees the same result.
+ *   Anything that would fail at runtime, like `"a" - 1`, is left for the runtime to report.
+ */
+#[derive(Default)]
+pub struct Optimizer;
+
+impl Optimizer {
+    pub fn new() -> Self {
+        Optimizer
+    }
+
+    /// Returns an optimized copy of a parsed program. It must be resolved afterwards.
+    pub fn optimize(&mut self, statements: &[Stmt]) -> Vec<Stmt> {
+        fold::fold_stmts(self, statements)
+    }
+}
+
+impl Fold for Optimizer {
+    fn fold_binary_expr(&mut self, expr: &BinaryExpr) -> Expr {
+        let operator = expr.operator();
+        let lhs = self.fold_expr(expr.lhs());
+        let rhs = self.fold_expr(expr.rhs());
+        if let (Some(a), Some(b)) = (literal(&lhs), literal(&rhs)) {
+            if let Some(folded) = fold_binary(operator.token_type(), a, b) {
+                return LiteralExpr::new(folded);
+            }
+        }
+        match (operator.token_type(), number(&lhs), number(&rhs)) {
+            // -0 + 0 is 0, so adding zero isn't an identity, but subtracting it is
+            (TokenType::Star, _, Some(n)) | (TokenType::Slash, _, Some(n))
+                if n == 1.0 && is_number(&lhs) =>
+            {
+                lhs
+            }
+            (TokenType::Star, Some(n), _) if n == 1.0 && is_number(&rhs) => rhs,
+            (TokenType::Minus, _, Some(n))
+                if n == 0.0 && n.is_sign_positive() && is_number(&lhs) =>
+            {
+                lhs
+            }
+            _ => BinaryExpr::new(operator.clone(), lhs, rhs),
+        }
+    }
+
+    fn fold_logical_expr(&mut self, expr: &LogicalExpr) -> Expr {
+        let lhs = self.fold_expr(expr.lhs());
+        let rhs = self.fold_expr(expr.rhs());
+        let short_circuits = match (expr.operator().token_type(), literal(&lhs)) {
+            (TokenType::Or, Some(a)) => Some(is_truthy(a)),
+            (TokenType::And, Some(a)) => Some(!is_truthy(a)),
+            _ => None,
+        };
+        match short_circuits {
+            Some(true) => lhs,
+            Some(false) => rhs,
+            None => LogicalExpr::new(expr.operator().clone(), lhs, rhs),
+        }
+    }
+
+    fn fold_unary_expr(&mut self, expr: &UnaryExpr) -> Expr {
+        let operator = expr.operator();
+        let rhs = self.fold_expr(expr.rhs());
+        match (operator.token_type(), literal(&rhs)) {
+            (TokenType::Minus, Some(LiteralExpr::Float(n))) => {
+                return LiteralExpr::new(LiteralExpr::Float(-n))
+            }
+            (TokenType::Bang, Some(a)) => {
+                return LiteralExpr::new(LiteralExpr::Bool(!is_truthy(a)))
+            }
+            _ => (),
+        }
+        // `!!b` is `b` for booleans, and `--n` is `n` for numbers
+        if let Expr::Unary(inner) = &rhs {
+            if inner.operator().token_type() == operator.token_type() {
+                let operand = inner.rhs();
+                match operator.token_type() {
+                    TokenType::Bang if is_boolean(operand) => return operand.clone(),
+                    TokenType::Minus if is_number(operand) => return operand.clone(),
+                    _ => (),
+                }
+            }
+        }
+        UnaryExpr::new(operator.clone(), rhs)
+    }
+
+    // Parentheses around a literal are no longer needed to group anything
+    fn fold_grouping_expr(&mut self, expr: &GroupingExpr) -> Expr {
+        match self.fold_expr(expr.expression()) {
+            folded @ Expr::Literal(_) => folded,
+            folded => GroupingExpr::new(folded),
+        }
+    }
+}
+
+fn literal(expr: &Expr) -> Option<&LiteralExpr> {
+    match expr {
+        Expr::Literal(e) => Some(e),
+        _ => None,
+    }
+}
+
+fn number(expr: &Expr) -> Option<f64> {
+    match literal(expr) {
+        Some(LiteralExpr::Float(n)) => Some(*n),
+        _ => None,
+    }
+}
+
+fn is_truthy(literal: &LiteralExpr) -> bool {
+    !matches!(literal, LiteralExpr::Nil | LiteralExpr::Bool(false))
+}
+
+// Whether `expr` evaluates to a number whenever it doesn't raise an error
+fn is_number(expr: &Expr) -> bool {
+    match expr {
+        Expr::Literal(e) => matches!(**e, LiteralExpr::Float(_)),
+        Expr::Grouping(e) => is_number(e.expression()),
+        Expr::Unary(e) => *e.operator().token_type() == TokenType::Minus,
+        Expr::Binary(e) => matches!(
+            e.operator().token_type(),
+            TokenType::Minus | TokenType::Star | TokenType::Slash
+        ),
+        _ => false,
+    }
+}
+
+// Whether `expr` evaluates to a boolean whenever it doesn't raise an error
+fn is_boolean(expr: &Expr) -> bool {
+    match expr {
+        Expr::Literal(e) => matches!(**e, LiteralExpr::Bool(_)),
+        Expr::Grouping(e) => is_boolean(e.expression()),
+        Expr::Unary(e) => *e.operator().token_type() == TokenType::Bang,
+        Expr::Binary(e) => matches!(
+            e.operator().token_type(),
+            TokenType::Greater
+                | TokenType::GreaterEqual
+                | TokenType::Less
+                | TokenType::LessEqual
+                | TokenType::EqualEqual
+                | TokenType::BangEqual
+        ),
+        _ => false,
+    }
+}
+
+// Evaluates a binary operator on two literals, or None where the runtime would raise an error
+fn fold_binary(operator: &TokenType, a: &LiteralExpr, b: &LiteralExpr) -> Option<LiteralExpr> {
+    use LiteralExpr::*;
+    let folded = match (operator, a, b) {
+        (TokenType::Plus, Float(a), Float(b)) => Float(a + b),
+        (TokenType::Plus, String(a), String(b)) => String(format!("{}{}", a, b)),
+        (TokenType::Minus, Float(a), Float(b)) => Float(a - b),
+        (TokenType::Star, Float(a), Float(b)) => Float(a * b),
+        (TokenType::Slash, Float(a), Float(b)) => Float(a / b),
+        (TokenType::Greater, Float(a), Float(b)) => Bool(a > b),
+        (TokenType::GreaterEqual, Float(a), Float(b)) => Bool(a >= b),
+        (TokenType::Less, Float(a), Float(b)) => Bool(a < b),
+        (TokenType::LessEqual, Float(a), Float(b)) => Bool(a <= b),
+        (TokenType::EqualEqual, a, b) => Bool(literals_equal(a, b)),
+        (TokenType::BangEqual, a, b) => Bool(!literals_equal(a, b)),
+        _ => return None,
+    };
+    Some(folded)
+}
+
+fn literals_equal(a: &LiteralExpr, b: &LiteralExpr) -> bool {
+    match (a, b) {
+        (LiteralExpr::Nil, LiteralExpr::Nil) => true,
+        (LiteralExpr::Bool(a), LiteralExpr::Bool(b)) => a == b,
+        (LiteralExpr::Float(a), LiteralExpr::Float(b)) => a == b,
+        (LiteralExpr::String(a), LiteralExpr::String(b)) => a == b,
+        _ => false,
+    }
+}
@@ -1,40 +1,603 @@
-use crate::expr::{BinaryExpr, Expr, GroupingExpr, LiteralExpr, UnaryExpr};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::error::{RloxError, RloxSyntaxError};
+use crate::expr::{
+    AssignExpr, BinaryExpr, CallExpr, Expr, FunctionExpr, GetExpr, GroupingExpr, IndexExpr,
+    IndexSetExpr, ListExpr, LiteralExpr, LogicalExpr, MapExpr, SetExpr, SuperExpr, ThisExpr,
+    UnaryExpr, VariableExpr,
+};
+use crate::interpreter::{RED_ZONE, STACK_SEGMENT};
+use crate::stmt::{
+    BlockStmt, BreakStmt, ClassStmt, ContinueStmt, ExpressionStmt, FunctionStmt, IfStmt,
+    ImportStmt, PrintStmt, ReturnStmt, Stmt, ThrowStmt, TryStmt, VarStmt, WhileStmt,
+};
 use crate::token::{Literal, Token, TokenType};
 
-pub struct Parser {
+const MAX_ARGUMENTS: usize = 255;
+// Deeper trees would overflow the stack of whatever walks them, or drops them
+pub(crate) const MAX_DEPTH: usize = 128;
+
+pub struct Parser<B: Builder = TreeBuilder> {
     tokens: Vec<Token>,
     current: usize,
+    errors: Vec<RloxSyntaxError>,
+    loop_depth: usize, // loops enclosing the current statement within the current function
+    depth: usize,      // nodes enclosing the one being parsed
+    height: usize,     // nodes on the longest path down from the last node made
+    builder: B,
 }
 
-enum ParseError {
-    // TODO
-}
+type ParseResult<T> = Result<T, RloxSyntaxError>;
 
 impl Parser {
-    pub fn parse(&self) -> Result<Expr, String> {
-        self.expression() // TODO
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Parser::with_builder(tokens, TreeBuilder::default())
+    }
+
+    /**
+     * The first and last line of every statement parsed so far, and of every function body.
+     *   Statements are keyed by `Stmt::id`, function bodies by the id of their `FunctionExpr`.
+     *   The formatter uses them to put comments and blank lines back where they were.
+     */
+    pub(crate) fn take_spans(&mut self) -> HashMap<usize, (usize, usize)> {
+        std::mem::take(&mut self.builder.spans)
+    }
+
+    /// How each `for` loop parsed so far was written, keyed by the `Stmt::id` it was turned into.
+    pub(crate) fn take_for_loops(&mut self) -> HashMap<usize, ForLoop> {
+        std::mem::take(&mut self.builder.for_loops)
+    }
+
+    /// The line each literal parsed so far was written on, keyed by its `Expr::id`.
+    pub(crate) fn take_literal_lines(&mut self) -> HashMap<usize, usize> {
+        std::mem::take(&mut self.builder.literal_lines)
+    }
+}
+
+impl<B: Builder> Parser<B> {
+    pub(crate) fn with_builder(tokens: Vec<Token>, builder: B) -> Self {
+        Parser {
+            tokens,
+            current: 0,
+            errors: Vec::new(),
+            loop_depth: 0,
+            depth: 0,
+            height: 0,
+            builder,
+        }
+    }
+
+    pub(crate) fn into_builder(self) -> B {
+        self.builder
+    }
+
+    // program --> declaration* EOF ;
+    pub fn parse(&mut self) -> Result<Vec<B::Stmt>, RloxError> {
+        let mut statements = Vec::new();
+        while !self.is_at_end() {
+            match self.declaration() {
+                Ok(stmt) => statements.push(stmt),
+                Err(e) => {
+                    self.errors.push(e);
+                    self.synchronize();
+                }
+            }
+        }
+        self.finish(statements)
+    }
+
+    // Parses a single expression which must span the whole token stream
+    pub fn parse_expression(&mut self) -> Result<B::Expr, RloxError> {
+        let result = self.expression().and_then(|expr| {
+            if self.is_at_end() {
+                Ok(expr)
+            } else {
+                Err(self.error(self.peek(), "Expect end of expression."))
+            }
+        });
+        match result {
+            Ok(expr) => self.finish(expr),
+            Err(e) => {
+                self.errors.push(e);
+                Err(RloxError::SyntaxErrors(std::mem::take(&mut self.errors)))
+            }
+        }
+    }
+
+    // The lines of a node, from the line `start` to the line of the last token consumed
+    fn lines_from(&self, start: usize) -> (usize, usize) {
+        (start, *self.previous().line_number())
+    }
+
+    /**
+     * Makes a node over children no higher than `height`, at the last token consumed.
+     *   Reports a tree that gets too deep, or a builder that can hold no more.
+     */
+    fn build<T>(&mut self, height: usize, make: impl FnOnce(&mut B) -> Built<T>) -> ParseResult<T> {
+        self.height = height + 1;
+        if self.height > MAX_DEPTH {
+            return Err(self.error(self.previous(), "Too much nesting."));
+        }
+        make(&mut self.builder).map_err(|msg| self.error(self.previous(), msg))
+    }
+
+    // Parses a node inside the one being parsed, growing the stack as the interpreter does
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> ParseResult<T>) -> ParseResult<T> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error(self.peek(), "Too much nesting."));
+        }
+        self.depth += 1;
+        let parsed = stacker::maybe_grow(RED_ZONE, STACK_SEGMENT, || parse(self));
+        self.depth -= 1;
+        parsed
+    }
+
+    fn finish<T>(&mut self, parsed: T) -> Result<T, RloxError> {
+        match self.errors.is_empty() {
+            true => Ok(parsed),
+            false => Err(RloxError::SyntaxErrors(std::mem::take(&mut self.errors))),
+        }
+    }
+
+    // declaration --> classDecl | funDecl | importDecl | varDecl | statement ;
+    fn declaration(&mut self) -> ParseResult<B::Stmt> {
+        let start = *self.peek().line_number();
+        let stmt = if self.advance_if_match(&[&TokenType::Class]) {
+            self.class_declaration()
+        } else if self.is_current_token_type(&TokenType::Fun)
+            && self.peek_next_is(&TokenType::Identifier)
+        {
+            // `fun` followed by anything but a name starts a lambda expression statement
+            self.advance();
+            let function = self.function("function")?;
+            self.build(self.height, |b| b.function_stmt(function))
+        } else if self.advance_if_match(&[&TokenType::Import]) {
+            self.import_declaration()
+        } else if self.advance_if_match(&[&TokenType::Var]) {
+            self.var_declaration()
+        } else {
+            self.statement()
+        }?;
+        let lines = self.lines_from(start);
+        self.builder.statement_lines(&stmt, lines);
+        Ok(stmt)
+    }
+
+    // importDecl --> "import" STRING "as" IDENTIFIER ";" ;
+    // `as` is not reserved, it only has a meaning right after the module path
+    fn import_declaration(&mut self) -> ParseResult<B::Stmt> {
+        let keyword = self.previous().clone();
+        let path = match self.consume(&TokenType::String, "Expect module path.")?.literal() {
+            Some(Literal::String(path)) => path.clone(),
+            _ => unreachable!("the scanner gives every string token a string literal"),
+        };
+        if self.peek().lexeme() != "as" {
+            return Err(self.error(self.peek(), "Expect 'as' after module path."));
+        }
+        self.advance();
+        let name = self
+            .consume(&TokenType::Identifier, "Expect module name.")?
+            .clone();
+        self.consume(&TokenType::Semicolon, "Expect ';' after import.")?;
+        self.build(0, |b| b.import_stmt(keyword, path, name))
+    }
+
+    // classDecl --> "class" IDENTIFIER ( "<" IDENTIFIER )? "{" function* "}" ;
+    fn class_declaration(&mut self) -> ParseResult<B::Stmt> {
+        let name = self
+            .consume(&TokenType::Identifier, "Expect class name.")?
+            .clone();
+        let superclass = match self.advance_if_match(&[&TokenType::Less]) {
+            true => {
+                let name = self
+                    .consume(&TokenType::Identifier, "Expect superclass name.")?
+                    .clone();
+                Some(self.build(0, |b| b.variable_expr(name))?)
+            }
+            false => None,
+        };
+        self.consume(&TokenType::LeftBrace, "Expect '{' before class body.")?;
+        let mut methods = Vec::new();
+        let mut height = 1;
+        while !self.is_current_token_type(&TokenType::RightBrace) && !self.is_at_end() {
+            methods.push(self.function("method")?);
+            height = height.max(self.height);
+        }
+        self.consume(&TokenType::RightBrace, "Expect '}' after class body.")?;
+        self.build(height, |b| b.class_stmt(name, superclass, methods))
+    }
+
+    // function --> IDENTIFIER "(" parameters? ")" block ;
+    fn function(&mut self, kind: &str) -> ParseResult<B::Function> {
+        let name = self
+            .consume(&TokenType::Identifier, &format!("Expect {} name.", kind))?
+            .clone();
+        self.consume(
+            &TokenType::LeftParen,
+            &format!("Expect '(' after {} name.", kind),
+        )?;
+        let (params, body) = self.function_params_and_body(kind)?;
+        let lines = self.lines_from(*name.line_number());
+        let function = self.build(self.height, |b| b.function_declaration(name, params, body))?;
+        self.builder.function_lines(&function, lines);
+        Ok(function)
+    }
+
+    // lambda --> "fun" "(" parameters? ")" block ;
+    fn lambda(&mut self) -> ParseResult<B::Expr> {
+        let keyword = self.previous().clone();
+        self.consume(&TokenType::LeftParen, "Expect '(' after 'fun'.")?;
+        let (params, body) = self.function_params_and_body("function")?;
+        let lines = self.lines_from(*keyword.line_number());
+        let lambda = self.build(self.height, |b| b.function_expr(keyword, params, body))?;
+        self.builder.lambda_lines(&lambda, lines);
+        Ok(lambda)
+    }
+
+    // parameters --> IDENTIFIER ( "," IDENTIFIER )* ;
+    // Leaves the height of the tallest statement of the body as the height
+    fn function_params_and_body(&mut self, kind: &str) -> ParseResult<(Vec<Token>, Vec<B::Stmt>)> {
+        let mut params = Vec::new();
+        if !self.is_current_token_type(&TokenType::RightParen) {
+            loop {
+                if params.len() >= MAX_ARGUMENTS {
+                    let e = self.error(self.peek(), "Can't have more than 255 parameters.");
+                    self.errors.push(e);
+                }
+                params.push(
+                    self.consume(&TokenType::Identifier, "Expect parameter name.")?
+                        .clone(),
+                );
+                if !self.advance_if_match(&[&TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(&TokenType::RightParen, "Expect ')' after parameters.")?;
+        self.consume(
+            &TokenType::LeftBrace,
+            &format!("Expect '{{' before {} body.", kind),
+        )?;
+        // break and continue can't jump out of a function body into a loop around it
+        let enclosing_loop_depth = std::mem::replace(&mut self.loop_depth, 0);
+        let body = self.nested(Self::block);
+        self.loop_depth = enclosing_loop_depth;
+        Ok((params, body?))
+    }
+
+    // varDecl --> "var" IDENTIFIER ( "=" expression )? ";" ;
+    fn var_declaration(&mut self) -> ParseResult<B::Stmt> {
+        let name = self
+            .consume(&TokenType::Identifier, "Expect variable name.")?
+            .clone();
+        let (initializer, height) = match self.advance_if_match(&[&TokenType::Equal]) {
+            true => (Some(self.expression()?), self.height),
+            false => (None, 0),
+        };
+        self.consume(
+            &TokenType::Semicolon,
+            "Expect ';' after variable declaration.",
+        )?;
+        self.build(height, |b| b.var_stmt(name, initializer))
+    }
+
+    // statement --> exprStmt | forStmt | ifStmt | printStmt | returnStmt | whileStmt | block
+    //               | breakStmt | continueStmt | throwStmt | tryStmt ;
+    fn statement(&mut self) -> ParseResult<B::Stmt> {
+        self.nested(Self::nested_statement)
+    }
+
+    fn nested_statement(&mut self) -> ParseResult<B::Stmt> {
+        let start = *self.peek().line_number();
+        let stmt = if self.advance_if_match(&[&TokenType::Break]) {
+            let keyword = self.loop_jump_statement("break")?;
+            self.build(0, |b| b.break_stmt(keyword))
+        } else if self.advance_if_match(&[&TokenType::Continue]) {
+            let keyword = self.loop_jump_statement("continue")?;
+            self.build(0, |b| b.continue_stmt(keyword))
+        } else if self.advance_if_match(&[&TokenType::For]) {
+            self.for_statement()
+        } else if self.advance_if_match(&[&TokenType::If]) {
+            self.if_statement()
+        } else if self.advance_if_match(&[&TokenType::Print]) {
+            self.print_statement()
+        } else if self.advance_if_match(&[&TokenType::Return]) {
+            self.return_statement()
+        } else if self.advance_if_match(&[&TokenType::Throw]) {
+            self.throw_statement()
+        } else if self.advance_if_match(&[&TokenType::Try]) {
+            self.try_statement()
+        } else if self.advance_if_match(&[&TokenType::While]) {
+            self.while_statement()
+        } else if self.is_current_token_type(&TokenType::LeftBrace) && !self.starts_map_literal() {
+            self.advance();
+            let statements = self.block()?;
+            self.build(self.height, |b| b.block_stmt(statements))
+        } else {
+            self.expression_statement()
+        }?;
+        let lines = self.lines_from(start);
+        self.builder.statement_lines(&stmt, lines);
+        Ok(stmt)
+    }
+
+    /**
+     * A statement starting with '{' is a block, unless the brace is followed by `} ;` or by a key
+     *   of a single token and a colon, since no statement can start with those. Maps in statement
+     *   position whose first key is any longer have to be put in parentheses.
+     */
+    fn starts_map_literal(&self) -> bool {
+        let token_type = |offset| self.tokens.get(self.current + offset).map(Token::token_type);
+        match (token_type(1), token_type(2)) {
+            (Some(TokenType::RightBrace), Some(TokenType::Semicolon)) => true,
+            (Some(key), Some(TokenType::Colon)) => matches!(
+                key,
+                TokenType::String
+                    | TokenType::Number
+                    | TokenType::Identifier
+                    | TokenType::True
+                    | TokenType::False
+                    | TokenType::Nil
+                    | TokenType::This
+            ),
+            _ => false,
+        }
     }
 
-    // expression --> equality ;
-    fn expression(&mut self) -> Expr {
-        self.equality()
+    // breakStmt --> "break" ";" ;
+    // continueStmt --> "continue" ";" ;
+    fn loop_jump_statement(&mut self, keyword: &str) -> ParseResult<Token> {
+        let token = self.previous().clone();
+        if self.loop_depth == 0 {
+            // Report without unwinding, the statement itself is well formed
+            let e = self.error(&token, &format!("Can't use '{}' outside of a loop.", keyword));
+            self.errors.push(e);
+        }
+        self.consume(
+            &TokenType::Semicolon,
+            &format!("Expect ';' after '{}'.", keyword),
+        )?;
+        Ok(token)
+    }
+
+    // throwStmt --> "throw" expression ";" ;
+    fn throw_statement(&mut self) -> ParseResult<B::Stmt> {
+        let keyword = self.previous().clone();
+        let value = self.expression()?;
+        self.consume(&TokenType::Semicolon, "Expect ';' after thrown value.")?;
+        self.build(self.height, |b| b.throw_stmt(keyword, value))
+    }
+
+    // tryStmt --> "try" block ( "catch" "(" IDENTIFIER ")" block )? ( "finally" block )? ;
+    fn try_statement(&mut self) -> ParseResult<B::Stmt> {
+        let keyword = self.previous().clone();
+        self.consume(&TokenType::LeftBrace, "Expect '{' after 'try'.")?;
+        let body = self.block()?;
+        let mut height = self.height;
+        let catch = match self.advance_if_match(&[&TokenType::Catch]) {
+            true => {
+                self.consume(&TokenType::LeftParen, "Expect '(' after 'catch'.")?;
+                let name = self
+                    .consume(&TokenType::Identifier, "Expect exception variable name.")?
+                    .clone();
+                self.consume(&TokenType::RightParen, "Expect ')' after exception variable.")?;
+                self.consume(&TokenType::LeftBrace, "Expect '{' before catch body.")?;
+                let body = self.block()?;
+                height = height.max(self.height);
+                Some((name, body))
+            }
+            false => None,
+        };
+        let finally = match self.advance_if_match(&[&TokenType::Finally]) {
+            true => {
+                self.consume(&TokenType::LeftBrace, "Expect '{' after 'finally'.")?;
+                let body = self.block()?;
+                height = height.max(self.height);
+                Some(body)
+            }
+            false => None,
+        };
+        if catch.is_none() && finally.is_none() {
+            return Err(self.error(&keyword, "Expect 'catch' or 'finally' after try block."));
+        }
+        self.build(height, |b| b.try_stmt(body, catch, finally))
+    }
+
+    fn loop_body(&mut self) -> ParseResult<B::Stmt> {
+        self.loop_depth += 1;
+        let body = self.statement();
+        self.loop_depth -= 1;
+        body
+    }
+
+    // forStmt --> "for" "(" ( varDecl | exprStmt | ";" ) expression? ";" expression? ")" statement ;
+    // There is no for node in the tree, the loop is desugared into a while loop
+    // that carries the increment, so that `continue` still runs it
+    fn for_statement(&mut self) -> ParseResult<B::Stmt> {
+        self.consume(&TokenType::LeftParen, "Expect '(' after 'for'.")?;
+        let initializer = if self.advance_if_match(&[&TokenType::Semicolon]) {
+            None
+        } else if self.advance_if_match(&[&TokenType::Var]) {
+            Some(self.var_declaration()?)
+        } else {
+            Some(self.expression_statement()?)
+        };
+        let initializer_height = self.height;
+        let form = ForLoop {
+            initializer: initializer.is_some(),
+            condition: !self.is_current_token_type(&TokenType::Semicolon),
+        };
+        let condition = match self.is_current_token_type(&TokenType::Semicolon) {
+            true => {
+                let line = *self.peek().line_number();
+                self.build(0, |b| b.literal_expr(LiteralExpr::Bool(true), line))?
+            }
+            false => self.expression()?,
+        };
+        let mut height = self.height;
+        self.consume(&TokenType::Semicolon, "Expect ';' after loop condition.")?;
+        let increment = match self.is_current_token_type(&TokenType::RightParen) {
+            true => None,
+            false => Some(self.expression()?),
+        };
+        height = height.max(self.height);
+        self.consume(&TokenType::RightParen, "Expect ')' after for clauses.")?;
+
+        let body = self.loop_body()?;
+        height = height.max(self.height);
+        let body = self.build(height, |b| b.while_stmt(condition, body, increment))?;
+        let stmt = match initializer {
+            Some(initializer) => {
+                let height = self.height.max(initializer_height);
+                self.build(height, |b| b.block_stmt(vec![initializer, body]))?
+            }
+            None => body,
+        };
+        self.builder.for_loop(&stmt, form);
+        Ok(stmt)
+    }
+
+    // ifStmt --> "if" "(" expression ")" statement ( "else" statement )? ;
+    fn if_statement(&mut self) -> ParseResult<B::Stmt> {
+        self.consume(&TokenType::LeftParen, "Expect '(' after 'if'.")?;
+        let condition = self.expression()?;
+        let mut height = self.height;
+        self.consume(&TokenType::RightParen, "Expect ')' after if condition.")?;
+        let then_branch = self.statement()?;
+        height = height.max(self.height);
+        let else_branch = match self.advance_if_match(&[&TokenType::Else]) {
+            true => Some(self.statement()?),
+            false => None,
+        };
+        height = height.max(self.height);
+        self.build(height, |b| b.if_stmt(condition, then_branch, else_branch))
+    }
+
+    // printStmt --> "print" expression ";" ;
+    fn print_statement(&mut self) -> ParseResult<B::Stmt> {
+        let keyword = self.previous().clone();
+        let value = self.expression()?;
+        self.consume(&TokenType::Semicolon, "Expect ';' after value.")?;
+        self.build(self.height, |b| b.print_stmt(keyword, value))
+    }
+
+    // returnStmt --> "return" expression? ";" ;
+    fn return_statement(&mut self) -> ParseResult<B::Stmt> {
+        let keyword = self.previous().clone();
+        let (value, height) = match self.is_current_token_type(&TokenType::Semicolon) {
+            true => (None, 0),
+            false => (Some(self.expression()?), self.height),
+        };
+        self.consume(&TokenType::Semicolon, "Expect ';' after return value.")?;
+        self.build(height, |b| b.return_stmt(keyword, value))
+    }
+
+    // whileStmt --> "while" "(" expression ")" statement ;
+    fn while_statement(&mut self) -> ParseResult<B::Stmt> {
+        self.consume(&TokenType::LeftParen, "Expect '(' after 'while'.")?;
+        let condition = self.expression()?;
+        let height = self.height;
+        self.consume(&TokenType::RightParen, "Expect ')' after condition.")?;
+        let body = self.loop_body()?;
+        self.build(height.max(self.height), |b| b.while_stmt(condition, body, None))
+    }
+
+    // block --> "{" declaration* "}" ;
+    // Leaves the height of the tallest statement as the height
+    fn block(&mut self) -> ParseResult<Vec<B::Stmt>> {
+        let mut statements = Vec::new();
+        let mut height = 0;
+        while !self.is_current_token_type(&TokenType::RightBrace) && !self.is_at_end() {
+            statements.push(self.declaration()?);
+            height = height.max(self.height);
+        }
+        self.consume(&TokenType::RightBrace, "Expect '}' after block.")?;
+        self.height = height;
+        Ok(statements)
+    }
+
+    // exprStmt --> expression ";" ;
+    fn expression_statement(&mut self) -> ParseResult<B::Stmt> {
+        let expr = self.expression()?;
+        self.consume(&TokenType::Semicolon, "Expect ';' after expression.")?;
+        self.build(self.height, |b| b.expression_stmt(expr))
+    }
+
+    // expression --> assignment ;
+    fn expression(&mut self) -> ParseResult<B::Expr> {
+        self.nested(Self::assignment)
+    }
+
+    // assignment --> ( call "." )? IDENTIFIER "=" assignment
+    //                | call "[" expression "]" "=" assignment | logic_or ;
+    fn assignment(&mut self) -> ParseResult<B::Expr> {
+        let expr = self.or()?;
+        if self.advance_if_match(&[&TokenType::Equal]) {
+            let equals = self.previous().clone();
+            let target_height = self.height;
+            let target = self.builder.assignment_target(expr);
+            let value = self.nested(Self::assignment)?;
+            let height = self.height.max(target_height);
+            return match target {
+                Ok(Target::Variable(name)) => self.build(height, |b| b.assign_expr(name, value)),
+                Ok(Target::Get(object, name)) => {
+                    self.build(height, |b| b.set_expr(object, name, value))
+                }
+                Ok(Target::Index(object, bracket, index)) => {
+                    self.build(height, |b| b.index_set_expr(object, bracket, index, value))
+                }
+                Err(expr) => {
+                    // Report without unwinding, the parser is not in a confused state
+                    let e = self.error(&equals, "Invalid assignment target.");
+                    self.errors.push(e);
+                    self.height = target_height;
+                    Ok(expr)
+                }
+            };
+        }
+        Ok(expr)
+    }
+
+    // logic_or --> logic_and ( "or" logic_and )* ;
+    fn or(&mut self) -> ParseResult<B::Expr> {
+        let mut expr = self.and()?;
+        while self.advance_if_match(&[&TokenType::Or]) {
+            let operator = self.previous().clone();
+            let height = self.height;
+            let rhs = self.and()?;
+            expr = self.build(height.max(self.height), |b| b.logical_expr(operator, expr, rhs))?;
+        }
+        Ok(expr)
+    }
+
+    // logic_and --> equality ( "and" equality )* ;
+    fn and(&mut self) -> ParseResult<B::Expr> {
+        let mut expr = self.equality()?;
+        while self.advance_if_match(&[&TokenType::And]) {
+            let operator = self.previous().clone();
+            let height = self.height;
+            let rhs = self.equality()?;
+            expr = self.build(height.max(self.height), |b| b.logical_expr(operator, expr, rhs))?;
+        }
+        Ok(expr)
     }
 
     // equality --> comparison ( ( "!=" | "==" ) comparison )* ;
-    fn equality(&mut self) -> Expr {
-        let mut expr = self.comparison();
-        while self.advance_if_match(&[&TokenType::BangEqual, &TokenType::Equal]) {
+    fn equality(&mut self) -> ParseResult<B::Expr> {
+        let mut expr = self.comparison()?;
+        while self.advance_if_match(&[&TokenType::BangEqual, &TokenType::EqualEqual]) {
             let operator = self.previous().clone();
-            let lhs = expr.clone();
-            let rhs = self.comparison().clone();
-            expr = BinaryExpr::new(operator, lhs, rhs)
+            let height = self.height;
+            let rhs = self.comparison()?;
+            expr = self.build(height.max(self.height), |b| b.binary_expr(operator, expr, rhs))?;
         }
-        expr
+        Ok(expr)
     }
 
     // comparison --> term ( ( ">" | ">=" | "<" | "<=" ) term )* ;
-    fn comparison(&mut self) -> Expr {
-        let mut expr = self.term();
+    fn comparison(&mut self) -> ParseResult<B::Expr> {
+        let mut expr = self.term()?;
         while self.advance_if_match(&[
             &TokenType::Greater,
             &TokenType::GreaterEqual,
@@ -42,16 +605,186 @@ impl Parser {
             &TokenType::LessEqual,
         ]) {
             let operator = self.previous().clone();
-            let lhs = expr.clone();
-            let rhs = self.term().clone();
-            expr = BinaryExpr::new(operator, lhs, rhs)
+            let height = self.height;
+            let rhs = self.term()?;
+            expr = self.build(height.max(self.height), |b| b.binary_expr(operator, expr, rhs))?;
+        }
+        Ok(expr)
+    }
+
+    // term --> factor ( ( "-" | "+" ) factor )* ;
+    fn term(&mut self) -> ParseResult<B::Expr> {
+        let mut expr = self.factor()?;
+        while self.advance_if_match(&[&TokenType::Minus, &TokenType::Plus]) {
+            let operator = self.previous().clone();
+            let height = self.height;
+            let rhs = self.factor()?;
+            expr = self.build(height.max(self.height), |b| b.binary_expr(operator, expr, rhs))?;
+        }
+        Ok(expr)
+    }
+
+    // factor --> unary ( ( "/" | "*" ) unary )* ;
+    fn factor(&mut self) -> ParseResult<B::Expr> {
+        let mut expr = self.unary()?;
+        while self.advance_if_match(&[&TokenType::Slash, &TokenType::Star]) {
+            let operator = self.previous().clone();
+            let height = self.height;
+            let rhs = self.unary()?;
+            expr = self.build(height.max(self.height), |b| b.binary_expr(operator, expr, rhs))?;
+        }
+        Ok(expr)
+    }
+
+    // unary --> ( "!" | "-" ) unary | call ;
+    fn unary(&mut self) -> ParseResult<B::Expr> {
+        if self.advance_if_match(&[&TokenType::Bang, &TokenType::Minus]) {
+            let operator = self.previous().clone();
+            let rhs = self.nested(Self::unary)?;
+            return self.build(self.height, |b| b.unary_expr(operator, rhs));
+        }
+        self.call()
+    }
+
+    // call --> primary ( "(" arguments? ")" | "." IDENTIFIER | "[" expression "]" )* ;
+    fn call(&mut self) -> ParseResult<B::Expr> {
+        let mut expr = self.primary()?;
+        loop {
+            if self.advance_if_match(&[&TokenType::LeftParen]) {
+                expr = self.finish_call(expr)?;
+            } else if self.advance_if_match(&[&TokenType::Dot]) {
+                let name = self
+                    .consume(&TokenType::Identifier, "Expect property name after '.'.")?
+                    .clone();
+                expr = self.build(self.height, |b| b.get_expr(expr, name))?;
+            } else if self.advance_if_match(&[&TokenType::LeftBracket]) {
+                let height = self.height;
+                let index = self.expression()?;
+                let bracket = self
+                    .consume(&TokenType::RightBracket, "Expect ']' after index.")?
+                    .clone();
+                let height = height.max(self.height);
+                expr = self.build(height, |b| b.index_expr(expr, bracket, index))?;
+            } else {
+                break;
+            }
+        }
+        Ok(expr)
+    }
+
+    // arguments --> expression ( "," expression )* ;
+    fn finish_call(&mut self, callee: B::Expr) -> ParseResult<B::Expr> {
+        let mut arguments = Vec::new();
+        let mut height = self.height;
+        if !self.is_current_token_type(&TokenType::RightParen) {
+            loop {
+                if arguments.len() >= MAX_ARGUMENTS {
+                    let e = self.error(self.peek(), "Can't have more than 255 arguments.");
+                    self.errors.push(e);
+                }
+                arguments.push(self.expression()?);
+                height = height.max(self.height);
+                if !self.advance_if_match(&[&TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        let paren = self
+            .consume(&TokenType::RightParen, "Expect ')' after arguments.")?
+            .clone();
+        self.build(height, |b| b.call_expr(callee, paren, arguments))
+    }
+
+    // primary --> NUMBER | STRING | "true" | "false" | "nil" | "this"
+    //             | "(" expression ")" | IDENTIFIER | "super" "." IDENTIFIER
+    //             | "[" ( expression ( "," expression )* )? "]"
+    //             | "{" ( expression ":" expression ( "," expression ":" expression )* )? "}"
+    //             | lambda ;
+    fn primary(&mut self) -> ParseResult<B::Expr> {
+        let line = *self.peek().line_number();
+        if self.advance_if_match(&[&TokenType::False]) {
+            self.build(0, |b| b.literal_expr(LiteralExpr::Bool(false), line))
+        } else if self.advance_if_match(&[&TokenType::True]) {
+            self.build(0, |b| b.literal_expr(LiteralExpr::Bool(true), line))
+        } else if self.advance_if_match(&[&TokenType::Nil]) {
+            self.build(0, |b| b.literal_expr(LiteralExpr::Nil, line))
+        } else if self.advance_if_match(&[&TokenType::Number, &TokenType::String]) {
+            let prev = self.previous();
+            let value = match prev.literal() {
+                Some(Literal::String(s)) => LiteralExpr::String(s.to_string()),
+                Some(Literal::Float(f)) => LiteralExpr::Float(*f),
+                None => return Err(self.error(prev, "Expect literal value.")),
+            };
+            self.build(0, |b| b.literal_expr(value, line))
+        } else if self.advance_if_match(&[&TokenType::Super]) {
+            let keyword = self.previous().clone();
+            self.consume(&TokenType::Dot, "Expect '.' after 'super'.")?;
+            let method = self
+                .consume(&TokenType::Identifier, "Expect superclass method name.")?
+                .clone();
+            self.build(0, |b| b.super_expr(keyword, method))
+        } else if self.advance_if_match(&[&TokenType::This]) {
+            let keyword = self.previous().clone();
+            self.build(0, |b| b.this_expr(keyword))
+        } else if self.advance_if_match(&[&TokenType::Identifier]) {
+            let name = self.previous().clone();
+            self.build(0, |b| b.variable_expr(name))
+        } else if self.advance_if_match(&[&TokenType::LeftParen]) {
+            let expr = self.expression()?;
+            self.consume(&TokenType::RightParen, "Expect ')' after expression.")?;
+            self.build(self.height, |b| b.grouping_expr(expr))
+        } else if self.advance_if_match(&[&TokenType::LeftBracket]) {
+            self.list()
+        } else if self.advance_if_match(&[&TokenType::LeftBrace]) {
+            self.map()
+        } else if self.advance_if_match(&[&TokenType::Fun]) {
+            self.lambda()
+        } else {
+            Err(self.error(self.peek(), "Expect expression."))
         }
-        expr
+    }
+
+    fn list(&mut self) -> ParseResult<B::Expr> {
+        let bracket = self.previous().clone();
+        let mut elements = Vec::new();
+        let mut height = 0;
+        if !self.is_current_token_type(&TokenType::RightBracket) {
+            loop {
+                elements.push(self.expression()?);
+                height = height.max(self.height);
+                if !self.advance_if_match(&[&TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(&TokenType::RightBracket, "Expect ']' after list elements.")?;
+        self.build(height, |b| b.list_expr(bracket, elements))
+    }
+
+    fn map(&mut self) -> ParseResult<B::Expr> {
+        let brace = self.previous().clone();
+        let mut entries = Vec::new();
+        let mut height = 0;
+        if !self.is_current_token_type(&TokenType::RightBrace) {
+            loop {
+                let key = self.expression()?;
+                height = height.max(self.height);
+                self.consume(&TokenType::Colon, "Expect ':' after map key.")?;
+                let value = self.expression()?;
+                height = height.max(self.height);
+                entries.push((key, value));
+                if !self.advance_if_match(&[&TokenType::Comma]) {
+                    break;
+                }
+            }
+        }
+        self.consume(&TokenType::RightBrace, "Expect '}' after map entries.")?;
+        self.build(height, |b| b.map_expr(brace, entries))
     }
 
     fn advance_if_match(&mut self, token_types: &[&TokenType]) -> bool {
-        for token in &self.tokens {
-            if self.is_current_token_type(&token.token_type()) {
+        for token_type in token_types {
+            if self.is_current_token_type(token_type) {
                 self.advance();
                 return true;
             }
@@ -64,7 +797,7 @@ impl Parser {
     }
 
     fn advance(&mut self) -> &Token {
-        if self.is_at_end() {
+        if !self.is_at_end() {
             self.current += 1;
         }
         self.previous()
@@ -85,82 +818,29 @@ impl Parser {
         &self.tokens[self.current]
     }
 
-    fn term(&mut self) -> Expr {
-        let mut expr = self.factor();
-        while self.advance_if_match(&[&TokenType::Minus, &TokenType::Plus]) {
-            let operator = self.previous().clone();
-            let lhs = expr.clone();
-            let rhs = self.factor().clone();
-            expr = BinaryExpr::new(operator, lhs, rhs);
-        }
-        expr
-    }
-
-    fn factor(&mut self) -> Expr {
-        let mut expr = self.unary();
-        while self.advance_if_match(&[&TokenType::Slash, &TokenType::Star]) {
-            let operator = self.previous().clone();
-            let lhs = expr.clone();
-            let rhs = self.unary().clone();
-            expr = BinaryExpr::new(operator, lhs, rhs);
-        }
-        expr
-    }
-
-    fn unary(&mut self) -> Expr {
-        if self.advance_if_match(&[&TokenType::Bang, &TokenType::Minus]) {
-            let operator = self.previous().clone();
-            let rhs = self.unary().clone();
-            return UnaryExpr::new(operator, rhs);
+    fn peek_next_is(&self, token_type: &TokenType) -> bool {
+        match self.tokens.get(self.current + 1) {
+            Some(token) => token.token_type() == token_type,
+            None => false,
         }
-        self.primary()
     }
 
-    // primary --> NUMBER | STRING | "true" | "false" | "nil"
-    //             | "(" expression ")" ;
-    fn primary(&mut self) -> Expr {
-        if self.advance_if_match(&[&TokenType::False]) {
-            LiteralExpr::new(LiteralExpr::Bool(false))
-        } else if self.advance_if_match(&[&TokenType::True]) {
-            LiteralExpr::new(LiteralExpr::Bool(true))
-        } else if self.advance_if_match(&[&TokenType::Nil]) {
-            LiteralExpr::new(LiteralExpr::Nil)
-        } else if self.advance_if_match(&[&TokenType::Number, &TokenType::String]) {
-            let prev = self.previous();
-            match prev.literal() {
-                Some(v) => match v {
-                    Literal::String(s) => LiteralExpr::new(LiteralExpr::String(s.to_string())),
-                    Literal::Float(f) => LiteralExpr::new(LiteralExpr::Float(*f)),
-                },
-                None => panic!("Something went wrong. Literal should exist for any Number or String token"), // TODO: design this potential bug away
-            }
-        } else if self.advance_if_match(&[&TokenType::LeftParen]) {
-            let expr = self.expression();
-            self.consume(&TokenType::RightParen, "Expect ')' after expression.");
-            GroupingExpr::new(expr)
-        }
-        else {
-            self.error(self.peek(), "Expect expression.");
-            panic!("Something went wrong. Unable to parse Primary expression.")
-        }
-    }
-
-    fn consume(&mut self, token_type: &TokenType, msg: &str) -> &Token {
+    fn consume(&mut self, token_type: &TokenType, msg: &str) -> ParseResult<&Token> {
         if self.is_current_token_type(token_type) {
-            return self.advance()
-        }
-        else {
-            self.error(self.peek(), msg);
-            panic!("{} {}", self.peek(), msg);
+            Ok(self.advance())
+        } else {
+            Err(self.error(self.peek(), msg))
         }
     }
 
-    fn error(&self, token: &Token, msg: &str) {
-        if token.token_type() == &TokenType::Eof {
-            println!("{} at end. {}", token.line_number(), msg)
-        }
-        else {
-            println!("{} at '{}' {}", token.line_number(), token.lexeme(), msg)
+    fn error(&self, token: &Token, msg: &str) -> RloxSyntaxError {
+        let description = match token.token_type() {
+            TokenType::Eof => format!("at end. {}", msg),
+            _ => format!("at '{}' {}", token.lexeme(), msg),
+        };
+        RloxSyntaxError {
+            line_number: *token.line_number(),
+            description,
         }
     }
 
@@ -173,19 +853,363 @@ impl Parser {
             }
 
             match self.peek().token_type() {
-                TokenType::Class
-                    | TokenType::Fun
-                    | TokenType::Var
-                    | TokenType::For
-                    | TokenType::If
-                    | TokenType::While
-                    | TokenType::Print
-                    | TokenType::Return
-                        => return,
-                _ => ()
+                TokenType::Break
+                | TokenType::Class
+                | TokenType::Continue
+                | TokenType::Fun
+                | TokenType::Var
+                | TokenType::For
+                | TokenType::If
+                | TokenType::Import
+                | TokenType::While
+                | TokenType::Print
+                | TokenType::Return
+                | TokenType::Throw
+                | TokenType::Try => return,
+                _ => (),
             }
 
             self.advance();
         }
     }
 }
+
+/// What a builder fails with when it can hold no more nodes, reported as a syntax error.
+pub type Built<T> = Result<T, &'static str>;
+
+/// How a `for` loop was written, which the `while` loop it is turned into doesn't tell.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ForLoop {
+    pub initializer: bool,
+    pub condition: bool,
+}
+
+/// An expression taken apart to be assigned to.
+pub enum Target<E> {
+    Variable(Token),
+    Get(E, Token),
+    Index(E, Token, E),
+}
+
+/**
+ * Makes the nodes of the syntax tree as the parser recognizes them, children before parents.
+ *   `TreeBuilder` makes the `Rc` tree of `Expr` and `Stmt` the backends run, while the arena's
+ *   builder adds nodes straight to an `arena::Ast`, so neither is made from the other.
+ */
+pub trait Builder {
+    type Expr;
+    type Stmt;
+    // A named function, declared on its own or as a method
+    type Function;
+    /// Takes apart an expression followed by `=`, or hands it back if it can't be assigned to.
+    fn assignment_target(&mut self, expr: Self::Expr) -> Result<Target<Self::Expr>, Self::Expr>;
+
+    // The first and last lines of statements and functions, for builders that keep them
+    fn statement_lines(&mut self, _stmt: &Self::Stmt, _lines: (usize, usize)) {}
+    fn function_lines(&mut self, _function: &Self::Function, _lines: (usize, usize)) {}
+    fn lambda_lines(&mut self, _lambda: &Self::Expr, _lines: (usize, usize)) {}
+    // A `for` loop, as the block holding its initializer or else as its while loop
+    fn for_loop(&mut self, _stmt: &Self::Stmt, _form: ForLoop) {}
+
+    fn assign_expr(&mut self, name: Token, value: Self::Expr) -> Built<Self::Expr>;
+    fn binary_expr(
+        &mut self,
+        operator: Token,
+        lhs: Self::Expr,
+        rhs: Self::Expr,
+    ) -> Built<Self::Expr>;
+    fn call_expr(
+        &mut self,
+        callee: Self::Expr,
+        paren: Token,
+        arguments: Vec<Self::Expr>,
+    ) -> Built<Self::Expr>;
+    fn function_expr(
+        &mut self,
+        keyword: Token,
+        params: Vec<Token>,
+        body: Vec<Self::Stmt>,
+    ) -> Built<Self::Expr>;
+    fn get_expr(&mut self, object: Self::Expr, name: Token) -> Built<Self::Expr>;
+    fn grouping_expr(&mut self, expression: Self::Expr) -> Built<Self::Expr>;
+    fn index_expr(
+        &mut self,
+        object: Self::Expr,
+        bracket: Token,
+        index: Self::Expr,
+    ) -> Built<Self::Expr>;
+    fn index_set_expr(
+        &mut self,
+        object: Self::Expr,
+        bracket: Token,
+        index: Self::Expr,
+        value: Self::Expr,
+    ) -> Built<Self::Expr>;
+    fn list_expr(&mut self, bracket: Token, elements: Vec<Self::Expr>) -> Built<Self::Expr>;
+    fn literal_expr(&mut self, value: LiteralExpr, line: usize) -> Built<Self::Expr>;
+    fn logical_expr(
+        &mut self,
+        operator: Token,
+        lhs: Self::Expr,
+        rhs: Self::Expr,
+    ) -> Built<Self::Expr>;
+    fn map_expr(
+        &mut self,
+        brace: Token,
+        entries: Vec<(Self::Expr, Self::Expr)>,
+    ) -> Built<Self::Expr>;
+    fn set_expr(&mut self, object: Self::Expr, name: Token, value: Self::Expr)
+        -> Built<Self::Expr>;
+    fn super_expr(&mut self, keyword: Token, method: Token) -> Built<Self::Expr>;
+    fn this_expr(&mut self, keyword: Token) -> Built<Self::Expr>;
+    fn unary_expr(&mut self, operator: Token, rhs: Self::Expr) -> Built<Self::Expr>;
+    fn variable_expr(&mut self, name: Token) -> Built<Self::Expr>;
+
+    fn block_stmt(&mut self, statements: Vec<Self::Stmt>) -> Built<Self::Stmt>;
+    fn break_stmt(&mut self, keyword: Token) -> Built<Self::Stmt>;
+    fn class_stmt(
+        &mut self,
+        name: Token,
+        superclass: Option<Self::Expr>,
+        methods: Vec<Self::Function>,
+    ) -> Built<Self::Stmt>;
+    fn continue_stmt(&mut self, keyword: Token) -> Built<Self::Stmt>;
+    fn expression_stmt(&mut self, expression: Self::Expr) -> Built<Self::Stmt>;
+    fn function_declaration(
+        &mut self,
+        name: Token,
+        params: Vec<Token>,
+        body: Vec<Self::Stmt>,
+    ) -> Built<Self::Function>;
+    fn function_stmt(&mut self, function: Self::Function) -> Built<Self::Stmt>;
+    fn if_stmt(
+        &mut self,
+        condition: Self::Expr,
+        then_branch: Self::Stmt,
+        else_branch: Option<Self::Stmt>,
+    ) -> Built<Self::Stmt>;
+    fn import_stmt(&mut self, keyword: Token, path: String, name: Token) -> Built<Self::Stmt>;
+    fn print_stmt(&mut self, keyword: Token, expression: Self::Expr) -> Built<Self::Stmt>;
+    fn return_stmt(&mut self, keyword: Token, value: Option<Self::Expr>) -> Built<Self::Stmt>;
+    fn throw_stmt(&mut self, keyword: Token, value: Self::Expr) -> Built<Self::Stmt>;
+    fn try_stmt(
+        &mut self,
+        body: Vec<Self::Stmt>,
+        catch: Option<(Token, Vec<Self::Stmt>)>,
+        finally: Option<Vec<Self::Stmt>>,
+    ) -> Built<Self::Stmt>;
+    fn var_stmt(&mut self, name: Token, initializer: Option<Self::Expr>) -> Built<Self::Stmt>;
+    fn while_stmt(
+        &mut self,
+        condition: Self::Expr,
+        body: Self::Stmt,
+        increment: Option<Self::Expr>,
+    ) -> Built<Self::Stmt>;
+}
+
+/// Makes the `Rc` tree, keeping the lines of statements, function bodies and literals on the side.
+#[derive(Default)]
+pub struct TreeBuilder {
+    spans: HashMap<usize, (usize, usize)>, // Stmt::id or function body -> first and last line
+    for_loops: HashMap<usize, ForLoop>,    // Stmt::id -> how the loop was written
+    literal_lines: HashMap<usize, usize>,  // Expr::id -> line of the literal
+}
+
+impl Builder for TreeBuilder {
+    type Expr = Expr;
+    type Stmt = Stmt;
+    type Function = Rc<FunctionStmt>;
+    fn assignment_target(&mut self, expr: Expr) -> Result<Target<Expr>, Expr> {
+        match &expr {
+            Expr::Variable(v) => Ok(Target::Variable(v.name().clone())),
+            Expr::Get(g) => Ok(Target::Get(g.object().clone(), g.name().clone())),
+            Expr::Index(i) => Ok(Target::Index(
+                i.object().clone(),
+                i.bracket().clone(),
+                i.index().clone(),
+            )),
+            _ => Err(expr),
+        }
+    }
+
+    fn statement_lines(&mut self, stmt: &Stmt, lines: (usize, usize)) {
+        self.spans.insert(stmt.id(), lines);
+    }
+
+    // Methods are no statements of their own, so the declaration is recorded here too
+    fn function_lines(&mut self, function: &Rc<FunctionStmt>, lines: (usize, usize)) {
+        let declaration = Rc::as_ptr(function) as *const () as usize;
+        let body = Rc::as_ptr(function.function()) as *const () as usize;
+        self.spans.insert(declaration, lines);
+        self.spans.insert(body, lines);
+    }
+
+    fn lambda_lines(&mut self, lambda: &Expr, lines: (usize, usize)) {
+        self.spans.insert(lambda.id(), lines);
+    }
+
+    fn for_loop(&mut self, stmt: &Stmt, form: ForLoop) {
+        self.for_loops.insert(stmt.id(), form);
+    }
+
+    fn assign_expr(&mut self, name: Token, value: Expr) -> Built<Expr> {
+        Ok(AssignExpr::new(name, value))
+    }
+
+    fn binary_expr(&mut self, operator: Token, lhs: Expr, rhs: Expr) -> Built<Expr> {
+        Ok(BinaryExpr::new(operator, lhs, rhs))
+    }
+
+    fn call_expr(&mut self, callee: Expr, paren: Token, arguments: Vec<Expr>) -> Built<Expr> {
+        Ok(CallExpr::new(callee, paren, arguments))
+    }
+
+    fn function_expr(
+        &mut self,
+        keyword: Token,
+        params: Vec<Token>,
+        body: Vec<Stmt>,
+    ) -> Built<Expr> {
+        Ok(FunctionExpr::new(keyword, params, body))
+    }
+
+    fn get_expr(&mut self, object: Expr, name: Token) -> Built<Expr> {
+        Ok(GetExpr::new(object, name))
+    }
+
+    fn grouping_expr(&mut self, expression: Expr) -> Built<Expr> {
+        Ok(GroupingExpr::new(expression))
+    }
+
+    fn index_expr(&mut self, object: Expr, bracket: Token, index: Expr) -> Built<Expr> {
+        Ok(IndexExpr::new(object, bracket, index))
+    }
+
+    fn index_set_expr(
+        &mut self,
+        object: Expr,
+        bracket: Token,
+        index: Expr,
+        value: Expr,
+    ) -> Built<Expr> {
+        Ok(IndexSetExpr::new(object, bracket, index, value))
+    }
+
+    fn list_expr(&mut self, bracket: Token, elements: Vec<Expr>) -> Built<Expr> {
+        Ok(ListExpr::new(bracket, elements))
+    }
+
+    fn literal_expr(&mut self, value: LiteralExpr, line: usize) -> Built<Expr> {
+        let literal = LiteralExpr::new(value);
+        self.literal_lines.insert(literal.id(), line);
+        Ok(literal)
+    }
+
+    fn logical_expr(&mut self, operator: Token, lhs: Expr, rhs: Expr) -> Built<Expr> {
+        Ok(LogicalExpr::new(operator, lhs, rhs))
+    }
+
+    fn map_expr(&mut self, brace: Token, entries: Vec<(Expr, Expr)>) -> Built<Expr> {
+        Ok(MapExpr::new(brace, entries))
+    }
+
+    fn set_expr(&mut self, object: Expr, name: Token, value: Expr) -> Built<Expr> {
+        Ok(SetExpr::new(object, name, value))
+    }
+
+    fn super_expr(&mut self, keyword: Token, method: Token) -> Built<Expr> {
+        Ok(SuperExpr::new(keyword, method))
+    }
+
+    fn this_expr(&mut self, keyword: Token) -> Built<Expr> {
+        Ok(ThisExpr::new(keyword))
+    }
+
+    fn unary_expr(&mut self, operator: Token, rhs: Expr) -> Built<Expr> {
+        Ok(UnaryExpr::new(operator, rhs))
+    }
+
+    fn variable_expr(&mut self, name: Token) -> Built<Expr> {
+        Ok(VariableExpr::new(name))
+    }
+
+    fn block_stmt(&mut self, statements: Vec<Stmt>) -> Built<Stmt> {
+        Ok(BlockStmt::new(statements))
+    }
+
+    fn break_stmt(&mut self, keyword: Token) -> Built<Stmt> {
+        Ok(BreakStmt::new(keyword))
+    }
+
+    fn class_stmt(
+        &mut self,
+        name: Token,
+        superclass: Option<Expr>,
+        methods: Vec<Rc<FunctionStmt>>,
+    ) -> Built<Stmt> {
+        Ok(ClassStmt::new(name, superclass, methods))
+    }
+
+    fn continue_stmt(&mut self, keyword: Token) -> Built<Stmt> {
+        Ok(ContinueStmt::new(keyword))
+    }
+
+    fn expression_stmt(&mut self, expression: Expr) -> Built<Stmt> {
+        Ok(ExpressionStmt::new(expression))
+    }
+
+    fn function_declaration(
+        &mut self,
+        name: Token,
+        params: Vec<Token>,
+        body: Vec<Stmt>,
+    ) -> Built<Rc<FunctionStmt>> {
+        Ok(FunctionStmt::new(name, params, body))
+    }
+
+    fn function_stmt(&mut self, function: Rc<FunctionStmt>) -> Built<Stmt> {
+        Ok(Stmt::Function(function))
+    }
+
+    fn if_stmt(
+        &mut self,
+        condition: Expr,
+        then_branch: Stmt,
+        else_branch: Option<Stmt>,
+    ) -> Built<Stmt> {
+        Ok(IfStmt::new(condition, then_branch, else_branch))
+    }
+
+    fn import_stmt(&mut self, keyword: Token, path: String, name: Token) -> Built<Stmt> {
+        Ok(ImportStmt::new(keyword, path, name))
+    }
+
+    fn print_stmt(&mut self, _keyword: Token, expression: Expr) -> Built<Stmt> {
+        Ok(PrintStmt::new(expression))
+    }
+
+    fn return_stmt(&mut self, keyword: Token, value: Option<Expr>) -> Built<Stmt> {
+        Ok(ReturnStmt::new(keyword, value))
+    }
+
+    fn throw_stmt(&mut self, keyword: Token, value: Expr) -> Built<Stmt> {
+        Ok(ThrowStmt::new(keyword, value))
+    }
+
+    fn try_stmt(
+        &mut self,
+        body: Vec<Stmt>,
+        catch: Option<(Token, Vec<Stmt>)>,
+        finally: Option<Vec<Stmt>>,
+    ) -> Built<Stmt> {
+        Ok(TryStmt::new(body, catch, finally))
+    }
+
+    fn var_stmt(&mut self, name: Token, initializer: Option<Expr>) -> Built<Stmt> {
+        Ok(VarStmt::new(name, initializer))
+    }
+
+    fn while_stmt(&mut self, condition: Expr, body: Stmt, increment: Option<Expr>) -> Built<Stmt> {
+        Ok(WhileStmt::with_increment(condition, body, increment))
+    }
+}
@@ -0,0 +1,120 @@
+use crate::ast_printer::AstPrinter;
+use crate::ast_printer_rpn::AstPrinterRpn;
+use crate::error::RloxError;
+use crate::scanner::Scanner;
+use crate::stmt::{PrintStmt, Stmt};
+use crate::token::TokenType;
+
+const UNTERMINATED_STRING: &str = "Unterminated string.";
+
+/**
+ * Whether `source` stops in the middle of a string or with brackets left open, in which case
+ *   the REPL asks for more lines before running it. Other errors are left for the parser to
+ *   report, including closing brackets with nothing to close.
+ */
+pub fn is_incomplete(source: &str) -> bool {
+    let tokens = match crate::tokenize(source) {
+        Ok(tokens) => tokens,
+        Err(RloxError::SyntaxError(e)) => return e.description == UNTERMINATED_STRING,
+        Err(RloxError::SyntaxErrors(errors)) => {
+            return errors.iter().any(|e| e.description == UNTERMINATED_STRING)
+        }
+        Err(_) => return false,
+    };
+    let mut depth = 0;
+    for token in tokens {
+        match token.token_type() {
+            TokenType::LeftParen | TokenType::LeftBrace | TokenType::LeftBracket => depth += 1,
+            TokenType::RightParen | TokenType::RightBrace | TokenType::RightBracket => {
+                if depth == 0 {
+                    return false;
+                }
+                depth -= 1;
+            }
+            _ => (),
+        }
+    }
+    depth > 0
+}
+
+pub const HELP: &str = "\
+Enter statements to run them, or an expression without a semicolon to print its value.
+  :tokens <source>  print the tokens of some source
+  :ast <source>     print the syntax tree of an expression or program
+  :rpn <expr>       print an expression in reverse Polish notation
+  :env              list the globals defined so far
+  :load <file>      run a script, keeping what it defines
+  :reset            forget every definition
+  :help             show this message
+Ctrl-D or exit quits.";
+
+/// A line of the REPL starting with a colon, with what follows its name.
+#[derive(Debug, PartialEq)]
+pub enum Command<'a> {
+    Tokens(&'a str),
+    Ast(&'a str),
+    Rpn(&'a str),
+    Env,
+    Load(&'a str),
+    Reset,
+    Help,
+    Unknown(&'a str),
+}
+
+impl<'a> Command<'a> {
+    /// The command on `line`, if it starts with a colon.
+    pub fn parse(line: &'a str) -> Option<Command<'a>> {
+        let line = line.trim().strip_prefix(':')?;
+        let (name, argument) = match line.find(char::is_whitespace) {
+            Some(i) => (&line[..i], line[i..].trim()),
+            None => (line, ""),
+        };
+        let command = match name {
+            "tokens" => Command::Tokens(argument),
+            "ast" => Command::Ast(argument),
+            "rpn" => Command::Rpn(argument),
+            "env" => Command::Env,
+            "load" => Command::Load(argument),
+            "reset" => Command::Reset,
+            "help" => Command::Help,
+            _ => Command::Unknown(name),
+        };
+        Some(command)
+    }
+}
+
+/**
+ * Parses a REPL entry. A lone expression, without the semicolon that would make it a statement,
+ *   is printed, so typing `1 + 2` shows `3`.
+ */
+pub fn parse_entry(source: &str) -> Result<Vec<Stmt>, RloxError> {
+    match crate::parse_expression(source) {
+        Ok(expression) => Ok(vec![PrintStmt::new(expression)]),
+        Err(_) => crate::parse_program(source),
+    }
+}
+
+/// The tokens of `source` one per line, without the final `Eof`, as in `Identifier "x" @1`.
+pub fn tokens(source: &str) -> Result<String, RloxError> {
+    let scanner = Scanner::try_new(source.to_string())?;
+    let tokens = scanner.tokens().iter();
+    let tokens = tokens.filter(|token| *token.token_type() != TokenType::Eof);
+    Ok(tokens.map(|token| format!("{:?}\n", token)).collect())
+}
+
+/// The syntax tree of `source` as S-expressions, one line per statement unless it is a lone
+/// expression.
+pub fn ast(source: &str) -> Result<String, RloxError> {
+    let printer = AstPrinter::default();
+    if let Ok(expression) = crate::parse_expression(source) {
+        return Ok(format!("{}\n", printer.print(expression)));
+    }
+    let statements = crate::parse_program(source)?;
+    let statements = statements.iter();
+    Ok(statements.map(|s| format!("{}\n", printer.print_stmt(s))).collect())
+}
+
+pub fn rpn(source: &str) -> Result<String, RloxError> {
+    let expression = crate::parse_expression(source)?;
+    Ok(format!("{}\n", AstPrinterRpn::default().print(expression)))
+}
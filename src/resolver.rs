@@ -0,0 +1,377 @@
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+
+use crate::error::{RloxError, RloxSyntaxError};
+use crate::expr::{self, Expr, FunctionExpr};
+use crate::stmt::{self, Stmt};
+use crate::token::Token;
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+impl FunctionType {
+    pub(crate) fn method(name: &Token) -> FunctionType {
+        match name.lexeme() {
+            "init" => FunctionType::Initializer,
+            _ => FunctionType::Method,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+/**
+ * Static pass run between parsing and interpreting.
+ *   Works out how many scopes away each local variable reference is declared, and reports misuse
+ *   such as a top-level `return` or `this` outside of a class.
+ *   Variables not found in any scope are assumed to be globals and are left out of the result.
+ */
+pub struct Resolver {
+    scopes: RefCell<Vec<HashMap<String, bool>>>, // name -> whether its initializer has finished
+    locals: RefCell<HashMap<usize, usize>>,      // Expr::id -> scope distance
+    errors: RefCell<Vec<RloxSyntaxError>>,
+    current_function: Cell<FunctionType>,
+    current_class: Cell<ClassType>,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Resolver {
+            scopes: RefCell::new(Vec::new()),
+            locals: RefCell::new(HashMap::new()),
+            errors: RefCell::new(Vec::new()),
+            current_function: Cell::new(FunctionType::None),
+            current_class: Cell::new(ClassType::None),
+        }
+    }
+
+    pub fn resolve(self, statements: &[Stmt]) -> Result<HashMap<usize, usize>, RloxError> {
+        self.resolve_statements(statements);
+        self.finish()
+    }
+
+    // The scope distance of every local found by the hooks, or the errors they reported
+    pub(crate) fn finish(self) -> Result<HashMap<usize, usize>, RloxError> {
+        let errors = self.errors.into_inner();
+        match errors.is_empty() {
+            true => Ok(self.locals.into_inner()),
+            false => Err(RloxError::SyntaxErrors(errors)),
+        }
+    }
+
+    fn resolve_statements(&self, statements: &[Stmt]) {
+        for statement in statements {
+            statement.accept(self);
+        }
+    }
+
+    fn resolve_function(&self, function: &FunctionExpr, function_type: FunctionType) {
+        let enclosing_function = self.begin_function(function.params(), function_type);
+        self.resolve_statements(function.body());
+        self.end_function(enclosing_function);
+    }
+
+    // The hooks below hold the rules of resolution, so the walk over the nodes of an
+    // `arena::Ast` shares them. Expressions are keyed by `Expr::id`, or by `ExprId::index` there.
+
+    pub(crate) fn begin_function(&self, params: &[Token], kind: FunctionType) -> FunctionType {
+        let enclosing_function = self.current_function.replace(kind);
+        self.begin_scope();
+        for param in params {
+            self.declare(param);
+            self.define(param);
+        }
+        enclosing_function
+    }
+
+    pub(crate) fn end_function(&self, enclosing_function: FunctionType) {
+        self.end_scope();
+        self.current_function.set(enclosing_function);
+    }
+
+    pub(crate) fn begin_class(&self, name: &Token) -> ClassType {
+        let enclosing_class = self.current_class.replace(ClassType::Class);
+        self.declare(name);
+        self.define(name);
+        enclosing_class
+    }
+
+    // Called before resolving the superclass, with its name when it is a plain variable
+    pub(crate) fn superclass(&self, class: &Token, superclass: Option<&Token>) {
+        if let Some(superclass) = superclass {
+            if superclass.lexeme() == class.lexeme() {
+                self.error(superclass, "A class can't inherit from itself.");
+            }
+        }
+        self.current_class.set(ClassType::Subclass);
+    }
+
+    pub(crate) fn begin_methods(&self, has_superclass: bool) {
+        if has_superclass {
+            self.begin_scope();
+            self.define_keyword("super");
+        }
+        self.begin_scope();
+        self.define_keyword("this");
+    }
+
+    pub(crate) fn end_class(&self, has_superclass: bool, enclosing_class: ClassType) {
+        self.end_scope();
+        if has_superclass {
+            self.end_scope();
+        }
+        self.current_class.set(enclosing_class);
+    }
+
+    pub(crate) fn import(&self, keyword: &Token, name: &Token) {
+        if !self.scopes.borrow().is_empty() {
+            self.error(keyword, "Can only import at the top level of a file.");
+        }
+        self.declare(name);
+        self.define(name);
+    }
+
+    // Called before resolving the returned value, if there is one
+    pub(crate) fn return_value(&self, keyword: &Token, has_value: bool) {
+        if self.current_function.get() == FunctionType::None {
+            self.error(keyword, "Can't return from top-level code.");
+        }
+        if has_value && self.current_function.get() == FunctionType::Initializer {
+            self.error(keyword, "Can't return a value from an initializer.");
+        }
+    }
+
+    pub(crate) fn super_keyword(&self, id: usize, keyword: &Token) {
+        match self.current_class.get() {
+            ClassType::None => self.error(keyword, "Can't use 'super' outside of a class."),
+            ClassType::Class => {
+                self.error(keyword, "Can't use 'super' in a class with no superclass.")
+            }
+            ClassType::Subclass => (),
+        }
+        self.resolve_local(id, keyword);
+    }
+
+    pub(crate) fn this_keyword(&self, id: usize, keyword: &Token) {
+        if self.current_class.get() == ClassType::None {
+            self.error(keyword, "Can't use 'this' outside of a class.");
+            return;
+        }
+        self.resolve_local(id, keyword);
+    }
+
+    pub(crate) fn variable(&self, id: usize, name: &Token) {
+        let declared_but_undefined = self
+            .scopes
+            .borrow()
+            .last()
+            .and_then(|scope| scope.get(name.lexeme()))
+            == Some(&false);
+        if declared_but_undefined {
+            self.error(name, "Can't read local variable in its own initializer.");
+        }
+        self.resolve_local(id, name);
+    }
+
+    pub(crate) fn resolve_local(&self, id: usize, name: &Token) {
+        let scopes = self.scopes.borrow();
+        for (distance, scope) in scopes.iter().rev().enumerate() {
+            if scope.contains_key(name.lexeme()) {
+                self.locals.borrow_mut().insert(id, distance);
+                return;
+            }
+        }
+    }
+
+    pub(crate) fn begin_scope(&self) {
+        self.scopes.borrow_mut().push(HashMap::new());
+    }
+
+    pub(crate) fn end_scope(&self) {
+        self.scopes.borrow_mut().pop();
+    }
+
+    fn define_keyword(&self, keyword: &str) {
+        if let Some(scope) = self.scopes.borrow_mut().last_mut() {
+            scope.insert(keyword.to_string(), true);
+        }
+    }
+
+    pub(crate) fn declare(&self, name: &Token) {
+        let mut scopes = self.scopes.borrow_mut();
+        if let Some(scope) = scopes.last_mut() {
+            if scope.contains_key(name.lexeme()) {
+                self.error(name, "Already a variable with this name in this scope.");
+            }
+            scope.insert(name.lexeme().to_string(), false);
+        }
+    }
+
+    pub(crate) fn define(&self, name: &Token) {
+        if let Some(scope) = self.scopes.borrow_mut().last_mut() {
+            scope.insert(name.lexeme().to_string(), true);
+        }
+    }
+
+    fn error(&self, token: &Token, msg: &str) {
+        self.errors.borrow_mut().push(RloxSyntaxError {
+            line_number: *token.line_number(),
+            description: format!("at '{}' {}", token.lexeme(), msg),
+        });
+    }
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Resolver::new()
+    }
+}
+
+impl stmt::Visitor<()> for Resolver {
+    fn visit_stmt(&self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Block(s) => {
+                self.begin_scope();
+                self.resolve_statements(s.statements());
+                self.end_scope();
+            }
+            Stmt::Class(s) => {
+                let enclosing_class = self.begin_class(s.name());
+                if let Some(superclass) = s.superclass() {
+                    let name = match superclass {
+                        Expr::Variable(v) => Some(v.name()),
+                        _ => None,
+                    };
+                    self.superclass(s.name(), name);
+                    superclass.accept(self);
+                }
+                self.begin_methods(s.superclass().is_some());
+                for method in s.methods() {
+                    self.resolve_function(method.function(), FunctionType::method(method.name()));
+                }
+                self.end_class(s.superclass().is_some(), enclosing_class);
+            }
+            Stmt::Break(_) | Stmt::Continue(_) => (),
+            Stmt::Expression(s) => s.expression().accept(self),
+            Stmt::Function(s) => {
+                self.declare(s.name());
+                self.define(s.name());
+                self.resolve_function(s.function(), FunctionType::Function);
+            }
+            Stmt::If(s) => {
+                s.condition().accept(self);
+                s.then_branch().accept(self);
+                if let Some(else_branch) = s.else_branch() {
+                    else_branch.accept(self);
+                }
+            }
+            Stmt::Import(s) => self.import(s.keyword(), s.name()),
+            Stmt::Print(s) => s.expression().accept(self),
+            Stmt::Return(s) => {
+                self.return_value(s.keyword(), s.value().is_some());
+                if let Some(value) = s.value() {
+                    value.accept(self);
+                }
+            }
+            Stmt::Throw(s) => s.value().accept(self),
+            Stmt::Try(s) => {
+                self.begin_scope();
+                self.resolve_statements(s.body());
+                self.end_scope();
+                if let (Some(name), Some(body)) = (s.catch_name(), s.catch_body()) {
+                    self.begin_scope();
+                    self.declare(name);
+                    self.define(name);
+                    self.resolve_statements(body);
+                    self.end_scope();
+                }
+                if let Some(finally) = s.finally_body() {
+                    self.begin_scope();
+                    self.resolve_statements(finally);
+                    self.end_scope();
+                }
+            }
+            Stmt::Var(s) => {
+                self.declare(s.name());
+                if let Some(initializer) = s.initializer() {
+                    initializer.accept(self);
+                }
+                self.define(s.name());
+            }
+            Stmt::While(s) => {
+                s.condition().accept(self);
+                s.body().accept(self);
+                if let Some(increment) = s.increment() {
+                    increment.accept(self);
+                }
+            }
+        }
+    }
+}
+
+impl expr::Visitor<()> for Resolver {
+    fn visit_expr(&self, expr: &Expr) {
+        match expr {
+            Expr::Assign(e) => {
+                e.value().accept(self);
+                self.resolve_local(expr.id(), e.name());
+            }
+            Expr::Binary(e) => {
+                e.lhs().accept(self);
+                e.rhs().accept(self);
+            }
+            Expr::Call(e) => {
+                e.callee().accept(self);
+                for argument in e.arguments() {
+                    argument.accept(self);
+                }
+            }
+            Expr::Function(e) => self.resolve_function(e, FunctionType::Function),
+            Expr::Get(e) => e.object().accept(self),
+            Expr::Grouping(e) => e.expression().accept(self),
+            Expr::Index(e) => {
+                e.object().accept(self);
+                e.index().accept(self);
+            }
+            Expr::IndexSet(e) => {
+                e.object().accept(self);
+                e.index().accept(self);
+                e.value().accept(self);
+            }
+            Expr::List(e) => {
+                for element in e.elements() {
+                    element.accept(self);
+                }
+            }
+            Expr::Literal(_) => (),
+            Expr::Logical(e) => {
+                e.lhs().accept(self);
+                e.rhs().accept(self);
+            }
+            Expr::Map(e) => {
+                for (key, value) in e.entries() {
+                    key.accept(self);
+                    value.accept(self);
+                }
+            }
+            Expr::Set(e) => {
+                e.value().accept(self);
+                e.object().accept(self);
+            }
+            Expr::Super(e) => self.super_keyword(expr.id(), e.keyword()),
+            Expr::This(e) => self.this_keyword(expr.id(), e.keyword()),
+            Expr::Unary(e) => e.rhs().accept(self),
+            Expr::Variable(e) => self.variable(expr.id(), e.name()),
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use std::convert::TryInto;
+use std::io::{BufRead, Write};
+
+use crate::error::RloxError;
+use crate::value::Value;
+
+/**
+ * A stack machine for the reverse Polish notation written by `AstPrinterRpn`.
+ *   Literals are pushed on the stack, and every operator pops its operands and pushes its result,
+ *   with the same semantics as in Lox. Only literals and the arithmetic, comparison and logical
+ *   operators are understood, since calls, properties and the like have nothing to refer to here.
+ *   Both operands of `and` and `or` are evaluated, but the result is the one Lox would give.
+ *   The printer writes strings without quotes, so any other word is read as a string; strings
+ *   with spaces in them can be quoted. It also writes negation and subtraction both as `-`, so
+ *   a `-` subtracts unless the rest of the text would then not leave a single result. Text that
+ *   reads both ways, like `1 2 - -`, is read as subtracting first.
+ */
+pub fn evaluate(text: &str) -> Result<Value, RloxError> {
+    let mut stack = Vec::new();
+    let words = words(text)?;
+    let negations = negations(&words);
+    for (word, negation) in words.into_iter().zip(negations) {
+        match word {
+            Word::String(s) => stack.push(Value::from(s)),
+            Word::Other(_) if negation => match pop(&mut stack, "-")? {
+                [Value::Number(n)] => stack.push(Value::Number(-n)),
+                _ => return Err(RloxError::runtime("Operand must be a number.")),
+            },
+            Word::Other(word) => execute(&mut stack, word)?,
+        }
+    }
+    match stack.len() {
+        1 => Ok(stack.remove(0)),
+        0 => Err(RloxError::runtime("Expect an expression.")),
+        n => Err(RloxError::runtime(format!(
+            "Expect a single result, found {} values on the stack.",
+            n
+        ))),
+    }
+}
+
+/**
+ * Evaluates each non-blank line of `input` as an expression and writes its result to `out`.
+ *   A line that fails has its error written to `errors` instead, and the lines after it are still
+ *   evaluated. Returns how many lines failed.
+ */
+pub fn run(
+    input: impl BufRead,
+    out: &mut impl Write,
+    errors: &mut impl Write,
+) -> Result<usize, RloxError> {
+    let mut failed = 0;
+    for (i, line) in input.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match evaluate(&line) {
+            Ok(value) => writeln!(out, "{}", value)?,
+            Err(e) => {
+                writeln!(errors, "{}", e.or_at_line(i + 1))?;
+                failed += 1;
+            }
+        }
+    }
+    Ok(failed)
+}
+
+enum Word<'a> {
+    String(String),
+    Other(&'a str),
+}
+
+// Splits the text at whitespace, keeping quoted strings whole since they may contain spaces
+fn words(text: &str) -> Result<Vec<Word<'_>>, RloxError> {
+    let mut words = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        if let Some(string) = rest.strip_prefix('"') {
+            let end = string
+                .find('"')
+                .ok_or_else(|| RloxError::runtime("Unterminated string."))?;
+            words.push(Word::String(string[..end].to_string()));
+            rest = &string[end + 1..];
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            words.push(Word::Other(&rest[..end]));
+            rest = &rest[end..];
+        }
+        rest = rest.trim_start();
+    }
+    Ok(words)
+}
+
+// Whether each word is a `-` that negates. Reading the words from the last, the depths the stack
+// can have before each one and still end with a single value form a range, which each `-` then
+// checks for its result as a subtraction.
+fn negations(words: &[Word<'_>]) -> Vec<bool> {
+    let mut ranges = vec![None; words.len() + 1];
+    ranges[words.len()] = Some((1, 1));
+    for (i, word) in words.iter().enumerate().rev() {
+        ranges[i] = ranges[i + 1].and_then(|(low, high): (i64, i64)| match arity(word) {
+            // As many values as after it when negating, or one more when subtracting
+            None if low.max(1) <= high => Some((low.max(1), high + 1)),
+            None => None,
+            Some(n) if high >= 1 => Some(((low + n - 1).max(n), high + n - 1)),
+            Some(_) => None,
+        });
+    }
+    let mut depth = 0;
+    let mut negations = Vec::with_capacity(words.len());
+    for (word, range) in words.iter().zip(&ranges[1..]) {
+        let fits = |depth| matches!(range, Some((low, high)) if *low <= depth && depth <= *high);
+        let negation = arity(word).is_none() && (depth < 2 || !fits(depth - 1));
+        depth += match arity(word) {
+            Some(n) => 1 - n,
+            None if negation => 0,
+            None => -1,
+        };
+        negations.push(negation);
+    }
+    negations
+}
+
+// How many operands a word pops, or None for `-` which pops one or two
+fn arity(word: &Word<'_>) -> Option<i64> {
+    match word {
+        Word::Other("-") => None,
+        Word::Other("!") => Some(1),
+        Word::Other("and" | "or" | "==" | "!=" | "+" | "*" | "/" | ">" | ">=" | "<" | "<=") => {
+            Some(2)
+        }
+        _ => Some(0),
+    }
+}
+
+// Pops the operands of `operator`, the last one pushed coming last
+fn pop<const N: usize>(stack: &mut Vec<Value>, operator: &str) -> Result<[Value; N], RloxError> {
+    if stack.len() < N {
+        return Err(RloxError::runtime(format!(
+            "Stack underflow: '{}' needs {} operand(s), found {}.",
+            operator,
+            N,
+            stack.len()
+        )));
+    }
+    let operands = stack.split_off(stack.len() - N);
+    Ok(operands.try_into().unwrap_or_else(|_| unreachable!()))
+}
+
+fn execute(stack: &mut Vec<Value>, word: &str) -> Result<(), RloxError> {
+    let value = match word {
+        "nil" => Value::Nil,
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        "!" => {
+            let [a] = pop(stack, word)?;
+            Value::Bool(!a.is_truthy())
+        }
+        "and" => {
+            let [a, b] = pop(stack, word)?;
+            if a.is_truthy() {
+                b
+            } else {
+                a
+            }
+        }
+        "or" => {
+            let [a, b] = pop(stack, word)?;
+            if a.is_truthy() {
+                a
+            } else {
+                b
+            }
+        }
+        "==" => {
+            let [a, b] = pop(stack, word)?;
+            Value::Bool(a == b)
+        }
+        "!=" => {
+            let [a, b] = pop(stack, word)?;
+            Value::Bool(a != b)
+        }
+        "+" => match pop(stack, word)? {
+            [Value::Number(a), Value::Number(b)] => Value::Number(a + b),
+            [Value::String(a), Value::String(b)] => Value::from(format!("{}{}", a, b)),
+            _ => {
+                return Err(RloxError::runtime(
+                    "Operands must be two numbers or two strings.",
+                ))
+            }
+        },
+        "-" | "*" | "/" | ">" | ">=" | "<" | "<=" => match pop(stack, word)? {
+            [Value::Number(a), Value::Number(b)] => arithmetic(word, a, b),
+            _ => return Err(RloxError::runtime("Operands must be numbers.")),
+        },
+        _ if needs_environment(word) => {
+            return Err(RloxError::runtime(format!(
+                "Can't evaluate '{}' without an environment.",
+                word
+            )))
+        }
+        _ => match number(word) {
+            Some(n) => Value::Number(n),
+            None => Value::from(word),
+        },
+    };
+    stack.push(value);
+    Ok(())
+}
+
+fn arithmetic(operator: &str, a: f64, b: f64) -> Value {
+    match operator {
+        "-" => Value::Number(a - b),
+        "*" => Value::Number(a * b),
+        "/" => Value::Number(a / b),
+        ">" => Value::Bool(a > b),
+        ">=" => Value::Bool(a >= b),
+        "<" => Value::Bool(a < b),
+        _ => Value::Bool(a <= b),
+    }
+}
+
+// The words `AstPrinterRpn` writes for calls, properties, assignments and the like
+fn needs_environment(word: &str) -> bool {
+    let forms = ["=", ".", ".=", "index", "index=", "this", "super"];
+    forms.contains(&word) || word.contains('/')
+}
+
+// Folded constants can be negative, so a leading minus is part of the number
+fn number(word: &str) -> Option<f64> {
+    let digits = word.strip_prefix('-').unwrap_or(word);
+    match digits.starts_with(|c: char| c.is_ascii_digit()) {
+        true => word.parse().ok(),
+        false => None,
+    }
+}
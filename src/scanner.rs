@@ -1,34 +1,66 @@
 use crate::error::{RloxError, RloxSyntaxError};
 use crate::token::{get_keyword_token_type, Literal, Token, TokenType};
 
-pub(crate) struct Scanner {
-    source: String,
+/**
+ * A `//` comment, which the scanner keeps aside from the tokens so the formatter can put it back.
+ *   A trailing comment follows a token on the same line, any other comment has a line to itself.
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    line_number: usize,
+    text: String, // including the leading `//`, without the line break
+    trailing: bool,
+}
+
+impl Comment {
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_trailing(&self) -> bool {
+        self.trailing
+    }
+}
+
+pub struct Scanner {
+    source: Vec<char>,
     tokens: Vec<Token>,
+    comments: Vec<Comment>,
     start: usize,
     current: usize,
     line: usize,
 }
 
 impl Scanner {
-    pub(crate) fn new(source: String) -> Self {
+    pub fn try_new(source: String) -> Result<Self, RloxError> {
         let mut s = Scanner {
-            source,
+            source: source.chars().collect(),
             tokens: Vec::new(),
+            comments: Vec::new(),
             start: 0,
             current: 0,
             line: 1,
         };
-        match s.scan_tokens() {
-            Ok(_) => (),
-            Err(e) => panic!("{}", e),
-        }
-        s
+        s.scan_tokens()?;
+        Ok(s)
     }
 
-    pub(crate) fn tokens(&self) -> &Vec<Token> {
+    pub fn tokens(&self) -> &Vec<Token> {
         &self.tokens
     }
 
+    pub fn comments(&self) -> &[Comment] {
+        &self.comments
+    }
+
+    pub fn into_tokens(self) -> Vec<Token> {
+        self.tokens
+    }
+
     fn scan_tokens(&mut self) -> Result<(), RloxError> {
         while !self.is_at_end() {
             self.start = self.current;
@@ -53,6 +85,9 @@ impl Scanner {
             ')' => self.add_token(TokenType::RightParen, None),
             '{' => self.add_token(TokenType::LeftBrace, None),
             '}' => self.add_token(TokenType::RightBrace, None),
+            '[' => self.add_token(TokenType::LeftBracket, None),
+            ']' => self.add_token(TokenType::RightBracket, None),
+            ':' => self.add_token(TokenType::Colon, None),
             ',' => self.add_token(TokenType::Comma, None),
             '.' => self.add_token(TokenType::Dot, None),
             '-' => self.add_token(TokenType::Minus, None),
@@ -61,7 +96,7 @@ impl Scanner {
             '*' => self.add_token(TokenType::Star, None),
             '!' => match self.advance_if_match('=') {
                 true => self.add_token(TokenType::BangEqual, None),
-                false => self.add_token(TokenType::Equal, None),
+                false => self.add_token(TokenType::Bang, None),
             },
             '=' => match self.advance_if_match('=') {
                 true => self.add_token(TokenType::EqualEqual, None),
@@ -78,6 +113,7 @@ impl Scanner {
             '/' => match self.advance_if_match('/') {
                 true => {
                     self.advance_through_end_of_line();
+                    self.add_comment();
                     Ok(())
                 }
                 false => self.add_token(TokenType::Slash, None),
@@ -103,26 +139,20 @@ impl Scanner {
     }
 
     fn advance(&mut self) -> char {
-        let current_char = *(self
-            .source
-            .chars()
-            .collect::<Vec<_>>()
-            .get(self.current)
-            .unwrap());
+        let current_char = self.source[self.current];
         self.current += 1;
-        return current_char;
+        current_char
     }
 
     fn advance_if_match(&mut self, expected: char) -> bool {
         if self.is_at_end() {
             return false;
         }
-        let c = self.source.chars().nth(self.current).unwrap();
-        if c != expected {
+        if self.source[self.current] != expected {
             return false;
         }
         self.current += 1;
-        return true;
+        true
     }
 
     fn add_token(
@@ -130,29 +160,43 @@ impl Scanner {
         token_type: TokenType,
         literal: Option<Literal>,
     ) -> Result<(), RloxError> {
-        let text = &self.source[self.start..self.current];
+        let text = self.source_between(self.start, self.current);
         self.tokens.push(Token::new(
             token_type,
-            text.to_string(),
+            text,
             literal,
             self.line,
         )?);
         Ok(())
     }
 
+    fn add_comment(&mut self) {
+        let text = self.source_between(self.start, self.current);
+        let trailing = self.tokens.last().map(|t| *t.line_number()) == Some(self.line);
+        self.comments.push(Comment {
+            line_number: self.line,
+            text: text.trim_end().to_string(),
+            trailing,
+        });
+    }
+
     fn advance_through_end_of_line(&mut self) {
         while self.peek() != '\n' && !self.is_at_end() {
             self.advance();
         }
     }
 
-    fn peek(&mut self) -> char {
+    fn peek(&self) -> char {
         match self.is_at_end() {
             true => '\0',
-            false => self.source.chars().nth(self.current).unwrap(),
+            false => self.source[self.current],
         }
     }
 
+    fn source_between(&self, start: usize, end: usize) -> String {
+        self.source[start..end].iter().collect()
+    }
+
     fn consume_string_literal(&mut self) -> Result<(), RloxError> {
         while self.peek() != '"' && !self.is_at_end() {
             if self.peek() == '\n' {
@@ -167,8 +211,8 @@ impl Scanner {
             }));
         }
         self.advance();
-        let value = &self.source[&self.start + 1..&self.current - 1];
-        let literal = Some(Literal::String(value.to_string()));
+        let value = self.source_between(self.start + 1, self.current - 1);
+        let literal = Some(Literal::String(value));
         self.add_token(TokenType::String, literal)?;
         Ok(())
     }
@@ -183,16 +227,17 @@ impl Scanner {
                 self.advance();
             }
         }
-        let value = &self.source[self.start..self.current]
-            .parse::<f32>()
+        let value = self
+            .source_between(self.start, self.current)
+            .parse::<f64>()
             .unwrap();
-        self.add_token(TokenType::Number, Some(Literal::Float(*value)))
+        self.add_token(TokenType::Number, Some(Literal::Float(value)))
     }
 
     fn peek_next(&self) -> char {
         match self.current + 1 >= self.source.len() {
             true => '\0',
-            false => self.source.chars().nth(self.current + 1).unwrap(),
+            false => self.source[self.current + 1],
         }
     }
 
@@ -200,8 +245,8 @@ impl Scanner {
         while self.peek().is_alphanumeric() || self.peek() == '_' {
             self.advance();
         }
-        let text = &self.source[self.start..self.current];
-        match get_keyword_token_type(text) {
+        let text = self.source_between(self.start, self.current);
+        match get_keyword_token_type(&text) {
             Some(t) => self.add_token(t, None),
             None => self.add_token(TokenType::Identifier, None),
         }
@@ -217,7 +262,7 @@ mod tests {
     #[test]
     fn given_valid_input() {
         let source = String::from("if(example_var){ print \"hi!\"; }");
-        let scanner = Scanner::new(source);
+        let scanner = Scanner::try_new(source).unwrap();
         let received_tokens = scanner.tokens();
         let expected_tokens = &vec![
             Token::new(TokenType::If, "if".to_string(), None, 1).unwrap(),
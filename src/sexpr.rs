@@ -0,0 +1,518 @@
+use crate::ast_printer::Printer;
+use crate::error::{RloxError, RloxSyntaxError};
+use crate::expr::{
+    AssignExpr, BinaryExpr, CallExpr, Expr, FunctionExpr, GetExpr, GroupingExpr, IndexExpr,
+    IndexSetExpr, ListExpr, LiteralExpr, LogicalExpr, MapExpr, SetExpr, SuperExpr, ThisExpr,
+    UnaryExpr, VariableExpr,
+};
+use crate::interpreter::{RED_ZONE, STACK_SEGMENT};
+use crate::parser::MAX_DEPTH;
+use crate::stmt::{
+    BlockStmt, BreakStmt, ClassStmt, ContinueStmt, ExpressionStmt, FunctionStmt, IfStmt,
+    ImportStmt, PrintStmt, ReturnStmt, Stmt, ThrowStmt, TryStmt, VarStmt, WhileStmt,
+};
+use crate::token::{get_keyword_token_type, Token, TokenType};
+
+type ReadResult<T> = Result<T, RloxError>;
+
+/**
+ * Reads the Lisp-style text written by `print_sexpr` back into a syntax tree.
+ *   Reading printed text gives back the printed tree, so `parse_sexpr(&print_sexpr(&e))` equals
+ *   `e` up to `Expr::eq_ignoring_locations`. Tokens are made up from the text, and all of them
+ *   carry the line of the text they were read from rather than the line of the original source.
+ *   Numbers are read with Rust's float syntax, except for `inf` and `NaN` which are variables.
+ *   Text printed by `AstPrinter` reads back the same unless it has strings in it.
+ */
+pub fn parse_sexpr(text: &str) -> ReadResult<Expr> {
+    expr(&read(text)?)
+}
+
+/// Reads a statement, as written by `print_sexpr_stmt`.
+pub fn parse_sexpr_stmt(text: &str) -> ReadResult<Stmt> {
+    stmt(&read(text)?)
+}
+
+/// Prints an expression like `AstPrinter`, but so that `parse_sexpr` can read it back.
+pub fn print_sexpr(expr: &Expr) -> String {
+    expr.accept(&Printer { readable: true })
+}
+
+/// Prints a statement like `AstPrinter::print_stmt`, but so that it can be read back.
+pub fn print_sexpr_stmt(stmt: &Stmt) -> String {
+    Printer { readable: true }.print_stmt(stmt)
+}
+
+// One datum of the text, with the line it starts on
+enum Sexpr {
+    Atom(String, usize),
+    Str(String, usize),
+    List(Vec<Sexpr>, usize),
+}
+
+impl Sexpr {
+    fn line(&self) -> usize {
+        match self {
+            Sexpr::Atom(_, line) | Sexpr::Str(_, line) | Sexpr::List(_, line) => *line,
+        }
+    }
+
+    fn atom(&self) -> Option<&str> {
+        match self {
+            Sexpr::Atom(atom, _) => Some(atom),
+            _ => None,
+        }
+    }
+
+    // The elements of a list that starts with the atom `head`
+    fn form(&self, head: &str) -> Option<&[Sexpr]> {
+        match self {
+            Sexpr::List(elements, _) if elements.first()?.atom() == Some(head) => {
+                Some(&elements[1..])
+            }
+            _ => None,
+        }
+    }
+}
+
+fn error(line: usize, description: &str) -> RloxError {
+    RloxError::SyntaxError(RloxSyntaxError {
+        line_number: line,
+        description: description.to_string(),
+    })
+}
+
+// Reads exactly one datum from `text`
+fn read(text: &str) -> ReadResult<Sexpr> {
+    let mut reader = Reader {
+        chars: text.chars().peekable(),
+        line: 1,
+        depth: 0,
+    };
+    let sexpr = match reader.next()? {
+        Some(Item::Open) => reader.list()?,
+        Some(Item::Datum(sexpr)) => sexpr,
+        Some(Item::Close) => return Err(error(reader.line, "Unexpected ')'.")),
+        None => return Err(error(reader.line, "Expect an expression.")),
+    };
+    match reader.next()? {
+        None => Ok(sexpr),
+        Some(_) => Err(error(reader.line, "Expect end of input after expression.")),
+    }
+}
+
+enum Item {
+    Open,
+    Close,
+    Datum(Sexpr),
+}
+
+struct Reader<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+    depth: usize, // lists open around the one being read
+}
+
+impl Reader<'_> {
+    fn next(&mut self) -> ReadResult<Option<Item>> {
+        while let Some(c) = self.chars.peek().copied() {
+            if !c.is_whitespace() {
+                break;
+            }
+            if c == '\n' {
+                self.line += 1;
+            }
+            self.chars.next();
+        }
+        let line = self.line;
+        let item = match self.chars.next() {
+            None => return Ok(None),
+            Some('(') => Item::Open,
+            Some(')') => Item::Close,
+            // Lox strings can't contain quotes, so the next one always ends the string
+            Some('"') => {
+                let mut s = String::new();
+                loop {
+                    match self.chars.next() {
+                        Some('"') => break,
+                        Some(c) => {
+                            if c == '\n' {
+                                self.line += 1;
+                            }
+                            s.push(c);
+                        }
+                        None => return Err(error(line, "Unterminated string.")),
+                    }
+                }
+                Item::Datum(Sexpr::Str(s, line))
+            }
+            Some(c) => {
+                let mut atom = c.to_string();
+                while let Some(&c) = self.chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' || c == '"' {
+                        break;
+                    }
+                    atom.push(c);
+                    self.chars.next();
+                }
+                Item::Datum(Sexpr::Atom(atom, line))
+            }
+        };
+        Ok(Some(item))
+    }
+
+    // Reads the rest of a list after its opening parenthesis
+    // Printed trees only nest lists one deeper than their nodes, for parameter lists
+    fn list(&mut self) -> ReadResult<Sexpr> {
+        let line = self.line;
+        let mut elements = vec![];
+        loop {
+            match self.next()? {
+                Some(Item::Open) if self.depth == MAX_DEPTH => {
+                    return Err(error(self.line, "Too much nesting."))
+                }
+                Some(Item::Open) => {
+                    self.depth += 1;
+                    elements.push(self.list()?);
+                    self.depth -= 1;
+                }
+                Some(Item::Close) => return Ok(Sexpr::List(elements, line)),
+                Some(Item::Datum(sexpr)) => elements.push(sexpr),
+                None => return Err(error(self.line, "Expect ')' to close list.")),
+            }
+        }
+    }
+}
+
+fn token(token_type: TokenType, lexeme: &str, line: usize) -> ReadResult<Token> {
+    Token::new(token_type, lexeme.to_string(), None, line)
+}
+
+fn identifier(sexpr: &Sexpr) -> ReadResult<Token> {
+    match sexpr.atom() {
+        Some(name) if is_identifier(name) => token(TokenType::Identifier, name, sexpr.line()),
+        _ => Err(error(sexpr.line(), "Expect identifier.")),
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    let starts_like_one = chars.next().is_some_and(|c| c == '_' || c.is_alphabetic());
+    starts_like_one
+        && chars.all(|c| c == '_' || c.is_alphanumeric())
+        && get_keyword_token_type(name).is_none()
+}
+
+fn binary_operator(lexeme: &str) -> Option<TokenType> {
+    let token_type = match lexeme {
+        "+" => TokenType::Plus,
+        "-" => TokenType::Minus,
+        "*" => TokenType::Star,
+        "/" => TokenType::Slash,
+        ">" => TokenType::Greater,
+        ">=" => TokenType::GreaterEqual,
+        "<" => TokenType::Less,
+        "<=" => TokenType::LessEqual,
+        "==" => TokenType::EqualEqual,
+        "!=" => TokenType::BangEqual,
+        _ => return None,
+    };
+    Some(token_type)
+}
+
+fn exprs(sexprs: &[Sexpr]) -> ReadResult<Vec<Expr>> {
+    sexprs.iter().map(expr).collect()
+}
+
+fn stmts(sexprs: &[Sexpr]) -> ReadResult<Vec<Stmt>> {
+    sexprs.iter().map(stmt).collect()
+}
+
+// Checks the number of arguments of a form
+fn arity<'a>(args: &'a [Sexpr], count: usize, line: usize, form: &str) -> ReadResult<&'a [Sexpr]> {
+    match args.len() == count {
+        true => Ok(args),
+        false => Err(error(
+            line,
+            &format!("Expect {} argument(s) to '{}'.", count, form),
+        )),
+    }
+}
+
+// Trees as deep as the parser allows take more than a thread's stack to read back
+fn expr(sexpr: &Sexpr) -> ReadResult<Expr> {
+    stacker::maybe_grow(RED_ZONE, STACK_SEGMENT, || nested_expr(sexpr))
+}
+
+fn nested_expr(sexpr: &Sexpr) -> ReadResult<Expr> {
+    let line = sexpr.line();
+    let elements = match sexpr {
+        Sexpr::Str(s, _) => return Ok(LiteralExpr::new(LiteralExpr::String(s.clone()))),
+        Sexpr::Atom(atom, _) => return atom_expr(atom, line),
+        Sexpr::List(elements, _) => elements,
+    };
+    let (head, args) = match elements.split_first() {
+        Some((head, args)) => (head.atom().unwrap_or(""), args),
+        None => return Err(error(line, "Expect a form, not '()'.")),
+    };
+    match head {
+        "group" => Ok(GroupingExpr::new(expr(&arity(args, 1, line, head)?[0])?)),
+        "call" => match args.split_first() {
+            Some((callee, arguments)) => Ok(CallExpr::new(
+                expr(callee)?,
+                token(TokenType::RightParen, ")", line)?,
+                exprs(arguments)?,
+            )),
+            None => Err(error(line, "Expect a callee in 'call'.")),
+        },
+        "." => {
+            let args = arity(args, 2, line, head)?;
+            Ok(GetExpr::new(expr(&args[0])?, identifier(&args[1])?))
+        }
+        "=" => {
+            let args = arity(args, 2, line, head)?;
+            let value = expr(&args[1])?;
+            match args[0].form(".") {
+                Some(target) => {
+                    let target = arity(target, 2, line, ".")?;
+                    Ok(SetExpr::new(
+                        expr(&target[0])?,
+                        identifier(&target[1])?,
+                        value,
+                    ))
+                }
+                None => Ok(AssignExpr::new(identifier(&args[0])?, value)),
+            }
+        }
+        "index" => {
+            let args = arity(args, 2, line, head)?;
+            let bracket = token(TokenType::RightBracket, "]", line)?;
+            Ok(IndexExpr::new(expr(&args[0])?, bracket, expr(&args[1])?))
+        }
+        "index=" => {
+            let args = exprs(arity(args, 3, line, head)?)?;
+            let bracket = token(TokenType::RightBracket, "]", line)?;
+            let (object, index, value) = (args[0].clone(), args[1].clone(), args[2].clone());
+            Ok(IndexSetExpr::new(object, bracket, index, value))
+        }
+        "list" => Ok(ListExpr::new(
+            token(TokenType::LeftBracket, "[", line)?,
+            exprs(args)?,
+        )),
+        "map" => {
+            if args.len() % 2 != 0 {
+                return Err(error(line, "Expect a value for every key in 'map'."));
+            }
+            let entries = args
+                .chunks(2)
+                .map(|entry| Ok((expr(&entry[0])?, expr(&entry[1])?)))
+                .collect::<ReadResult<Vec<_>>>()?;
+            Ok(MapExpr::new(
+                token(TokenType::LeftBrace, "{", line)?,
+                entries,
+            ))
+        }
+        "super" => Ok(SuperExpr::new(
+            token(TokenType::Super, "super", line)?,
+            identifier(&arity(args, 1, line, head)?[0])?,
+        )),
+        "fun" => {
+            let (params, body) = function(args, line)?;
+            Ok(FunctionExpr::new(
+                token(TokenType::Fun, "fun", line)?,
+                params,
+                body,
+            ))
+        }
+        "and" | "or" => {
+            let args = arity(args, 2, line, head)?;
+            let token_type = match head {
+                "and" => TokenType::And,
+                _ => TokenType::Or,
+            };
+            let operator = token(token_type, head, line)?;
+            Ok(LogicalExpr::new(operator, expr(&args[0])?, expr(&args[1])?))
+        }
+        "!" => Ok(UnaryExpr::new(
+            token(TokenType::Bang, head, line)?,
+            expr(&arity(args, 1, line, head)?[0])?,
+        )),
+        "-" if args.len() == 1 => Ok(UnaryExpr::new(
+            token(TokenType::Minus, head, line)?,
+            expr(&args[0])?,
+        )),
+        _ => match binary_operator(head) {
+            Some(token_type) => {
+                let args = arity(args, 2, line, head)?;
+                let operator = token(token_type, head, line)?;
+                Ok(BinaryExpr::new(operator, expr(&args[0])?, expr(&args[1])?))
+            }
+            None => Err(error(line, &format!("Unknown form '{}'.", head))),
+        },
+    }
+}
+
+fn atom_expr(atom: &str, line: usize) -> ReadResult<Expr> {
+    let literal = match atom {
+        "nil" => LiteralExpr::Nil,
+        "true" => LiteralExpr::Bool(true),
+        "false" => LiteralExpr::Bool(false),
+        "this" => return Ok(ThisExpr::new(token(TokenType::This, atom, line)?)),
+        // Constant folding can make numbers no literal in the source can be
+        "+inf.0" => LiteralExpr::Float(f64::INFINITY),
+        "-inf.0" => LiteralExpr::Float(f64::NEG_INFINITY),
+        "+nan.0" => LiteralExpr::Float(f64::NAN),
+        "-nan.0" => LiteralExpr::Float(-f64::NAN),
+        _ => {
+            let digits = atom.strip_prefix('-').unwrap_or(atom);
+            if !digits.starts_with(|c: char| c.is_ascii_digit()) {
+                return Ok(VariableExpr::new(identifier(&Sexpr::Atom(
+                    atom.to_string(),
+                    line,
+                ))?));
+            }
+            match atom.parse::<f64>() {
+                Ok(n) => LiteralExpr::Float(n),
+                Err(_) => return Err(error(line, &format!("Invalid number '{}'.", atom))),
+            }
+        }
+    };
+    Ok(LiteralExpr::new(literal))
+}
+
+// The parameters and body of `(fun (params) body...)`, after the name if it has one
+fn function(args: &[Sexpr], line: usize) -> ReadResult<(Vec<Token>, Vec<Stmt>)> {
+    match args.split_first() {
+        Some((Sexpr::List(params, _), body)) => {
+            let params = params.iter().map(identifier).collect::<ReadResult<_>>()?;
+            Ok((params, stmts(body)?))
+        }
+        _ => Err(error(line, "Expect a parameter list in 'fun'.")),
+    }
+}
+
+fn function_stmt(sexpr: &Sexpr) -> ReadResult<std::rc::Rc<FunctionStmt>> {
+    match sexpr.form("fun") {
+        Some(args) if !args.is_empty() => {
+            let (params, body) = function(&args[1..], sexpr.line())?;
+            Ok(FunctionStmt::new(identifier(&args[0])?, params, body))
+        }
+        _ => Err(error(sexpr.line(), "Expect a method.")),
+    }
+}
+
+fn stmt(sexpr: &Sexpr) -> ReadResult<Stmt> {
+    stacker::maybe_grow(RED_ZONE, STACK_SEGMENT, || nested_stmt(sexpr))
+}
+
+fn nested_stmt(sexpr: &Sexpr) -> ReadResult<Stmt> {
+    let line = sexpr.line();
+    let (head, args) = match sexpr {
+        Sexpr::List(elements, _) => match elements.split_first() {
+            Some((Sexpr::Atom(head, _), args)) => (head.as_str(), args),
+            _ => return Ok(ExpressionStmt::new(expr(sexpr)?)),
+        },
+        _ => return Ok(ExpressionStmt::new(expr(sexpr)?)),
+    };
+    match head {
+        "block" => Ok(BlockStmt::new(stmts(args)?)),
+        "break" => {
+            arity(args, 0, line, head)?;
+            Ok(BreakStmt::new(token(TokenType::Break, head, line)?))
+        }
+        "continue" => {
+            arity(args, 0, line, head)?;
+            Ok(ContinueStmt::new(token(TokenType::Continue, head, line)?))
+        }
+        "class" => {
+            let (name, rest) = match args.split_first() {
+                Some((name, rest)) => (identifier(name)?, rest),
+                None => return Err(error(line, "Expect class name.")),
+            };
+            let (superclass, methods) = match rest {
+                [less, superclass, methods @ ..] if less.atom() == Some("<") => {
+                    (Some(VariableExpr::new(identifier(superclass)?)), methods)
+                }
+                _ => (None, rest),
+            };
+            let methods = methods
+                .iter()
+                .map(function_stmt)
+                .collect::<ReadResult<_>>()?;
+            Ok(ClassStmt::new(name, superclass, methods))
+        }
+        // A lambda in statement position has no name
+        "fun" if args.first().and_then(Sexpr::atom).is_some() => {
+            Ok(Stmt::Function(function_stmt(sexpr)?))
+        }
+        "if" => match args {
+            [condition, then_branch] => Ok(IfStmt::new(expr(condition)?, stmt(then_branch)?, None)),
+            [condition, then_branch, else_branch] => Ok(IfStmt::new(
+                expr(condition)?,
+                stmt(then_branch)?,
+                Some(stmt(else_branch)?),
+            )),
+            _ => Err(error(
+                line,
+                "Expect a condition and one or two branches in 'if'.",
+            )),
+        },
+        "import" => match arity(args, 2, line, head)? {
+            [Sexpr::Str(path, _), name] => Ok(ImportStmt::new(
+                token(TokenType::Import, head, line)?,
+                path.clone(),
+                identifier(name)?,
+            )),
+            _ => Err(error(line, "Expect a module path in 'import'.")),
+        },
+        "print" => Ok(PrintStmt::new(expr(&arity(args, 1, line, head)?[0])?)),
+        "return" => {
+            let keyword = token(TokenType::Return, head, line)?;
+            match args {
+                [] => Ok(ReturnStmt::new(keyword, None)),
+                [value] => Ok(ReturnStmt::new(keyword, Some(expr(value)?))),
+                _ => Err(error(line, "Expect at most one value in 'return'.")),
+            }
+        }
+        "throw" => Ok(ThrowStmt::new(
+            token(TokenType::Throw, head, line)?,
+            expr(&arity(args, 1, line, head)?[0])?,
+        )),
+        "try" => {
+            let (mut body, mut catch, mut finally) = (vec![], None, None);
+            for arg in args {
+                if let Some(clause) = arg.form("catch") {
+                    match clause.split_first() {
+                        Some((name, body)) => catch = Some((identifier(name)?, stmts(body)?)),
+                        None => return Err(error(arg.line(), "Expect a name in 'catch'.")),
+                    }
+                } else if let Some(clause) = arg.form("finally") {
+                    finally = Some(stmts(clause)?);
+                } else {
+                    body.push(stmt(arg)?);
+                }
+            }
+            Ok(TryStmt::new(body, catch, finally))
+        }
+        "var" => match args {
+            [name] => Ok(VarStmt::new(identifier(name)?, None)),
+            [name, initializer] => Ok(VarStmt::new(identifier(name)?, Some(expr(initializer)?))),
+            _ => Err(error(
+                line,
+                "Expect a name and at most one initializer in 'var'.",
+            )),
+        },
+        "while" => match args {
+            [condition, body] => Ok(WhileStmt::new(expr(condition)?, stmt(body)?)),
+            [condition, increment, body] => Ok(WhileStmt::with_increment(
+                expr(condition)?,
+                stmt(body)?,
+                Some(expr(increment)?),
+            )),
+            _ => Err(error(
+                line,
+                "Expect a condition, an optional increment and a body in 'while'.",
+            )),
+        },
+        _ => Ok(ExpressionStmt::new(expr(sexpr)?)),
+    }
+}
@@ -0,0 +1,449 @@
+// Constructors return the wrapping `Stmt` rather than `Self` so nodes can be nested directly.
+#![allow(clippy::new_ret_no_self)]
+
+use std::rc::Rc;
+
+use crate::expr::{all_eq, Expr, FunctionExpr};
+use crate::token::Token;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Stmt {
+    Block(Rc<BlockStmt>),
+    Break(Rc<BreakStmt>),
+    Class(Rc<ClassStmt>),
+    Continue(Rc<ContinueStmt>),
+    Expression(Rc<ExpressionStmt>),
+    Function(Rc<FunctionStmt>),
+    If(Rc<IfStmt>),
+    Import(Rc<ImportStmt>),
+    Print(Rc<PrintStmt>),
+    Return(Rc<ReturnStmt>),
+    Throw(Rc<ThrowStmt>),
+    Try(Rc<TryStmt>),
+    Var(Rc<VarStmt>),
+    While(Rc<WhileStmt>),
+}
+
+impl Stmt {
+    pub fn accept<T>(&self, visitor: &dyn Visitor<T>) -> T {
+        visitor.visit_stmt(self)
+    }
+
+    // Identity of the node, like `Expr::id`. Used to key the line spans the parser records.
+    pub(crate) fn id(&self) -> usize {
+        match self {
+            Stmt::Block(s) => Rc::as_ptr(s) as *const () as usize,
+            Stmt::Break(s) => Rc::as_ptr(s) as *const () as usize,
+            Stmt::Class(s) => Rc::as_ptr(s) as *const () as usize,
+            Stmt::Continue(s) => Rc::as_ptr(s) as *const () as usize,
+            Stmt::Expression(s) => Rc::as_ptr(s) as *const () as usize,
+            Stmt::Function(s) => Rc::as_ptr(s) as *const () as usize,
+            Stmt::If(s) => Rc::as_ptr(s) as *const () as usize,
+            Stmt::Import(s) => Rc::as_ptr(s) as *const () as usize,
+            Stmt::Print(s) => Rc::as_ptr(s) as *const () as usize,
+            Stmt::Return(s) => Rc::as_ptr(s) as *const () as usize,
+            Stmt::Throw(s) => Rc::as_ptr(s) as *const () as usize,
+            Stmt::Try(s) => Rc::as_ptr(s) as *const () as usize,
+            Stmt::Var(s) => Rc::as_ptr(s) as *const () as usize,
+            Stmt::While(s) => Rc::as_ptr(s) as *const () as usize,
+        }
+    }
+
+    /// Compares two trees like `==`, but ignores the lines their tokens come from.
+    pub fn eq_ignoring_locations(&self, other: &Stmt) -> bool {
+        let same_bodies = |a: &[Stmt], b: &[Stmt]| all_eq(a, b, Stmt::eq_ignoring_locations);
+        let same_exprs = |a: Option<&Expr>, b: Option<&Expr>| match (a, b) {
+            (Some(a), Some(b)) => a.eq_ignoring_locations(b),
+            (a, b) => a.is_none() && b.is_none(),
+        };
+        match (self, other) {
+            (Stmt::Block(a), Stmt::Block(b)) => same_bodies(&a.statements, &b.statements),
+            (Stmt::Break(_), Stmt::Break(_)) | (Stmt::Continue(_), Stmt::Continue(_)) => true,
+            (Stmt::Class(a), Stmt::Class(b)) => {
+                a.name.eq_ignoring_location(&b.name)
+                    && same_exprs(a.superclass.as_ref(), b.superclass.as_ref())
+                    && all_eq(&a.methods, &b.methods, |a, b| a.eq_ignoring_locations(b))
+            }
+            (Stmt::Expression(a), Stmt::Expression(b)) => {
+                a.expression.eq_ignoring_locations(&b.expression)
+            }
+            (Stmt::Function(a), Stmt::Function(b)) => a.eq_ignoring_locations(b),
+            (Stmt::If(a), Stmt::If(b)) => {
+                a.condition.eq_ignoring_locations(&b.condition)
+                    && a.then_branch.eq_ignoring_locations(&b.then_branch)
+                    && match (&a.else_branch, &b.else_branch) {
+                        (Some(a), Some(b)) => a.eq_ignoring_locations(b),
+                        (a, b) => a.is_none() && b.is_none(),
+                    }
+            }
+            (Stmt::Import(a), Stmt::Import(b)) => {
+                a.path == b.path && a.name.eq_ignoring_location(&b.name)
+            }
+            (Stmt::Print(a), Stmt::Print(b)) => a.expression.eq_ignoring_locations(&b.expression),
+            (Stmt::Return(a), Stmt::Return(b)) => same_exprs(a.value.as_ref(), b.value.as_ref()),
+            (Stmt::Throw(a), Stmt::Throw(b)) => a.value.eq_ignoring_locations(&b.value),
+            (Stmt::Try(a), Stmt::Try(b)) => {
+                same_bodies(&a.body, &b.body)
+                    && match (&a.catch, &b.catch) {
+                        (Some((n1, b1)), Some((n2, b2))) => {
+                            n1.eq_ignoring_location(n2) && same_bodies(b1, b2)
+                        }
+                        (a, b) => a.is_none() && b.is_none(),
+                    }
+                    && match (&a.finally, &b.finally) {
+                        (Some(a), Some(b)) => same_bodies(a, b),
+                        (a, b) => a.is_none() && b.is_none(),
+                    }
+            }
+            (Stmt::Var(a), Stmt::Var(b)) => {
+                a.name.eq_ignoring_location(&b.name)
+                    && same_exprs(a.initializer.as_ref(), b.initializer.as_ref())
+            }
+            (Stmt::While(a), Stmt::While(b)) => {
+                a.condition.eq_ignoring_locations(&b.condition)
+                    && a.body.eq_ignoring_locations(&b.body)
+                    && same_exprs(a.increment.as_ref(), b.increment.as_ref())
+            }
+            _ => false,
+        }
+    }
+}
+
+/**
+ * Statement counterpart of `expr::Visitor`.
+ *   Statements produce no value, so implementers typically use `T = ()` or a `Result` carrying control flow.
+ */
+pub trait Visitor<T> {
+    fn visit_stmt(&self, stmt: &Stmt) -> T;
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct BlockStmt {
+    statements: Vec<Stmt>,
+}
+
+impl BlockStmt {
+    pub fn new(statements: Vec<Stmt>) -> Stmt {
+        Stmt::Block(Rc::new(BlockStmt { statements }))
+    }
+
+    pub fn statements(&self) -> &[Stmt] {
+        &self.statements
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct BreakStmt {
+    keyword: Token,
+}
+
+impl BreakStmt {
+    pub fn new(keyword: Token) -> Stmt {
+        Stmt::Break(Rc::new(BreakStmt { keyword }))
+    }
+
+    pub fn keyword(&self) -> &Token {
+        &self.keyword
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct ClassStmt {
+    name: Token,
+    superclass: Option<Expr>,
+    methods: Vec<Rc<FunctionStmt>>,
+}
+
+impl ClassStmt {
+    pub fn new(name: Token, superclass: Option<Expr>, methods: Vec<Rc<FunctionStmt>>) -> Stmt {
+        Stmt::Class(Rc::new(ClassStmt {
+            name,
+            superclass,
+            methods,
+        }))
+    }
+
+    pub fn name(&self) -> &Token {
+        &self.name
+    }
+
+    pub fn superclass(&self) -> Option<&Expr> {
+        self.superclass.as_ref()
+    }
+
+    pub fn methods(&self) -> &[Rc<FunctionStmt>] {
+        &self.methods
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct ContinueStmt {
+    keyword: Token,
+}
+
+impl ContinueStmt {
+    pub fn new(keyword: Token) -> Stmt {
+        Stmt::Continue(Rc::new(ContinueStmt { keyword }))
+    }
+
+    pub fn keyword(&self) -> &Token {
+        &self.keyword
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct ExpressionStmt {
+    expression: Expr,
+}
+
+impl ExpressionStmt {
+    pub fn new(expression: Expr) -> Stmt {
+        Stmt::Expression(Rc::new(ExpressionStmt { expression }))
+    }
+
+    pub fn expression(&self) -> &Expr {
+        &self.expression
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct FunctionStmt {
+    name: Token,
+    function: Rc<FunctionExpr>,
+}
+
+impl FunctionStmt {
+    // Returns the bare declaration, since class bodies hold methods outside of a `Stmt`
+    pub fn new(name: Token, params: Vec<Token>, body: Vec<Stmt>) -> Rc<FunctionStmt> {
+        let function = FunctionExpr::new_declaration(name.clone(), params, body);
+        Rc::new(FunctionStmt { name, function })
+    }
+
+    pub fn name(&self) -> &Token {
+        &self.name
+    }
+
+    pub fn function(&self) -> &Rc<FunctionExpr> {
+        &self.function
+    }
+
+    pub fn params(&self) -> &[Token] {
+        self.function.params()
+    }
+
+    pub fn body(&self) -> &[Stmt] {
+        self.function.body()
+    }
+
+    pub fn eq_ignoring_locations(&self, other: &FunctionStmt) -> bool {
+        self.name.eq_ignoring_location(&other.name)
+            && self.function.eq_ignoring_locations(&other.function)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct IfStmt {
+    condition: Expr,
+    then_branch: Stmt,
+    else_branch: Option<Stmt>,
+}
+
+impl IfStmt {
+    pub fn new(condition: Expr, then_branch: Stmt, else_branch: Option<Stmt>) -> Stmt {
+        Stmt::If(Rc::new(IfStmt {
+            condition,
+            then_branch,
+            else_branch,
+        }))
+    }
+
+    pub fn condition(&self) -> &Expr {
+        &self.condition
+    }
+
+    pub fn then_branch(&self) -> &Stmt {
+        &self.then_branch
+    }
+
+    pub fn else_branch(&self) -> Option<&Stmt> {
+        self.else_branch.as_ref()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct ImportStmt {
+    keyword: Token,
+    path: String,
+    name: Token,
+}
+
+impl ImportStmt {
+    pub fn new(keyword: Token, path: String, name: Token) -> Stmt {
+        Stmt::Import(Rc::new(ImportStmt {
+            keyword,
+            path,
+            name,
+        }))
+    }
+
+    pub fn keyword(&self) -> &Token {
+        &self.keyword
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn name(&self) -> &Token {
+        &self.name
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct PrintStmt {
+    expression: Expr,
+}
+
+impl PrintStmt {
+    pub fn new(expression: Expr) -> Stmt {
+        Stmt::Print(Rc::new(PrintStmt { expression }))
+    }
+
+    pub fn expression(&self) -> &Expr {
+        &self.expression
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct ReturnStmt {
+    keyword: Token,
+    value: Option<Expr>,
+}
+
+impl ReturnStmt {
+    pub fn new(keyword: Token, value: Option<Expr>) -> Stmt {
+        Stmt::Return(Rc::new(ReturnStmt { keyword, value }))
+    }
+
+    pub fn keyword(&self) -> &Token {
+        &self.keyword
+    }
+
+    pub fn value(&self) -> Option<&Expr> {
+        self.value.as_ref()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct ThrowStmt {
+    keyword: Token,
+    value: Expr,
+}
+
+impl ThrowStmt {
+    pub fn new(keyword: Token, value: Expr) -> Stmt {
+        Stmt::Throw(Rc::new(ThrowStmt { keyword, value }))
+    }
+
+    pub fn keyword(&self) -> &Token {
+        &self.keyword
+    }
+
+    pub fn value(&self) -> &Expr {
+        &self.value
+    }
+}
+
+/**
+ * `try { } catch (e) { } finally { }`, where at least one of the two clauses is present.
+ *   The catch clause binds the caught value to its name in a scope of its own.
+ */
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct TryStmt {
+    body: Vec<Stmt>,
+    catch: Option<(Token, Vec<Stmt>)>,
+    finally: Option<Vec<Stmt>>,
+}
+
+impl TryStmt {
+    pub fn new(
+        body: Vec<Stmt>,
+        catch: Option<(Token, Vec<Stmt>)>,
+        finally: Option<Vec<Stmt>>,
+    ) -> Stmt {
+        Stmt::Try(Rc::new(TryStmt {
+            body,
+            catch,
+            finally,
+        }))
+    }
+
+    pub fn body(&self) -> &[Stmt] {
+        &self.body
+    }
+
+    pub fn catch_name(&self) -> Option<&Token> {
+        self.catch.as_ref().map(|(name, _)| name)
+    }
+
+    pub fn catch_body(&self) -> Option<&[Stmt]> {
+        self.catch.as_ref().map(|(_, body)| body.as_slice())
+    }
+
+    pub fn finally_body(&self) -> Option<&[Stmt]> {
+        self.finally.as_deref()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct VarStmt {
+    name: Token,
+    initializer: Option<Expr>,
+}
+
+impl VarStmt {
+    pub fn new(name: Token, initializer: Option<Expr>) -> Stmt {
+        Stmt::Var(Rc::new(VarStmt { name, initializer }))
+    }
+
+    pub fn name(&self) -> &Token {
+        &self.name
+    }
+
+    pub fn initializer(&self) -> Option<&Expr> {
+        self.initializer.as_ref()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct WhileStmt {
+    condition: Expr,
+    body: Stmt,
+    increment: Option<Expr>, // from a desugared for loop, run after every iteration including `continue`
+}
+
+impl WhileStmt {
+    pub fn new(condition: Expr, body: Stmt) -> Stmt {
+        WhileStmt::with_increment(condition, body, None)
+    }
+
+    pub fn with_increment(condition: Expr, body: Stmt, increment: Option<Expr>) -> Stmt {
+        Stmt::While(Rc::new(WhileStmt {
+            condition,
+            body,
+            increment,
+        }))
+    }
+
+    pub fn condition(&self) -> &Expr {
+        &self.condition
+    }
+
+    pub fn body(&self) -> &Stmt {
+        &self.body
+    }
+
+    pub fn increment(&self) -> Option<&Expr> {
+        self.increment.as_ref()
+    }
+}
@@ -1,17 +1,41 @@
-use std::fmt::{self, Display};
+use std::fmt::{self, Debug, Display};
+use std::hash::{Hash, Hasher};
 
 use crate::error::RloxError;
+use crate::interner::{intern, Symbol};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub enum Literal {
     String(String),
-    Float(f32),
+    Float(f64),
 }
 
-#[derive(Debug, Clone, PartialEq)]
+// Numbers are compared by their bits, so that tokens and the trees holding them can be `Eq`
+impl PartialEq for Literal {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Literal::String(a), Literal::String(b)) => a == b,
+            (Literal::Float(a), Literal::Float(b)) => a.to_bits() == b.to_bits(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Literal {}
+
+impl Hash for Literal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Literal::String(s) => s.hash(state),
+            Literal::Float(n) => n.to_bits().hash(state),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct Token {
     token_type: TokenType,
-    lexeme: String,
+    lexeme: Symbol,
     literal: Option<Literal>,
     line_number: usize,
 }
@@ -22,6 +46,17 @@ impl Display for Token {
     }
 }
 
+// Kept to one line, as tokens are the leaves of syntax tree dumps: `Identifier "x" @3`
+impl Debug for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:?} {:?} @{}",
+            self.token_type, self.lexeme, self.line_number
+        )
+    }
+}
+
 impl Token {
     pub fn new(
         token_type: TokenType,
@@ -31,7 +66,7 @@ impl Token {
     ) -> Result<Self, RloxError> {
         Ok(Token {
             token_type,
-            lexeme,
+            lexeme: intern(&lexeme),
             literal,
             line_number,
         })
@@ -42,21 +77,34 @@ impl Token {
     pub fn lexeme(&self) -> &str {
         &self.lexeme
     }
+    pub fn symbol(&self) -> &Symbol {
+        &self.lexeme
+    }
     pub fn literal(&self) -> &Option<Literal> {
         &self.literal
     }
     pub fn line_number(&self) -> &usize {
         &self.line_number
     }
+
+    /// Compares two tokens like `==`, but ignores the lines they come from.
+    pub fn eq_ignoring_location(&self, other: &Token) -> bool {
+        self.token_type == other.token_type
+            && self.lexeme == other.lexeme
+            && self.literal == other.literal
+    }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum TokenType {
     // Single-character tokens.
     LeftParen,
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
     Comma,
     Dot,
     Minus,
@@ -82,19 +130,26 @@ pub enum TokenType {
 
     // Keywords.
     And,
+    Break,
+    Catch,
     Class,
+    Continue,
     Else,
     False,
+    Finally,
     Fun,
     For,
     If,
+    Import,
     Nil,
     Or,
     Print,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
 
@@ -104,19 +159,26 @@ pub enum TokenType {
 pub(crate) fn get_keyword_token_type(key: &str) -> Option<TokenType> {
     match key {
         "and" => Some(TokenType::And),
+        "break" => Some(TokenType::Break),
+        "catch" => Some(TokenType::Catch),
         "class" => Some(TokenType::Class),
+        "continue" => Some(TokenType::Continue),
         "else" => Some(TokenType::Else),
         "false" => Some(TokenType::False),
+        "finally" => Some(TokenType::Finally),
         "for" => Some(TokenType::For),
         "fun" => Some(TokenType::Fun),
         "if" => Some(TokenType::If),
+        "import" => Some(TokenType::Import),
         "nil" => Some(TokenType::Nil),
         "or" => Some(TokenType::Or),
         "print" => Some(TokenType::Print),
         "return" => Some(TokenType::Return),
         "super" => Some(TokenType::Super),
         "this" => Some(TokenType::This),
+        "throw" => Some(TokenType::Throw),
         "true" => Some(TokenType::True),
+        "try" => Some(TokenType::Try),
         "var" => Some(TokenType::Var),
         "while" => Some(TokenType::While),
         _ => None,
@@ -0,0 +1,238 @@
+use std::cell::RefCell;
+use std::convert::TryFrom;
+use std::fmt::{self, Debug, Display};
+use std::rc::Rc;
+
+use crate::class::{LoxClass, LoxInstance};
+use crate::error::RloxError;
+use crate::function::{LoxFunction, NativeFunction};
+use crate::interner::{intern, Symbol};
+use crate::map::LoxMap;
+use crate::module::LoxModule;
+
+/// A Lox runtime value.
+#[derive(Clone)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(Symbol),
+    Function(Rc<LoxFunction>),
+    NativeFunction(Rc<NativeFunction>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+    List(Rc<RefCell<Vec<Value>>>),
+    Map(Rc<RefCell<LoxMap>>),
+    Module(Rc<LoxModule>),
+}
+
+impl Value {
+    // Lox follows Ruby's rule: false and nil are falsey, everything else is truthy
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Bool(false))
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Nil => "nil",
+            Value::Bool(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Function(_) | Value::NativeFunction(_) => "function",
+            Value::Class(_) => "class",
+            Value::Instance(_) => "instance",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Module(_) => "module",
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            (Value::NativeFunction(a), Value::NativeFunction(b)) => Rc::ptr_eq(a, b),
+            (Value::Class(a), Value::Class(b)) => Rc::ptr_eq(a, b),
+            (Value::Instance(a), Value::Instance(b)) => Rc::ptr_eq(a, b),
+            (Value::List(a), Value::List(b)) => Rc::ptr_eq(a, b),
+            (Value::Map(a), Value::Map(b)) => Rc::ptr_eq(a, b),
+            (Value::Module(a), Value::Module(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl Value {
+    /**
+     * Writes the value, quoting strings when `quoted`. `path` holds the collections being written
+     *   around it, so a list or map that contains itself prints as `[...]` or `{...}` where it
+     *   repeats instead of recursing forever.
+     */
+    fn write(
+        &self,
+        f: &mut fmt::Formatter,
+        quoted: bool,
+        path: &mut Vec<*const ()>,
+    ) -> fmt::Result {
+        match self {
+            Value::Nil => write!(f, "nil"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(s) if quoted => write!(f, "{:?}", s),
+            Value::String(s) => write!(f, "{}", s),
+            Value::Function(function) => write!(f, "{}", function),
+            Value::NativeFunction(_) => write!(f, "<native fn>"),
+            Value::Class(class) => write!(f, "{}", class.name()),
+            Value::Instance(instance) => write!(f, "{} instance", instance.borrow().class().name()),
+            Value::List(list) => {
+                let pointer = Rc::as_ptr(list) as *const ();
+                if path.contains(&pointer) {
+                    return write!(f, "[...]");
+                }
+                path.push(pointer);
+                write!(f, "[")?;
+                for (i, element) in list.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    element.write(f, true, path)?;
+                }
+                path.pop();
+                write!(f, "]")
+            }
+            Value::Map(map) => {
+                let pointer = Rc::as_ptr(map) as *const ();
+                if path.contains(&pointer) {
+                    return write!(f, "{{...}}");
+                }
+                path.push(pointer);
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.borrow().iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    key.to_value().write(f, true, path)?;
+                    write!(f, ": ")?;
+                    value.write(f, true, path)?;
+                }
+                path.pop();
+                write!(f, "}}")
+            }
+            Value::Module(module) => write!(f, "<module {}>", module.path().display()),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, false, &mut Vec::new())
+    }
+}
+
+impl Debug for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.write(f, true, &mut Vec::new())
+    }
+}
+
+impl From<()> for Value {
+    fn from(_: ()) -> Self {
+        Value::Nil
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(intern(s))
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(intern(&s))
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(elements: Vec<Value>) -> Self {
+        Value::List(Rc::new(RefCell::new(elements)))
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(option: Option<T>) -> Self {
+        match option {
+            Some(v) => v.into(),
+            None => Value::Nil,
+        }
+    }
+}
+
+fn type_error(expected: &str, value: &Value) -> RloxError {
+    RloxError::runtime(format!(
+        "Expected a {} but got {}.",
+        expected,
+        value.type_name()
+    ))
+}
+
+impl TryFrom<Value> for bool {
+    type Error = RloxError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Bool(b) => Ok(b),
+            _ => Err(type_error("boolean", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = RloxError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Number(n) => Ok(n),
+            _ => Err(type_error("number", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = RloxError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(s) => Ok(s.to_string()),
+            _ => Err(type_error("string", &value)),
+        }
+    }
+}
+
+impl TryFrom<Value> for () {
+    type Error = RloxError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Nil => Ok(()),
+            _ => Err(type_error("nil", &value)),
+        }
+    }
+}
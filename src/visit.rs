@@ -0,0 +1,293 @@
+use crate::expr::{
+    AssignExpr, BinaryExpr, CallExpr, Expr, FunctionExpr, GetExpr, GroupingExpr, IndexExpr,
+    IndexSetExpr, ListExpr, LiteralExpr, LogicalExpr, MapExpr, SetExpr, SuperExpr, ThisExpr,
+    UnaryExpr, VariableExpr,
+};
+use crate::stmt::{
+    BlockStmt, BreakStmt, ClassStmt, ContinueStmt, ExpressionStmt, FunctionStmt, IfStmt,
+    ImportStmt, PrintStmt, ReturnStmt, Stmt, ThrowStmt, TryStmt, VarStmt, WhileStmt,
+};
+
+/**
+ * A traversal of expressions and statements that can update its own state as it goes, for
+ *   linters and analyses. Every method defaults to the matching `walk_*` function, which visits
+ *   the children of the node, so implementers override only the nodes they care about and call
+ *   `walk_*` themselves where they still want to descend.
+ */
+pub trait VisitorMut {
+    fn visit_expr(&mut self, expr: &Expr) {
+        walk_expr(self, expr)
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        walk_stmt(self, stmt)
+    }
+
+    fn visit_assign_expr(&mut self, expr: &AssignExpr) {
+        walk_assign_expr(self, expr)
+    }
+
+    fn visit_binary_expr(&mut self, expr: &BinaryExpr) {
+        walk_binary_expr(self, expr)
+    }
+
+    fn visit_call_expr(&mut self, expr: &CallExpr) {
+        walk_call_expr(self, expr)
+    }
+
+    fn visit_function_expr(&mut self, expr: &FunctionExpr) {
+        walk_function_expr(self, expr)
+    }
+
+    fn visit_get_expr(&mut self, expr: &GetExpr) {
+        walk_get_expr(self, expr)
+    }
+
+    fn visit_grouping_expr(&mut self, expr: &GroupingExpr) {
+        walk_grouping_expr(self, expr)
+    }
+
+    fn visit_index_expr(&mut self, expr: &IndexExpr) {
+        walk_index_expr(self, expr)
+    }
+
+    fn visit_index_set_expr(&mut self, expr: &IndexSetExpr) {
+        walk_index_set_expr(self, expr)
+    }
+
+    fn visit_list_expr(&mut self, expr: &ListExpr) {
+        walk_list_expr(self, expr)
+    }
+
+    fn visit_literal_expr(&mut self, _expr: &LiteralExpr) {}
+
+    fn visit_logical_expr(&mut self, expr: &LogicalExpr) {
+        walk_logical_expr(self, expr)
+    }
+
+    fn visit_map_expr(&mut self, expr: &MapExpr) {
+        walk_map_expr(self, expr)
+    }
+
+    fn visit_set_expr(&mut self, expr: &SetExpr) {
+        walk_set_expr(self, expr)
+    }
+
+    fn visit_super_expr(&mut self, _expr: &SuperExpr) {}
+
+    fn visit_this_expr(&mut self, _expr: &ThisExpr) {}
+
+    fn visit_unary_expr(&mut self, expr: &UnaryExpr) {
+        walk_unary_expr(self, expr)
+    }
+
+    fn visit_variable_expr(&mut self, _expr: &VariableExpr) {}
+
+    fn visit_block_stmt(&mut self, stmt: &BlockStmt) {
+        walk_stmts(self, stmt.statements())
+    }
+
+    fn visit_break_stmt(&mut self, _stmt: &BreakStmt) {}
+
+    fn visit_class_stmt(&mut self, stmt: &ClassStmt) {
+        walk_class_stmt(self, stmt)
+    }
+
+    fn visit_continue_stmt(&mut self, _stmt: &ContinueStmt) {}
+
+    fn visit_expression_stmt(&mut self, stmt: &ExpressionStmt) {
+        self.visit_expr(stmt.expression())
+    }
+
+    // Also called for the methods of classes
+    fn visit_function_stmt(&mut self, stmt: &FunctionStmt) {
+        self.visit_function_expr(stmt.function())
+    }
+
+    fn visit_if_stmt(&mut self, stmt: &IfStmt) {
+        walk_if_stmt(self, stmt)
+    }
+
+    fn visit_import_stmt(&mut self, _stmt: &ImportStmt) {}
+
+    fn visit_print_stmt(&mut self, stmt: &PrintStmt) {
+        self.visit_expr(stmt.expression())
+    }
+
+    fn visit_return_stmt(&mut self, stmt: &ReturnStmt) {
+        if let Some(value) = stmt.value() {
+            self.visit_expr(value)
+        }
+    }
+
+    fn visit_throw_stmt(&mut self, stmt: &ThrowStmt) {
+        self.visit_expr(stmt.value())
+    }
+
+    fn visit_try_stmt(&mut self, stmt: &TryStmt) {
+        walk_try_stmt(self, stmt)
+    }
+
+    fn visit_var_stmt(&mut self, stmt: &VarStmt) {
+        if let Some(initializer) = stmt.initializer() {
+            self.visit_expr(initializer)
+        }
+    }
+
+    fn visit_while_stmt(&mut self, stmt: &WhileStmt) {
+        walk_while_stmt(self, stmt)
+    }
+}
+
+/// Dispatches to the `visit_*_expr` method for the variant of `expr`.
+pub fn walk_expr<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &Expr) {
+    match expr {
+        Expr::Assign(e) => visitor.visit_assign_expr(e),
+        Expr::Binary(e) => visitor.visit_binary_expr(e),
+        Expr::Call(e) => visitor.visit_call_expr(e),
+        Expr::Function(e) => visitor.visit_function_expr(e),
+        Expr::Get(e) => visitor.visit_get_expr(e),
+        Expr::Grouping(e) => visitor.visit_grouping_expr(e),
+        Expr::Index(e) => visitor.visit_index_expr(e),
+        Expr::IndexSet(e) => visitor.visit_index_set_expr(e),
+        Expr::List(e) => visitor.visit_list_expr(e),
+        Expr::Literal(e) => visitor.visit_literal_expr(e),
+        Expr::Logical(e) => visitor.visit_logical_expr(e),
+        Expr::Map(e) => visitor.visit_map_expr(e),
+        Expr::Set(e) => visitor.visit_set_expr(e),
+        Expr::Super(e) => visitor.visit_super_expr(e),
+        Expr::This(e) => visitor.visit_this_expr(e),
+        Expr::Unary(e) => visitor.visit_unary_expr(e),
+        Expr::Variable(e) => visitor.visit_variable_expr(e),
+    }
+}
+
+/// Dispatches to the `visit_*_stmt` method for the variant of `stmt`.
+pub fn walk_stmt<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &Stmt) {
+    match stmt {
+        Stmt::Block(s) => visitor.visit_block_stmt(s),
+        Stmt::Break(s) => visitor.visit_break_stmt(s),
+        Stmt::Class(s) => visitor.visit_class_stmt(s),
+        Stmt::Continue(s) => visitor.visit_continue_stmt(s),
+        Stmt::Expression(s) => visitor.visit_expression_stmt(s),
+        Stmt::Function(s) => visitor.visit_function_stmt(s),
+        Stmt::If(s) => visitor.visit_if_stmt(s),
+        Stmt::Import(s) => visitor.visit_import_stmt(s),
+        Stmt::Print(s) => visitor.visit_print_stmt(s),
+        Stmt::Return(s) => visitor.visit_return_stmt(s),
+        Stmt::Throw(s) => visitor.visit_throw_stmt(s),
+        Stmt::Try(s) => visitor.visit_try_stmt(s),
+        Stmt::Var(s) => visitor.visit_var_stmt(s),
+        Stmt::While(s) => visitor.visit_while_stmt(s),
+    }
+}
+
+pub fn walk_exprs<V: VisitorMut + ?Sized>(visitor: &mut V, exprs: &[Expr]) {
+    for expr in exprs {
+        visitor.visit_expr(expr);
+    }
+}
+
+pub fn walk_stmts<V: VisitorMut + ?Sized>(visitor: &mut V, stmts: &[Stmt]) {
+    for stmt in stmts {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+pub fn walk_assign_expr<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &AssignExpr) {
+    visitor.visit_expr(expr.value());
+}
+
+pub fn walk_binary_expr<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &BinaryExpr) {
+    visitor.visit_expr(expr.lhs());
+    visitor.visit_expr(expr.rhs());
+}
+
+pub fn walk_call_expr<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &CallExpr) {
+    visitor.visit_expr(expr.callee());
+    walk_exprs(visitor, expr.arguments());
+}
+
+pub fn walk_function_expr<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &FunctionExpr) {
+    walk_stmts(visitor, expr.body());
+}
+
+pub fn walk_get_expr<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &GetExpr) {
+    visitor.visit_expr(expr.object());
+}
+
+pub fn walk_grouping_expr<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &GroupingExpr) {
+    visitor.visit_expr(expr.expression());
+}
+
+pub fn walk_index_expr<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &IndexExpr) {
+    visitor.visit_expr(expr.object());
+    visitor.visit_expr(expr.index());
+}
+
+pub fn walk_index_set_expr<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &IndexSetExpr) {
+    visitor.visit_expr(expr.object());
+    visitor.visit_expr(expr.index());
+    visitor.visit_expr(expr.value());
+}
+
+pub fn walk_list_expr<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &ListExpr) {
+    walk_exprs(visitor, expr.elements());
+}
+
+pub fn walk_logical_expr<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &LogicalExpr) {
+    visitor.visit_expr(expr.lhs());
+    visitor.visit_expr(expr.rhs());
+}
+
+pub fn walk_map_expr<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &MapExpr) {
+    for (key, value) in expr.entries() {
+        visitor.visit_expr(key);
+        visitor.visit_expr(value);
+    }
+}
+
+pub fn walk_set_expr<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &SetExpr) {
+    visitor.visit_expr(expr.object());
+    visitor.visit_expr(expr.value());
+}
+
+pub fn walk_unary_expr<V: VisitorMut + ?Sized>(visitor: &mut V, expr: &UnaryExpr) {
+    visitor.visit_expr(expr.rhs());
+}
+
+pub fn walk_class_stmt<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &ClassStmt) {
+    if let Some(superclass) = stmt.superclass() {
+        visitor.visit_expr(superclass);
+    }
+    for method in stmt.methods() {
+        visitor.visit_function_stmt(method);
+    }
+}
+
+pub fn walk_if_stmt<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &IfStmt) {
+    visitor.visit_expr(stmt.condition());
+    visitor.visit_stmt(stmt.then_branch());
+    if let Some(else_branch) = stmt.else_branch() {
+        visitor.visit_stmt(else_branch);
+    }
+}
+
+pub fn walk_try_stmt<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &TryStmt) {
+    walk_stmts(visitor, stmt.body());
+    if let Some(body) = stmt.catch_body() {
+        walk_stmts(visitor, body);
+    }
+    if let Some(body) = stmt.finally_body() {
+        walk_stmts(visitor, body);
+    }
+}
+
+// The increment of a desugared `for` loop runs after the body, so it is visited after it
+pub fn walk_while_stmt<V: VisitorMut + ?Sized>(visitor: &mut V, stmt: &WhileStmt) {
+    visitor.visit_expr(stmt.condition());
+    visitor.visit_stmt(stmt.body());
+    if let Some(increment) = stmt.increment() {
+        visitor.visit_expr(increment);
+    }
+}
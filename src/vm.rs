@@ -0,0 +1,1353 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use crate::bytecode;
+use crate::chunk::{Constant, OpCode, Prototype};
+use crate::class::{LoxClass, LoxInstance};
+use crate::compiler::Compiler;
+use crate::environment::Environment;
+use crate::error::RloxError;
+use crate::expr::FunctionExpr;
+use crate::function::{Callable, LoxFunction, NativeFunction};
+use crate::heap::{
+    BoundMethod, BuiltinMethod, Class, Closure, GcConfig, Heap, Instance, Module, ObjRef, Object,
+    Upvalue, VmValue,
+};
+use crate::interner::{intern, Symbol};
+use crate::interpreter::MAX_CALL_DEPTH;
+use crate::list;
+use crate::map::{LoxMap, MapKey};
+use crate::module::{self, LoxModule};
+use crate::natives;
+use crate::optimizer::Optimizer;
+use crate::resolver::Resolver;
+use crate::stmt::Stmt;
+use crate::token::{Token, TokenType};
+use crate::value::Value;
+
+enum FrameKind {
+    Script,                  // the top level of the main program
+    Module(PathBuf, ObjRef), // the top level of an imported file, filling in its module
+    Function(String),        // a called function, named the way stack traces show it
+}
+
+struct CallFrame {
+    closure: ObjRef,
+    prototype: Rc<Prototype>,
+    module: ObjRef,
+    ip: usize,
+    base: usize, // stack index of slot zero
+    kind: FrameKind,
+}
+
+// Where to resume when an error unwinds to a try statement
+struct Handler {
+    frame_count: usize,
+    ip: usize,
+    stack_height: usize,
+    catches: bool, // a catch clause, or else a finally block that rethrows
+}
+
+/**
+ * Stack-based virtual machine running the bytecode produced by the `Compiler`.
+ *   It behaves like the tree-walking `Interpreter`: same output, same runtime errors and
+ *   stack traces. Native functions are shared with it by converting values at the boundary.
+ */
+pub struct Vm {
+    heap: Heap,
+    stack: Vec<VmValue>,
+    frames: Vec<CallFrame>,
+    handlers: Vec<Handler>,
+    open_upvalues: Vec<ObjRef>,
+    thrown: Option<VmValue>, // the value of the `throw` being unwound, if it came from one
+    main: ObjRef,
+    main_path: Option<PathBuf>,
+    builtins: HashMap<Symbol, VmValue>, // what the globals of every imported module start out with
+    modules: HashMap<PathBuf, ObjRef>,
+    output: Box<dyn Write>,
+    optimize: bool, // whether programs are constant folded before they are compiled
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Vm::new()
+    }
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        Vm::with_output(std::io::stdout())
+    }
+
+    /// Creates a VM whose `print` statements write to `output` instead of stdout.
+    pub fn with_output(output: impl Write + 'static) -> Self {
+        Vm::with_gc_config(output, GcConfig::default())
+    }
+
+    /// Creates a VM writing to `output` whose garbage collector runs as `config` says.
+    pub fn with_gc_config(output: impl Write + 'static, config: GcConfig) -> Self {
+        let mut heap = Heap::with_config(config);
+        let main = heap.alloc(Object::Module(Module {
+            path: None,
+            globals: HashMap::new(),
+        }));
+        let mut vm = Vm {
+            heap,
+            stack: Vec::new(),
+            frames: Vec::new(),
+            handlers: Vec::new(),
+            open_upvalues: Vec::new(),
+            thrown: None,
+            main,
+            main_path: None,
+            builtins: HashMap::new(),
+            modules: HashMap::new(),
+            output: Box::new(output),
+            optimize: true,
+        };
+        for native in natives::library() {
+            let name = native.name().to_string();
+            let native = vm.heap.alloc(Object::Native(Rc::new(native)));
+            vm.globals_mut(main).insert(intern(&name), VmValue::Object(native));
+        }
+        let prelude = crate::parse_program(natives::PRELUDE).expect("the prelude is valid Lox");
+        vm.interpret(prelude).expect("the prelude runs");
+        vm.builtins = vm.globals(main).clone();
+        vm
+    }
+
+    /// Resolves, compiles and runs a parsed program.
+    pub fn interpret(&mut self, statements: Vec<Stmt>) -> Result<(), RloxError> {
+        let script = self.compile(statements)?;
+        self.run(script)
+    }
+
+    /// Turns constant folding of the programs run from now on off or back on, as `--no-opt` does.
+    pub fn set_optimize(&mut self, optimize: bool) {
+        self.optimize = optimize;
+    }
+
+    fn compile(&self, mut statements: Vec<Stmt>) -> Result<Rc<Prototype>, RloxError> {
+        if self.optimize {
+            statements = Optimizer::new().optimize(&statements);
+        }
+        Resolver::new().resolve(&statements)?;
+        Compiler::new().compile(&statements)
+    }
+
+    /// Runs the script at `path`, resolving its imports relative to its directory.
+    pub fn run_file(&mut self, path: impl AsRef<Path>) -> Result<(), RloxError> {
+        let source = fs::read_to_string(&path)?;
+        let statements = crate::parse_program(&source)?;
+        self.main_path = Some(path.as_ref().canonicalize()?);
+        let result = self.interpret(statements);
+        self.main_path = None;
+        result
+    }
+
+    /// Runs a script compiled ahead of time by `rlox --compile`, unless the source next to it
+    /// has changed since.
+    pub fn run_compiled_file(&mut self, path: impl AsRef<Path>) -> Result<(), RloxError> {
+        let compiled = bytecode::decode(&fs::read(&path)?)?;
+        if let Ok(source) = fs::read_to_string(path.as_ref().with_extension("lox")) {
+            compiled.check_source(&source)?;
+        }
+        self.main_path = Some(path.as_ref().canonicalize()?);
+        let result = self.run(compiled.script);
+        self.main_path = None;
+        result
+    }
+
+    /// Runs compiled code as the top level of the main program.
+    pub fn run(&mut self, script: Rc<Prototype>) -> Result<(), RloxError> {
+        let closure = self.alloc(Object::Closure(Closure {
+            prototype: Rc::clone(&script),
+            upvalues: Vec::new(),
+            module: self.main,
+        }));
+        self.stack.push(VmValue::Object(closure));
+        self.frames.push(CallFrame {
+            closure,
+            prototype: script,
+            module: self.main,
+            ip: 0,
+            base: self.stack.len() - 1,
+            kind: FrameKind::Script,
+        });
+        loop {
+            match self.step() {
+                Ok(true) => return Ok(()),
+                Ok(false) => (),
+                Err(e) => self.unwind(e)?,
+            }
+        }
+    }
+
+    /// The globals defined by the programs run so far, sorted by name, leaving out the natives
+    /// and prelude functions they didn't redefine.
+    pub fn user_globals(&self) -> Vec<(String, Value)> {
+        let mut globals = self
+            .globals(self.main)
+            .iter()
+            .filter(|(name, value)| self.builtins.get(*name) != Some(*value))
+            .map(|(name, value)| (name.to_string(), self.to_value(*value)))
+            .collect::<Vec<_>>();
+        globals.sort_by(|a, b| a.0.cmp(&b.0));
+        globals
+    }
+
+    pub fn heap(&self) -> &Heap {
+        &self.heap
+    }
+
+    fn alloc(&mut self, object: Object) -> ObjRef {
+        if self.heap.should_collect() {
+            // The new object isn't on the heap yet, so what it refers to has to be kept alive here
+            object.trace(|value| self.heap.mark(value));
+            self.collect_garbage();
+        }
+        self.heap.alloc(object)
+    }
+
+    /**
+     * Frees the objects the program can no longer reach, returning how many were freed.
+     *   The roots are the value stack, the running closures, open upvalues, the globals of every
+     *   module and the value of a `throw` being unwound.
+     */
+    pub fn collect_garbage(&mut self) -> usize {
+        let heap = &mut self.heap;
+        self.stack.iter().for_each(|value| heap.mark(*value));
+        for frame in &self.frames {
+            heap.mark(VmValue::Object(frame.closure));
+            heap.mark(VmValue::Object(frame.module));
+        }
+        self.open_upvalues.iter().for_each(|r| heap.mark(VmValue::Object(*r)));
+        heap.mark(VmValue::Object(self.main));
+        self.modules.values().for_each(|r| heap.mark(VmValue::Object(*r)));
+        self.builtins.values().for_each(|value| heap.mark(*value));
+        if let Some(thrown) = self.thrown {
+            heap.mark(thrown);
+        }
+        heap.trace_and_sweep()
+    }
+
+    fn frame(&self) -> &CallFrame {
+        self.frames.last().expect("the VM only runs with a frame")
+    }
+
+    fn frame_mut(&mut self) -> &mut CallFrame {
+        self.frames.last_mut().expect("the VM only runs with a frame")
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        let frame = self.frame_mut();
+        let byte = frame.prototype.chunk().code()[frame.ip];
+        frame.ip += 1;
+        byte
+    }
+
+    fn read_u16(&mut self) -> usize {
+        let high = self.read_byte() as usize;
+        let low = self.read_byte() as usize;
+        high << 8 | low
+    }
+
+    fn read_constant(&mut self) -> Constant {
+        let index = self.read_byte() as usize;
+        self.frame().prototype.chunk().constants()[index].clone()
+    }
+
+    fn read_name(&mut self) -> Symbol {
+        match self.read_constant() {
+            Constant::String(name) => name,
+            _ => unreachable!("the compiler only refers to names through string constants"),
+        }
+    }
+
+    // Line of the instruction being executed in the given frame
+    fn line_of(frame: &CallFrame) -> usize {
+        frame.prototype.chunk().lines()[frame.ip.saturating_sub(1)]
+    }
+
+    fn push(&mut self, value: VmValue) {
+        self.stack.push(value);
+    }
+
+    fn pop(&mut self) -> VmValue {
+        self.stack.pop().expect("the compiler keeps the stack balanced")
+    }
+
+    fn peek(&self, distance: usize) -> VmValue {
+        self.stack[self.stack.len() - 1 - distance]
+    }
+
+    fn alloc_string(&mut self, s: impl Into<Symbol>) -> VmValue {
+        VmValue::Object(self.alloc(Object::String(s.into())))
+    }
+
+    fn globals(&self, module: ObjRef) -> &HashMap<Symbol, VmValue> {
+        match self.heap.get(module) {
+            Object::Module(module) => &module.globals,
+            _ => unreachable!("frames always belong to a module"),
+        }
+    }
+
+    fn globals_mut(&mut self, module: ObjRef) -> &mut HashMap<Symbol, VmValue> {
+        match self.heap.get_mut(module) {
+            Object::Module(module) => &mut module.globals,
+            _ => unreachable!("frames always belong to a module"),
+        }
+    }
+
+    /// Executes one instruction, returning true once the main program has finished.
+    fn step(&mut self) -> Result<bool, RloxError> {
+        let byte = self.read_byte();
+        let op = OpCode::try_from(byte)
+            .map_err(|byte| RloxError::runtime(format!("Unknown opcode {}.", byte)))?;
+        match op {
+            OpCode::Constant => {
+                let value = match self.read_constant() {
+                    Constant::Number(n) => VmValue::Number(n),
+                    Constant::String(s) => self.alloc_string(s),
+                    Constant::Function(_) => unreachable!("functions are loaded by OP_CLOSURE"),
+                };
+                self.push(value);
+            }
+            OpCode::Nil => self.push(VmValue::Nil),
+            OpCode::True => self.push(VmValue::Bool(true)),
+            OpCode::False => self.push(VmValue::Bool(false)),
+            OpCode::Pop => {
+                self.pop();
+            }
+            OpCode::GetLocal => {
+                let slot = self.frame().base + self.read_byte() as usize;
+                self.push(self.stack[slot]);
+            }
+            OpCode::SetLocal => {
+                let slot = self.frame().base + self.read_byte() as usize;
+                self.stack[slot] = self.peek(0);
+            }
+            OpCode::GetGlobal => {
+                let name = self.read_name();
+                match self.globals(self.frame().module).get(&name) {
+                    Some(value) => self.push(*value),
+                    None => return Err(undefined_variable(&name)),
+                }
+            }
+            OpCode::DefineGlobal => {
+                let name = self.read_name();
+                let value = self.pop();
+                let module = self.frame().module;
+                self.globals_mut(module).insert(name, value);
+            }
+            OpCode::SetGlobal => {
+                let name = self.read_name();
+                let value = self.peek(0);
+                let module = self.frame().module;
+                match self.globals_mut(module).get_mut(&name) {
+                    Some(slot) => *slot = value,
+                    None => return Err(undefined_variable(&name)),
+                }
+            }
+            OpCode::GetUpvalue => {
+                let index = self.read_byte();
+                let upvalue = self.upvalue(index);
+                let value = match self.heap.get(upvalue) {
+                    Object::Upvalue(Upvalue::Open(slot)) => self.stack[*slot],
+                    Object::Upvalue(Upvalue::Closed(value)) => *value,
+                    _ => unreachable!("closures only capture upvalues"),
+                };
+                self.push(value);
+            }
+            OpCode::SetUpvalue => {
+                let index = self.read_byte();
+                let upvalue = self.upvalue(index);
+                let value = self.peek(0);
+                match self.heap.get_mut(upvalue) {
+                    Object::Upvalue(Upvalue::Open(slot)) => {
+                        let slot = *slot;
+                        self.stack[slot] = value;
+                    }
+                    Object::Upvalue(closed) => *closed = Upvalue::Closed(value),
+                    _ => unreachable!("closures only capture upvalues"),
+                }
+            }
+            OpCode::GetProperty => {
+                let name = self.read_name();
+                let object = self.pop();
+                let value = self.get_property(object, &name)?;
+                self.push(value);
+            }
+            OpCode::SetProperty => {
+                let name = self.read_name();
+                let value = self.pop();
+                let object = self.pop();
+                match object {
+                    VmValue::Object(r) => match self.heap.get_mut(r) {
+                        Object::Instance(instance) => {
+                            instance.fields.insert(name, value);
+                        }
+                        _ => return Err(RloxError::runtime("Only instances have fields.")),
+                    },
+                    _ => return Err(RloxError::runtime("Only instances have fields.")),
+                }
+                self.push(value);
+            }
+            OpCode::GetSuper => {
+                let name = self.read_name();
+                let superclass = self.pop();
+                let receiver = self.pop();
+                let method = match superclass {
+                    VmValue::Object(r) => match self.heap.get(r) {
+                        Object::Class(class) => class.methods.get(&name).copied(),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                match method {
+                    Some(method) => {
+                        let bound = Object::BoundMethod(BoundMethod { receiver, method });
+                        let bound = self.alloc(bound);
+                        self.push(VmValue::Object(bound));
+                    }
+                    None => return Err(undefined_property(&name)),
+                }
+            }
+            OpCode::GetIndex => {
+                let index = self.pop();
+                let object = self.pop();
+                let value = self.get_index(object, index)?;
+                self.push(value);
+            }
+            OpCode::SetIndex => {
+                let value = self.pop();
+                let index = self.pop();
+                let object = self.pop();
+                self.set_index(object, index, value)?;
+                self.push(value);
+            }
+            OpCode::Equal => {
+                let b = self.pop();
+                let a = self.pop();
+                self.push(VmValue::Bool(self.values_equal(a, b)));
+            }
+            OpCode::Greater => self.number_operands(|a, b| VmValue::Bool(a > b))?,
+            OpCode::GreaterEqual => self.number_operands(|a, b| VmValue::Bool(a >= b))?,
+            OpCode::Less => self.number_operands(|a, b| VmValue::Bool(a < b))?,
+            OpCode::LessEqual => self.number_operands(|a, b| VmValue::Bool(a <= b))?,
+            OpCode::Add => self.add()?,
+            OpCode::Subtract => self.number_operands(|a, b| VmValue::Number(a - b))?,
+            OpCode::Multiply => self.number_operands(|a, b| VmValue::Number(a * b))?,
+            OpCode::Divide => self.number_operands(|a, b| VmValue::Number(a / b))?,
+            OpCode::Not => {
+                let value = self.pop();
+                self.push(VmValue::Bool(!value.is_truthy()));
+            }
+            OpCode::Negate => match self.pop() {
+                VmValue::Number(n) => self.push(VmValue::Number(-n)),
+                _ => return Err(RloxError::runtime("Operand must be a number.")),
+            },
+            OpCode::Print => {
+                let value = self.pop();
+                let text = self.display(value);
+                writeln!(self.output, "{}", text)?;
+            }
+            OpCode::Jump => {
+                let offset = self.read_u16();
+                self.frame_mut().ip += offset;
+            }
+            OpCode::JumpIfFalse => {
+                let offset = self.read_u16();
+                if !self.peek(0).is_truthy() {
+                    self.frame_mut().ip += offset;
+                }
+            }
+            OpCode::Loop => {
+                let offset = self.read_u16();
+                self.frame_mut().ip -= offset;
+            }
+            OpCode::Call => {
+                let argument_count = self.read_byte() as usize;
+                self.call_value(self.peek(argument_count), argument_count)?;
+            }
+            OpCode::Closure => self.closure(),
+            OpCode::CloseUpvalue => {
+                self.close_upvalues(self.stack.len() - 1);
+                self.pop();
+            }
+            OpCode::Return => return Ok(self.return_from_frame()),
+            OpCode::Class => {
+                let name = self.read_name();
+                let class = self.alloc(Object::Class(Class {
+                    name: name.to_string(),
+                    methods: HashMap::new(),
+                }));
+                self.push(VmValue::Object(class));
+            }
+            OpCode::Inherit => {
+                let methods = match self.peek(1) {
+                    VmValue::Object(r) => match self.heap.get(r) {
+                        Object::Class(superclass) => Some(superclass.methods.clone()),
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                let methods =
+                    methods.ok_or_else(|| RloxError::runtime("Superclass must be a class."))?;
+                if let VmValue::Object(class) = self.pop() {
+                    if let Object::Class(class) = self.heap.get_mut(class) {
+                        class.methods.extend(methods);
+                    }
+                }
+            }
+            OpCode::Method => {
+                let name = self.read_name();
+                let method = self.pop();
+                if let (VmValue::Object(class), VmValue::Object(method)) = (self.peek(0), method) {
+                    if let Object::Class(class) = self.heap.get_mut(class) {
+                        class.methods.insert(name, method);
+                    }
+                }
+            }
+            OpCode::List => {
+                let count = self.read_u16();
+                let elements = self.stack.split_off(self.stack.len() - count);
+                let list = self.alloc(Object::List(elements));
+                self.push(VmValue::Object(list));
+            }
+            OpCode::Map => {
+                let count = self.read_u16();
+                let entries = self.stack.split_off(self.stack.len() - 2 * count);
+                let mut map = LoxMap::new();
+                for entry in entries.chunks(2) {
+                    map.insert(self.map_key(entry[0])?, entry[1]);
+                }
+                let map = self.alloc(Object::Map(map));
+                self.push(VmValue::Object(map));
+            }
+            OpCode::Import => {
+                let path = self.read_name();
+                self.import(&path)?;
+            }
+            OpCode::Throw => {
+                let value = self.pop();
+                return Err(self.throw(value));
+            }
+            OpCode::PushCatch | OpCode::PushFinally => {
+                let offset = self.read_u16();
+                let handler = Handler {
+                    frame_count: self.frames.len(),
+                    ip: self.frame().ip + offset,
+                    stack_height: self.stack.len(),
+                    catches: op == OpCode::PushCatch,
+                };
+                self.handlers.push(handler);
+            }
+            OpCode::PopHandler => {
+                self.handlers.pop();
+            }
+            OpCode::Rethrow => {
+                if let VmValue::Object(pending) = self.pop() {
+                    if let Object::Exception(pending) = self.heap.get_mut(pending) {
+                        if let Some((error, thrown)) = pending.take() {
+                            self.thrown = thrown;
+                            return Err(error);
+                        }
+                    }
+                }
+                unreachable!("finally blocks only rethrow the pending error once");
+            }
+        }
+        Ok(false)
+    }
+
+    fn upvalue(&self, index: u8) -> ObjRef {
+        match self.heap.get(self.frame().closure) {
+            Object::Closure(closure) => closure.upvalues[index as usize],
+            _ => unreachable!("frames always run a closure"),
+        }
+    }
+
+    fn closure(&mut self) {
+        let prototype = match self.read_constant() {
+            Constant::Function(prototype) => prototype,
+            _ => unreachable!("OP_CLOSURE always refers to a function constant"),
+        };
+        let mut upvalues = Vec::with_capacity(prototype.upvalue_count());
+        for _ in 0..prototype.upvalue_count() {
+            let is_local = self.read_byte() == 1;
+            let index = self.read_byte();
+            let upvalue = match is_local {
+                true => self.capture_upvalue(self.frame().base + index as usize),
+                false => self.upvalue(index),
+            };
+            upvalues.push(upvalue);
+        }
+        let closure = self.alloc(Object::Closure(Closure {
+            prototype,
+            upvalues,
+            module: self.frame().module,
+        }));
+        self.push(VmValue::Object(closure));
+    }
+
+    // Several closures capturing the same variable share one upvalue
+    fn capture_upvalue(&mut self, slot: usize) -> ObjRef {
+        let existing = self.open_upvalues.iter().copied().find(|upvalue| {
+            matches!(self.heap.get(*upvalue), Object::Upvalue(Upvalue::Open(s)) if *s == slot)
+        });
+        existing.unwrap_or_else(|| {
+            let upvalue = self.alloc(Object::Upvalue(Upvalue::Open(slot)));
+            self.open_upvalues.push(upvalue);
+            upvalue
+        })
+    }
+
+    // Moves the variables in stack slots from `first` on into the upvalues capturing them
+    fn close_upvalues(&mut self, first: usize) {
+        let stack = &self.stack;
+        let heap = &mut self.heap;
+        self.open_upvalues.retain(|upvalue| match heap.get_mut(*upvalue) {
+            Object::Upvalue(open) => match *open {
+                Upvalue::Open(slot) if slot >= first => {
+                    *open = Upvalue::Closed(stack[slot]);
+                    false
+                }
+                _ => true,
+            },
+            _ => false,
+        });
+    }
+
+    fn return_from_frame(&mut self) -> bool {
+        let result = self.pop();
+        let frame = self.frames.pop().expect("the VM only runs with a frame");
+        self.close_upvalues(frame.base);
+        let frame_count = self.frames.len();
+        self.handlers.retain(|handler| handler.frame_count <= frame_count);
+        self.stack.truncate(frame.base);
+        match frame.kind {
+            FrameKind::Script => return true,
+            FrameKind::Module(path, module) => {
+                self.modules.insert(path, module);
+                self.push(VmValue::Object(module));
+            }
+            FrameKind::Function(_) => self.push(result),
+        }
+        false
+    }
+
+    fn call_value(&mut self, callee: VmValue, argument_count: usize) -> Result<(), RloxError> {
+        let callee = match callee {
+            VmValue::Object(callee) => callee,
+            _ => return Err(RloxError::runtime("Can only call functions and classes.")),
+        };
+        match self.heap.get(callee) {
+            Object::Closure(closure) => {
+                let name = format!("{}()", closure.prototype.name().unwrap_or("<fn>"));
+                self.call_closure(callee, argument_count, name)
+            }
+            Object::BoundMethod(bound) => {
+                let (receiver, method) = (bound.receiver, bound.method);
+                let slot = self.stack.len() - argument_count - 1;
+                self.stack[slot] = receiver;
+                let name = match self.heap.get(method) {
+                    Object::Closure(closure) => closure.prototype.name().unwrap_or("<fn>"),
+                    _ => "<fn>",
+                };
+                let name = format!("{}()", name);
+                self.call_closure(method, argument_count, name)
+            }
+            Object::Class(class) => {
+                let initializer = class.methods.get(&intern("init")).copied();
+                let name = format!("{}()", class.name);
+                let instance = self.alloc(Object::Instance(Instance {
+                    class: callee,
+                    fields: HashMap::new(),
+                }));
+                let slot = self.stack.len() - argument_count - 1;
+                self.stack[slot] = VmValue::Object(instance);
+                match initializer {
+                    Some(initializer) => self.call_closure(initializer, argument_count, name),
+                    None => check_arity(0, argument_count),
+                }
+            }
+            Object::Native(native) => {
+                let native = Rc::clone(native);
+                check_arity(native.arity(), argument_count)?;
+                let start = self.stack.len() - argument_count;
+                let arguments = self.stack[start..].to_vec();
+                let result = match self.collection_native(native.name(), &arguments) {
+                    Some(result) => result,
+                    None => {
+                        let arguments = arguments
+                            .iter()
+                            .map(|argument| self.to_value(*argument))
+                            .collect::<Vec<_>>();
+                        let result = native.invoke(&arguments)?;
+                        self.import_value(result)
+                    }
+                };
+                self.stack.truncate(start - 1);
+                self.push(result);
+                Ok(())
+            }
+            Object::BuiltinMethod(method) => {
+                let (receiver, name, arity) = (method.receiver, method.name, method.arity);
+                check_arity(arity, argument_count)?;
+                let start = self.stack.len() - argument_count;
+                let arguments = self.stack[start..].to_vec();
+                let result = self.call_builtin(receiver, name, &arguments)?;
+                self.stack.truncate(start - 1);
+                self.push(result);
+                Ok(())
+            }
+            _ => Err(RloxError::runtime("Can only call functions and classes.")),
+        }
+    }
+
+    fn call_closure(
+        &mut self,
+        closure: ObjRef,
+        argument_count: usize,
+        name: String,
+    ) -> Result<(), RloxError> {
+        let (prototype, module) = match self.heap.get(closure) {
+            Object::Closure(c) => (Rc::clone(&c.prototype), c.module),
+            _ => unreachable!("only closures are called through frames"),
+        };
+        check_arity(prototype.arity(), argument_count)?;
+        // One deeper than the tree-walker allows, whose stack trace lists the call that overflowed
+        if self.call_depth() > MAX_CALL_DEPTH {
+            return Err(RloxError::runtime("Stack overflow."));
+        }
+        self.frames.push(CallFrame {
+            closure,
+            prototype,
+            module,
+            ip: 0,
+            base: self.stack.len() - argument_count - 1,
+            kind: FrameKind::Function(name),
+        });
+        Ok(())
+    }
+
+    fn call_depth(&self) -> usize {
+        match self.frames.len() <= MAX_CALL_DEPTH {
+            true => 0, // the common case, no need to count
+            false => self
+                .frames
+                .iter()
+                .filter(|frame| matches!(frame.kind, FrameKind::Function(_)))
+                .count(),
+        }
+    }
+
+    fn get_property(&mut self, object: VmValue, name: &Symbol) -> Result<VmValue, RloxError> {
+        let r = match object {
+            VmValue::Object(r) => r,
+            _ => return Err(RloxError::runtime("Only instances have properties.")),
+        };
+        let builtin = |receiver, name: &str, methods: &[(&'static str, usize)]| {
+            match methods.iter().find(|(method, _)| *method == name) {
+                Some(&(name, arity)) => Ok(Object::BuiltinMethod(BuiltinMethod {
+                    receiver,
+                    name,
+                    arity,
+                })),
+                None => Err(undefined_property(name)),
+            }
+        };
+        let object = match self.heap.get(r) {
+            Object::Instance(instance) => {
+                if let Some(value) = instance.fields.get(name) {
+                    return Ok(*value);
+                }
+                let method = match self.heap.get(instance.class) {
+                    Object::Class(class) => class.methods.get(name).copied(),
+                    _ => None,
+                };
+                match method {
+                    Some(method) => Object::BoundMethod(BoundMethod {
+                        receiver: object,
+                        method,
+                    }),
+                    None => return Err(undefined_property(name)),
+                }
+            }
+            Object::List(_) => builtin(r, name, LIST_METHODS)?,
+            Object::Map(_) => builtin(r, name, MAP_METHODS)?,
+            Object::Module(module) => {
+                return module.globals.get(name).copied().ok_or_else(|| {
+                    let path = module.path.as_deref().unwrap_or_else(|| Path::new(""));
+                    RloxError::runtime(format!(
+                        "Module '{}' has no member '{}'.",
+                        path.display(),
+                        name
+                    ))
+                })
+            }
+            _ => return Err(RloxError::runtime("Only instances have properties.")),
+        };
+        Ok(VmValue::Object(self.alloc(object)))
+    }
+
+    fn get_index(&mut self, object: VmValue, index: VmValue) -> Result<VmValue, RloxError> {
+        if let VmValue::Object(r) = object {
+            match self.heap.get(r) {
+                Object::List(list) => {
+                    let i = list::index(&self.to_value(index), list.len())?;
+                    return Ok(list[i]);
+                }
+                Object::Map(map) => {
+                    let key = self.map_key(index)?;
+                    return match map.get(&key) {
+                        Some(value) => Ok(*value),
+                        None => Err(RloxError::runtime(format!(
+                            "Undefined key {:?}.",
+                            self.to_value(index)
+                        ))),
+                    };
+                }
+                _ => (),
+            }
+        }
+        Err(RloxError::runtime("Only lists and maps can be indexed."))
+    }
+
+    fn set_index(
+        &mut self,
+        object: VmValue,
+        index: VmValue,
+        value: VmValue,
+    ) -> Result<(), RloxError> {
+        if let VmValue::Object(r) = object {
+            match self.heap.get(r) {
+                Object::List(list) => {
+                    let i = list::index(&self.to_value(index), list.len())?;
+                    if let Object::List(list) = self.heap.get_mut(r) {
+                        list[i] = value;
+                    }
+                    return Ok(());
+                }
+                Object::Map(_) => {
+                    let key = self.map_key(index)?;
+                    if let Object::Map(map) = self.heap.get_mut(r) {
+                        map.insert(key, value);
+                    }
+                    return Ok(());
+                }
+                _ => (),
+            }
+        }
+        Err(RloxError::runtime("Only lists and maps can be indexed."))
+    }
+
+    fn call_builtin(
+        &mut self,
+        receiver: ObjRef,
+        name: &str,
+        arguments: &[VmValue],
+    ) -> Result<VmValue, RloxError> {
+        let keys = match self.heap.get(receiver) {
+            Object::Map(map) if name == "keys" => {
+                Some(map.iter().map(|(key, _)| key.to_value()).collect::<Vec<_>>())
+            }
+            _ => None,
+        };
+        if let Some(keys) = keys {
+            return Ok(self.import_value(Value::from(keys)));
+        }
+        let key = match (self.heap.get(receiver), arguments.first()) {
+            (Object::Map(_), Some(key)) => Some(self.map_key(*key)?),
+            _ => None,
+        };
+        let index = arguments.first().map(|i| self.to_value(*i));
+        let result = match self.heap.get_mut(receiver) {
+            Object::List(list) => match name {
+                "push" => {
+                    list.push(arguments[0]);
+                    VmValue::Nil
+                }
+                "pop" => list
+                    .pop()
+                    .ok_or_else(|| RloxError::runtime("Can't pop from an empty list."))?,
+                "len" => VmValue::Number(list.len() as f64),
+                "insert" => {
+                    let len = list.len();
+                    let i = match index.unwrap() {
+                        Value::Number(n) if n == len as f64 => len,
+                        i => list::index(&i, len)?,
+                    };
+                    list.insert(i, arguments[1]);
+                    VmValue::Nil
+                }
+                _ => {
+                    let i = list::index(&index.unwrap(), list.len())?;
+                    list.remove(i)
+                }
+            },
+            Object::Map(map) => match name {
+                "values" => {
+                    let values = map.iter().map(|(_, value)| *value).collect();
+                    return Ok(VmValue::Object(self.alloc(Object::List(values))));
+                }
+                "has" => VmValue::Bool(map.contains_key(&key.unwrap())),
+                "remove" => map.remove(&key.unwrap()).unwrap_or(VmValue::Nil),
+                _ => VmValue::Number(map.len() as f64),
+            },
+            _ => unreachable!("builtin methods belong to lists and maps"),
+        };
+        Ok(result)
+    }
+
+    fn map_key(&self, key: VmValue) -> Result<MapKey, RloxError> {
+        MapKey::from_value(&self.to_value(key))
+    }
+
+    fn values_equal(&self, a: VmValue, b: VmValue) -> bool {
+        match (a, b) {
+            (VmValue::Object(a), VmValue::Object(b)) if a != b => {
+                match (self.heap.get(a), self.heap.get(b)) {
+                    (Object::String(a), Object::String(b)) => a == b,
+                    _ => false,
+                }
+            }
+            _ => a == b,
+        }
+    }
+
+    fn number_operands(&mut self, op: impl Fn(f64, f64) -> VmValue) -> Result<(), RloxError> {
+        match (self.peek(1), self.peek(0)) {
+            (VmValue::Number(a), VmValue::Number(b)) => {
+                self.stack.truncate(self.stack.len() - 2);
+                self.push(op(a, b));
+                Ok(())
+            }
+            _ => Err(RloxError::runtime("Operands must be numbers.")),
+        }
+    }
+
+    fn add(&mut self) -> Result<(), RloxError> {
+        let (a, b) = (self.peek(1), self.peek(0));
+        let result = match (a, b) {
+            (VmValue::Number(a), VmValue::Number(b)) => VmValue::Number(a + b),
+            (VmValue::Object(a), VmValue::Object(b)) => match (self.heap.get(a), self.heap.get(b)) {
+                (Object::String(a), Object::String(b)) => {
+                    let concatenated = format!("{}{}", a, b);
+                    self.alloc_string(concatenated)
+                }
+                _ => return Err(operands_error()),
+            },
+            _ => return Err(operands_error()),
+        };
+        self.stack.truncate(self.stack.len() - 2);
+        self.push(result);
+        Ok(())
+    }
+
+    fn import(&mut self, path: &str) -> Result<(), RloxError> {
+        let importer = self
+            .frames
+            .iter()
+            .rev()
+            .find_map(|frame| match &frame.kind {
+                FrameKind::Module(path, _) => Some(path.clone()),
+                _ => None,
+            })
+            .or_else(|| self.main_path.clone());
+        let path = module::find(importer.as_deref(), path)
+            .ok_or_else(|| RloxError::runtime(format!("Could not find module '{}'.", path)))?;
+        if let Some(module) = self.modules.get(&path) {
+            self.push(VmValue::Object(*module));
+            return Ok(());
+        }
+
+        let loading = self
+            .main_path
+            .iter()
+            .chain(self.frames.iter().filter_map(|frame| match &frame.kind {
+                FrameKind::Module(path, _) => Some(path),
+                _ => None,
+            }))
+            .collect::<Vec<_>>();
+        if let Some(start) = loading.iter().position(|p| **p == path) {
+            let chain = loading[start..]
+                .iter()
+                .map(|p| p.display().to_string())
+                .chain(std::iter::once(path.display().to_string()))
+                .collect::<Vec<_>>();
+            return Err(RloxError::runtime(format!("Import cycle: {}.", chain.join(" -> "))));
+        }
+        let source = fs::read_to_string(&path).map_err(|e| {
+            RloxError::runtime(format!("Could not read module '{}': {}", path.display(), e))
+        })?;
+        let prototype = self.compile(crate::parse_program(&source)?)?;
+
+        let module = self.alloc(Object::Module(Module {
+            path: Some(path.clone()),
+            globals: self.builtins.clone(),
+        }));
+        let closure = self.alloc(Object::Closure(Closure {
+            prototype: Rc::clone(&prototype),
+            upvalues: Vec::new(),
+            module,
+        }));
+        self.push(VmValue::Object(closure));
+        self.frames.push(CallFrame {
+            closure,
+            prototype,
+            module,
+            ip: 0,
+            base: self.stack.len() - 1,
+            kind: FrameKind::Module(path, module),
+        });
+        Ok(())
+    }
+
+    // Builds the error for a `throw`, with the same description the tree-walker gives it
+    fn throw(&mut self, value: VmValue) -> RloxError {
+        let line = Vm::line_of(self.frame());
+        let mut message = None;
+        if let VmValue::Object(r) = value {
+            let class = match self.heap.get_mut(r) {
+                Object::Instance(instance) => {
+                    // Error objects learn where they were thrown from, unless being rethrown
+                    let line_field = intern("line");
+                    if let Some(VmValue::Nil) = instance.fields.get(&line_field) {
+                        instance.fields.insert(line_field, VmValue::Number(line as f64));
+                    }
+                    instance.fields.get(&intern("message")).map(|m| (instance.class, *m))
+                }
+                _ => None,
+            };
+            if let Some((class, text)) = class {
+                let class_name = match self.heap.get(class) {
+                    Object::Class(class) => class.name.clone(),
+                    _ => String::new(),
+                };
+                message = Some(format!("{}: {}", class_name, self.to_value(text)));
+            }
+        }
+        let description = match message {
+            Some(message) => format!("Uncaught {}", message),
+            None => format!("Uncaught exception: {}", self.to_value(value)),
+        };
+        self.thrown = Some(value);
+        RloxError::thrown(line, description, self.to_value(value))
+    }
+
+    /**
+     * Sends an error to the innermost try statement that handles it, or out of the VM.
+     *   Catch clauses only handle runtime errors, finally blocks run for every error.
+     */
+    fn unwind(&mut self, error: RloxError) -> Result<(), RloxError> {
+        let thrown = self.thrown.take();
+        let line = Vm::line_of(self.frame());
+        let mut error = error.or_at_line(line);
+        while let Some(handler) = self.handlers.pop() {
+            if handler.catches && !matches!(error, RloxError::RuntimeError(_)) {
+                continue;
+            }
+            self.record_stack_trace(&mut error, handler.frame_count);
+            self.frames.truncate(handler.frame_count);
+            self.close_upvalues(handler.stack_height);
+            self.stack.truncate(handler.stack_height);
+            let value = match handler.catches {
+                true => self.error_object(error, thrown),
+                false => {
+                    let pending = Object::Exception(Some((error, thrown)));
+                    VmValue::Object(self.alloc(pending))
+                }
+            };
+            self.push(value);
+            self.frame_mut().ip = handler.ip;
+            return Ok(());
+        }
+        self.record_stack_trace(&mut error, 0);
+        self.stack.clear();
+        self.frames.clear();
+        self.open_upvalues.clear();
+        Err(error)
+    }
+
+    /**
+     * Records where an error was when it first leaves a function, like the tree-walker does.
+     *   Frames report the line they are executing, which is the call into the next frame for all
+     *   but the innermost one, up to the top level of the file the outermost call came from.
+     */
+    fn record_stack_trace(&self, error: &mut RloxError, frames_kept: usize) {
+        let e = match error {
+            RloxError::RuntimeError(e) if e.stack_trace.is_empty() => e,
+            _ => return,
+        };
+        let leaves_function = self.frames[frames_kept..]
+            .last()
+            .is_some_and(|frame| matches!(frame.kind, FrameKind::Function(_)));
+        if !leaves_function {
+            return;
+        }
+        // The innermost frame failed where the error says; the others are paused mid-call
+        for (depth, frame) in self.frames.iter().rev().enumerate() {
+            let line = match depth {
+                0 => e.line_number.unwrap_or_default(),
+                _ => Vm::line_of(frame),
+            };
+            match &frame.kind {
+                FrameKind::Function(name) => {
+                    e.stack_trace.push(format!("[line {}] in {}", line, name))
+                }
+                _ => {
+                    e.stack_trace.push(format!("[line {}] in script", line));
+                    return;
+                }
+            }
+        }
+    }
+
+    // What a catch clause binds: the thrown value, or an `Error` instance for a runtime error
+    fn error_object(&mut self, error: RloxError, thrown: Option<VmValue>) -> VmValue {
+        if let Some(value) = thrown {
+            return value;
+        }
+        let e = match error {
+            RloxError::RuntimeError(e) => e,
+            _ => unreachable!("catch clauses only handle runtime errors"),
+        };
+        let message = self.alloc_string(e.description.as_str());
+        match self.globals(self.main).get(&intern("Error")) {
+            Some(&VmValue::Object(class)) if matches!(self.heap.get(class), Object::Class(_)) => {
+                let mut fields = HashMap::new();
+                fields.insert(intern("message"), message);
+                let line = e.line_number.map_or(VmValue::Nil, |l| VmValue::Number(l as f64));
+                fields.insert(intern("line"), line);
+                VmValue::Object(self.alloc(Object::Instance(Instance { class, fields })))
+            }
+            _ => message,
+        }
+    }
+
+    /**
+     * Converts a VM value into the tree-walker's representation, to hand it to natives or print it.
+     *   Lists and maps are copied. Functions, classes and instances become stand-ins that
+     *   only keep what printing them and naming their type needs.
+     */
+    fn to_value(&self, value: VmValue) -> Value {
+        self.convert(value, &mut HashMap::new())
+    }
+
+    /**
+     * Copies a value out of the heap. `converted` holds the copies of the lists and maps met so
+     *   far, so shared ones stay shared and one containing itself becomes a copy containing
+     *   itself, rather than being copied forever.
+     */
+    fn convert(&self, value: VmValue, converted: &mut HashMap<ObjRef, Value>) -> Value {
+        let r = match value {
+            VmValue::Nil => return Value::Nil,
+            VmValue::Bool(b) => return Value::Bool(b),
+            VmValue::Number(n) => return Value::Number(n),
+            VmValue::Object(r) => r,
+        };
+        if let Some(copy) = converted.get(&r) {
+            return copy.clone();
+        }
+        match self.heap.get(r) {
+            Object::String(s) => Value::String(s.clone()),
+            Object::List(elements) => {
+                let copy = Rc::new(RefCell::new(Vec::with_capacity(elements.len())));
+                converted.insert(r, Value::List(Rc::clone(&copy)));
+                for element in elements {
+                    let element = self.convert(*element, converted);
+                    copy.borrow_mut().push(element);
+                }
+                Value::List(copy)
+            }
+            Object::Map(map) => {
+                let copy = Rc::new(RefCell::new(LoxMap::new()));
+                converted.insert(r, Value::Map(Rc::clone(&copy)));
+                for (key, value) in map.iter() {
+                    let value = self.convert(*value, converted);
+                    copy.borrow_mut().insert(key.clone(), value);
+                }
+                Value::Map(copy)
+            }
+            Object::Closure(closure) => stand_in_function(closure.prototype.name()),
+            Object::BoundMethod(bound) => match self.heap.get(bound.method) {
+                Object::Closure(closure) => stand_in_function(closure.prototype.name()),
+                _ => stand_in_function(None),
+            },
+            Object::Native(native) => Value::NativeFunction(Rc::clone(native)),
+            Object::BuiltinMethod(method) => {
+                let native = NativeFunction::new(method.name, method.arity, |_| Ok(Value::Nil));
+                Value::NativeFunction(Rc::new(native))
+            }
+            Object::Class(class) => Value::Class(Rc::new(stand_in_class(&class.name))),
+            Object::Instance(instance) => {
+                let class = match self.heap.get(instance.class) {
+                    Object::Class(class) => stand_in_class(&class.name),
+                    _ => stand_in_class(""),
+                };
+                Value::Instance(Rc::new(RefCell::new(LoxInstance::new(Rc::new(class)))))
+            }
+            Object::Module(module) => {
+                let path = module.path.clone().unwrap_or_default();
+                Value::Module(Rc::new(LoxModule::new(path, Environment::new())))
+            }
+            Object::Upvalue(_) | Object::Exception(_) => Value::Nil,
+        }
+    }
+
+    /**
+     * Answers the natives that look into lists and maps, `len` and `str`, from the heap, so
+     *   calling them doesn't copy the collection. Other natives only accept strings and numbers.
+     */
+    fn collection_native(&mut self, name: &str, arguments: &[VmValue]) -> Option<VmValue> {
+        let r = match arguments {
+            [VmValue::Object(r)] => *r,
+            _ => return None,
+        };
+        let len = match self.heap.get(r) {
+            Object::List(elements) => elements.len(),
+            Object::Map(map) => map.len(),
+            _ => return None,
+        };
+        match name {
+            "len" => Some(VmValue::Number(len as f64)),
+            "str" => {
+                let text = self.display(VmValue::Object(r));
+                Some(self.alloc_string(text))
+            }
+            _ => None,
+        }
+    }
+
+    // The text `print` shows for a value, written like `Value` displays it
+    fn display(&self, value: VmValue) -> String {
+        let mut text = String::new();
+        self.write_value(&mut text, value, false, &mut Vec::new());
+        text
+    }
+
+    // Walks lists and maps in the heap, printing a collection met again inside itself as `[...]`
+    fn write_value(&self, text: &mut String, value: VmValue, quoted: bool, path: &mut Vec<ObjRef>) {
+        let r = match value {
+            VmValue::Object(r) => r,
+            _ => return text.push_str(&self.to_value(value).to_string()),
+        };
+        let (open, close) = match self.heap.get(r) {
+            Object::List(_) => ("[", "]"),
+            Object::Map(_) => ("{", "}"),
+            _ if quoted => return text.push_str(&format!("{:?}", self.to_value(value))),
+            _ => return text.push_str(&self.to_value(value).to_string()),
+        };
+        if path.contains(&r) {
+            return text.push_str(&format!("{}...{}", open, close));
+        }
+        path.push(r);
+        text.push_str(open);
+        match self.heap.get(r) {
+            Object::List(elements) => {
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        text.push_str(", ");
+                    }
+                    self.write_value(text, *element, true, path);
+                }
+            }
+            Object::Map(map) => {
+                for (i, (key, value)) in map.iter().enumerate() {
+                    if i > 0 {
+                        text.push_str(", ");
+                    }
+                    text.push_str(&format!("{:?}: ", key.to_value()));
+                    self.write_value(text, *value, true, path);
+                }
+            }
+            _ => unreachable!("only lists and maps are opened"),
+        }
+        path.pop();
+        text.push_str(close);
+    }
+
+    // Converts the result of a native function back into a VM value
+    fn import_value(&mut self, value: Value) -> VmValue {
+        match value {
+            Value::Nil => VmValue::Nil,
+            Value::Bool(b) => VmValue::Bool(b),
+            Value::Number(n) => VmValue::Number(n),
+            Value::String(s) => self.alloc_string(s),
+            // Elements wait on the stack, where the garbage collector can see them
+            Value::List(list) => {
+                let start = self.stack.len();
+                for element in list.borrow().iter() {
+                    let element = self.import_value(element.clone());
+                    self.push(element);
+                }
+                let elements = self.stack.split_off(start);
+                VmValue::Object(self.alloc(Object::List(elements)))
+            }
+            Value::Map(map) => {
+                let start = self.stack.len();
+                for (_, value) in map.borrow().iter() {
+                    let value = self.import_value(value.clone());
+                    self.push(value);
+                }
+                let values = self.stack.split_off(start);
+                let keys = map.borrow().iter().map(|(key, _)| key.clone()).collect::<Vec<_>>();
+                let mut copy = LoxMap::new();
+                for (key, value) in keys.into_iter().zip(values) {
+                    copy.insert(key, value);
+                }
+                VmValue::Object(self.alloc(Object::Map(copy)))
+            }
+            Value::NativeFunction(native) => {
+                VmValue::Object(self.alloc(Object::Native(native)))
+            }
+            // No native returns the tree-walker's own functions, classes or instances
+            _ => VmValue::Nil,
+        }
+    }
+}
+
+const LIST_METHODS: &[(&str, usize)] =
+    &[("push", 1), ("pop", 0), ("len", 0), ("insert", 2), ("remove", 1)];
+const MAP_METHODS: &[(&str, usize)] =
+    &[("keys", 0), ("values", 0), ("has", 1), ("remove", 1), ("len", 0)];
+
+fn stand_in_function(name: Option<&str>) -> Value {
+    let keyword = Token::new(TokenType::Fun, "fun".to_string(), None, 0).unwrap();
+    let declaration = FunctionExpr::new_declaration(keyword, Vec::new(), Vec::new());
+    let function = LoxFunction::new(name, declaration, Environment::new(), false);
+    Value::Function(Rc::new(function))
+}
+
+fn stand_in_class(name: &str) -> LoxClass {
+    LoxClass::new(name, None, HashMap::new())
+}
+
+fn check_arity(arity: usize, argument_count: usize) -> Result<(), RloxError> {
+    match arity == argument_count {
+        true => Ok(()),
+        false => Err(RloxError::runtime(format!(
+            "Expected {} arguments but got {}.",
+            arity, argument_count
+        ))),
+    }
+}
+
+fn undefined_variable(name: &str) -> RloxError {
+    RloxError::runtime(format!("Undefined variable '{}'.", name))
+}
+
+fn undefined_property(name: &str) -> RloxError {
+    RloxError::runtime(format!("Undefined property '{}'.", name))
+}
+
+fn operands_error() -> RloxError {
+    RloxError::runtime("Operands must be two numbers or two strings.")
+}
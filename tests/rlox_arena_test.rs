@@ -0,0 +1,140 @@
+use rlox::arena::{Ast, ExprId, ExprNode, SideTable, Span, StaticType, StmtNode};
+
+fn parse(source: &str) -> Ast {
+    Ast::parse(source).unwrap()
+}
+
+// The id of the first expression reading or assigning `name`, in order of id
+fn find(ast: &Ast, name: &str) -> ExprId {
+    ast.expr_ids()
+        .find(|id| match ast.expr(*id) {
+            ExprNode::Variable { name: n } | ExprNode::Assign { name: n, .. } => n.lexeme() == name,
+            _ => false,
+        })
+        .unwrap()
+}
+
+#[test]
+fn nodes_refer_to_their_children_by_id() {
+    let ast = parse("print a + -b;");
+    assert_eq!(ast.program().len(), 1);
+    let expression = match ast.stmt(ast.program()[0]) {
+        StmtNode::Print(id) => *id,
+        node => panic!("expected a print statement, found {:?}", node),
+    };
+    let (lhs, rhs) = match ast.expr(expression) {
+        ExprNode::Binary { operator, lhs, rhs } if operator.lexeme() == "+" => (*lhs, *rhs),
+        node => panic!("expected an addition, found {:?}", node),
+    };
+    assert_eq!(lhs, find(&ast, "a"));
+    assert!(matches!(ast.expr(rhs), ExprNode::Unary { .. }));
+    // Children are built first
+    assert!(lhs < expression && rhs < expression);
+    assert_eq!(ast.expr_ids().last(), Some(expression));
+}
+
+#[test]
+fn spans_cover_the_lines_of_children() {
+    let ast = parse("var x = 1 +\n  2 *\n  y;\nfun f() {\n  return z;\n}");
+    let is_sum = |id: &ExprId| match ast.expr(*id) {
+        ExprNode::Binary { operator, .. } => operator.lexeme() == "+",
+        _ => false,
+    };
+    let sum = ast.expr_ids().find(is_sum).unwrap();
+    let span = |start_line, end_line| Span {
+        start_line,
+        end_line,
+    };
+    assert_eq!(ast.spans().get(sum), Some(&span(1, 3)));
+    assert_eq!(ast.spans().get(find(&ast, "z")), Some(&span(5, 5)));
+    let function = ast
+        .expr_ids()
+        .find(|id| matches!(ast.expr(*id), ExprNode::Function { .. }))
+        .unwrap();
+    assert_eq!(ast.spans().get(function), Some(&span(4, 5)));
+}
+
+#[test]
+fn scopes_record_the_resolved_distance_of_locals() {
+    let source = "
+        var g = 1;
+        fun outer(p) {
+            var l = 2;
+            fun inner() { return p + l + g; }
+            { l = 3; }
+        }";
+    let ast = parse(source);
+    let distance = |name| ast.scopes().get(find(&ast, name)).copied();
+    assert_eq!(distance("p"), Some(1));
+    assert_eq!(distance("l"), Some(1));
+    assert_eq!(distance("g"), None);
+    let assignment = ast
+        .expr_ids()
+        .find(|id| matches!(ast.expr(*id), ExprNode::Assign { .. }))
+        .unwrap();
+    assert_eq!(ast.scopes().get(assignment), Some(&1));
+    assert!(Ast::parse("return 1;").is_err());
+}
+
+#[test]
+fn types_follow_literals_and_operators() {
+    let ast = parse("(1 + 2) * x; \"a\" + \"b\"; 1 + x; !x; [1] or [2]; nil and 1; fun () {};");
+    let types = ast
+        .program()
+        .iter()
+        .map(|stmt| match ast.stmt(*stmt) {
+            StmtNode::Expression(id) => ast.types().get(*id).copied(),
+            node => panic!("expected an expression, found {:?}", node),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        types,
+        vec![
+            Some(StaticType::Number),
+            Some(StaticType::String),
+            None,
+            Some(StaticType::Bool),
+            Some(StaticType::List),
+            None,
+            Some(StaticType::Function),
+        ]
+    );
+}
+
+#[test]
+fn side_tables_are_sparse_and_ordered_by_id() {
+    let ast = parse("1;\n2;\n3;");
+    let mut table = SideTable::new();
+    let ids = ast.expr_ids().collect::<Vec<_>>();
+    assert_eq!(table.insert(ids[2], "c"), None);
+    assert_eq!(table.insert(ids[0], "a"), None);
+    assert_eq!(table.insert(ids[0], "A"), Some("a"));
+    assert_eq!(table.get(ids[1]), None);
+    assert_eq!(
+        table.iter().collect::<Vec<_>>(),
+        vec![(ids[0], &"A"), (ids[2], &"c")]
+    );
+    // Literals take their span from their own line
+    let lines = ast.spans().iter().map(|(_, span)| span.start_line);
+    assert_eq!(lines.collect::<Vec<_>>(), vec![1, 2, 3]);
+}
+
+#[test]
+fn assignment_targets_and_map_statements_leave_no_stray_nodes() {
+    let ast = parse("a.b = c[0] = 1;\n{x: 2};");
+    // a, c, 0, 1, c[0] = 1, a.b = c[0] = 1, x, 2 and the map
+    assert_eq!(ast.expr_ids().count(), 9);
+    let expression = |i: usize| match ast.stmt(ast.program()[i]) {
+        StmtNode::Expression(id) => *id,
+        node => panic!("expected an expression, found {:?}", node),
+    };
+    match ast.expr(expression(0)) {
+        ExprNode::Set { object, value, .. } => {
+            assert_eq!(*object, find(&ast, "a"));
+            assert!(matches!(ast.expr(*value), ExprNode::IndexSet { .. }));
+        }
+        node => panic!("expected a property assignment, found {:?}", node),
+    }
+    assert!(matches!(ast.expr(expression(1)), ExprNode::Map { .. }));
+    assert!(Ast::parse("1 = 2;").is_err());
+}
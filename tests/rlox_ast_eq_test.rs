@@ -0,0 +1,67 @@
+use std::collections::HashSet;
+
+use rlox::stmt::Stmt;
+
+#[test]
+fn trees_parsed_from_the_same_source_are_equal() {
+    let source = "f(a.b, [1, \"two\"], {nil: true})[0] = fun (x) { return -x; }";
+    assert_eq!(
+        rlox::parse_expression(source).unwrap(),
+        rlox::parse_expression(source).unwrap()
+    );
+    assert_ne!(
+        rlox::parse_expression("a - b").unwrap(),
+        rlox::parse_expression("b - a").unwrap()
+    );
+    assert_ne!(
+        rlox::parse_expression("a - b").unwrap(),
+        rlox::parse_expression("a + b").unwrap()
+    );
+}
+
+#[test]
+fn locations_can_be_ignored() {
+    let one_line = rlox::parse_program("if (a) { print a +\n1; } else print nil;").unwrap();
+    let spread =
+        rlox::parse_program("if (a)\n{\n  print a\n  + 1;\n}\nelse\n  print nil;").unwrap();
+    assert_ne!(one_line, spread);
+    assert!(one_line[0].eq_ignoring_locations(&spread[0]));
+    let other = rlox::parse_program("if (a) { print a + 2; } else print nil;").unwrap();
+    assert!(!one_line[0].eq_ignoring_locations(&other[0]));
+}
+
+#[test]
+fn equal_trees_hash_alike() {
+    let exprs = ["x * 2", "x * 2", "x*2", "2 * x", "0 + -0", "0 + 0"]
+        .iter()
+        .map(|source| rlox::parse_expression(source).unwrap())
+        .collect::<HashSet<_>>();
+    assert_eq!(exprs.len(), 4);
+}
+
+#[test]
+fn debug_dumps_the_tree() {
+    let program = rlox::parse_program("var x = -y;").unwrap();
+    assert_eq!(
+        format!("{:#?}", program[0]),
+        "\
+Var(
+    VarStmt {
+        name: Identifier \"x\" @1,
+        initializer: Some(
+            Unary(
+                UnaryExpr {
+                    operator: Minus \"-\" @1,
+                    rhs: Variable(
+                        VariableExpr {
+                            name: Identifier \"y\" @1,
+                        },
+                    ),
+                },
+            ),
+        ),
+    },
+)"
+    );
+    assert!(matches!(program[0], Stmt::Var(_)));
+}
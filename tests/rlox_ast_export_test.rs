@@ -0,0 +1,116 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use predicates::str::contains;
+
+use rlox::ast_printer_dot::{self, AstPrinterDot};
+use rlox::ast_printer_json::AstPrinterJson;
+
+#[test]
+fn dot_labels_nodes_with_operators_and_lines() {
+    assert_eq!(
+        ast_printer_dot::print_source("print -x\n + 1;").unwrap(),
+        "\
+digraph ast {
+  node [shape=box];
+  n0 [label=\"Program\"];
+  n1 [label=\"Print\\nline 1\"];
+  n2 [label=\"Binary +\\nline 2\"];
+  n3 [label=\"Unary -\\nline 1\"];
+  n4 [label=\"Variable x\\nline 1\"];
+  n3 -> n4 [label=\"rhs\"];
+  n2 -> n3 [label=\"lhs\"];
+  n5 [label=\"Literal 1\\nline 2\"];
+  n2 -> n5 [label=\"rhs\"];
+  n1 -> n2;
+  n0 -> n1;
+}
+"
+    );
+}
+
+#[test]
+fn dot_leaves_out_lines_a_tree_alone_does_not_have() {
+    let statements = rlox::parse_program("print 1;").unwrap();
+    let dot = AstPrinterDot::default().print(&statements);
+    assert!(dot.contains("n1 [label=\"Print\"];\n  n2 [label=\"Literal 1\"];"));
+}
+
+#[test]
+fn dot_escapes_labels() {
+    let expr = rlox::parse_expression("\"a\\b\"").unwrap();
+    assert!(AstPrinterDot::default()
+        .print_expr(&expr)
+        .contains(r#"n0 [label="Literal \"a\\b\""];"#));
+}
+
+#[test]
+fn json_lists_every_field_in_a_fixed_order() {
+    let statements = rlox::parse_program("var a;\nreturn_value(a, \"x\ny\");").unwrap();
+    assert_eq!(
+        AstPrinterJson::default().print(&statements),
+        r#"{
+  "type": "Program",
+  "statements": [
+    {
+      "type": "Var",
+      "name": "a",
+      "line": 1,
+      "initializer": null
+    },
+    {
+      "type": "Expression",
+      "expression": {
+        "type": "Call",
+        "line": 3,
+        "callee": {
+          "type": "Variable",
+          "name": "return_value",
+          "line": 2
+        },
+        "arguments": [
+          {
+            "type": "Variable",
+            "name": "a",
+            "line": 2
+          },
+          {
+            "type": "Literal",
+            "value": "x\ny"
+          }
+        ]
+      }
+    }
+  ]
+}
+"#
+    );
+}
+
+#[test]
+fn cli_emits_the_tree_without_running() {
+    let emit = |format: &str| {
+        Command::cargo_bin("rlox")
+            .unwrap()
+            .arg(format!("--emit=ast-{}", format))
+            .arg("./tests/test_script.txt")
+            .assert()
+            .success()
+            .stdout(contains("hello, world\n").not())
+    };
+    emit("dot").stdout(contains("n1 [label=\"Print\\nline 1\"];"));
+    emit("json").stdout(contains("\"value\": \"hello, world\""));
+}
+
+#[test]
+fn cli_reports_programs_nested_too_deep_to_export() {
+    let source = format!("print {}1{};", "(".repeat(3000), ")".repeat(3000));
+    for format in ["dot", "json"].iter() {
+        Command::cargo_bin("rlox")
+            .unwrap()
+            .arg(format!("--emit=ast-{}", format))
+            .args(["-e", &source])
+            .assert()
+            .stderr(contains("Too much nesting."))
+            .code(65);
+    }
+}
@@ -0,0 +1,181 @@
+use std::fs;
+
+use assert_cmd::Command;
+use predicates::str::contains;
+
+use rlox::bytecode::{checksum, decode, encode};
+use rlox::chunk::OpCode;
+use rlox::error::RloxError;
+use rlox::Vm;
+
+const SOURCE: &str = "
+fun greet(name) {
+    var greeting = \"Hello, \" + name;
+    return fun () { return greeting + \"!\"; };
+}
+for (var i = 0; i < 2; i = i + 1) print greet(str(i))();
+";
+
+// Offset of the compiled script in a file, after the magic number, version and checksums
+const HEADER_LEN: usize = 22;
+
+// Replaces the body of a compiled file, fixing up its checksum so only the contents are checked
+fn with_body(bytes: &[u8], body: &[u8]) -> Vec<u8> {
+    let mut patched = bytes[..HEADER_LEN].to_vec();
+    patched[14..HEADER_LEN].copy_from_slice(&checksum(body).to_le_bytes());
+    patched.extend_from_slice(body);
+    patched
+}
+
+// A compiled file whose script is `code`, all on line 1 and without constants
+fn with_code(code: &[u8]) -> Vec<u8> {
+    let len = code.len() as u32;
+    let mut body = vec![0]; // anonymous
+    for n in &[0, 0, len] {
+        body.extend_from_slice(&n.to_le_bytes()); // arity, upvalues and the length of the code
+    }
+    body.extend_from_slice(code);
+    for n in &[1, 1, len, 0] {
+        body.extend_from_slice(&n.to_le_bytes()); // one run of lines, then no constants
+    }
+    with_body(&encode(&rlox::compile(SOURCE).unwrap(), SOURCE), &body)
+}
+
+// Fails after so many writes, ending scripts corrupted into printing forever
+struct LimitedOutput(usize);
+
+impl std::io::Write for LimitedOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0 = self.0.checked_sub(1).ok_or(std::io::ErrorKind::Other)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn is_bytecode_error(result: Result<rlox::bytecode::CompiledScript, RloxError>) -> bool {
+    matches!(result, Err(RloxError::BytecodeError(_)))
+}
+
+#[test]
+fn compiled_scripts_round_trip() {
+    let script = rlox::compile(SOURCE).unwrap();
+    let compiled = decode(&encode(&script, SOURCE)).unwrap();
+    assert_eq!(compiled.script, script);
+    assert_eq!(compiled.source_checksum, checksum(SOURCE.as_bytes()));
+}
+
+#[test]
+fn headers_are_validated() {
+    let bytes = encode(&rlox::compile(SOURCE).unwrap(), SOURCE);
+    let mut wrong_magic = bytes.clone();
+    wrong_magic[0] = b'X';
+    assert!(is_bytecode_error(decode(&wrong_magic)));
+    let mut wrong_version = bytes.clone();
+    wrong_version[4] = 99;
+    assert!(is_bytecode_error(decode(&wrong_version)));
+    let mut wrong_checksum = bytes;
+    wrong_checksum[HEADER_LEN + 3] ^= 1;
+    assert!(is_bytecode_error(decode(&wrong_checksum)));
+}
+
+#[test]
+fn truncated_files_are_errors() {
+    let bytes = encode(&rlox::compile(SOURCE).unwrap(), SOURCE);
+    for len in 0..bytes.len() {
+        assert!(is_bytecode_error(decode(&bytes[..len])), "length {}", len);
+        let body = &bytes[HEADER_LEN.min(len)..len];
+        assert!(is_bytecode_error(decode(&with_body(&bytes, body))), "length {}", len);
+    }
+}
+
+#[test]
+fn corrupted_contents_never_panic() {
+    let bytes = encode(&rlox::compile(SOURCE).unwrap(), SOURCE);
+    let body = &bytes[HEADER_LEN..];
+    for i in 0..body.len() {
+        for flip in &[0x01, 0x80, 0xff] {
+            let mut corrupted = body.to_vec();
+            corrupted[i] ^= flip;
+            // What gets past verification must run without panicking
+            if let Ok(compiled) = decode(&with_body(&bytes, &corrupted)) {
+                let _ = Vm::with_output(LimitedOutput(1000)).run(compiled.script);
+            }
+        }
+    }
+    let mut trailing = body.to_vec();
+    trailing.push(0);
+    assert!(is_bytecode_error(decode(&with_body(&bytes, &trailing))));
+}
+
+#[test]
+fn unbalanced_stacks_and_bad_slots_are_rejected() {
+    use OpCode::*;
+    let op = |op: OpCode| op as u8;
+    assert!(decode(&with_code(&[op(Nil), op(Return)])).is_ok());
+    for code in &[
+        vec![op(Pop), op(Pop), op(Nil), op(Return)],
+        vec![op(Nil), op(Add), op(Add), op(Return)],
+        vec![op(GetLocal), 200, op(Return)],
+        vec![op(Nil), op(SetLocal), 2, op(Return)],
+        vec![op(Nil), op(Call), 2, op(Return)],
+        vec![op(Nil)],
+        vec![op(Nil), op(JumpIfFalse), 0, 1, op(Nil), op(Return)],
+    ] {
+        assert!(is_bytecode_error(decode(&with_code(code))), "{:?}", code);
+    }
+}
+
+#[test]
+fn cli_compiles_and_runs_scripts() {
+    let dir = std::env::temp_dir().join(format!("rlox_bytecode_test_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("greet.lox");
+    let compiled = dir.join("out.loxc");
+    fs::write(&source, SOURCE).unwrap();
+
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .arg("--compile")
+        .arg(&source)
+        .arg("-o")
+        .arg(&compiled)
+        .assert()
+        .success();
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .arg(&compiled)
+        .assert()
+        .stdout("Hello, 0!\nHello, 1!\n")
+        .success();
+
+    let mut bytes = fs::read(&compiled).unwrap();
+    bytes.truncate(bytes.len() / 2);
+    fs::write(&compiled, bytes).unwrap();
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .arg(&compiled)
+        .assert()
+        .stderr(contains("Invalid bytecode file"))
+        .failure();
+
+    // A script compiled next to its source is only run while the source is unchanged
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .arg("--compile")
+        .arg(&source)
+        .assert()
+        .success();
+    let cached = dir.join("greet.loxc");
+    Command::cargo_bin("rlox").unwrap().arg(&cached).assert().success();
+    fs::write(&source, "print \"edited\";").unwrap();
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .arg(&cached)
+        .assert()
+        .stderr(contains("the source has changed since it was compiled"))
+        .failure();
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -5,7 +5,7 @@ use predicates::str::contains;
 fn cli_too_many_args() {
     Command::cargo_bin("rlox")
         .unwrap()
-        .args(&["one", "two"])
+        .args(["one", "two"])
         .assert()
         .code(64)
         .failure();
@@ -15,7 +15,7 @@ fn cli_too_many_args() {
 fn cli_one_arg_with_invalid_filepath() {
     Command::cargo_bin("rlox")
         .unwrap()
-        .args(&["./tests/i-do-not-exist.txt"])
+        .args(["./tests/i-do-not-exist.txt"])
         .assert()
         .failure();
 }
@@ -24,26 +24,156 @@ fn cli_one_arg_with_invalid_filepath() {
 fn cli_one_arg_with_valid_filepath() {
     Command::cargo_bin("rlox")
         .unwrap()
-        .args(&["./tests/test_script.txt"])
+        .args(["./tests/test_script.txt"])
         .assert()
-        .stdout(contains(
-"Print print
-String \"hello, world\"
-Semicolon ;
-Eof"))
+        .stdout(contains("hello, world"))
         .success();
 }
 
 #[test]
-#[ignore]
 fn cli_no_arg() {
     Command::cargo_bin("rlox")
         .unwrap()
+        .env_remove("HOME")
         .write_stdin("print \"hello, world\";")
         .assert()
-        .stdout(contains(
-"> Print print
-String \"hello, world\"
-Semicolon ;
-Eof"));
+        .stdout(contains("> hello, world"));
+}
+
+#[test]
+fn cli_exit_native_sets_exit_code() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["./tests/exit_script.txt"])
+        .assert()
+        .stdout(contains("before exit"))
+        .code(3);
+}
+
+#[test]
+fn cli_help_and_version() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .arg("--help")
+        .assert()
+        .stdout(contains("rlox ast [--format=lisp|rpn|json|dot]"))
+        .success();
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .arg("-V")
+        .assert()
+        .stdout(format!("rlox {}\n", env!("CARGO_PKG_VERSION")))
+        .success();
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["ast", "--bogus", "x.lox"])
+        .assert()
+        .stderr(contains("Usage:"))
+        .code(64);
+}
+
+#[test]
+fn cli_runs_inline_code_and_stdin() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["-e", "print 1 + 2;"])
+        .assert()
+        .stdout("3\n")
+        .success();
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["run", "--backend=vm", "-"])
+        .write_stdin("print \"from stdin\";")
+        .assert()
+        .stdout("from stdin\n")
+        .success();
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .arg("repl")
+        .env_remove("HOME")
+        .write_stdin("1 + 1\n")
+        .assert()
+        .stdout("> 2\n> ")
+        .success();
+}
+
+#[test]
+fn cli_tokens_and_ast_formats() {
+    let output = |args: &[&str]| {
+        let output = Command::cargo_bin("rlox").unwrap().args(args).output();
+        String::from_utf8(output.unwrap().stdout).unwrap()
+    };
+    assert_eq!(
+        output(&["tokens", "-e", "x = 1;"]),
+        "Identifier \"x\" @1\nEqual \"=\" @1\nNumber \"1\" @1\nSemicolon \";\" @1\n"
+    );
+    assert_eq!(
+        output(&["ast", "-e", "print -a; b = 2;"]),
+        "(print (- a))\n(= b 2)\n"
+    );
+    assert_eq!(
+        output(&["ast", "--format=rpn", "-e", "1 + 2 * 3; print -a;"]),
+        "1 2 3 * +\na -\n"
+    );
+    assert!(output(&["ast", "--format=dot", "./tests/test_script.txt"]).starts_with("digraph"));
+    assert!(output(&["ast", "--format=json", "-e", "1;"]).contains("\"Program\""));
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["ast", "--format=rpn", "-e", "var a;"])
+        .assert()
+        .stderr(contains("only expression and print statements can be printed in RPN"))
+        .code(65);
+}
+
+#[test]
+fn cli_subcommands_reject_flags_for_running() {
+    let code = |args: &[&str]| {
+        let status = Command::cargo_bin("rlox").unwrap().args(args).output();
+        status.unwrap().status.code().unwrap()
+    };
+    assert_eq!(code(&["tokens", "--backend=vm", "-e", "1;"]), 64);
+    assert_eq!(code(&["ast", "--no-opt", "-e", "1;"]), 64);
+    assert_eq!(code(&["check", "--gc-stress", "-e", "1;"]), 64);
+    assert_eq!(code(&["check", "--dump-bytecode", "-e", "1;"]), 64);
+    assert_eq!(code(&["repl", "--compile"]), 64);
+    assert_eq!(code(&["run", "--format=json", "-e", "1;"]), 64);
+    assert_eq!(code(&["check", "--intern-stats", "-e", "1;"]), 0);
+    assert_eq!(code(&["run", "--backend=vm", "--no-opt", "-e", "1;"]), 0);
+}
+
+#[test]
+fn cli_rejects_conflicting_flags_in_either_order() {
+    let code = |args: &[&str]| {
+        let status = Command::cargo_bin("rlox").unwrap().args(args).output();
+        status.unwrap().status.code().unwrap()
+    };
+    let conflicts = [
+        ["--gc-stress", "--backend=tree"],
+        ["--dump-bytecode", "--backend=tree"],
+        ["--compile", "--backend=tree"],
+        ["--dump-bytecode", "--emit=ast-json"],
+        ["--rpn", "--dump-bytecode"],
+    ];
+    for [a, b] in conflicts.iter() {
+        assert_eq!(code(&[a, b, "-e", "1;"]), 64, "{} {}", a, b);
+        assert_eq!(code(&[b, a, "-e", "1;"]), 64, "{} {}", b, a);
+    }
+    assert_eq!(code(&["--backend=vm", "--gc-stress", "-e", "1;"]), 0);
+    assert_eq!(code(&["--gc-stress", "-e", "1;"]), 0);
+}
+
+#[test]
+fn cli_exit_codes_follow_sysexits() {
+    let code = |args: &[&str]| {
+        let status = Command::cargo_bin("rlox").unwrap().args(args).output();
+        status.unwrap().status.code().unwrap()
+    };
+    assert_eq!(code(&["check", "./tests/test_script.txt"]), 0);
+    assert_eq!(code(&["check", "-e", "print (;"]), 65);
+    assert_eq!(code(&["check", "-e", "return 1;"]), 65);
+    assert_eq!(code(&["check", "./tests/i-do-not-exist.txt"]), 66);
+    assert_eq!(code(&["-e", "print nil + 1;"]), 70);
+    let deep = format!("print {}1{};", "(".repeat(50000), ")".repeat(50000));
+    assert_eq!(code(&["-e", &deep]), 65);
+    assert_eq!(code(&["tokens"]), 64);
 }
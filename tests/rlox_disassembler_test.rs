@@ -0,0 +1,94 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use predicates::str::contains;
+
+use rlox::chunk::{Chunk, Constant, OpCode};
+use rlox::disassembler::{disassemble, disassemble_chunk};
+
+#[test]
+fn chunks_are_listed_in_clox_format() {
+    let mut chunk = Chunk::new();
+    let constant = chunk.add_constant(Constant::Number(1.2));
+    chunk.write(OpCode::Constant as u8, 123);
+    chunk.write(constant as u8, 123);
+    chunk.write(OpCode::Negate as u8, 123);
+    chunk.write(OpCode::Return as u8, 124);
+    assert_eq!(
+        disassemble_chunk(&chunk, "test chunk"),
+        "\
+== test chunk ==
+0000  123 OP_CONSTANT         0 '1.2'
+0002    | OP_NEGATE
+0003  124 OP_RETURN
+"
+    );
+}
+
+#[test]
+fn scripts_are_listed_with_their_functions() {
+    let script = rlox::compile(
+        "var greeting = \"hi\";
+fun shout(s) { return toUpper(s); }
+if (greeting != nil) print shout(greeting);",
+    )
+    .unwrap();
+    assert_eq!(
+        disassemble(&script),
+        "\
+== <script> ==
+0000    1 OP_CONSTANT         0 'hi'
+0002    | OP_DEFINE_GLOBAL    1 'greeting'
+0004    2 OP_CLOSURE          2 <fn shout>
+0006    | OP_DEFINE_GLOBAL    3 'shout'
+0008    3 OP_GET_GLOBAL       1 'greeting'
+0010    | OP_NIL
+0011    | OP_EQUAL
+0012    | OP_NOT
+0013    | OP_JUMP_IF_FALSE   13 -> 27
+0016    | OP_POP
+0017    | OP_GET_GLOBAL       3 'shout'
+0019    | OP_GET_GLOBAL       1 'greeting'
+0021    | OP_CALL             1
+0023    | OP_PRINT
+0024    | OP_JUMP            24 -> 28
+0027    | OP_POP
+0028    | OP_NIL
+0029    | OP_RETURN
+== shout ==
+0000    2 OP_GET_GLOBAL       0 'toUpper'
+0002    | OP_GET_LOCAL        1
+0004    | OP_CALL             1
+0006    | OP_RETURN
+0007    | OP_NIL
+0008    | OP_RETURN
+"
+    );
+}
+
+#[test]
+fn captured_variables_are_listed_under_their_closure() {
+    let script = rlox::compile(
+        "fun outer() {
+  var x = 1;
+  fun inner() { return x; }
+}",
+    )
+    .unwrap();
+    assert!(disassemble(&script).contains(
+        "\
+0002    3 OP_CLOSURE          1 <fn inner>
+0004      |                     local 1
+"
+    ));
+}
+
+#[test]
+fn cli_dumps_bytecode_without_running() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["--dump-bytecode", "./tests/test_script.txt"])
+        .assert()
+        .stdout(contains("== <script> ==\n0000    1 OP_CONSTANT         0 'hello, world'"))
+        .stdout(contains("hello, world\n").not())
+        .success();
+}
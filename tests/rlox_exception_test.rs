@@ -0,0 +1,105 @@
+use rlox::error::RloxError;
+use rlox::{Interpreter, Value};
+
+#[test]
+fn thrown_values_are_caught_as_is() {
+    let interpreter = Interpreter::new();
+    let source = "
+        var caught;
+        try { throw 42; } catch (e) { caught = e; }
+        caught;
+    ";
+    assert_eq!(interpreter.eval(source).unwrap(), Value::Number(42.0));
+}
+
+#[test]
+fn runtime_errors_are_caught_as_error_objects() {
+    let interpreter = Interpreter::new();
+    let source = "
+        var e;
+        try {
+            -\"text\";
+        } catch (error) {
+            e = error;
+        }
+        str(e.message) + \" @\" + str(e.line);
+    ";
+    assert_eq!(
+        interpreter.eval(source).unwrap(),
+        Value::from("Operand must be a number. @4")
+    );
+}
+
+#[test]
+fn thrown_error_objects_record_the_throw_line() {
+    let interpreter = Interpreter::new();
+    let source = "
+        fun fail() {
+            throw Error(\"nope\");
+        }
+        var line;
+        try { fail(); } catch (e) { line = e.line; }
+        line;
+    ";
+    assert_eq!(interpreter.eval(source).unwrap(), Value::Number(3.0));
+}
+
+#[test]
+fn finally_runs_on_every_exit_path() {
+    let interpreter = Interpreter::new();
+    let source = "
+        var log = [];
+        fun f() {
+            try { return 1; } finally { log.push(\"return\"); }
+        }
+        f();
+        try { throw nil; } catch (e) { log.push(\"catch\"); } finally { log.push(\"throw\"); }
+        for (var i = 0; i < 1; i = i + 1) {
+            try { break; } finally { log.push(\"break\"); }
+        }
+        str(log);
+    ";
+    assert_eq!(
+        interpreter.eval(source).unwrap(),
+        Value::from("[\"return\", \"catch\", \"throw\", \"break\"]")
+    );
+}
+
+#[test]
+fn uncaught_exceptions_carry_a_stack_trace() {
+    let interpreter = Interpreter::new();
+    let source = "
+        fun inner() {
+            throw Error(\"deep\");
+        }
+        fun outer() {
+            inner();
+        }
+        outer();
+    ";
+    match interpreter.eval(source) {
+        Err(RloxError::RuntimeError(e)) => {
+            assert_eq!(e.description(), "Uncaught Error: deep");
+            assert_eq!(
+                e.stack_trace(),
+                ["[line 3] in inner()", "[line 6] in outer()", "[line 8] in script"]
+            );
+        }
+        result => panic!("expected an uncaught exception, got {:?}", result),
+    }
+}
+
+#[test]
+fn exit_is_not_caught() {
+    let interpreter = Interpreter::new();
+    let result = interpreter.eval("try { exit(2); } catch (e) { print e; }");
+    assert!(matches!(result, Err(RloxError::Exit(2))));
+}
+
+#[test]
+fn try_needs_a_catch_or_finally_clause() {
+    assert!(matches!(
+        rlox::parse_program("try { print 1; }"),
+        Err(RloxError::SyntaxErrors(_))
+    ));
+}
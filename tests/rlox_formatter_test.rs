@@ -0,0 +1,159 @@
+mod common;
+
+use assert_cmd::Command;
+
+use rlox::error::RloxError;
+use rlox::formatter::format_source;
+use rlox::Interpreter;
+
+use common::SharedOutput;
+
+fn run(source: &str) -> String {
+    let output = SharedOutput::default();
+    let interpreter = Interpreter::with_output(output.clone());
+    interpreter.interpret(rlox::parse_program(source).unwrap()).unwrap();
+    output.contents()
+}
+
+const PROGRAM: &str = "\
+// Counts down
+fun countdown(n){ for(var i=n;i>0;i=i-1){print i;} return (n); }
+
+class Point{init(x,y){this.x=x;this.y=y;} // coordinates
+sum(){return (this.x)+(this.y);}}
+var p=Point(1,2);
+if (p.sum()==3) print \"three\"; else { print \"not three\"; }
+countdown(2);
+var twice=fun(f){return fun(x){return f(f(x));};};
+print twice(fun(x){return x*2;})(3);
+print [1,2][0]+{\"a\":(1+2)*3}[\"a\"];
+";
+
+#[test]
+fn programs_are_laid_out_canonically() {
+    assert_eq!(
+        format_source(PROGRAM).unwrap(),
+        "\
+// Counts down
+fun countdown(n) {
+  for (var i = n; i > 0; i = i - 1) {
+    print i;
+  }
+  return n;
+}
+
+class Point {
+  init(x, y) {
+    this.x = x;
+    this.y = y;
+  } // coordinates
+  sum() {
+    return this.x + this.y;
+  }
+}
+var p = Point(1, 2);
+if (p.sum() == 3) print \"three\";
+else {
+  print \"not three\";
+}
+countdown(2);
+var twice = fun (f) {
+  return fun (x) {
+    return f(f(x));
+  };
+};
+print twice(fun (x) {
+  return x * 2;
+})(3);
+print [1, 2][0] + {\"a\": (1 + 2) * 3}[\"a\"];
+"
+    );
+}
+
+#[test]
+fn only_parentheses_needed_by_precedence_are_kept() {
+    let format = |source: &str| format_source(source).unwrap();
+    assert_eq!(format("print (a - b) - (c - d);"), "print a - b - (c - d);\n");
+    assert_eq!(format("print ((a or b)) and !(c == d);"), "print (a or b) and !(c == d);\n");
+    assert_eq!(format("print -(-x) * (f)(y).z;"), "print - -x * f(y).z;\n");
+    assert_eq!(format("a = (b = c);"), "a = b = c;\n");
+}
+
+#[test]
+fn for_loops_keep_the_clauses_they_were_written_with() {
+    let source = "\
+for (;;) break;
+for (i = 0; i < 3;) i = i + 1;
+for (;; i = i + 1) if (i > 5) break;
+for (; i < 10;) i = i + 1;
+for (var k = 0; k < 2; k = k + 1) print k;
+{
+  var j = 0;
+  while (j < 2) j = j + 1;
+}
+";
+    assert_eq!(format_source(source).unwrap(), source);
+}
+
+#[test]
+fn programs_nested_too_deep_are_a_syntax_error() {
+    let source = format!("print {}1{};", "[".repeat(3000), "]".repeat(3000));
+    assert!(matches!(format_source(&source), Err(RloxError::SyntaxErrors(_))));
+}
+
+#[test]
+fn comments_and_blank_lines_are_kept() {
+    let source = "\
+// leading
+
+var a = 1; // trailing
+
+
+{ // opening
+  print a;
+  // closing
+}
+// footer
+";
+    assert_eq!(
+        format_source(source).unwrap(),
+        "\
+// leading
+
+var a = 1; // trailing
+
+{ // opening
+  print a;
+  // closing
+}
+// footer
+"
+    );
+}
+
+#[test]
+fn formatting_is_idempotent_and_keeps_behavior() {
+    let formatted = format_source(PROGRAM).unwrap();
+    assert_eq!(format_source(&formatted).unwrap(), formatted);
+    assert_eq!(run(&formatted), run(PROGRAM));
+}
+
+#[test]
+fn cli_fmt_rewrites_files_and_check_reports_them() {
+    let path = std::env::temp_dir().join(format!("rlox_formatter_{}.lox", std::process::id()));
+    std::fs::write(&path, "print  1+2 ;").unwrap();
+    let fmt = |check: bool| {
+        let mut command = Command::cargo_bin("rlox").unwrap();
+        command.arg("fmt");
+        if check {
+            command.arg("--check");
+        }
+        command.arg(&path).assert()
+    };
+    fmt(true).code(1).stderr(predicates::str::contains("not formatted"));
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "print  1+2 ;");
+    fmt(false).success();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), "print 1 + 2;\n");
+    fmt(true).success();
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,123 @@
+mod common;
+
+use assert_cmd::Command;
+
+use rlox::heap::GcConfig;
+use rlox::Vm;
+
+use common::SharedOutput;
+
+fn stress() -> GcConfig {
+    GcConfig {
+        stress: true,
+        ..GcConfig::default()
+    }
+}
+
+// Runs `source` on a VM collecting before every allocation, returning what it printed
+fn run_stressed(source: &str) -> String {
+    let output = SharedOutput::default();
+    let mut vm = Vm::with_gc_config(output.clone(), stress());
+    let result = vm.interpret(rlox::parse_program(source).unwrap());
+    match result {
+        Ok(()) => output.contents(),
+        Err(e) => format!("{}{}", output.contents(), e),
+    }
+}
+
+#[test]
+fn closures_and_instances_survive_collections() {
+    let source = "
+        class Node {
+            init(value) { this.value = value; this.next = nil; }
+        }
+        fun build(n) {
+            var head = nil;
+            for (var i = 0; i < n; i = i + 1) {
+                var node = Node(\"node \" + str(i));
+                node.next = head;
+                head = node;
+            }
+            return head;
+        }
+        fun counter() {
+            var count = 0;
+            return fun () { count = count + 1; return count; };
+        }
+        var list = build(5);
+        var c = counter();
+        c();
+        print list.value + \", \" + list.next.value + \", \" + str(c());
+        ";
+    assert_eq!(run_stressed(source), "node 4, node 3, 2\n");
+}
+
+#[test]
+fn collections_and_exceptions_survive_collections() {
+    let source = "
+        var m = {\"a\": [1, \"two\"], \"b\": \"three\" + \"!\"};
+        m[\"c\"] = m.keys();
+        print m;
+        print m.values();
+        try {
+            throw Error(\"kept\" + \" alive\");
+        } catch (e) {
+            print e.message;
+        } finally {
+            print \"done\";
+        }
+        ";
+    assert_eq!(
+        run_stressed(source),
+        "{\"a\": [1, \"two\"], \"b\": \"three!\", \"c\": [\"a\", \"b\"]}\n\
+         [[1, \"two\"], \"three!\", [\"a\", \"b\"]]\nkept alive\ndone\n"
+    );
+}
+
+#[test]
+fn reference_cycles_are_collected() {
+    let mut vm = Vm::with_output(SharedOutput::default());
+    vm.collect_garbage();
+    let baseline = vm.heap().live_objects();
+    let source = "
+        class Owner {}
+        fun tangle() {
+            var owner = Owner();
+            owner.callback = fun () { return owner; };
+            owner.self = owner;
+        }
+        for (var i = 0; i < 100; i = i + 1) tangle();
+        ";
+    vm.interpret(rlox::parse_program(source).unwrap()).unwrap();
+    assert!(vm.heap().live_objects() > baseline + 100);
+    vm.collect_garbage();
+    // The class, `tangle` and their names stay reachable through the globals
+    assert!(vm.heap().live_objects() < baseline + 10);
+}
+
+#[test]
+fn collections_wait_for_the_heap_to_grow() {
+    let config = GcConfig {
+        initial_threshold: 64 * 1024,
+        growth_factor: 2,
+        stress: false,
+    };
+    let mut vm = Vm::with_gc_config(SharedOutput::default(), config);
+    let source = "
+        for (var i = 0; i < 5000; i = i + 1) {
+            var garbage = [str(i), str(i + 1)];
+        }
+        ";
+    vm.interpret(rlox::parse_program(source).unwrap()).unwrap();
+    assert!(vm.heap().bytes_allocated() <= 2 * 64 * 1024);
+}
+
+#[test]
+fn cli_runs_scripts_in_gc_stress_mode() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["--gc-stress", "./tests/test_script.txt"])
+        .assert()
+        .stdout("hello, world\n")
+        .success();
+}
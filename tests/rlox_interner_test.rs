@@ -0,0 +1,49 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+
+use rlox::interner::{intern, stats};
+use rlox::Value;
+
+#[test]
+fn equal_strings_share_one_symbol() {
+    let a = intern("counter");
+    let b = intern(&String::from("counter"));
+    assert_eq!(a, b);
+    assert_eq!(a.as_str().as_ptr(), b.as_str().as_ptr());
+    assert_ne!(a, intern("count"));
+}
+
+#[test]
+fn scanned_identifiers_are_interned() {
+    let tokens = rlox::tokenize("a b a").unwrap();
+    assert_eq!(tokens[0].symbol(), tokens[2].symbol());
+    assert_ne!(tokens[0].symbol(), tokens[1].symbol());
+}
+
+#[test]
+fn string_values_compare_by_contents() {
+    assert_eq!(Value::from("ab"), Value::from(format!("a{}", "b")));
+    assert_ne!(Value::from("ab"), Value::from("ba"));
+}
+
+#[test]
+fn stats_count_lookups_and_hits() {
+    let before = stats();
+    intern("stats_count_lookups_and_hits");
+    intern("stats_count_lookups_and_hits");
+    let after = stats();
+    assert_eq!(after.lookups, before.lookups + 2);
+    assert_eq!(after.hits, before.hits + 1);
+    assert!(after.strings > before.strings);
+}
+
+#[test]
+fn intern_stats_flag_reports_to_stderr() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["--intern-stats", "./tests/test_script.txt"])
+        .assert()
+        .stdout("hello, world\n")
+        .stderr(predicate::str::contains("interned strings:"))
+        .success();
+}
@@ -0,0 +1,144 @@
+mod common;
+
+use std::convert::TryFrom;
+
+use rlox::error::RloxError;
+use rlox::{Interpreter, Value};
+
+use common::SharedOutput;
+
+fn run(source: &str) -> String {
+    let output = SharedOutput::default();
+    let interpreter = Interpreter::with_output(output.clone());
+    interpreter.eval(source).unwrap();
+    output.contents()
+}
+
+#[test]
+fn eval_returns_value_of_expression() {
+    let interpreter = Interpreter::new();
+    assert_eq!(interpreter.eval("(1 + 2) * 3").unwrap(), Value::Number(9.0));
+    assert_eq!(interpreter.eval("var a = \"x\"; a + a;").unwrap(), Value::from("xx"));
+    assert_eq!(interpreter.eval("var b = 1;").unwrap(), Value::Nil);
+}
+
+#[test]
+fn globals_persist_across_evals() {
+    let interpreter = Interpreter::new();
+    interpreter.define_global("limit", 10.0);
+    interpreter.eval("fun twice(n) { return n * 2; }").unwrap();
+    let result = interpreter.eval("twice(limit)").unwrap();
+    assert_eq!(f64::try_from(result).unwrap(), 20.0);
+}
+
+#[test]
+fn native_functions_are_callable_from_lox() {
+    let interpreter = Interpreter::new();
+    interpreter.define_native("hypot", 2, |args| {
+        let a = f64::try_from(args[0].clone())?;
+        let b = f64::try_from(args[1].clone())?;
+        Ok(Value::from((a * a + b * b).sqrt()))
+    });
+    assert_eq!(interpreter.eval("hypot(3, 4)").unwrap(), Value::Number(5.0));
+
+    match interpreter.eval("\n\nhypot(3);") {
+        Err(RloxError::RuntimeError(e)) => {
+            assert_eq!(e.description(), "Expected 2 arguments but got 1.");
+            assert_eq!(e.line_number(), Some(3));
+        }
+        _ => panic!("expected an arity error"),
+    }
+    match interpreter.eval("hypot(3, \"4\");") {
+        Err(RloxError::RuntimeError(e)) => assert_eq!(e.line_number(), Some(1)),
+        _ => panic!("expected a type error"),
+    }
+}
+
+#[test]
+fn lox_functions_are_callable_from_rust() {
+    let interpreter = Interpreter::new();
+    interpreter.eval("fun greet(name) { return \"hi \" + name; }").unwrap();
+    let greet = interpreter.get_global("greet").unwrap();
+    let greeting = interpreter.call(&greet, vec![Value::from("lox")]).unwrap();
+    assert_eq!(String::try_from(greeting).unwrap(), "hi lox");
+}
+
+#[test]
+fn closures_capture_their_defining_scope() {
+    let source = "
+        var a = \"global\";
+        {
+            fun show() { print a; }
+            show();
+            var a = \"block\";
+            show();
+        }
+        fun makeCounter() {
+            var i = 0;
+            fun count() { i = i + 1; return i; }
+            return count;
+        }
+        var counter = makeCounter();
+        print counter();
+        print counter();
+    ";
+    assert_eq!(run(source), "global\nglobal\n1\n2\n");
+}
+
+#[test]
+fn classes_support_initializers_and_inheritance() {
+    let source = "
+        class A {
+            init(name) { this.name = name; }
+            greet() { return \"A \" + this.name; }
+        }
+        class B < A {
+            greet() { return super.greet() + \"!\"; }
+        }
+        var b = B(\"b\");
+        print b.greet();
+        print b;
+        print B;
+    ";
+    assert_eq!(run(source), "A b!\nB instance\nB\n");
+}
+
+#[test]
+fn runtime_errors_report_the_line() {
+    match Interpreter::new().eval("var a = 1;\nprint -\"a\";") {
+        Err(RloxError::RuntimeError(e)) => {
+            assert_eq!(e.description(), "Operand must be a number.");
+            assert_eq!(e.line_number(), Some(2));
+        }
+        _ => panic!("expected a runtime error"),
+    }
+}
+
+#[test]
+fn resolver_rejects_top_level_return() {
+    assert!(matches!(
+        Interpreter::new().eval("return 1;"),
+        Err(RloxError::SyntaxErrors(_))
+    ));
+}
+
+#[test]
+fn unbounded_recursion_is_a_runtime_error() {
+    match Interpreter::new().eval("fun f() { return f(); } f();") {
+        Err(e @ RloxError::RuntimeError(_)) => assert!(e
+            .to_string()
+            .contains("[line 1] in f()\n[previous frame repeated 10000 more times]")),
+        result => panic!("expected a stack overflow, got {:?}", result.map(|v| v.to_string())),
+    }
+}
+
+#[test]
+fn deep_recursion_through_nested_statements_fits_in_the_native_stack() {
+    let source = "
+        fun f(n) {
+            if (n == 0) return 0;
+            { { try { while (true) { return f(n - 1) + [[[1]]][0][0][0]; } } catch (e) {} } }
+        }
+        f(2000);";
+    assert_eq!(Interpreter::new().eval(source).unwrap(), Value::Number(2000.0));
+}
@@ -0,0 +1,56 @@
+use rlox::ast_printer::AstPrinter;
+use rlox::error::RloxError;
+use rlox::{Interpreter, Value};
+
+#[test]
+fn lambdas_can_be_passed_and_called() {
+    let interpreter = Interpreter::new();
+    let source = "
+        fun apply(f, x) { return f(x); }
+        apply(fun (n) { return n * 2; }, 21);
+    ";
+    assert_eq!(interpreter.eval(source).unwrap(), Value::Number(42.0));
+}
+
+#[test]
+fn lambdas_close_over_their_environment() {
+    let interpreter = Interpreter::new();
+    let source = "
+        var counter = fun () {
+            var i = 0;
+            return fun () { i = i + 1; return i; };
+        }();
+        counter();
+        counter();
+    ";
+    assert_eq!(interpreter.eval(source).unwrap(), Value::Number(2.0));
+}
+
+#[test]
+fn lambda_at_statement_start_is_an_expression() {
+    let interpreter = Interpreter::new();
+    let source = "
+        var result;
+        fun (x) { result = x + 1; }(1);
+        result;
+    ";
+    assert_eq!(interpreter.eval(source).unwrap(), Value::Number(2.0));
+}
+
+#[test]
+fn lambdas_display_without_a_name() {
+    let interpreter = Interpreter::new();
+    assert_eq!(interpreter.eval("str(fun () {});").unwrap(), Value::from("<fn>"));
+}
+
+#[test]
+fn ast_printer_prints_lambda_bodies() {
+    let expr = rlox::parse_expression("fun (a, b) { return a + b; }").unwrap();
+    assert_eq!(AstPrinter::default().print(expr), "(fun (a b) (return (+ a b)))");
+}
+
+#[test]
+fn loop_control_does_not_cross_a_lambda_boundary() {
+    let source = "while (true) { var f = fun () { break; }; }";
+    assert!(matches!(rlox::parse_program(source), Err(RloxError::SyntaxErrors(_))));
+}
@@ -0,0 +1,87 @@
+use rlox::ast_printer::AstPrinter;
+use rlox::ast_printer_rpn::AstPrinterRpn;
+use rlox::error::RloxError;
+use rlox::{Interpreter, Value};
+
+#[test]
+fn list_literals_and_indexing() {
+    let interpreter = Interpreter::new();
+    interpreter.eval("var xs = [1, 2, [3, \"four\"]];").unwrap();
+    assert_eq!(interpreter.eval("xs[2][1]").unwrap(), Value::from("four"));
+    assert_eq!(interpreter.eval("xs[0] = xs[0] + 10;").unwrap(), Value::Number(11.0));
+    assert_eq!(
+        interpreter.eval("str(xs)").unwrap(),
+        Value::from("[11, 2, [3, \"four\"]]")
+    );
+}
+
+#[test]
+fn list_methods() {
+    let interpreter = Interpreter::new();
+    interpreter
+        .eval("var xs = []; xs.push(1); xs.push(2); xs.insert(0, 0); xs.insert(3, 3);")
+        .unwrap();
+    assert_eq!(interpreter.eval("str(xs)").unwrap(), Value::from("[0, 1, 2, 3]"));
+    assert_eq!(interpreter.eval("xs.remove(1)").unwrap(), Value::Number(1.0));
+    assert_eq!(interpreter.eval("xs.pop()").unwrap(), Value::Number(3.0));
+    assert_eq!(interpreter.eval("xs.len()").unwrap(), Value::Number(2.0));
+    assert_eq!(interpreter.eval("len(xs)").unwrap(), Value::Number(2.0));
+}
+
+#[test]
+fn lists_are_shared_by_reference() {
+    let interpreter = Interpreter::new();
+    interpreter
+        .eval("var a = [1]; var b = a; b.push(2);")
+        .unwrap();
+    assert_eq!(interpreter.eval("a.len()").unwrap(), Value::Number(2.0));
+    assert_eq!(interpreter.eval("a == b").unwrap(), Value::Bool(true));
+    assert_eq!(interpreter.eval("a == [1, 2]").unwrap(), Value::Bool(false));
+}
+
+#[test]
+fn out_of_bounds_access_is_a_runtime_error() {
+    for source in &[
+        "var xs = [1, 2];\nxs[2];",
+        "var xs = [1, 2];\nxs[-1] = 0;",
+        "var xs = [1, 2];\nxs[0.5];",
+        "var xs = [];\nxs.pop();",
+        "var xs = [];\nxs.remove(0);",
+    ] {
+        match Interpreter::new().eval(source) {
+            Err(RloxError::RuntimeError(e)) => assert_eq!(e.line_number(), Some(2), "{}", source),
+            _ => panic!("expected a runtime error for {}", source),
+        }
+    }
+}
+
+#[test]
+fn printers_handle_list_nodes() {
+    let expr = rlox::parse_expression("xs[0] = [1, 2][1]").unwrap();
+    assert_eq!(
+        AstPrinter::default().print(expr.clone()),
+        "(index= xs 0 (index (list 1 2) 1))"
+    );
+    assert_eq!(
+        AstPrinterRpn::default().print(expr),
+        "xs 0 1 2 list/2 1 index index="
+    );
+}
+
+#[test]
+fn lists_containing_themselves_print_the_repeat_as_an_ellipsis() {
+    let interpreter = Interpreter::new();
+    interpreter
+        .eval("var l = []; l.push(l); var m = [1, l]; l.push(m);")
+        .unwrap();
+    assert_eq!(
+        interpreter.eval("str(l)").unwrap(),
+        Value::from("[[...], [1, [...]]]")
+    );
+    // A list appearing twice side by side is not a cycle
+    interpreter.eval("var a = [1]; var b = [a, a];").unwrap();
+    assert_eq!(
+        interpreter.eval("str(b)").unwrap(),
+        Value::from("[[1], [1]]")
+    );
+}
@@ -0,0 +1,68 @@
+use rlox::error::RloxError;
+use rlox::{Interpreter, Value};
+
+#[test]
+fn break_exits_the_innermost_loop() {
+    let interpreter = Interpreter::new();
+    let source = "
+        var hits = 0;
+        for (var i = 0; i < 5; i = i + 1) {
+            var j = 0;
+            while (true) {
+                if (j == 2) break;
+                hits = hits + 1;
+                j = j + 1;
+            }
+            if (i == 3) break;
+        }
+        hits;
+    ";
+    assert_eq!(interpreter.eval(source).unwrap(), Value::Number(8.0));
+}
+
+#[test]
+fn continue_in_a_for_loop_still_runs_the_increment() {
+    let interpreter = Interpreter::new();
+    let source = "
+        var odds = [];
+        for (var i = 0; i < 10; i = i + 1) {
+            if (i == 2 * floor(i / 2)) continue;
+            odds.push(i);
+        }
+        str(odds);
+    ";
+    assert_eq!(interpreter.eval(source).unwrap(), Value::from("[1, 3, 5, 7, 9]"));
+}
+
+#[test]
+fn continue_in_a_while_loop_rechecks_the_condition() {
+    let interpreter = Interpreter::new();
+    let source = "
+        var i = 0;
+        var sum = 0;
+        while (i < 5) {
+            i = i + 1;
+            if (i == 3) continue;
+            sum = sum + i;
+        }
+        sum;
+    ";
+    assert_eq!(interpreter.eval(source).unwrap(), Value::Number(12.0));
+}
+
+#[test]
+fn break_and_continue_are_rejected_outside_of_loops() {
+    for source in &[
+        "break;",
+        "continue;",
+        "if (true) { break; }",
+        "while (true) { fun f() { break; } }",
+        "for (;;) { fun f() { continue; } }",
+    ] {
+        assert!(
+            matches!(rlox::parse_program(source), Err(RloxError::SyntaxErrors(_))),
+            "{}",
+            source
+        );
+    }
+}
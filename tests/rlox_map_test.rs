@@ -0,0 +1,101 @@
+use rlox::ast_printer::AstPrinter;
+use rlox::error::RloxError;
+use rlox::{Interpreter, Value};
+
+#[test]
+fn map_literals_and_indexing() {
+    let interpreter = Interpreter::new();
+    interpreter
+        .eval("var m = {\"a\": 1, 2: \"two\", true: nil};")
+        .unwrap();
+    assert_eq!(interpreter.eval("m[\"a\"]").unwrap(), Value::Number(1.0));
+    assert_eq!(interpreter.eval("m[2]").unwrap(), Value::from("two"));
+    interpreter.eval("m[-0] = 0;").unwrap();
+    assert_eq!(interpreter.eval("m[0]").unwrap(), Value::Number(0.0));
+    assert_eq!(interpreter.eval("m.has(true)").unwrap(), Value::Bool(true));
+    assert_eq!(interpreter.eval("len(m)").unwrap(), Value::Number(4.0));
+}
+
+#[test]
+fn maps_iterate_in_insertion_order() {
+    let interpreter = Interpreter::new();
+    interpreter
+        .eval("var m = {\"z\": 1, \"a\": 2}; m[\"m\"] = 3; m[\"z\"] = 4;")
+        .unwrap();
+    assert_eq!(
+        interpreter.eval("str(m.keys())").unwrap(),
+        Value::from("[\"z\", \"a\", \"m\"]")
+    );
+    assert_eq!(interpreter.eval("str(m.values())").unwrap(), Value::from("[4, 2, 3]"));
+    assert_eq!(interpreter.eval("m.remove(\"a\")").unwrap(), Value::Number(2.0));
+    assert_eq!(interpreter.eval("m.remove(\"a\")").unwrap(), Value::Nil);
+    assert_eq!(
+        interpreter.eval("str(m)").unwrap(),
+        Value::from("{\"z\": 4, \"m\": 3}")
+    );
+}
+
+#[test]
+fn unhashable_keys_are_runtime_errors() {
+    for source in &["var m = {};\nm[[]] = 1;", "var m = {};\nm[m];", "var k = [];\n{k: 1};"] {
+        match Interpreter::new().eval(source) {
+            Err(RloxError::RuntimeError(e)) => {
+                assert!(e.description().starts_with("Map keys must be"), "{}", source);
+                assert_eq!(e.line_number(), Some(2));
+            }
+            _ => panic!("expected a runtime error for {}", source),
+        }
+    }
+}
+
+#[test]
+fn missing_keys_are_runtime_errors() {
+    assert!(matches!(
+        Interpreter::new().eval("var m = {\"a\": 1}; m[\"b\"];"),
+        Err(RloxError::RuntimeError(_))
+    ));
+}
+
+#[test]
+fn braces_in_statement_position_are_blocks_unless_followed_by_a_key() {
+    let program = rlox::parse_program("{ print 1; } {\"a\": 1}; { x; } {};").unwrap();
+    let printer = AstPrinter::default();
+    let kinds = program
+        .iter()
+        .map(|stmt| match stmt {
+            rlox::stmt::Stmt::Block(_) => "block".to_string(),
+            rlox::stmt::Stmt::Expression(e) => printer.print(e.expression().clone()),
+            _ => "other".to_string(),
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(kinds, vec!["block", "(map a 1)", "block", "(map)"]);
+}
+
+#[test]
+fn braces_are_told_apart_by_the_two_tokens_after_them() {
+    let is_block = |source: &str| match &rlox::parse_program(source).unwrap()[0] {
+        rlox::stmt::Stmt::Block(_) => true,
+        rlox::stmt::Stmt::Expression(_) => false,
+        stmt => panic!("{:?} is neither a block nor an expression", stmt),
+    };
+    for source in ["{x: 1};", "{1: 2};", "{nil: this};", "({-1: 2});", "({[0][0]: 2});"].iter() {
+        assert!(!is_block(source), "{} should be a map", source);
+    }
+    for source in ["{x;}", "{}", "{{}}", "{ {x: 1}; }", "{ print {}; }"].iter() {
+        assert!(is_block(source), "{} should be a block", source);
+    }
+    // Longer keys in statement position need parentheses
+    assert!(rlox::parse_program("{-1: 2};").is_err());
+}
+
+#[test]
+fn maps_containing_themselves_print_the_repeat_as_an_ellipsis() {
+    let interpreter = Interpreter::new();
+    interpreter
+        .eval("var m = {}; m[\"self\"] = m; m[\"list\"] = [m];")
+        .unwrap();
+    assert_eq!(
+        interpreter.eval("str(m)").unwrap(),
+        Value::from("{\"self\": {...}, \"list\": [{...}]}")
+    );
+}
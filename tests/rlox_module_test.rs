@@ -0,0 +1,109 @@
+use std::fs;
+use std::path::PathBuf;
+
+use rlox::error::RloxError;
+use rlox::Interpreter;
+
+// A fresh directory per test, so tests running in parallel don't see each other's files
+fn module_dir(test: &str, files: &[(&str, &str)]) -> PathBuf {
+    let dir =
+        std::env::temp_dir().join(format!("rlox-module-test-{}-{}", test, std::process::id()));
+    for (name, source) in files {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, source).unwrap();
+    }
+    dir
+}
+
+#[test]
+fn imports_resolve_relative_to_the_importing_file() {
+    let dir = module_dir(
+        "relative",
+        &[
+            (
+                "main.lox",
+                "import \"lib/shapes.lox\" as shapes; var area = shapes.square(3);",
+            ),
+            (
+                "lib/shapes.lox",
+                "import \"util.lox\" as util; fun square(x) { return util.times(x, x); }",
+            ),
+            ("lib/util.lox", "fun times(a, b) { return a * b; }"),
+        ],
+    );
+    let interpreter = Interpreter::new();
+    interpreter.run_file(dir.join("main.lox")).unwrap();
+    assert_eq!(interpreter.get_global("area"), Some(9.0.into()));
+}
+
+#[test]
+fn modules_run_once_and_keep_their_own_globals() {
+    let dir = module_dir(
+        "cache",
+        &[
+            (
+                "main.lox",
+                "import \"counter.lox\" as a; import \"counter.lox\" as b;
+                 var count = 100;
+                 a.bump(); b.bump();
+                 var result = a.count;",
+            ),
+            (
+                "counter.lox",
+                "var count = 0; fun bump() { count = count + 1; }",
+            ),
+        ],
+    );
+    let interpreter = Interpreter::new();
+    interpreter.run_file(dir.join("main.lox")).unwrap();
+    assert_eq!(interpreter.get_global("result"), Some(2.0.into()));
+    assert_eq!(interpreter.get_global("count"), Some(100.0.into()));
+}
+
+#[test]
+fn import_cycles_report_the_chain() {
+    let dir = module_dir(
+        "cycle",
+        &[
+            ("a.lox", "import \"b.lox\" as b;"),
+            ("b.lox", "import \"a.lox\" as a;"),
+        ],
+    );
+    match Interpreter::new().run_file(dir.join("a.lox")) {
+        Err(RloxError::RuntimeError(e)) => {
+            assert!(e.description().starts_with("Import cycle: "), "{}", e);
+            assert!(e.description().contains("a.lox -> "), "{}", e);
+            assert!(e.description().contains("b.lox -> "), "{}", e);
+        }
+        result => panic!("expected an import cycle error, got {:?}", result),
+    }
+}
+
+#[test]
+fn rlox_path_is_searched_after_the_importing_directory() {
+    let dir = module_dir(
+        "search-path",
+        &[
+            (
+                "app/main.lox",
+                "import \"shared.lox\" as shared; var name = shared.name;",
+            ),
+            ("lib/shared.lox", "var name = \"shared\";"),
+        ],
+    );
+    std::env::set_var("RLOX_PATH", dir.join("lib"));
+    let interpreter = Interpreter::new();
+    interpreter.run_file(dir.join("app/main.lox")).unwrap();
+    assert_eq!(interpreter.get_global("name"), Some("shared".into()));
+}
+
+#[test]
+fn imports_are_only_allowed_at_the_top_level() {
+    let source = "{ import \"x.lox\" as x; }";
+    let statements = rlox::parse_program(source).unwrap();
+    assert!(matches!(
+        Interpreter::new().interpret(statements),
+        Err(RloxError::SyntaxErrors(_))
+    ));
+}
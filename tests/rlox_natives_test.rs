@@ -0,0 +1,92 @@
+use rlox::error::RloxError;
+use rlox::{Interpreter, Value};
+
+#[test]
+fn string_natives() {
+    let interpreter = Interpreter::new();
+    assert_eq!(interpreter.eval("len(\"héllo\")").unwrap(), Value::Number(5.0));
+    assert_eq!(
+        interpreter.eval("toUpper(substr(\"hello world\", 6, 5))").unwrap(),
+        Value::from("WORLD")
+    );
+    assert_eq!(interpreter.eval("str(1.5) + \"!\"").unwrap(), Value::from("1.5!"));
+    assert_eq!(interpreter.eval("parseNumber(\" 42 \")").unwrap(), Value::Number(42.0));
+    assert_eq!(interpreter.eval("parseNumber(\"forty\")").unwrap(), Value::Nil);
+}
+
+#[test]
+fn math_natives() {
+    let interpreter = Interpreter::new();
+    assert_eq!(interpreter.eval("sqrt(16) + floor(2.7)").unwrap(), Value::Number(6.0));
+    assert_eq!(interpreter.eval("clock() > 0").unwrap(), Value::Bool(true));
+}
+
+#[test]
+fn random_is_reproducible_with_a_seed() {
+    let interpreter = Interpreter::new();
+    let source = "seedRandom(7); var a = random(); var b = random(); seedRandom(7);";
+    interpreter.eval(source).unwrap();
+    assert_eq!(
+        interpreter.eval("a == random() and b == random() and a != b").unwrap(),
+        Value::Bool(true)
+    );
+    assert_eq!(
+        interpreter.eval("var r = random(); r >= 0 and r < 1;").unwrap(),
+        Value::Bool(true)
+    );
+}
+
+#[test]
+fn files_round_trip() {
+    let path = std::env::temp_dir().join("rlox_natives_test.txt");
+    let interpreter = Interpreter::new();
+    interpreter.define_global("path", path.to_str().unwrap());
+    interpreter.eval("writeFile(path, \"line one\");").unwrap();
+    assert_eq!(interpreter.eval("readFile(path)").unwrap(), Value::from("line one"));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn argument_errors_are_runtime_errors_at_the_call_site() {
+    match Interpreter::new().eval("var x = 1;\nsubstr(\"abc\", 1, 5);") {
+        Err(RloxError::RuntimeError(e)) => assert_eq!(e.line_number(), Some(2)),
+        _ => panic!("expected an out of bounds error"),
+    }
+    match Interpreter::new().eval("substr(\"abc\", 1, 100000000000000000000000);") {
+        Err(RloxError::RuntimeError(e)) => assert_eq!(
+            e.description(),
+            "substr: 100000000000000000000000 characters from 1 are out of bounds for a string \
+             of length 3."
+        ),
+        _ => panic!("expected an out of bounds error"),
+    }
+    match Interpreter::new().eval("\n\n\nsqrt(\"nine\");") {
+        Err(RloxError::RuntimeError(e)) => {
+            assert_eq!(e.description(), "sqrt: expected a number but got string.");
+            assert_eq!(e.line_number(), Some(4));
+        }
+        _ => panic!("expected a type error"),
+    }
+}
+
+#[test]
+fn exit_is_left_to_the_host() {
+    assert!(matches!(
+        Interpreter::new().eval("exit(3);"),
+        Err(RloxError::Exit(3))
+    ));
+}
+
+#[test]
+fn exit_codes_must_be_small_integers() {
+    for code in &["1.5", "300", "-1", "0 / 0"] {
+        match Interpreter::new().eval(&format!("exit({});", code)) {
+            Err(RloxError::RuntimeError(e)) => assert!(
+                e.description().starts_with("exit: expected an integer from 0 to 255"),
+                "{}",
+                e.description()
+            ),
+            other => panic!("exit({}) gave {:?}", code, other),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use assert_cmd::Command;
+use predicates::str::contains;
+
+use rlox::ast_printer::AstPrinter;
+use rlox::fold::Fold;
+use rlox::optimizer::Optimizer;
+
+fn optimized(source: &str) -> String {
+    let expr = rlox::parse_expression(source).unwrap();
+    AstPrinter::default().print(Optimizer::new().fold_expr(&expr))
+}
+
+#[test]
+fn constant_subtrees_are_folded_into_literals() {
+    assert_eq!(optimized("(1 + 2) * 3"), "9");
+    assert_eq!(optimized("\"a\" + \"b\""), "ab");
+    assert_eq!(optimized("-(2 - 4) >= 2"), "true");
+    assert_eq!(optimized("nil == false"), "false");
+    assert_eq!(optimized("!nil"), "true");
+    assert_eq!(optimized("x + 2 * 3"), "(+ x 6)");
+}
+
+#[test]
+fn identities_are_simplified_only_when_types_allow() {
+    assert_eq!(optimized("-x * 1"), "(- x)");
+    assert_eq!(optimized("1 * (a - b)"), "(group (- a b))");
+    assert_eq!(optimized("!!(a < b)"), "(group (< a b))");
+    assert_eq!(optimized("!!!b"), "(! b)");
+    // x could be a string, and -0 + 0 isn't -0
+    assert_eq!(optimized("x * 1"), "(* x 1)");
+    assert_eq!(optimized("!!b"), "(! (! b))");
+    assert_eq!(optimized("-x + 0"), "(+ (- x) 0)");
+}
+
+#[test]
+fn logical_operators_with_a_constant_lhs_short_circuit() {
+    assert_eq!(optimized("nil or x"), "x");
+    assert_eq!(optimized("1 or x"), "1");
+    assert_eq!(optimized("false and x"), "false");
+    assert_eq!(optimized("true and x"), "x");
+}
+
+#[test]
+fn operations_that_fail_at_runtime_are_not_folded() {
+    assert_eq!(optimized("\"a\" - 1"), "(- a 1)");
+    assert_eq!(optimized("-\"a\""), "(- a)");
+    assert_eq!(optimized("1 + nil"), "(+ 1 nil)");
+}
+
+#[test]
+fn cli_no_opt_disables_folding() {
+    let script = std::env::temp_dir().join(format!("rlox_optimizer_{}.lox", std::process::id()));
+    std::fs::write(&script, "print (1 + 2) * 3;").unwrap();
+    let dump = |args: &[&str]| {
+        Command::cargo_bin("rlox")
+            .unwrap()
+            .args(args)
+            .arg("--dump-bytecode")
+            .arg(&script)
+            .assert()
+            .success()
+    };
+    dump(&[]).stdout(contains("OP_CONSTANT         0 '9'"));
+    dump(&["--no-opt"]).stdout(contains("OP_ADD"));
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .arg("--no-opt")
+        .arg(&script)
+        .assert()
+        .stdout("9\n")
+        .success();
+    std::fs::remove_file(&script).unwrap();
+}
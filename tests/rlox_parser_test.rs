@@ -0,0 +1,108 @@
+use rlox::ast_printer::AstPrinter;
+use rlox::error::RloxError;
+use rlox::stmt::Stmt;
+use rlox::token::TokenType;
+
+#[test]
+fn tokenize_returns_tokens_ending_with_eof() {
+    let tokens = rlox::tokenize("!a != b").unwrap();
+    let token_types = tokens.iter().map(|t| t.token_type().clone()).collect::<Vec<_>>();
+    assert_eq!(
+        token_types,
+        vec![
+            TokenType::Bang,
+            TokenType::Identifier,
+            TokenType::BangEqual,
+            TokenType::Identifier,
+            TokenType::Eof
+        ]
+    );
+}
+
+#[test]
+fn parse_expression_respects_precedence() {
+    let expr = rlox::parse_expression("-123 * (45.67) + 1 == 2 or !x").unwrap();
+    let printer = AstPrinter::default();
+    assert_eq!(
+        printer.print(expr),
+        "(or (== (+ (* (- 123) (group 45.67)) 1) 2) (! x))"
+    );
+}
+
+#[test]
+fn parse_expression_rejects_trailing_tokens() {
+    assert!(rlox::parse_expression("1 + 2 3").is_err());
+}
+
+#[test]
+fn parse_program_desugars_for_into_while() {
+    let program = rlox::parse_program("for (var i = 0; i < 3; i = i + 1) print i;").unwrap();
+    assert_eq!(program.len(), 1);
+    match &program[0] {
+        Stmt::Block(block) => {
+            assert!(matches!(block.statements()[0], Stmt::Var(_)));
+            assert!(matches!(block.statements()[1], Stmt::While(_)));
+        }
+        _ => panic!("expected the for loop to be wrapped in a block"),
+    }
+}
+
+#[test]
+fn parse_program_reports_every_syntax_error() {
+    match rlox::parse_program("var = 1;\nprint 2;\nprint (3;") {
+        Err(RloxError::SyntaxErrors(errors)) => assert_eq!(errors.len(), 2),
+        _ => panic!("expected two syntax errors"),
+    }
+}
+
+#[test]
+fn syntax_errors_are_displayed_one_per_line() {
+    let message = rlox::parse_program("var = 1;\nprint (3;").unwrap_err().to_string();
+    let starts = message.lines().filter(|line| line.starts_with("Syntax error: "));
+    assert_eq!(starts.count(), 2);
+    assert!(!message.ends_with('\n'));
+}
+
+fn is_too_deep(result: Result<Vec<Stmt>, RloxError>) -> bool {
+    match result {
+        Err(RloxError::SyntaxErrors(errors)) => {
+            errors.iter().any(|e| e.to_string().contains("Too much nesting."))
+        }
+        _ => false,
+    }
+}
+
+#[test]
+fn parse_program_rejects_trees_too_deep_to_walk() {
+    let parens = format!("print {}1{};", "(".repeat(50000), ")".repeat(50000));
+    assert!(is_too_deep(rlox::parse_program(&parens)));
+    let negations = format!("print {}1;", "-".repeat(50000));
+    assert!(is_too_deep(rlox::parse_program(&negations)));
+    let sum = format!("print 1{};", " + 1".repeat(50000));
+    assert!(is_too_deep(rlox::parse_program(&sum)));
+    let calls = format!("f{};", "()".repeat(50000));
+    assert!(is_too_deep(rlox::parse_program(&calls)));
+    let blocks = format!("{}{}", "{".repeat(2000), "}".repeat(2000));
+    assert!(is_too_deep(rlox::parse_program(&blocks)));
+    let functions = "fun f() {".repeat(2000) + &"}".repeat(2000);
+    assert!(is_too_deep(rlox::parse_program(&functions)));
+}
+
+#[test]
+fn trees_just_below_the_nesting_limit_can_be_walked() {
+    let source = format!(
+        "{}print {}1{} + 1{};{}",
+        "{".repeat(40),
+        "(".repeat(40),
+        ")".repeat(40),
+        " + 1".repeat(40),
+        "}".repeat(40)
+    );
+    let statements = rlox::parse_program(&source).unwrap();
+    assert!(AstPrinter::default().print_stmt(&statements[0]).starts_with("(block"));
+    rlox::ast_printer_dot::AstPrinterDot::default().print(&statements);
+    rlox::ast_printer_json::AstPrinterJson::default().print(&statements);
+    rlox::formatter::format_source(&source).unwrap();
+    rlox::compile(&source).unwrap();
+    rlox::Interpreter::with_output(Vec::new()).interpret(statements).unwrap();
+}
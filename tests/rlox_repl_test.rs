@@ -0,0 +1,160 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use predicates::str::contains;
+
+use rlox::line_editor::LineEditor;
+use rlox::repl;
+
+fn edit(editor: &mut LineEditor, keys: &str) -> Option<String> {
+    let mut output = Vec::new();
+    editor
+        .edit_line("> ", &mut keys.as_bytes(), &mut output)
+        .unwrap()
+}
+
+#[test]
+fn unbalanced_input_needs_more_lines() {
+    assert!(repl::is_incomplete("fun f() {"));
+    assert!(repl::is_incomplete("print [1, (2"));
+    assert!(repl::is_incomplete("print \"a"));
+    assert!(!repl::is_incomplete("fun f() { return (1); }"));
+    assert!(!repl::is_incomplete("print 1;"));
+    // Left for the parser to report
+    assert!(!repl::is_incomplete("}"));
+    assert!(!repl::is_incomplete("print @ {"));
+}
+
+#[test]
+fn keys_move_the_cursor_and_edit_the_line() {
+    let mut editor = LineEditor::new();
+    // Left twice, backspace, end, then a character
+    assert_eq!(
+        edit(&mut editor, "print 12;\x1b[D\x1b[D\x7f\x1b[F!\r"),
+        Some("print 2;!".to_string())
+    );
+    // Home, delete, Ctrl-E, Ctrl-U
+    assert_eq!(
+        edit(&mut editor, "xab\x01\x1b[3~\x05c\r"),
+        Some("abc".to_string())
+    );
+    assert_eq!(edit(&mut editor, "abc\x02\x15d\n"), Some("dc".to_string()));
+    assert_eq!(
+        edit(&mut editor, "é\u{3bb}\x1b[D\x04\r"),
+        Some("é".to_string())
+    );
+}
+
+#[test]
+fn arrows_recall_history_and_ctrl_d_ends_input() {
+    let mut editor = LineEditor::new();
+    editor.add_history("one");
+    editor.add_history("two");
+    editor.add_history("two");
+    editor.add_history("  ");
+    assert_eq!(editor.history(), ["one", "two"]);
+    assert_eq!(edit(&mut editor, "\x1b[A\x1b[A\r"), Some("one".to_string()));
+    assert_eq!(
+        edit(&mut editor, "new\x1b[A\x1b[B\x1b[B\r"),
+        Some("new".to_string())
+    );
+    assert_eq!(edit(&mut editor, "\x04"), None);
+    assert_eq!(edit(&mut editor, ""), None);
+    let mut output = Vec::new();
+    let interrupted = editor.edit_line("> ", &mut "ab\x03".as_bytes(), &mut output);
+    assert_eq!(
+        interrupted.unwrap_err().kind(),
+        std::io::ErrorKind::Interrupted
+    );
+}
+
+#[test]
+fn history_persists_in_the_home_directory() {
+    let home = std::env::temp_dir().join(format!("rlox_repl_home_{}", std::process::id()));
+    std::fs::create_dir_all(&home).unwrap();
+    let history = home.join(".rlox_history");
+    std::fs::write(&history, "print 0;\n").unwrap();
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .env("HOME", &home)
+        .write_stdin("print 1;\n\nprint 2;\n")
+        .assert()
+        .success();
+    let saved = std::fs::read_to_string(&history).unwrap();
+    std::fs::remove_dir_all(&home).unwrap();
+    assert_eq!(saved, "print 0;\nprint 1;\nprint 2;\n");
+    assert_eq!(
+        LineEditor::with_history_file(home.join("missing")).history(),
+        [] as [&str; 0]
+    );
+}
+
+#[test]
+fn cli_repl_keeps_definitions_across_multi_line_input() {
+    for backend in ["--backend=tree", "--backend=vm"].iter() {
+        Command::cargo_bin("rlox")
+            .unwrap()
+            .arg(backend)
+            .env_remove("HOME")
+            .write_stdin(
+                "var a = 1;\nfun f(x) {\n\n  return x + a;\n}\nprint f(2);\nexit\nprint 4;",
+            )
+            .assert()
+            .stdout(contains("> ... ... ... > 3\n> ").and(contains("4").not()))
+            .success();
+    }
+}
+
+#[test]
+fn meta_commands_take_the_rest_of_the_line() {
+    use repl::Command;
+    assert_eq!(Command::parse(":ast  1 + 2 "), Some(Command::Ast("1 + 2")));
+    assert_eq!(Command::parse(" :env"), Some(Command::Env));
+    assert_eq!(Command::parse(":load a.lox"), Some(Command::Load("a.lox")));
+    assert_eq!(Command::parse(":quit now"), Some(Command::Unknown("quit")));
+    assert_eq!(Command::parse("print 1;"), None);
+}
+
+#[test]
+fn front_end_commands_reuse_the_printers() {
+    assert_eq!(
+        repl::tokens("var x;").unwrap(),
+        "Var \"var\" @1\nIdentifier \"x\" @1\nSemicolon \";\" @1\n"
+    );
+    assert_eq!(repl::ast("-1 * x").unwrap(), "(* (- 1) x)\n");
+    assert_eq!(
+        repl::ast("var x = 1; print x;").unwrap(),
+        "(var x 1)\n(print x)\n"
+    );
+    assert_eq!(repl::rpn("(1 + 2) * x").unwrap(), "1 2 + x *\n");
+    assert!(repl::rpn("print 1;").is_err());
+    assert!(repl::tokens("\"open").is_err());
+}
+
+#[test]
+fn lone_expressions_are_printed() {
+    let printed = |source| {
+        let statements = repl::parse_entry(source).unwrap();
+        matches!(statements.as_slice(), [rlox::stmt::Stmt::Print(_)])
+    };
+    assert!(printed("1 + 2"));
+    assert!(printed("f()"));
+    assert!(!printed("f();"));
+    assert!(!printed("var a = 1;"));
+}
+
+#[test]
+fn cli_repl_echoes_values_and_runs_meta_commands() {
+    for backend in ["--backend=tree", "--backend=vm"].iter() {
+        Command::cargo_bin("rlox")
+            .unwrap()
+            .arg(backend)
+            .env_remove("HOME")
+            .write_stdin(
+                "1 + 2\nvar b = [1];\n:load tests/test_script.txt\n:env\n:reset\n:env\nb\n:nope\n",
+            )
+            .assert()
+            .stdout(contains("> 3\n").and(contains("hello, world\n> b = [1]\n> > > > ")))
+            .stderr(contains("Undefined variable 'b'").and(contains("Unknown command ':nope'")))
+            .success();
+    }
+}
@@ -0,0 +1,93 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use predicates::str::contains;
+
+use rlox::ast_printer_rpn::AstPrinterRpn;
+use rlox::error::RloxError;
+use rlox::rpn;
+use rlox::value::Value;
+use rlox::Interpreter;
+
+fn runtime_error(text: &str) -> String {
+    match rpn::evaluate(text) {
+        Err(RloxError::RuntimeError(e)) => e.description().to_string(),
+        _ => panic!("expected {:?} to fail at runtime", text),
+    }
+}
+
+#[test]
+fn printed_expressions_evaluate_like_the_interpreter() {
+    let sources = [
+        "-123 * (45.67) + 1",
+        "(1 + 2) * (4 - 3) / -2",
+        "\"ab\" + \"c\" == \"abc\"",
+        "!(1 < 2) or nil and true",
+        "1 >= 2 or 3 <= 4 and \"x\"",
+        "--5 != 5",
+    ];
+    let interpreter = Interpreter::new();
+    for source in sources.iter() {
+        let printed = AstPrinterRpn::default().print(rlox::parse_expression(source).unwrap());
+        let expected = interpreter.eval(source).unwrap();
+        assert_eq!(
+            rpn::evaluate(&printed).unwrap(),
+            expected,
+            "{} is {}",
+            source,
+            printed
+        );
+    }
+}
+
+#[test]
+fn operators_without_enough_operands_underflow_the_stack() {
+    assert_eq!(
+        runtime_error("1 +"),
+        "Stack underflow: '+' needs 2 operand(s), found 1."
+    );
+    assert_eq!(
+        runtime_error("-"),
+        "Stack underflow: '-' needs 1 operand(s), found 0."
+    );
+}
+
+#[test]
+fn malformed_input_is_reported() {
+    assert_eq!(runtime_error(""), "Expect an expression.");
+    assert_eq!(
+        runtime_error("1 2"),
+        "Expect a single result, found 2 values on the stack."
+    );
+    assert_eq!(runtime_error("\"a\" 1 -"), "Operands must be numbers.");
+    assert_eq!(
+        runtime_error("x 1 call/1"),
+        "Can't evaluate 'call/1' without an environment."
+    );
+}
+
+#[test]
+fn minus_negates_only_where_subtracting_would_leave_no_single_result() {
+    let evaluate = |text| rpn::evaluate(text).unwrap();
+    assert_eq!(evaluate("123 - 45.67 *"), Value::Number(-123.0 * 45.67));
+    assert_eq!(evaluate("3 2 - 2 - /"), Value::Number(-0.5));
+    assert_eq!(evaluate("1 2 - -"), Value::Number(1.0));
+    assert_eq!(evaluate("5 - - 5 !="), Value::Bool(false));
+}
+
+#[test]
+fn bare_words_are_strings() {
+    assert_eq!(rpn::evaluate("a b +").unwrap(), Value::from("ab"));
+    assert_eq!(rpn::evaluate("\"a b\" c +").unwrap(), Value::from("a bc"));
+}
+
+#[test]
+fn cli_rpn_evaluates_each_line() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .arg("--rpn")
+        .write_stdin("1 2 + 3 *\n\n\"a\" \"b\" +\n+\n4\n")
+        .assert()
+        .stdout("9\nab\n4\n")
+        .stderr(contains("Stack underflow").and(contains("[line 4]")))
+        .code(70);
+}
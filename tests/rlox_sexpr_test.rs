@@ -0,0 +1,137 @@
+use rlox::ast_printer::AstPrinter;
+use rlox::error::RloxError;
+use rlox::fold::Fold;
+use rlox::optimizer::Optimizer;
+use rlox::sexpr::{parse_sexpr, parse_sexpr_stmt, print_sexpr, print_sexpr_stmt};
+
+const EXPRESSIONS: &[&str] = &[
+    "-123 * (45.67) + 1 == 2 or !x",
+    "a and b or !(c != d) and e >= 1 / 3",
+    "\"a string\" + \"with (parens) and ; stuff\"",
+    "nil == false or true",
+    "x = y = 0.5",
+    "point.x = point.y.z",
+    "f(1, g(), \"three\")(4)",
+    "xs[0] = [1, 2][1] - -xs[-1]",
+    "{\"a\": [], 1: {}}",
+    "this.count",
+    "super.init",
+    "fun (a, b) { var c = a + b; if (c > 0) return c; else print nil; }",
+    "fun () { while (true) { break; } for (var i = 0; i < 3; i = i + 1) continue; }",
+];
+
+fn print(expr: rlox::expr::Expr) -> String {
+    print_sexpr(&expr)
+}
+
+#[test]
+fn printed_expressions_read_back_to_the_same_tree() {
+    for source in EXPRESSIONS {
+        let expr = rlox::parse_expression(source).unwrap();
+        let printed = print(expr.clone());
+        let read = parse_sexpr(&printed).unwrap_or_else(|e| panic!("{}: {}", printed, e));
+        assert!(
+            read.eq_ignoring_locations(&expr),
+            "round trip of {}",
+            source
+        );
+    }
+}
+
+#[test]
+fn printed_statements_read_back_to_the_same_tree() {
+    let program = rlox::parse_program(
+        "class B < A { init(x) { super.init(x); this.x = x; } get() { return this.x; } }
+         import \"lib/util.lox\" as util;
+         try { throw \"oops\"; } catch (e) { print e; } finally { print 1; }
+         { var x; x; }",
+    )
+    .unwrap();
+    for stmt in &program {
+        let printed = print_sexpr_stmt(stmt);
+        let read = parse_sexpr_stmt(&printed).unwrap_or_else(|e| panic!("{}: {}", printed, e));
+        assert!(
+            read.eq_ignoring_locations(stmt),
+            "round trip of {}",
+            printed
+        );
+    }
+}
+
+#[test]
+fn parser_golden_tests_can_be_written_as_sexprs() {
+    let cases = [
+        ("1 - 2 - 3", "(- (- 1 2) 3)"),
+        ("a or b and c", "(or a (and b c))"),
+        ("-a.b(c)[d]", "(- (index (call (. a b) c) d))"),
+        ("a.b = c[d] = e", "(= (. a b) (index= c d e))"),
+    ];
+    // All on line 1 like the tokens read from the text, so they compare equal with locations
+    for (source, expected) in cases.iter() {
+        assert_eq!(
+            rlox::parse_expression(source).unwrap(),
+            parse_sexpr(expected).unwrap()
+        );
+    }
+}
+
+#[test]
+fn folded_numbers_without_digits_read_back_to_the_same_tree() {
+    for source in ["1 / 0", "-1 / 0", "0 / 0", "-(0 / 0)", "[1 / 0, \"inf\", inf, NaN]"].iter() {
+        let folded = Optimizer::new().fold_expr(&rlox::parse_expression(source).unwrap());
+        let printed = print(folded.clone());
+        let read = parse_sexpr(&printed).unwrap_or_else(|e| panic!("{}: {}", printed, e));
+        assert_eq!(read, folded, "round trip of {}", printed);
+    }
+}
+
+#[test]
+fn ast_printer_text_without_strings_reads_back_too() {
+    let expr = rlox::parse_expression("-a.b(1, nil)[true] * inf").unwrap();
+    let read = parse_sexpr(&AstPrinter::default().print(expr.clone())).unwrap();
+    assert!(read.eq_ignoring_locations(&expr));
+}
+
+#[test]
+fn strings_numbers_and_names_are_told_apart() {
+    assert_eq!(print(parse_sexpr("(+ \"1\" 1)").unwrap()), "(+ \"1\" 1)");
+    assert_eq!(print(parse_sexpr("(- -0.5)").unwrap()), "(- -0.5)");
+    assert_eq!(
+        print(parse_sexpr("(call nil_or_this this nil)").unwrap()),
+        "(call nil_or_this this nil)"
+    );
+}
+
+#[test]
+fn malformed_text_is_a_syntax_error() {
+    for text in [
+        "",
+        "(+ 1 2",
+        "(+ 1 2))",
+        "\"unterminated",
+        "(+ 1)",
+        "(frobnicate 1)",
+        "(= 1 2)",
+        "(. a 1)",
+        "(map 1)",
+        "()",
+        "var",
+        "1 2",
+    ]
+    .iter()
+    {
+        assert!(
+            matches!(parse_sexpr(text), Err(RloxError::SyntaxError(_))),
+            "{:?} should not read",
+            text
+        );
+    }
+}
+
+#[test]
+fn text_nested_too_deep_is_a_syntax_error() {
+    let deep = format!("{}1{}", "(group ".repeat(50000), ")".repeat(50000));
+    assert!(matches!(parse_sexpr(&deep), Err(RloxError::SyntaxError(_))));
+    let expr = rlox::parse_expression(&format!("{}1", "-".repeat(120))).unwrap();
+    assert!(parse_sexpr(&print(expr.clone())).unwrap().eq_ignoring_locations(&expr));
+}
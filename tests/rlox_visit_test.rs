@@ -0,0 +1,126 @@
+use rlox::ast_printer::AstPrinter;
+use rlox::expr::{Expr, FunctionExpr, UnaryExpr, VariableExpr};
+use rlox::fold::{self, Fold};
+use rlox::visit::{self, VisitorMut};
+
+// Records the names read by a program, and how deep in nested functions each is read
+#[derive(Default)]
+struct Reads {
+    depth: usize,
+    names: Vec<(String, usize)>,
+    skip_functions: bool,
+}
+
+impl VisitorMut for Reads {
+    fn visit_variable_expr(&mut self, expr: &VariableExpr) {
+        self.names
+            .push((expr.name().lexeme().to_string(), self.depth));
+    }
+
+    fn visit_function_expr(&mut self, expr: &FunctionExpr) {
+        if self.skip_functions {
+            return;
+        }
+        self.depth += 1;
+        visit::walk_function_expr(self, expr);
+        self.depth -= 1;
+    }
+}
+
+fn reads(source: &str, skip_functions: bool) -> Vec<(String, usize)> {
+    let mut reads = Reads {
+        skip_functions,
+        ..Reads::default()
+    };
+    for stmt in rlox::parse_program(source).unwrap() {
+        reads.visit_stmt(&stmt);
+    }
+    reads.names
+}
+
+#[test]
+fn visitor_mut_walks_every_node_it_does_not_override() {
+    let source = "
+        var a = b + [c, {d: e}][f];
+        class A < B { m() { return this.g(h); } }
+        for (var i = 0; i < n; i = i + 1) {
+            try { throw x; } catch (err) { print fun () { y; }; }
+        }";
+    let names = reads(source, false);
+    let expected = [
+        ("b", 0),
+        ("c", 0),
+        ("d", 0),
+        ("e", 0),
+        ("f", 0),
+        ("B", 0),
+        ("h", 1),
+        ("i", 0),
+        ("n", 0),
+        ("x", 0),
+        ("y", 1),
+        ("i", 0),
+    ];
+    let expected = expected
+        .iter()
+        .map(|(n, d)| (n.to_string(), *d))
+        .collect::<Vec<_>>();
+    assert_eq!(names, expected);
+}
+
+#[test]
+fn visitor_mut_descends_only_where_walk_is_called() {
+    let names = reads("fun f() { a; } b; print fun () { c; };", true);
+    assert_eq!(names, vec![("b".to_string(), 0)]);
+}
+
+// Desugars `a - b` into `a + -b`, counting the rewrites
+#[derive(Default)]
+struct NoMinus {
+    rewrites: usize,
+}
+
+impl Fold for NoMinus {
+    fn fold_binary_expr(&mut self, expr: &rlox::expr::BinaryExpr) -> Expr {
+        let folded = fold::fold_binary_expr(self, expr);
+        match &folded {
+            Expr::Binary(e) if e.operator().lexeme() == "-" => {
+                self.rewrites += 1;
+                let plus = rlox::tokenize("+").unwrap().remove(0);
+                let minus = e.operator().clone();
+                let rhs = UnaryExpr::new(minus, e.rhs().clone());
+                rlox::expr::BinaryExpr::new(plus, e.lhs().clone(), rhs)
+            }
+            _ => folded,
+        }
+    }
+}
+
+#[test]
+fn fold_rebuilds_the_tree_around_rewritten_nodes() {
+    let program = rlox::parse_program("fun f(a) { return g(a - 1)[a - (2 - b)]; }").unwrap();
+    let mut folder = NoMinus::default();
+    let folded = fold::fold_stmts(&mut folder, &program);
+    assert_eq!(folder.rewrites, 3);
+    assert_eq!(
+        AstPrinter::default().print_stmt(&folded[0]),
+        "(fun f (a) (return (index (call g (+ a (- 1))) (+ a (- (group (+ 2 (- b))))))))"
+    );
+    // The original tree is left as it was
+    assert_eq!(
+        AstPrinter::default().print_stmt(&program[0]),
+        "(fun f (a) (return (index (call g (- a 1)) (- a (group (- 2 b))))))"
+    );
+}
+
+#[test]
+fn fold_without_overrides_copies_the_tree() {
+    let program = rlox::parse_program(
+        "class A { init() { this.x = [1, {\"k\": nil}]; } }
+         while (x) { if (y) break; else continue; }",
+    )
+    .unwrap();
+    struct Identity;
+    impl Fold for Identity {}
+    assert_eq!(fold::fold_stmts(&mut Identity, &program), program);
+}
@@ -0,0 +1,240 @@
+mod common;
+
+use assert_cmd::Command;
+use predicates::str::contains;
+
+use rlox::{Interpreter, Vm};
+
+use common::SharedOutput;
+
+// Runs `source` on both backends, returning what each printed followed by any error it reported
+fn run_both(source: &str) -> (String, String) {
+    let statements = rlox::parse_program(source).unwrap();
+    let output = SharedOutput::default();
+    let result = Interpreter::with_output(output.clone()).interpret(statements);
+    let tree = match result {
+        Ok(()) => output.contents(),
+        Err(e) => format!("{}{}", output.contents(), e),
+    };
+
+    let statements = rlox::parse_program(source).unwrap();
+    let output = SharedOutput::default();
+    let result = Vm::with_output(output.clone()).interpret(statements);
+    let vm = match result {
+        Ok(()) => output.contents(),
+        Err(e) => format!("{}{}", output.contents(), e),
+    };
+    (tree, vm)
+}
+
+fn assert_same(source: &str) {
+    let (tree, vm) = run_both(source);
+    assert_eq!(tree, vm, "backends disagree on:\n{}", source);
+}
+
+#[test]
+fn arithmetic_strings_and_logic() {
+    assert_same(
+        "
+        print 1 + 2 * 3 - 4 / 8;
+        print -(3) == -3;
+        print \"con\" + \"cat\";
+        print 1 < 2 and 2 <= 2 and 3 > 2 and 3 >= 4;
+        print nil or \"default\";
+        print !nil;
+        print 0.1 + 0.2;
+        print 10 / 4;
+        ",
+    );
+}
+
+#[test]
+fn scopes_and_control_flow() {
+    assert_same(
+        "
+        var a = \"global\";
+        {
+            var a = \"outer\";
+            {
+                var a = \"inner\";
+                print a;
+            }
+            print a;
+        }
+        print a;
+        for (var i = 0; i < 10; i = i + 1) {
+            if (i == 2) continue;
+            if (i == 5) break;
+            print i;
+        }
+        var n = 0;
+        while (true) {
+            n = n + 1;
+            if (n > 3) break;
+        }
+        print n;
+        ",
+    );
+}
+
+#[test]
+fn closures_share_captured_variables() {
+    assert_same(
+        "
+        fun counter() {
+            var count = 0;
+            fun increment() {
+                count = count + 1;
+                return count;
+            }
+            return increment;
+        }
+        var c = counter();
+        c();
+        print c();
+        var fns = [];
+        for (var i = 0; i < 3; i = i + 1) {
+            var j = i;
+            fns.push(fun () { return j * 10; });
+        }
+        print fns[0]() + fns[1]() + fns[2]();
+        fun fib(n) { if (n < 2) return n; return fib(n - 1) + fib(n - 2); }
+        print fib(15);
+        print counter;
+        print fun (x) { return x; };
+        print clock;
+        ",
+    );
+}
+
+#[test]
+fn classes_methods_and_inheritance() {
+    assert_same(
+        "
+        class Animal {
+            init(name) { this.name = name; }
+            speak() { return this.name + \" makes a sound\"; }
+        }
+        class Dog < Animal {
+            init(name) { super.init(name); this.tricks = 0; }
+            speak() { return super.speak() + \" (woof)\"; }
+        }
+        var d = Dog(\"Rex\");
+        print d.speak();
+        var speak = d.speak;
+        print speak();
+        print d.init(\"Max\").name;
+        print Dog;
+        print d;
+        ",
+    );
+}
+
+#[test]
+fn lists_maps_and_natives() {
+    assert_same(
+        "
+        var xs = [1, 2, 3];
+        xs.push(4);
+        xs[0] = 10;
+        print xs;
+        print xs.pop();
+        print len(xs);
+        var m = {\"a\": 1, \"b\": [true, nil]};
+        m[\"c\"] = 3;
+        print m;
+        print m.keys();
+        print m.has(\"b\");
+        print toUpper(\"loud\") + str(12) + substr(\"hello\", 1, 3);
+        print sqrt(16) + floor(2.7);
+        ",
+    );
+}
+
+#[test]
+fn natives_and_print_handle_collections_containing_themselves() {
+    let source = "
+        var l = [1];
+        l.push(l);
+        var m = {\"list\": l};
+        m[\"self\"] = m;
+        l.push(m);
+        print len(l);
+        print len(m);
+        print l;
+        print str(m);
+        print substr(str(l), 0, 6);
+        ";
+    let (tree, vm) = run_both(source);
+    assert_eq!(tree, vm);
+    assert_eq!(
+        vm,
+        "3\n2\n[1, [...], {\"list\": [...], \"self\": {...}}]\n\
+         {\"list\": [1, [...], {...}], \"self\": {...}}\n[1, [.\n"
+    );
+}
+
+#[test]
+fn exceptions_and_finally() {
+    assert_same(
+        "
+        fun risky(n) {
+            if (n > 1) throw Error(\"too big\");
+            return n;
+        }
+        try {
+            print risky(1);
+            print risky(2);
+        } catch (e) {
+            print e.message + \" at \" + str(e.line);
+        } finally {
+            print \"cleanup\";
+        }
+        fun early() {
+            for (var i = 0; i < 3; i = i + 1) {
+                try {
+                    if (i == 1) return \"returned\";
+                } finally {
+                    print \"finally \" + str(i);
+                }
+            }
+        }
+        print early();
+        try { nil.field; } catch (e) { print e.message; }
+        ",
+    );
+}
+
+#[test]
+fn runtime_errors_are_reported_alike() {
+    assert_same("print 1; print \"a\" - 1;");
+    assert_same("var x = 1;\nprint y;");
+    assert_same("fun f() { return 1 + nil; }\nfun g() { f(); }\ng();");
+    assert_same("fun loop() { loop(); }\nloop();");
+    assert_same("throw Error(\"boom\");");
+    assert_same("clock(1);");
+    assert_same("[1, 2][5];");
+    assert_same("fun f(a) {}\nfun g() {\n  f();\n}\ng();");
+    assert_same("class C { init(a) {} }\nfun g() {\n  C();\n}\ng();");
+    assert_same("class C {}\nC(1);");
+    assert_same("fun f(a) {}\nf(1, 2);");
+}
+
+#[test]
+fn cli_runs_scripts_on_the_vm_backend() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["--backend=vm", "./tests/test_script.txt"])
+        .assert()
+        .stdout(contains("hello, world"))
+        .success();
+}
+
+#[test]
+fn cli_rejects_unknown_backends() {
+    Command::cargo_bin("rlox")
+        .unwrap()
+        .args(["--backend=jit", "./tests/test_script.txt"])
+        .assert()
+        .code(64);
+}